@@ -0,0 +1,76 @@
+//! Native build output goes to `$OUT_DIR/native-build.log` instead of flooding cargo's output.
+//! When a step fails, only the tail of the log is shown.
+use crate::error::{hint_for, BuildError};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Stdio};
+
+/// How many lines of the log to show when a step fails.
+const LOG_TAIL_LINES: usize = 50;
+
+pub fn log_file_path() -> PathBuf {
+    PathBuf::from(env::var("OUT_DIR").unwrap()).join("native-build.log")
+}
+
+/// Redirects both stdout and stderr of `cmd` to the build log.
+pub fn redirect_output(cmd: &mut Command) -> Result<&mut Command, io::Error> {
+    let mut log_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file_path())?;
+
+    // Mark where each step starts, to make the log easier to navigate.
+    writeln!(log_file, "\n>>> {:?}", cmd)?;
+
+    let stderr_log_file = log_file.try_clone()?;
+
+    Ok(cmd
+        .stdout(Stdio::from(log_file))
+        .stderr(Stdio::from(stderr_log_file)))
+}
+
+/// Returns the last `LOG_TAIL_LINES` lines of the build log.
+fn log_tail() -> String {
+    let lines: Vec<String> = File::open(log_file_path())
+        .map(|log_file| {
+            BufReader::new(log_file)
+                .lines()
+                .filter_map(|line| line.ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let tail_start = lines.len().saturating_sub(LOG_TAIL_LINES);
+
+    lines[tail_start..].join("\n")
+}
+
+/// The error for step `name` exiting with `status`, including the tail of the build log.
+pub fn command_failed(name: &str, status: ExitStatus) -> BuildError {
+    BuildError::CommandFailed {
+        command: name.to_owned(),
+        status,
+        log_tail: log_tail(),
+        log_path: log_file_path().display().to_string(),
+        hint: hint_for(name),
+    }
+}
+
+/// Runs `cmd` with its output going to the build log.
+pub fn run_logged(cmd: &mut Command, name: &str) -> Result<(), BuildError> {
+    let status = redirect_output(cmd)?
+        .status()
+        .map_err(|e| BuildError::FailedToRun {
+            command: name.to_owned(),
+            cause: e,
+            hint: hint_for(name),
+        })?;
+
+    if !status.success() {
+        return Err(command_failed(name, status));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use crate::error::BuildError;
+use crate::{
+    build_lib_msvc, copy_to_build_dir, generate_headers_msvc, is_in_out_dir, is_msvc_target,
+    native_cache, sync_libs, unix,
+};
+use std::env;
+use std::path::PathBuf;
+
+/// Configures and builds a libyal library.
+///
+/// Extra `configure` arguments can also be given without touching the build script,
+/// using the `<LIBNAME>_CONFIGURE_ARGS` env var (e.g. `LIBFSNTFS_CONFIGURE_ARGS="--disable-nls"`).
+pub struct LibBuilder {
+    lib_path: PathBuf,
+    shared: bool,
+    configure_args: Vec<String>,
+    extra_flags: Vec<(String, String)>,
+    share_sublibraries: bool,
+}
+
+impl LibBuilder {
+    /// `lib_path` is the library's source folder (see `get_lib_and_copy_to_out_dir`).
+    pub fn new(lib_path: PathBuf) -> Self {
+        LibBuilder {
+            lib_path,
+            shared: false,
+            configure_args: vec![],
+            extra_flags: vec![],
+            share_sublibraries: false,
+        }
+    }
+
+    /// Build a shared library instead of a static one.
+    pub fn shared(mut self, shared: bool) -> Self {
+        self.shared = shared;
+        self
+    }
+
+    /// Pass an extra argument to `configure`.
+    pub fn configure_arg(mut self, arg: impl Into<String>) -> Self {
+        self.configure_args.push(arg.into());
+        self
+    }
+
+    /// Pass extra arguments to `configure`.
+    pub fn configure_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.configure_args
+            .extend(args.into_iter().map(|arg| arg.into()));
+        self
+    }
+
+    /// Append `flags` to the `var` flags (e.g. `CFLAGS` or `LDFLAGS`) `configure` runs with, after
+    /// those of the user (from `<var>_<target>`, `TARGET_<var>` or `<var>`). The shared
+    /// sublibraries are built with them as well. Only applies to autotools builds.
+    pub fn flags(mut self, var: impl Into<String>, flags: impl Into<String>) -> Self {
+        self.extra_flags.push((var.into(), flags.into()));
+        self
+    }
+
+    /// Use the `SHARED_SUBLIBRARIES` from the build cache of the workspace instead of building the
+    /// bundled copies, so crates depending on several libyal libraries build them only once.
+    /// Only applies to autotools builds.
+    pub fn share_sublibraries(mut self, share_sublibraries: bool) -> Self {
+        self.share_sublibraries = share_sublibraries;
+        self
+    }
+
+    /// Sync dependencies and build the lib.
+    pub fn sync_and_build(self) -> Result<PathBuf, BuildError> {
+        let builder = self.in_out_dir()?;
+
+        sync_libs(&builder.lib_path)?;
+
+        builder.build()
+    }
+
+    /// Build the lib for the current target.
+    /// Note, this will not sync dependencies, use `sync_and_build` for that.
+    /// This will also add the needed folder to the `link-search` path.
+    /// Return the "include" folder for the library (to be used by bindgen).
+    pub fn build(self) -> Result<PathBuf, BuildError> {
+        let builder = self.in_out_dir()?;
+
+        let mut configure_args = builder.configure_args;
+        configure_args.extend(configure_args_from_env(&builder.lib_path));
+
+        if is_msvc_target() {
+            build_lib_msvc(builder.lib_path, builder.shared, &configure_args)
+        } else {
+            if builder.share_sublibraries {
+                // A shared lib contains the sublibraries, a static one needs them linked as well.
+                configure_args.extend(native_cache::shared_sublibraries(
+                    !builder.shared,
+                    &builder.extra_flags,
+                )?);
+            }
+
+            unix::build_lib(
+                builder.lib_path,
+                builder.shared,
+                &configure_args,
+                &builder.extra_flags,
+            )
+        }
+    }
+
+    /// Only generate the headers of the lib, for bindings without building or linking it (the
+    /// `no-link` feature of the -sys crates).
+    /// Return the "include" folder for the library (to be used by bindgen).
+    pub fn generate_headers(self) -> Result<PathBuf, BuildError> {
+        let builder = self.in_out_dir()?;
+
+        if is_msvc_target() {
+            generate_headers_msvc(builder.lib_path)
+        } else {
+            let mut configure_args = builder.configure_args;
+            configure_args.extend(configure_args_from_env(&builder.lib_path));
+
+            unix::generate_headers(builder.lib_path, &configure_args, &builder.extra_flags)
+        }
+    }
+
+    /// Building writes into the source folder, so never build outside of `OUT_DIR`
+    /// (the crate's folder might be read-only, and concurrent builds would step on each other).
+    fn in_out_dir(mut self) -> Result<Self, BuildError> {
+        if !is_in_out_dir(&self.lib_path) {
+            self.lib_path = copy_to_build_dir(&self.lib_path)?;
+        }
+
+        Ok(self)
+    }
+}
+
+/// Reads whitespace separated `configure` arguments from `<LIBNAME>_CONFIGURE_ARGS`.
+fn configure_args_from_env(lib_path: &PathBuf) -> Vec<String> {
+    let lib_name = lib_path.file_name().unwrap().to_string_lossy();
+    let env_var_name = format!("{}_CONFIGURE_ARGS", lib_name.to_uppercase());
+
+    println!("cargo:rerun-if-env-changed={}", env_var_name);
+
+    env::var(env_var_name)
+        .map(|args| args.split_whitespace().map(|arg| arg.to_owned()).collect())
+        .unwrap_or_default()
+}
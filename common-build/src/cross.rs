@@ -0,0 +1,179 @@
+//! Helpers for building the C libraries for a target other than the host.
+use crate::error::{hint_for, BuildError};
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Converts a rust target triple to the triple autotools expects in `--host`.
+pub fn gnu_host_triple(target: &str) -> String {
+    match target {
+        "x86_64-pc-windows-gnu" => "x86_64-w64-mingw32".to_owned(),
+        "i686-pc-windows-gnu" => "i686-w64-mingw32".to_owned(),
+        "armv7-linux-androideabi" => "arm-linux-androideabi".to_owned(),
+        // Older `config.sub` scripts do not know about iOS, which is darwin as far as autotools cares.
+        _ if target.contains("apple-ios") => format!("{}-apple-darwin", apple_arch(target)),
+        _ => target.to_owned(),
+    }
+}
+
+/// Environment variables `configure` needs to pick up the right toolchain for `target`.
+pub fn configure_env(target: &str) -> Result<Vec<(String, String)>, BuildError> {
+    if target.contains("android") {
+        android_configure_env(target)
+    } else if target.contains("apple") {
+        apple_configure_env(target)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Extra clang arguments bindgen needs to parse the headers for `target`.
+pub fn bindgen_clang_args(target: &str) -> Result<Vec<String>, BuildError> {
+    if target.contains("android") {
+        let sysroot = android_toolchain_dir()?.join("sysroot");
+        Ok(vec![format!("--sysroot={}", sysroot.display())])
+    } else if target.contains("apple") {
+        Ok(vec!["-isysroot".to_owned(), apple_sdk_path(target)?])
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// The NDK API level to build against, taken from `ANDROID_API_LEVEL` (defaults to 21).
+fn android_api_level() -> String {
+    env::var("ANDROID_API_LEVEL").unwrap_or_else(|_| "21".to_owned())
+}
+
+/// The NDK's prebuilt LLVM toolchain directory for the host.
+/// The NDK is located using `ANDROID_NDK_HOME` (or `ANDROID_NDK_ROOT`).
+fn android_toolchain_dir() -> Result<PathBuf, BuildError> {
+    let ndk_path = env::var("ANDROID_NDK_HOME")
+        .or_else(|_| env::var("ANDROID_NDK_ROOT"))
+        .map_err(|_| BuildError::MissingEnvVar {
+            name: "ANDROID_NDK_HOME".to_owned(),
+            hint: "point it at the android NDK to build for android targets",
+        })?;
+
+    let host_tag = if cfg!(target_os = "windows") {
+        "windows-x86_64"
+    } else if cfg!(target_os = "macos") {
+        "darwin-x86_64"
+    } else {
+        "linux-x86_64"
+    };
+
+    Ok(PathBuf::from(ndk_path)
+        .join("toolchains")
+        .join("llvm")
+        .join("prebuilt")
+        .join(host_tag))
+}
+
+fn android_configure_env(target: &str) -> Result<Vec<(String, String)>, BuildError> {
+    let bin_dir = android_toolchain_dir()?.join("bin");
+
+    // The NDK names its clang wrappers after the target, with the API level appended.
+    let clang_prefix = match target {
+        "armv7-linux-androideabi" => "armv7a-linux-androideabi",
+        _ => target,
+    };
+
+    let cc = bin_dir.join(format!("{}{}-clang", clang_prefix, android_api_level()));
+
+    Ok(vec![
+        ("CC".to_owned(), cc.display().to_string()),
+        ("AR".to_owned(), bin_dir.join("llvm-ar").display().to_string()),
+        (
+            "RANLIB".to_owned(),
+            bin_dir.join("llvm-ranlib").display().to_string(),
+        ),
+    ])
+}
+
+/// The architecture name Apple's clang expects in `-arch`.
+fn apple_arch(target: &str) -> &'static str {
+    if target.starts_with("aarch64") {
+        "arm64"
+    } else {
+        "x86_64"
+    }
+}
+
+fn apple_sdk_name(target: &str) -> &'static str {
+    if target.contains("apple-ios") {
+        if target.ends_with("-sim") || target.starts_with("x86_64") {
+            "iphonesimulator"
+        } else {
+            "iphoneos"
+        }
+    } else {
+        "macosx"
+    }
+}
+
+fn xcrun(target: &str, args: &[&str]) -> Result<String, BuildError> {
+    let command = format!("xcrun {}", args.join(" "));
+
+    let output = Command::new("xcrun")
+        .arg("--sdk")
+        .arg(apple_sdk_name(target))
+        .args(args)
+        .output()
+        .map_err(|cause| BuildError::FailedToRun {
+            command: command.clone(),
+            cause,
+            hint: hint_for("xcrun"),
+        })?;
+
+    if !output.status.success() {
+        return Err(BuildError::CommandFailed {
+            command,
+            status: output.status,
+            log_tail: String::from_utf8_lossy(&output.stderr).into_owned(),
+            log_path: "(not logged)".to_owned(),
+            hint: hint_for("xcrun"),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// The SDK to build against, from `SDKROOT` or as reported by `xcrun`.
+fn apple_sdk_path(target: &str) -> Result<String, BuildError> {
+    match env::var("SDKROOT") {
+        Ok(sdk_path) => Ok(sdk_path),
+        Err(_) => xcrun(target, &["--show-sdk-path"]),
+    }
+}
+
+fn apple_configure_env(target: &str) -> Result<Vec<(String, String)>, BuildError> {
+    let min_version_flag = if target.contains("apple-ios") {
+        let version = env::var("IPHONEOS_DEPLOYMENT_TARGET").unwrap_or_else(|_| "9.0".to_owned());
+
+        if apple_sdk_name(target) == "iphonesimulator" {
+            format!("-mios-simulator-version-min={}", version)
+        } else {
+            format!("-miphoneos-version-min={}", version)
+        }
+    } else {
+        let version = env::var("MACOSX_DEPLOYMENT_TARGET").unwrap_or_else(|_| "11.0".to_owned());
+        format!("-mmacosx-version-min={}", version)
+    };
+
+    // Pin the architecture and SDK explicitly, so nothing from the host leaks into the build.
+    let target_flags = format!(
+        "-arch {} -isysroot {} {}",
+        apple_arch(target),
+        apple_sdk_path(target)?,
+        min_version_flag
+    );
+
+    let mut configure_env = vec![("CC".to_owned(), xcrun(target, &["--find", "clang"])?)];
+
+    // Appended to the flags of the user by `configure`.
+    for flags_var in ["CFLAGS", "LDFLAGS"].iter() {
+        configure_env.push((flags_var.to_string(), target_flags.clone()));
+    }
+
+    Ok(configure_env)
+}
@@ -0,0 +1,71 @@
+use failure::Fail;
+use std::io;
+use std::process::ExitStatus;
+
+#[derive(Fail, Debug)]
+pub enum BuildError {
+    #[fail(display = "Could not run {}: {}\nhint: {}", command, cause, hint)]
+    FailedToRun {
+        command: String,
+        #[cause]
+        cause: io::Error,
+        hint: &'static str,
+    },
+    #[fail(
+        display = "{} failed ({})\n\n{}\n\nThe full output is in {}\nhint: {}",
+        command, status, log_tail, log_path, hint
+    )]
+    CommandFailed {
+        command: String,
+        status: ExitStatus,
+        log_tail: String,
+        log_path: String,
+        hint: &'static str,
+    },
+    #[fail(display = "Could not find {}\nhint: {}", tool, hint)]
+    MissingTool { tool: String, hint: &'static str },
+    #[fail(display = "{} must be set\nhint: {}", name, hint)]
+    MissingEnvVar { name: String, hint: &'static str },
+    #[fail(display = "Expected {} to exist after the build, see {}", path, log_path)]
+    MissingBuildOutput { path: String, log_path: String },
+    #[fail(display = "Failed to fetch {} {}: {}", name, version, reason)]
+    FailedToFetch {
+        name: String,
+        version: String,
+        reason: String,
+    },
+    #[fail(display = "Failed to copy sources from {} into OUT_DIR: {}", path, reason)]
+    FailedToCopySources { path: String, reason: String },
+    #[fail(display = "{} is not a usable prebuilt library: {}", path, reason)]
+    InvalidPrebuilt { path: String, reason: String },
+    #[fail(display = "Failed to patch {}: {}", path, reason)]
+    FailedToPatch { path: String, reason: String },
+    #[fail(display = "Failed to generate bindings for {}", header)]
+    FailedToGenerateBindings { header: String },
+    #[fail(display = "An IO error has occurred: {}", _0)]
+    Io(#[cause] io::Error),
+}
+
+impl From<io::Error> for BuildError {
+    fn from(err: io::Error) -> Self {
+        BuildError::Io(err)
+    }
+}
+
+/// What the user can do about a failing build step.
+pub fn hint_for(step: &str) -> &'static str {
+    match step {
+        "synclibs" | "autogen" => {
+            "building from a git checkout requires git, autoconf, automake, autopoint (gettext), \
+             libtool and pkg-config. Alternatively, build from a release package (see `<LIBNAME>_VERSION`)"
+        }
+        "configure" => {
+            "make sure a C compiler is installed (and the target's toolchain when cross compiling)"
+        }
+        "make" | "make install" => "make sure make and a C compiler are installed",
+        "python" => "install python, or point `PYTHON_SYS_EXECUTABLE` at it",
+        "msbuild" => "install Visual Studio (or the Visual Studio build tools)",
+        "xcrun" => "install the Xcode command line tools (`xcode-select --install`)",
+        _ => "see the build output above",
+    }
+}
@@ -0,0 +1,127 @@
+//! Downloads libyal release source packages.
+//!
+//! Release packages come with their dependencies already synced and with `configure` generated,
+//! so they build without a git checkout or autotools.
+use crate::error::BuildError;
+use crate::random_build_dir;
+use failure::{bail, format_err, Error, ResultExt};
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs::{self, create_dir_all, File};
+use std::io::{self, Read};
+use std::path::PathBuf;
+use tar::Archive;
+
+/// The maturity status libyal puts in the release package name.
+fn release_status(name: &str) -> &'static str {
+    match name {
+        "libfsntfs" | "libvshadow" | "libewf" => "experimental",
+        _ => "alpha",
+    }
+}
+
+fn release_url(name: &str, version: &str) -> String {
+    format!(
+        "https://github.com/libyal/{name}/releases/download/{version}/{name}-{status}-{version}.tar.gz",
+        name = name,
+        version = version,
+        status = release_status(name)
+    )
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Archives are only unpacked once their checksum matched, those without one are rejected.
+fn verify(archive: &[u8], sha256: Option<&str>) -> Result<(), Error> {
+    let actual = sha256_hex(archive);
+
+    match sha256 {
+        Some(expected) if actual.eq_ignore_ascii_case(expected) => Ok(()),
+        Some(expected) => bail!(
+            "checksum mismatch, expected {} but got {}",
+            expected,
+            actual
+        ),
+        None => bail!(
+            "no SHA-256 is pinned for this release, pin {} (or set `<LIBNAME>_SHA256`) to use it",
+            actual
+        ),
+    }
+}
+
+fn download(url: &str) -> Result<Vec<u8>, Error> {
+    let response = ureq::get(url).call();
+
+    if !response.ok() {
+        bail!("GET {} returned {}", url, response.status());
+    }
+
+    let mut archive = vec![];
+    response
+        .into_reader()
+        .read_to_end(&mut archive)
+        .context(format!("reading {}", url))?;
+
+    Ok(archive)
+}
+
+/// Returns the release archive, from the cache under `OUT_DIR` if it is there (and valid).
+fn cached_archive(name: &str, version: &str, sha256: Option<&str>) -> Result<Vec<u8>, Error> {
+    let downloads_dir = PathBuf::from(env::var("OUT_DIR").unwrap()).join("downloads");
+    create_dir_all(&downloads_dir)?;
+
+    let archive_path = downloads_dir.join(format!("{}-{}.tar.gz", name, version));
+
+    if let Ok(archive) = fs::read(&archive_path) {
+        if verify(&archive, sha256).is_ok() {
+            return Ok(archive);
+        }
+    }
+
+    let url_env_var_name = format!("{}_URL", name.to_uppercase());
+    println!("cargo:rerun-if-env-changed={}", url_env_var_name);
+
+    let url = env::var(url_env_var_name).unwrap_or_else(|_| release_url(name, version));
+
+    let archive = download(&url)?;
+    verify(&archive, sha256).context(format!("verifying {}", url))?;
+
+    fs::write(&archive_path, &archive)?;
+
+    Ok(archive)
+}
+
+fn try_fetch_libyal(name: &str, version: &str, sha256: Option<&str>) -> Result<PathBuf, Error> {
+    let archive = cached_archive(name, version, sha256)?;
+
+    let build_dir = random_build_dir()?;
+    Archive::new(GzDecoder::new(io::Cursor::new(archive))).unpack(&build_dir)?;
+
+    // Packages unpack to `<name>-<version>`, but the rest of the build expects the folder to be named after the lib.
+    let unpacked_path = fs::read_dir(&build_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.is_dir())
+        .ok_or_else(|| format_err!("the release package of {} is empty", name))?;
+
+    let lib_path = build_dir.join(name);
+    fs::rename(&unpacked_path, &lib_path)?;
+
+    Ok(lib_path)
+}
+
+/// Download release `version` of libyal's `name` library and unpack it inside `OUT_DIR`.
+/// Downloads are cached under `OUT_DIR` and verified against `sha256`, without it they are
+/// rejected.
+/// The download URL can be overridden with `<LIBNAME>_URL`.
+/// Return the unpacked source folder.
+pub fn fetch_libyal(name: &str, version: &str, sha256: Option<&str>) -> Result<PathBuf, BuildError> {
+    try_fetch_libyal(name, version, sha256).map_err(|e| BuildError::FailedToFetch {
+        name: name.to_owned(),
+        version: version.to_owned(),
+        reason: e.to_string(),
+    })
+}
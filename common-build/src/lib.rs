@@ -1,41 +1,118 @@
-#[cfg(not(target_os = "windows"))]
-mod posix;
-
-#[cfg(not(target_os = "windows"))]
-pub use crate::posix::{build_lib, sync_libs};
+mod build_log;
+mod builder;
+mod cross;
+mod error;
+mod fetch;
+mod native_cache;
+mod unix;
 
 #[cfg(target_os = "windows")]
 mod windows;
 
-#[cfg(target_os = "windows")]
-pub use crate::windows::{build_lib, sync_libs};
+pub use crate::builder::LibBuilder;
+pub use crate::error::BuildError;
+pub use crate::fetch::fetch_libyal;
+pub use crate::native_cache::SHARED_SUBLIBRARIES;
 
+use bindgen::Bindings;
 use fs_extra::dir::{copy, CopyOptions};
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use std::env;
-use std::fs::{create_dir, create_dir_all};
+use std::fs::{self, create_dir, create_dir_all};
 use std::path::PathBuf;
 
-/// Sync dependencies and build the lib.
-/// See `build_lib` for more.
-pub fn sync_and_build_lib(lib_path: PathBuf, shared: bool) -> PathBuf {
-    sync_libs(&lib_path);
+/// Whether the crate is being built for an MSVC target.
+/// Every other target (including `*-windows-gnu`) is built with autotools.
+pub fn is_msvc_target() -> bool {
+    env::var("CARGO_CFG_TARGET_ENV")
+        .map(|target_env| target_env == "msvc")
+        .unwrap_or(false)
+}
 
-    build_lib(lib_path, shared)
+/// Synchronizes the local library dependencies.
+pub fn sync_libs(lib_path: &PathBuf) -> Result<(), BuildError> {
+    if is_msvc_target() {
+        sync_libs_msvc(lib_path)
+    } else {
+        unix::sync_libs(lib_path)
+    }
 }
 
-/// Find the library (based on env var or using the local submodule),
-/// copy it to the output folder and return the copied folder's path.
-pub fn get_lib_and_copy_to_out_dir(lib_name: &str) -> PathBuf {
-    let lib_path =
-        if let Ok(local_install) = env::var(format!("{}_LIBPATH", lib_name.to_uppercase())) {
-            PathBuf::from(local_install)
-        } else {
-            // For each `-sys` package, we expect the lib to be next to the Cargo.toml file.
-            PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join(lib_name)
-        };
+/// Build the lib for the current target.
+/// See `LibBuilder::build` for more.
+pub fn build_lib(
+    lib_path: PathBuf,
+    shared: bool,
+    configure_args: &[&str],
+) -> Result<PathBuf, BuildError> {
+    LibBuilder::new(lib_path)
+        .shared(shared)
+        .configure_args(configure_args.iter().cloned())
+        .build()
+}
+
+#[cfg(target_os = "windows")]
+fn sync_libs_msvc(lib_path: &PathBuf) -> Result<(), BuildError> {
+    windows::sync_libs(lib_path)
+}
+
+#[cfg(target_os = "windows")]
+fn build_lib_msvc(
+    lib_path: PathBuf,
+    shared: bool,
+    configure_args: &[String],
+) -> Result<PathBuf, BuildError> {
+    windows::build_lib(lib_path, shared, configure_args)
+}
+
+#[cfg(target_os = "windows")]
+fn generate_headers_msvc(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    windows::generate_headers(lib_path)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn generate_headers_msvc(_lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    Err(msbuild_unavailable())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn sync_libs_msvc(_lib_path: &PathBuf) -> Result<(), BuildError> {
+    Err(msbuild_unavailable())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn build_lib_msvc(
+    _lib_path: PathBuf,
+    _shared: bool,
+    _configure_args: &[String],
+) -> Result<PathBuf, BuildError> {
+    Err(msbuild_unavailable())
+}
 
+#[cfg(not(target_os = "windows"))]
+fn msbuild_unavailable() -> BuildError {
+    BuildError::MissingTool {
+        tool: "msbuild".to_owned(),
+        hint: "MSVC targets can only be built on a Windows host, use a `*-windows-gnu` target instead",
+    }
+}
+
+/// Sync dependencies and build the lib.
+/// See `LibBuilder::sync_and_build` for more.
+pub fn sync_and_build_lib(
+    lib_path: PathBuf,
+    shared: bool,
+    configure_args: &[&str],
+) -> Result<PathBuf, BuildError> {
+    LibBuilder::new(lib_path)
+        .shared(shared)
+        .configure_args(configure_args.iter().cloned())
+        .sync_and_build()
+}
+
+/// Create a new (randomly named) folder inside `OUT_DIR` to build in.
+fn random_build_dir() -> Result<PathBuf, BuildError> {
     let rand_folder_name: String = thread_rng().sample_iter(&Alphanumeric).take(6).collect();
 
     let build_out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
@@ -43,37 +120,352 @@ pub fn get_lib_and_copy_to_out_dir(lib_name: &str) -> PathBuf {
     // We have to use a random build dir because multiple builds of the same lib might happen at the same time.
     let random_build_dir = build_out_dir.join(rand_folder_name);
 
-    create_dir_all(&random_build_dir).unwrap();
+    create_dir_all(&random_build_dir)?;
+
+    Ok(random_build_dir)
+}
+
+fn local_lib_path(lib_name: &str) -> PathBuf {
+    if let Ok(local_install) = env::var(format!("{}_LIBPATH", lib_name.to_uppercase())) {
+        PathBuf::from(local_install)
+    } else {
+        // For each `-sys` package, we expect the lib to be next to the Cargo.toml file.
+        PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join(lib_name)
+    }
+}
+
+/// Find the library sources and return a copy of them inside `OUT_DIR`.
+/// Uses `<LIBNAME>_LIBPATH` or the local submodule when there is one,
+/// otherwise fetches the pinned release `version` (see `fetch_libyal`).
+/// `<LIBNAME>_VERSION` overrides the pinned version (and `<LIBNAME>_SHA256` its checksum, which
+/// must be set along with it).
+pub fn get_lib_or_fetch(
+    lib_name: &str,
+    version: &str,
+    sha256: Option<&str>,
+) -> Result<PathBuf, BuildError> {
+    let env_prefix = lib_name.to_uppercase();
+
+    for env_var_name in ["LIBPATH", "VERSION", "SHA256"].iter() {
+        println!("cargo:rerun-if-env-changed={}_{}", env_prefix, env_var_name);
+    }
+
+    let local_path = local_lib_path(lib_name);
 
-    let copied_lib_path = random_build_dir.join(lib_name);
-    let _ = std::fs::remove_dir_all(&copied_lib_path);
+    // An uninitialized submodule is just an empty folder.
+    let has_local_sources = local_path
+        .read_dir()
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
 
-    copy(&lib_path, &random_build_dir, &CopyOptions::new()).expect(&format!(
-        "Error while copying sources from {:?} to `OUT_DIR` {:?}",
-        &lib_path, &random_build_dir
-    ));
+    if has_local_sources {
+        return get_lib_and_copy_to_out_dir(lib_name);
+    }
 
-    copied_lib_path
+    let (version, sha256) = match env::var(format!("{}_VERSION", env_prefix)) {
+        Ok(version) => (version, env::var(format!("{}_SHA256", env_prefix)).ok()),
+        Err(_) => (
+            version.to_owned(),
+            env::var(format!("{}_SHA256", env_prefix))
+                .ok()
+                .or_else(|| sha256.map(|s| s.to_owned())),
+        ),
+    };
+
+    fetch_libyal(lib_name, &version, sha256.as_ref().map(|s| s.as_str()))
 }
 
-pub fn generate_bindings(include_folder_path: &PathBuf, header_file_name: &str) {
-    // The bindgen::Builder is the main entry point
-    // to bindgen, and lets you build up options for
-    // the resulting bindings.
-    let bindings = bindgen::Builder::default()
+/// The prebuilt library to use instead of fetching and building it, from the
+/// `<LIBNAME>_PREBUILT` env var (e.g. `LIBFSNTFS_PREBUILT`): a folder with the static or
+/// dynamic libraries in `lib` and the headers in `include`, as `make install` lays them out.
+/// Adds the `lib` folder to the `link-search` path.
+/// Return the "include" folder for the library (to be used by bindgen).
+pub fn get_prebuilt_lib(lib_name: &str) -> Result<Option<PathBuf>, BuildError> {
+    let env_var_name = format!("{}_PREBUILT", lib_name.to_uppercase());
+    println!("cargo:rerun-if-env-changed={}", env_var_name);
+
+    let prebuilt_path = match env::var_os(env_var_name) {
+        Some(prebuilt_path) => PathBuf::from(prebuilt_path),
+        None => return Ok(None),
+    };
+
+    let invalid = |reason: String| BuildError::InvalidPrebuilt {
+        path: prebuilt_path.display().to_string(),
+        reason,
+    };
+
+    let include_path = prebuilt_path.join("include");
+    let header_path = include_path.join(format!("{}.h", lib_name));
+    let lib_dir = prebuilt_path.join("lib");
+
+    if !header_path.exists() {
+        return Err(invalid(format!("{} is missing", header_path.display())));
+    }
+
+    if !lib_dir.is_dir() {
+        return Err(invalid(format!("{} is missing", lib_dir.display())));
+    }
+
+    println!(
+        "cargo:rustc-link-search=native={}",
+        lib_dir.canonicalize()?.to_string_lossy()
+    );
+
+    Ok(Some(include_path))
+}
+
+/// Find the library (based on env var or using the local submodule),
+/// copy it to the output folder and return the copied folder's path.
+pub fn get_lib_and_copy_to_out_dir(lib_name: &str) -> Result<PathBuf, BuildError> {
+    copy_to_build_dir(&local_lib_path(lib_name))
+}
+
+/// Whether `path` is inside `OUT_DIR`, the only place a build script should write to.
+fn is_in_out_dir(path: &PathBuf) -> bool {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    match (path.canonicalize(), out_dir.canonicalize()) {
+        (Ok(path), Ok(out_dir)) => path.starts_with(out_dir),
+        _ => false,
+    }
+}
+
+/// Copy the sources at `lib_path` to a new build dir inside `OUT_DIR` and return the copied folder's path.
+/// The sources might be read-only (e.g. a registry checkout), so the copy is made writable.
+fn copy_to_build_dir(lib_path: &PathBuf) -> Result<PathBuf, BuildError> {
+    let random_build_dir = random_build_dir()?;
+
+    let copied_lib_path = random_build_dir.join(lib_path.file_name().unwrap());
+
+    copy(&lib_path, &random_build_dir, &CopyOptions::new()).map_err(|e| {
+        BuildError::FailedToCopySources {
+            path: lib_path.display().to_string(),
+            reason: e.to_string(),
+        }
+    })?;
+
+    make_writable(&copied_lib_path)?;
+
+    Ok(copied_lib_path)
+}
+
+fn make_writable(path: &PathBuf) -> Result<(), BuildError> {
+    let metadata = fs::metadata(path)?;
+
+    let mut permissions = metadata.permissions();
+
+    if permissions.readonly() {
+        permissions.set_readonly(false);
+        fs::set_permissions(path, permissions)?;
+    }
+
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            make_writable(&entry?.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Types of the C library which the libyal headers use, taken from the `libc` crate so the
+/// bindings of different -sys crates (and of other crates) agree on them.
+const LIBC_TYPES: &[&str] = &["FILE"];
+
+/// Definitions the headers of the C library pull in, which would otherwise end up (and clash)
+/// in every -sys crate. Not used by the libyal APIs.
+const LIBC_INTERNAL_TYPES: &[&str] = &[
+    "_IO_.*",
+    "__fsid_t",
+    "__mbstate_t",
+    "__.*va_list.*",
+    "_G_fpos.*_t",
+    "fpos_t",
+    "max_align_t",
+    "va_list",
+];
+
+/// A bindgen builder for `header_file_name`, with the clang arguments of the target and the
+/// C types taken from `libc`.
+fn bindings_builder(
+    include_folder_path: &PathBuf,
+    header_file_name: &str,
+) -> Result<bindgen::Builder, BuildError> {
+    let mut clang_args = vec![format!("-I{}", include_folder_path.to_string_lossy())];
+
+    // When cross compiling, make sure clang parses the headers for the target and not the host.
+    let target = env::var("TARGET").unwrap();
+    if target != env::var("HOST").unwrap() {
+        clang_args.push(format!("--target={}", target));
+        clang_args.extend(cross::bindgen_clang_args(&target)?);
+    }
+
+    let mut builder = bindgen::Builder::default()
         // The input header we would like to generate
         // bindings for.
-        .clang_args(&[format!("-I{}", include_folder_path.to_string_lossy())])
+        .clang_args(&clang_args)
         .header(header_file_name)
-        // Finish the builder and generate the bindings.
+        // C types come from `libc` (which takes `c_void` from `core::ffi`), the same types
+        // in every -sys crate.
+        .use_core()
+        .ctypes_prefix("::libc");
+
+    for libc_type in LIBC_TYPES.iter().chain(LIBC_INTERNAL_TYPES) {
+        builder = builder.blacklist_type(libc_type);
+    }
+
+    Ok(builder)
+}
+
+fn generate(builder: bindgen::Builder, header_file_name: &str) -> Result<Bindings, BuildError> {
+    builder
         .generate()
-        // Unwrap the Result and panic on failure.
-        .expect("Unable to generate bindings");
+        .map_err(|_| BuildError::FailedToGenerateBindings {
+            header: header_file_name.to_owned(),
+        })
+}
+
+fn libc_types_line() -> String {
+    format!("pub use libc::{{{}}};", LIBC_TYPES.join(", "))
+}
+
+pub fn generate_bindings(
+    include_folder_path: &PathBuf,
+    header_file_name: &str,
+) -> Result<(), BuildError> {
+    let builder =
+        bindings_builder(include_folder_path, header_file_name)?.raw_line(libc_types_line());
+    let bindings = generate(builder, header_file_name)?;
 
     // Write the bindings to the $OUT_DIR/bindings.rs file.
     let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
 
-    bindings
-        .write_to_file(out_path.join("bindings.rs"))
-        .expect("Couldn't write bindings!");
+    bindings.write_to_file(out_path.join("bindings.rs"))?;
+
+    Ok(())
+}
+
+/// Like `generate_bindings`, split into `$OUT_DIR/types.rs`, with every type and constant, and
+/// a file per module of `modules`, with the functions `module_of` places in it. The function
+/// files refer to the types by name, they are meant to be included next to
+/// `use super::types::*;`.
+pub fn generate_split_bindings<F>(
+    include_folder_path: &PathBuf,
+    header_file_name: &str,
+    modules: &[&str],
+    module_of: F,
+) -> Result<(), BuildError>
+where
+    F: Fn(&str) -> &'static str,
+{
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    let builder = bindings_builder(include_folder_path, header_file_name)?
+        .raw_line(libc_types_line())
+        .ignore_functions();
+    generate(builder, header_file_name)?.write_to_file(out_path.join("types.rs"))?;
+
+    let all_bindings = generate(
+        bindings_builder(include_folder_path, header_file_name)?,
+        header_file_name,
+    )?
+    .to_string();
+
+    for module in modules {
+        let functions: Vec<&str> = function_names(&all_bindings)
+            .filter(|function| module_of(function) == *module)
+            .collect();
+        let module_path = out_path.join(format!("{}.rs", module));
+
+        if functions.is_empty() {
+            fs::write(&module_path, "")?;
+            continue;
+        }
+
+        let mut builder = bindings_builder(include_folder_path, header_file_name)?
+            .blacklist_type(".*")
+            .layout_tests(false);
+
+        for function in functions {
+            builder = builder.whitelist_function(function);
+        }
+
+        generate(builder, header_file_name)?.write_to_file(module_path)?;
+    }
+
+    Ok(())
+}
+
+/// Writes `$OUT_DIR/loader.rs`, a `Library` struct with a field per function matching
+/// `function_pattern` (a regex), which loads them from a shared library at runtime.
+/// Like the split bindings, it is meant to be included next to `use super::types::*;`, and
+/// needs the `libloading` crate.
+pub fn generate_runtime_loader(
+    include_folder_path: &PathBuf,
+    header_file_name: &str,
+    function_pattern: &str,
+) -> Result<(), BuildError> {
+    let builder = bindings_builder(include_folder_path, header_file_name)?
+        .blacklist_type(".*")
+        .layout_tests(false)
+        .whitelist_function(function_pattern);
+    let bindings = generate(builder, header_file_name)?.to_string();
+
+    let mut fields = String::new();
+    let mut loads = String::new();
+    let mut names = String::new();
+
+    for declaration in bindings.split("pub fn ").skip(1) {
+        let declaration = declaration.split(';').next().unwrap_or_default();
+        let signature_start = declaration.find('(').unwrap_or_else(|| declaration.len());
+        let (name, signature) = declaration.split_at(signature_start);
+
+        fields.push_str(&format!(
+            "    pub {}: unsafe extern \"C\" fn{},\n",
+            name, signature
+        ));
+        loads.push_str(&format!(
+            "        let {0} = *library.get(b\"{0}\\0\")?;\n",
+            name
+        ));
+        names.push_str(&format!("            {},\n", name));
+    }
+
+    let loader = format!(
+        r#"/// The functions, loaded from a shared library when the library is opened.
+pub struct Library {{
+    // Kept loaded for as long as the functions can be called.
+    _library: ::libloading::Library,
+{}}}
+
+impl Library {{
+    /// Loads the shared library at `path`, fails if it lacks any of the functions.
+    ///
+    /// Unsafe because the library runs its initialization code, and must match the headers
+    /// the bindings were generated from.
+    pub unsafe fn open<P: AsRef<::std::ffi::OsStr>>(path: P) -> ::std::io::Result<Self> {{
+        let library = ::libloading::Library::new(path)?;
+{}
+        Ok(Library {{
+{}            _library: library,
+        }})
+    }}
+}}
+"#,
+        fields, loads, names
+    );
+
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_path.join("loader.rs"), loader)?;
+
+    Ok(())
+}
+
+/// The names of the functions declared in `bindings`.
+fn function_names(bindings: &str) -> impl Iterator<Item = &str> {
+    bindings.split("pub fn ").skip(1).filter_map(|declaration| {
+        declaration
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .next()
+    })
 }
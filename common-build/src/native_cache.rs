@@ -0,0 +1,181 @@
+//! A build cache shared by all libyal `-sys` crates of a workspace, for the sublibraries every
+//! libyal library bundles (e.g. libcerror). Each is built once per version, target and compiler
+//! flags (e.g. those of the `asan` feature), and the libraries are configured to use it
+//! (`--with-<sublibrary>=<dir>`) instead of their own copy.
+//!
+//! The cache lives next to the build folders of the cargo profile,
+//! or in `LIBYAL_NATIVE_CACHE_DIR` when it is set.
+use crate::error::BuildError;
+use crate::fetch::fetch_libyal;
+use crate::unix;
+use fs_extra::dir::{copy, CopyOptions};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs::{self, create_dir_all};
+use std::path::PathBuf;
+
+/// The sublibraries which are shared, with the release used for each and the SHA-256 of its
+/// package. Releases without a pinned SHA-256 are only fetched when `<SUBLIBRARY>_SHA256` gives it,
+/// otherwise the libraries keep using their own copy of the sublibrary.
+/// `<SUBLIBRARY>_VERSION` (e.g. `LIBCERROR_VERSION`) overrides the release, along with
+/// `<SUBLIBRARY>_SHA256`.
+pub const SHARED_SUBLIBRARIES: [(&str, &str, Option<&str>); 3] = [
+    ("libcerror", "20201121", None),
+    ("libcdata", "20200509", None),
+    ("libcthreads", "20200508", None),
+];
+
+/// The env vars which change how the sublibraries are compiled.
+const BUILD_FLAGS_ENV_VARS: [&str; 5] =
+    ["CC", "CFLAGS", "CPPFLAGS", "LDFLAGS", "CARGO_FEATURE_ASAN"];
+
+fn cache_dir() -> PathBuf {
+    println!("cargo:rerun-if-env-changed=LIBYAL_NATIVE_CACHE_DIR");
+
+    match env::var_os("LIBYAL_NATIVE_CACHE_DIR") {
+        Some(cache_dir) => PathBuf::from(cache_dir),
+        // `OUT_DIR` is `<target dir>/<profile>/build/<crate>-<hash>/out`.
+        None => PathBuf::from(env::var("OUT_DIR").unwrap())
+            .ancestors()
+            .nth(3)
+            .unwrap()
+            .join("libyal-native"),
+    }
+}
+
+/// Return the `configure` arguments of the shared sublibraries, building those which are not
+/// cached yet, and add them to the `link-search` path. Sublibraries whose release is not pinned
+/// are left out (with a warning), the library is then built with the copy it bundles.
+/// When `link` is set (the library is linked statically), also link them.
+/// They are built with the `extra_flags` of the library (see `LibBuilder::flags`).
+pub fn shared_sublibraries(
+    link: bool,
+    extra_flags: &[(String, String)],
+) -> Result<Vec<String>, BuildError> {
+    let mut configure_args = vec![];
+
+    for (name, version, sha256) in SHARED_SUBLIBRARIES.iter() {
+        let env_prefix = name.to_uppercase();
+
+        for env_var_name in ["VERSION", "SHA256"].iter() {
+            println!("cargo:rerun-if-env-changed={}_{}", env_prefix, env_var_name);
+        }
+
+        let sha256_override = env::var(format!("{}_SHA256", env_prefix)).ok();
+        let (version, sha256) = match env::var(format!("{}_VERSION", env_prefix)) {
+            Ok(version) => (version, sha256_override),
+            Err(_) => (
+                version.to_string(),
+                sha256_override.or_else(|| sha256.map(|s| s.to_owned())),
+            ),
+        };
+
+        let sha256 = match sha256 {
+            Some(sha256) => sha256,
+            None => {
+                println!(
+                    "cargo:warning=The {} {} release is not pinned (see `{}_SHA256`), \
+                     using the copy bundled with each library instead.",
+                    name, version, env_prefix
+                );
+                continue;
+            }
+        };
+
+        let prefix = get_or_build(name, &version, &sha256, extra_flags)?;
+
+        println!(
+            "cargo:rustc-link-search=native={}",
+            prefix.join("lib").display()
+        );
+
+        if link {
+            println!(
+                "cargo:rustc-link-lib=static={}",
+                name.trim_start_matches("lib")
+            );
+        }
+
+        configure_args.push(format!("--with-{}={}", name, prefix.display()));
+    }
+
+    Ok(configure_args)
+}
+
+/// A short hash of the `BUILD_FLAGS_ENV_VARS` and `extra_flags`, part of the cache key.
+fn build_flags_hash(extra_flags: &[(String, String)]) -> String {
+    let mut hasher = Sha256::new();
+
+    for env_var_name in BUILD_FLAGS_ENV_VARS.iter() {
+        println!("cargo:rerun-if-env-changed={}", env_var_name);
+
+        hasher.input(env_var_name.as_bytes());
+        hasher.input(b"=");
+        hasher.input(env::var(env_var_name).unwrap_or_default().as_bytes());
+        hasher.input(b"\0");
+    }
+
+    for (var, flags) in extra_flags {
+        hasher.input(var.as_bytes());
+        hasher.input(b"+=");
+        hasher.input(flags.as_bytes());
+        hasher.input(b"\0");
+    }
+
+    format!("{:x}", hasher.result())[..16].to_owned()
+}
+
+/// Return the folder `name` is installed to in the cache, building it first if needed.
+fn get_or_build(
+    name: &str,
+    version: &str,
+    sha256: &str,
+    extra_flags: &[(String, String)],
+) -> Result<PathBuf, BuildError> {
+    let cache_dir = cache_dir();
+    let target = env::var("TARGET").unwrap();
+    let prefix = cache_dir.join(format!(
+        "{}-{}-{}-{}",
+        name,
+        version,
+        target,
+        build_flags_hash(extra_flags)
+    ));
+
+    if prefix.join("lib").exists() {
+        return Ok(prefix);
+    }
+
+    let lib_path = fetch_libyal(name, version, Some(sha256))?;
+
+    // Position independent, so it can also end up in shared libraries.
+    let installed_path =
+        unix::install_lib(&lib_path, false, &["--with-pic".to_owned()], extra_flags)?;
+
+    // Other crates might be building the same sublibrary at the same time, install it under a
+    // unique name and move it in place (which fails if another build got there first).
+    let rand_folder_name: String = thread_rng().sample_iter(&Alphanumeric).take(6).collect();
+    let staging_dir = cache_dir.join(format!(".{}", rand_folder_name));
+    create_dir_all(&staging_dir)?;
+
+    copy(&installed_path, &staging_dir, &CopyOptions::new()).map_err(|e| {
+        BuildError::FailedToCopySources {
+            path: installed_path.display().to_string(),
+            reason: e.to_string(),
+        }
+    })?;
+
+    let staged_path = staging_dir.join(installed_path.file_name().unwrap());
+
+    if let Err(e) = fs::rename(&staged_path, &prefix) {
+        if !prefix.join("lib").exists() {
+            return Err(e.into());
+        }
+    }
+
+    fs::remove_dir_all(&staging_dir)?;
+
+    Ok(prefix)
+}
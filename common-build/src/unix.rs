@@ -0,0 +1,206 @@
+//! The autotools build flow shared by all libyal `-sys` crates:
+//! synclibs, autogen, configure, make and make install.
+use crate::build_log::{log_file_path, run_logged};
+use crate::cross::{configure_env, gnu_host_triple};
+use crate::error::BuildError;
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Runs `cmd` inside `lib_path`, logging its output.
+fn run(cmd: &mut Command, lib_path: &PathBuf, name: &str) -> Result<(), BuildError> {
+    run_logged(cmd.current_dir(&lib_path), name)
+}
+
+/// Synchronizes the local library dependencies.
+/// Release packages already contain them, so there is nothing to do for those.
+pub fn sync_libs(lib_path: &PathBuf) -> Result<(), BuildError> {
+    if !lib_path.join("synclibs.sh").exists() {
+        return Ok(());
+    }
+
+    run(
+        Command::new("sh").arg("synclibs.sh"),
+        lib_path,
+        "synclibs",
+    )
+}
+
+/// Build the lib using configure and make.
+/// Used for every non-MSVC target, including cross builds (e.g. `x86_64-pc-windows-gnu` with mingw-w64).
+/// Note, this function will not sync dependencies. use `sync_libs` or `sync_and_build_lib`.
+/// This function will also add the needed folder to the `link-search` path.
+/// `configure_args` are passed as-is to the `configure` script, `extra_flags` are appended to
+/// the flags variables of the user (e.g. `("CFLAGS", "-fsanitize=address")`).
+/// Return the "include" folder for the library (to be used by bindgen).
+pub fn build_lib(
+    lib_path: PathBuf,
+    shared: bool,
+    configure_args: &[String],
+    extra_flags: &[(String, String)],
+) -> Result<PathBuf, BuildError> {
+    let target = install_lib(&lib_path, shared, configure_args, extra_flags)?;
+    let lib_dir = target.join("lib");
+
+    println!(
+        "cargo:rustc-link-search=native={}",
+        lib_dir.canonicalize()?.to_string_lossy()
+    );
+
+    Ok(target.join("include"))
+}
+
+/// Configures, builds and installs the lib, return the folder it was installed to
+/// (with the libraries in `lib` and the headers in `include`).
+pub fn install_lib(
+    lib_path: &PathBuf,
+    shared: bool,
+    configure_args: &[String],
+    extra_flags: &[(String, String)],
+) -> Result<PathBuf, BuildError> {
+    let target = configure(lib_path, shared, configure_args, extra_flags)?;
+
+    run(&mut Command::new("make"), lib_path, "make")?;
+    run(
+        Command::new("make").arg("install"),
+        lib_path,
+        "make install",
+    )?;
+
+    let lib_dir = target.join("lib");
+
+    if !lib_dir.exists() {
+        return Err(BuildError::MissingBuildOutput {
+            path: lib_dir.display().to_string(),
+            log_path: log_file_path().display().to_string(),
+        });
+    }
+
+    Ok(target)
+}
+
+/// Configures the lib without building it, which generates its headers (`.h.in` -> `.h`).
+/// Return the "include" folder of the sources (to be used by bindgen).
+pub fn generate_headers(
+    lib_path: PathBuf,
+    configure_args: &[String],
+    extra_flags: &[(String, String)],
+) -> Result<PathBuf, BuildError> {
+    configure(&lib_path, false, configure_args, extra_flags)?;
+
+    Ok(lib_path.join("include"))
+}
+
+/// Flags variables `configure` passes to the compiler and linker, `CFLAGS` first.
+const FLAGS_VARS: [&str; 3] = ["CFLAGS", "CPPFLAGS", "LDFLAGS"];
+
+/// The flags of the user for `var`, from the most specific of `<var>_<target>`,
+/// `<var>_<target_with_underscores>`, `TARGET_<var>` and `<var>` (like the `cc` crate).
+fn user_flags(var: &str, target: &str) -> String {
+    let candidates = [
+        format!("{}_{}", var, target),
+        format!("{}_{}", var, target.replace('-', "_")),
+        format!("TARGET_{}", var),
+        var.to_owned(),
+    ];
+
+    for candidate in candidates.iter() {
+        println!("cargo:rerun-if-env-changed={}", candidate);
+    }
+
+    candidates
+        .iter()
+        .filter_map(|candidate| env::var(candidate).ok())
+        .next()
+        .unwrap_or_default()
+}
+
+/// The optimization level and debug info of the cargo profile, instead of the `-g -O2`
+/// configure picks by default, so debug builds of the C code can be debugged.
+fn profile_cflags() -> String {
+    let opt_level = match env::var("OPT_LEVEL") {
+        // gcc only knows `-Oz` since version 12.
+        Ok(ref level) if level == "z" => "s".to_owned(),
+        Ok(level) => level,
+        Err(_) => "0".to_owned(),
+    };
+
+    let mut flags = format!("-O{}", opt_level);
+
+    let debug = env::var("DEBUG")
+        .map(|debug| debug != "false")
+        .unwrap_or(false);
+
+    if debug {
+        flags.push_str(" -g");
+    }
+
+    flags
+}
+
+/// Runs `configure`, return the folder `make install` installs to.
+fn configure(
+    lib_path: &PathBuf,
+    shared: bool,
+    configure_args: &[String],
+    extra_flags: &[(String, String)],
+) -> Result<PathBuf, BuildError> {
+    let target = lib_path.join("dist");
+
+    // Release packages ship with a generated `configure` script.
+    if !lib_path.join("configure").exists() {
+        run(Command::new("sh").arg("autogen.sh"), &lib_path, "autogen")?;
+    }
+
+    let mut configure_cmd = Command::new("sh");
+
+    configure_cmd
+        .arg("configure")
+        .arg(format!("--prefix={}", target.display()));
+
+    if !shared {
+        configure_cmd.arg("--enable-shared=no");
+    }
+
+    let target_triple = env::var("TARGET").unwrap();
+
+    let mut flags: Vec<(&str, String)> = FLAGS_VARS
+        .iter()
+        .map(|var| (*var, user_flags(var, &target_triple)))
+        .collect();
+
+    // The flags of the user come last, so they win over the profile.
+    flags[0].1 = format!("{} {}", profile_cflags(), flags[0].1);
+
+    if target_triple != env::var("HOST").unwrap() {
+        configure_cmd.arg(format!("--host={}", gnu_host_triple(&target_triple)));
+
+        for (var, value) in configure_env(&target_triple)? {
+            match flags.iter_mut().find(|(flags_var, _)| *flags_var == var) {
+                Some(flag) => flag.1 = format!("{} {}", flag.1, value),
+                None => {
+                    configure_cmd.env(var, value);
+                }
+            }
+        }
+    }
+
+    for (var, value) in extra_flags {
+        match flags.iter_mut().find(|(flags_var, _)| *flags_var == var.as_str()) {
+            Some(flag) => flag.1 = format!("{} {}", flag.1, value),
+            None => {
+                configure_cmd.env(var, value);
+            }
+        }
+    }
+
+    for (var, value) in flags {
+        configure_cmd.env(var, value.trim());
+    }
+
+    configure_cmd.args(configure_args);
+
+    run(&mut configure_cmd, &lib_path, "configure")?;
+
+    Ok(target)
+}
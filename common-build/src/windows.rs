@@ -1,51 +1,54 @@
+use crate::build_log::{command_failed, log_file_path, redirect_output, run_logged};
+use crate::error::{hint_for, BuildError};
 use encoding_rs_io::DecodeReaderBytesBuilder;
-use failure::{bail, Error};
 use std::env;
 use std::fs::{remove_dir_all, File};
 use std::io;
 use std::io::{Read, Write};
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
+use std::process::Command;
 use walkdir::WalkDir;
 
 /// Synchronizes the local library dependencies.
-pub fn sync_libs(lib_path: &PathBuf) {
-    let status = Command::new("powershell")
-        .arg("-NoProfile")
-        .arg("-ExecutionPolicy")
-        .arg("Bypass")
-        .arg("-File")
-        .arg("synclibs.ps1")
-        .current_dir(&lib_path)
-        .stderr(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .status()
-        .expect("synclibs failed");
+/// Release packages already contain them, so there is nothing to do for those.
+pub fn sync_libs(lib_path: &PathBuf) -> Result<(), BuildError> {
+    if !lib_path.join("synclibs.ps1").exists() {
+        return Ok(());
+    }
 
-    assert!(status.success(), "synclibs failed");
+    run_logged(
+        Command::new("powershell")
+            .arg("-NoProfile")
+            .arg("-ExecutionPolicy")
+            .arg("Bypass")
+            .arg("-File")
+            .arg("synclibs.ps1")
+            .current_dir(&lib_path),
+        "synclibs",
+    )
 }
 
 /// Build the lib on windows (using msbuild and libyal's vstools).
 /// Note, this function will not sync dependencies. use `sync_libs` or `sync_and_build_lib`.
 /// Require python to be installed.
 /// This function will also add the needed folder to the `link-search` path.
+/// `configure_args` only apply to autotools builds and are ignored here.
 /// Return the "include" folder for the library (to be used by bindgen).
-pub fn build_lib(lib_path: PathBuf, shared: bool) -> PathBuf {
-    let python_exec = env::var("PYTHON_SYS_EXECUTABLE").unwrap_or_else(|_| "python.exe".to_owned());
+pub fn build_lib(
+    lib_path: PathBuf,
+    shared: bool,
+    configure_args: &[String],
+) -> Result<PathBuf, BuildError> {
+    if !configure_args.is_empty() {
+        println!(
+            "cargo:warning=Ignoring configure arguments {:?} for msbuild build",
+            configure_args
+        );
+    }
 
-    let status = Command::new("powershell")
-        .arg("-NoProfile")
-        .arg("-ExecutionPolicy")
-        .arg("Bypass")
-        .arg("-File")
-        .arg("autogen.ps1")
-        .current_dir(&lib_path)
-        .stderr(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .status()
-        .expect("autogen failed");
+    let python_exec = env::var("PYTHON_SYS_EXECUTABLE").unwrap_or_else(|_| "python.exe".to_owned());
 
-    assert!(status.success(), "autogen failed");
+    let run_autogen = autogen(&lib_path)?;
 
     // The folder might not exists from a previous build, but we don't care.
     let _ = remove_dir_all(&lib_path.join("vs2015"));
@@ -56,7 +59,9 @@ pub fn build_lib(lib_path: PathBuf, shared: bool) -> PathBuf {
 
     let lib_name = lib_path.file_name().unwrap().to_string_lossy().into_owned();
 
-    let py_convert_status = Command::new(&python_exec)
+    let mut py_convert = Command::new(&python_exec);
+
+    py_convert
         .arg(vstools_path.join("scripts").join("msvscpp-convert.py"))
         .arg("--extend-with-x64")
         .arg("--no_python_dll")
@@ -65,31 +70,29 @@ pub fn build_lib(lib_path: PathBuf, shared: bool) -> PathBuf {
         .arg(format!("msvscpp\\{}.sln", lib_name))
         .current_dir(&lib_path)
         .env("PYTHONPATH", vstools_path.into_os_string())
-        .stderr(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .status();
-
-    match py_convert_status {
-        Err(err) => {
-            if err.kind() == io::ErrorKind::NotFound {
-                panic!(format!(
-                    "Could not find python at {}, \
-                     You might need to set PYTHON_SYS_EXECUTABLE: {:?}",
-                    python_exec, err
-                ));
-            } else {
-                panic!(format!("Failed to convert the solution: {:?}", err));
-            }
-        }
-        Ok(status) => {
-            assert!(status.success(), "Failed to convert the solution");
-        }
-    };
+        // vstools lives in this crate's folder, which might be read-only.
+        .env("PYTHONDONTWRITEBYTECODE", "1");
+
+    let py_convert_status =
+        redirect_output(&mut py_convert)?
+            .status()
+            .map_err(|cause| BuildError::FailedToRun {
+                command: format!("{} msvscpp-convert.py", python_exec),
+                cause,
+                hint: hint_for("python"),
+            })?;
+
+    if !py_convert_status.success() {
+        return Err(command_failed("Converting the solution", py_convert_status));
+    }
 
     let target = env::var("TARGET").unwrap();
 
     let mut msbuild =
-        cc::windows_registry::find(&target, "msbuild").expect("Needs msbuild installed");
+        cc::windows_registry::find(&target, "msbuild").ok_or_else(|| BuildError::MissingTool {
+            tool: "msbuild".to_owned(),
+            hint: hint_for("msbuild"),
+        })?;
 
     let msbuild_platform = if target.contains("x86_64") {
         "x64"
@@ -97,13 +100,16 @@ pub fn build_lib(lib_path: PathBuf, shared: bool) -> PathBuf {
         "Win32"
     };
 
+    let configuration = msbuild_configuration();
+
     msbuild
         .arg(format!("vs2015\\{}.sln", lib_name))
         .arg("/p:PlatformToolset=v141")
         .arg(format!("/p:Platform={}", msbuild_platform))
-        .current_dir(&lib_path)
-        .stderr(Stdio::inherit())
-        .stdout(Stdio::inherit());
+        .arg(format!("/p:Configuration={}", configuration))
+        // cl appends `_CL_` to its arguments, overriding the CRT of the project files.
+        .env("_CL_", crt_flag())
+        .current_dir(&lib_path);
 
     if !shared {
         msbuild.arg("/p:ConfigurationType=StaticLibrary");
@@ -111,30 +117,105 @@ pub fn build_lib(lib_path: PathBuf, shared: bool) -> PathBuf {
 
     // We do not check status here because the Python bindings might failed to build,
     // but we don't care about that.
-    let _status = msbuild.status().expect("Building the solution failed");
+    let _status = redirect_output(&mut msbuild)?
+        .status()
+        .map_err(|cause| BuildError::FailedToRun {
+            command: "msbuild".to_owned(),
+            cause,
+            hint: hint_for("msbuild"),
+        })?;
 
     let build_dir = lib_path
         .join("vs2015")
-        .join("Release")
+        .join(configuration)
         .join(msbuild_platform);
 
-    assert!(build_dir.exists(), "Expected {:?} to exist", build_dir);
+    if !build_dir.exists() {
+        return Err(BuildError::MissingBuildOutput {
+            path: build_dir.display().to_string(),
+            log_path: log_file_path().display().to_string(),
+        });
+    }
 
     println!(
         "cargo:rustc-link-search=native={}",
         build_dir.to_string_lossy()
     );
 
-    // h files created by autogen.ps1 (`.in.h` -> `.h`) are UTF16LE encoded,
-    // which llvm (and therefore bindgen) does not accept.
-    // So convert them back to UTF8.
-    let autogen_dirs: Vec<PathBuf> = ["common", "include", &lib_name]
+    if run_autogen {
+        convert_generated_headers(&lib_path, &lib_name)?;
+    }
+
+    Ok(lib_path.join("include"))
+}
+
+/// The configuration of the solution to build: libyal's debug configuration (`VSDebug`) for
+/// debug profiles, so the native code can be debugged, `Release` otherwise.
+fn msbuild_configuration() -> &'static str {
+    match env::var("PROFILE") {
+        Ok(ref profile) if profile == "debug" => "VSDebug",
+        _ => "Release",
+    }
+}
+
+/// The CRT to compile against, which must be the one the Rust binary links: the static CRT
+/// (`/MT`) with the `crt-static` target feature, the DLL (`/MD`) otherwise. Rust always links
+/// the release CRT, so debug builds use it as well.
+fn crt_flag() -> &'static str {
+    let crt_static = env::var("CARGO_CFG_TARGET_FEATURE")
+        .map(|features| features.split(',').any(|feature| feature == "crt-static"))
+        .unwrap_or(false);
+
+    if crt_static {
+        "/MT"
+    } else {
+        "/MD"
+    }
+}
+
+/// Generates the headers (`.h.in` -> `.h`) without building the lib.
+/// Return the "include" folder for the library (to be used by bindgen).
+pub fn generate_headers(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    if autogen(&lib_path)? {
+        let lib_name = lib_path.file_name().unwrap().to_string_lossy().into_owned();
+        convert_generated_headers(&lib_path, &lib_name)?;
+    }
+
+    Ok(lib_path.join("include"))
+}
+
+/// Runs `autogen.ps1`, return whether there was one to run.
+fn autogen(lib_path: &PathBuf) -> Result<bool, BuildError> {
+    // Release packages ship with the generated headers.
+    if !lib_path.join("autogen.ps1").exists() {
+        return Ok(false);
+    }
+
+    run_logged(
+        Command::new("powershell")
+            .arg("-NoProfile")
+            .arg("-ExecutionPolicy")
+            .arg("Bypass")
+            .arg("-File")
+            .arg("autogen.ps1")
+            .current_dir(&lib_path),
+        "autogen",
+    )?;
+
+    Ok(true)
+}
+
+/// h files created by autogen.ps1 (`.in.h` -> `.h`) are UTF16LE encoded,
+/// which llvm (and therefore bindgen) does not accept.
+/// So convert them back to UTF8.
+fn convert_generated_headers(lib_path: &PathBuf, lib_name: &str) -> Result<(), BuildError> {
+    let autogen_dirs: Vec<PathBuf> = ["common", "include", lib_name]
         .into_iter()
         .map(|dir_name| lib_path.join(dir_name))
         .collect();
 
     for file_entry in autogen_dirs.iter().map(WalkDir::new).flatten() {
-        let file_entry = file_entry.unwrap();
+        let file_entry = file_entry.map_err(io::Error::from)?;
         let file_path = file_entry.path();
         let file_name = file_path.file_name().unwrap().to_string_lossy();
 
@@ -144,15 +225,13 @@ pub fn build_lib(lib_path: PathBuf, shared: bool) -> PathBuf {
 
         let h_file_path = file_path.with_file_name(file_name.replace(".h.in", ".h"));
 
-        utf16le_to_utf8(&h_file_path).unwrap();
+        utf16le_to_utf8(&h_file_path)?;
     }
 
-    let include_folder_path = lib_path.join("include");
-
-    include_folder_path
+    Ok(())
 }
 
-fn utf16le_to_utf8(file_path: &PathBuf) -> Result<(), Error> {
+fn utf16le_to_utf8(file_path: &PathBuf) -> Result<(), io::Error> {
     let h_file = File::open(&file_path)?;
 
     let mut transcoded = DecodeReaderBytesBuilder::new()
@@ -166,7 +245,7 @@ fn utf16le_to_utf8(file_path: &PathBuf) -> Result<(), Error> {
     drop(transcoded);
 
     let mut h_file = File::create(&file_path)?;
-    h_file.write(content.as_bytes())?;
+    h_file.write_all(content.as_bytes())?;
 
     Ok(())
 }
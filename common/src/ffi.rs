@@ -72,6 +72,43 @@ macro_rules! get_sized_utf8_string {
     }};
 }
 
+/// Like `get_sized_utf8_string`, for the utf16 functions. Gives the units without the nul
+/// terminator, leaving decoding to the caller.
+#[macro_export]
+macro_rules! get_sized_utf16_units {
+    ($self: ident, $get_size: ident, $get_string: ident) => {{
+        let mut name_size = 0_usize;
+        let mut error = ptr::null_mut();
+
+        if unsafe { $get_size($self.as_type_ref(), &mut name_size, &mut error) } != 1 {
+            return Err(Error::try_from(error)?);
+        };
+
+        if name_size == 0 {
+            Ok(Vec::<u16>::new())
+        } else {
+            let mut name = vec![0_u16; name_size];
+            let mut error = ptr::null_mut();
+
+            if unsafe {
+                $get_string(
+                    $self.as_type_ref(),
+                    name.as_mut_ptr(),
+                    name.len(),
+                    &mut error,
+                )
+            } != 1
+            {
+                Err(Error::try_from(error)?)
+            } else {
+                // Discard nul terminator;
+                name.pop().expect("name_size was checked to be > 0");
+                Ok(name)
+            }
+        }
+    }};
+}
+
 #[macro_export]
 macro_rules! get_sized_bytes {
     ($self: ident, $get_size: ident, $get_string: ident) => {{
@@ -140,3 +177,95 @@ macro_rules! get_u64_field {
         }
     }};
 }
+
+#[macro_export]
+macro_rules! get_u32_field {
+    ($self: ident, $getter: ident) => {{
+        let mut value = 0_u32;
+        let mut error = ptr::null_mut();
+
+        if unsafe { $getter($self.as_type_ref(), &mut value, &mut error) } != 1 {
+            Err(Error::try_from(error)?)
+        } else {
+            Ok(value)
+        }
+    }};
+}
+
+/// Like `get_sized_utf8_string!`, for values which might not be present.
+/// libyal size getters return 0 (instead of 1) when the value is not available.
+#[macro_export]
+macro_rules! get_optional_sized_utf8_string {
+    ($self: ident, $get_size: ident, $get_string: ident) => {{
+        let mut name_size = 0_usize;
+        let mut error = ptr::null_mut();
+
+        match unsafe { $get_size($self.as_type_ref(), &mut name_size, &mut error) } {
+            1 => {}
+            0 => return Ok(None),
+            _ => return Err(Error::try_from(error)?),
+        };
+
+        if name_size == 0 {
+            Ok(Some(String::new()))
+        } else {
+            let mut name = vec![0; name_size];
+            let mut error = ptr::null_mut();
+
+            if unsafe {
+                $get_string(
+                    $self.as_type_ref(),
+                    name.as_mut_ptr(),
+                    name.len(),
+                    &mut error,
+                )
+            } != 1
+            {
+                Err(Error::try_from(error)?)
+            } else {
+                // Discard nul terminator;
+                name.pop().expect("name_size was checked to be > 0");
+                let s = String::from_utf8(name).map_err(|e| Error::StringContainsInvalidUTF8(e))?;
+                Ok(Some(s))
+            }
+        }
+    }};
+}
+
+/// Like `get_sized_utf16_units!`, for values which might not be present.
+#[macro_export]
+macro_rules! get_optional_sized_utf16_units {
+    ($self: ident, $get_size: ident, $get_string: ident) => {{
+        let mut name_size = 0_usize;
+        let mut error = ptr::null_mut();
+
+        match unsafe { $get_size($self.as_type_ref(), &mut name_size, &mut error) } {
+            1 => {}
+            0 => return Ok(None),
+            _ => return Err(Error::try_from(error)?),
+        };
+
+        if name_size == 0 {
+            Ok(Some(Vec::<u16>::new()))
+        } else {
+            let mut name = vec![0_u16; name_size];
+            let mut error = ptr::null_mut();
+
+            if unsafe {
+                $get_string(
+                    $self.as_type_ref(),
+                    name.as_mut_ptr(),
+                    name.len(),
+                    &mut error,
+                )
+            } != 1
+            {
+                Err(Error::try_from(error)?)
+            } else {
+                // Discard nul terminator;
+                name.pop().expect("name_size was checked to be > 0");
+                Ok(Some(name))
+            }
+        }
+    }};
+}
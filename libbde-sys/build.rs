@@ -0,0 +1,90 @@
+use failure::{bail, Error};
+use libyal_rs_common_build::{
+    generate_bindings, get_lib_or_fetch, is_msvc_target, BuildError, LibBuilder,
+};
+use std::env;
+use std::path::PathBuf;
+
+fn build_and_link_static(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    if is_msvc_target() {
+        println!("cargo:rustc-link-lib=static=libbde");
+
+        // Also static-link deps (otherwise we'll get missing symbols at link time).
+        let deps = [
+            "libbfio",
+            "libcaes",
+            "libcdata",
+            "libcerror",
+            "libcfile",
+            "libclocale",
+            "libcnotify",
+            "libcpath",
+            "libcsplit",
+            "libcthreads",
+            "libfcache",
+            "libfdata",
+            "libfdatetime",
+            "libfguid",
+            "libfvalue",
+            "libhmac",
+            "libuna",
+        ];
+
+        for dep in deps.iter() {
+            println!("cargo:rustc-link-lib=static={}", dep);
+        }
+    } else {
+        println!("cargo:rustc-link-lib=static=bde");
+    }
+
+    builder(lib_path).shared(false).sync_and_build()
+}
+
+fn build_and_link_dynamic(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    if is_msvc_target() {
+        println!("cargo:rustc-link-lib=dylib=libbde");
+    } else {
+        println!("cargo:rustc-link-lib=dylib=bde");
+    }
+
+    builder(lib_path).shared(true).sync_and_build()
+}
+
+/// libbde opens volumes through handles of the `libbfio-sys` libbfio (see `DEP_BFIO_ROOT`),
+/// so it is built against that libbfio instead of the copy it bundles, which might be another
+/// revision of its structures.
+fn builder(lib_path: PathBuf) -> LibBuilder {
+    let builder = LibBuilder::new(lib_path).share_sublibraries(true);
+
+    match env::var("DEP_BFIO_ROOT") {
+        Ok(root) if !is_msvc_target() => {
+            builder.configure_args(vec![format!("--with-libbfio={}", root)])
+        }
+        _ => builder,
+    }
+}
+
+/// The release used when there is no local checkout of libbde.
+const LIBBDE_VERSION: &str = "20210605";
+/// The SHA-256 of the release package of `LIBBDE_VERSION`. While it is not pinned, fetching
+/// the release is rejected unless the `LIBBDE_SHA256` env var gives it.
+const LIBBDE_SHA256: Option<&str> = None;
+
+fn build() -> Result<(), BuildError> {
+    let lib_path = get_lib_or_fetch("libbde", LIBBDE_VERSION, LIBBDE_SHA256)?;
+
+    let include_folder_path = if cfg!(feature = "dynamic_link") {
+        build_and_link_dynamic(lib_path)?
+    } else {
+        build_and_link_static(lib_path)?
+    };
+
+    generate_bindings(&include_folder_path, "wrapper.h")
+}
+
+fn main() {
+    if let Err(e) = build() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,12 @@
+#![allow(non_upper_case_globals)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+// libbde is built against this libbfio (see build.rs), which has to be linked after it.
+extern crate libbfio_sys;
+
+include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+pub mod ptr;
+
+pub use crate::ptr::*;
@@ -0,0 +1 @@
+#include "libbde.h"
\ No newline at end of file
@@ -1,10 +1,12 @@
 use failure::{bail, Error};
-use libyal_rs_common_build::{sync_and_build_lib, generate_bindings, get_lib_and_copy_to_out_dir};
+use libyal_rs_common_build::{
+    generate_bindings, get_lib_or_fetch, is_msvc_target, BuildError, LibBuilder,
+};
 use std::env;
 use std::path::PathBuf;
 
-fn build_and_link_static(lib_path: PathBuf) -> PathBuf {
-    if cfg!(target_os = "windows") {
+fn build_and_link_static(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    if is_msvc_target() {
         println!("cargo:rustc-link-lib=static=libbfio");
 
         // Also static-link deps (otherwise we'll get missing symbols at link time).
@@ -15,27 +17,52 @@ fn build_and_link_static(lib_path: PathBuf) -> PathBuf {
         println!("cargo:rustc-link-lib=static=bfio");
     }
 
-    sync_and_build_lib(lib_path, false)
+    LibBuilder::new(lib_path)
+        .share_sublibraries(true)
+        .shared(false)
+        .sync_and_build()
 }
 
-fn build_and_link_dynamic(lib_path: PathBuf) -> PathBuf {
-    if cfg!(target_os = "windows") {
+fn build_and_link_dynamic(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    if is_msvc_target() {
         println!("cargo:rustc-link-lib=dylib=libbfio");
     } else {
         println!("cargo:rustc-link-lib=dylib=bfio");
     }
 
-    sync_and_build_lib(lib_path, true)
+    LibBuilder::new(lib_path)
+        .share_sublibraries(true)
+        .shared(true)
+        .sync_and_build()
 }
 
-fn main() {
-    let lib_path = get_lib_and_copy_to_out_dir("libbfio");
+/// The release used when there is no local checkout of libbfio.
+const LIBBFIO_VERSION: &str = "20201125";
+/// The SHA-256 of the release package of `LIBBFIO_VERSION`. While it is not pinned, fetching
+/// the release is rejected unless the `LIBBFIO_SHA256` env var gives it.
+const LIBBFIO_SHA256: Option<&str> = None;
+
+fn build() -> Result<(), BuildError> {
+    let lib_path = get_lib_or_fetch("libbfio", LIBBFIO_VERSION, LIBBFIO_SHA256)?;
 
     let include_folder_path = if cfg!(feature = "dynamic_link") {
-        build_and_link_dynamic(lib_path)
+        build_and_link_dynamic(lib_path)?
     } else {
-        build_and_link_static(lib_path)
+        build_and_link_static(lib_path)?
     };
 
-    generate_bindings(&include_folder_path, "wrapper.h");
+    // Where libbfio is installed, for -sys crates of libraries which take a libbfio handle to
+    // be built against this libbfio (`DEP_BFIO_ROOT`) rather than the copy they bundle.
+    if let Some(root) = include_folder_path.parent() {
+        println!("cargo:root={}", root.display());
+    }
+
+    generate_bindings(&include_folder_path, "wrapper.h")
+}
+
+fn main() {
+    if let Err(e) = build() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
 }
@@ -1,9 +1,9 @@
 use crate::error::Error;
 use libbfio_sys::FILE;
+use libcerror_rs::CError;
 use libyal_rs_common::ffi::AsTypeRef;
 use log::trace;
 use std::convert::TryFrom;
-use std::ffi::{CStr};
 
 
 
@@ -73,16 +73,12 @@ impl TryFrom<*mut __LibbfioError> for Error {
             return Err(Error::Other("Error pointer cannot be NULL".to_owned()));
         }
 
-        let mut buffer = vec![0; 1024];
-
-        let retcode =
-            unsafe { libbfio_error_sprint(err as *const _, buffer.as_mut_ptr(), buffer.len()) };
+        Ok(Error::from(unsafe { CError::from_raw(err) }))
+    }
+}
 
-        if retcode == -1 {
-            Err(Error::FFI("Failed to print error".to_owned()))
-        } else {
-            let repr = unsafe { CStr::from_ptr(buffer.as_ptr()) };
-            Ok(Error::FFI(repr.to_string_lossy().to_string()))
-        }
+impl From<CError> for Error {
+    fn from(err: CError) -> Self {
+        Error::FFI(err.message())
     }
 }
@@ -296,10 +296,8 @@ impl Handle {
         if retcode != 1 {
             Err(Error::try_from(error)?)
         } else {
-            let mut err = ptr::null_mut();
-            if unsafe { libbfio_handle_set_access_flags(handle, flags.to_int(), &mut err) } != 1 {
-                return Err(Error::try_from(err)?);
-            }
+            try_ffi!(libbfio_handle_set_access_flags(handle, flags.to_int()));
+
             Ok(Handle::wrap_ptr(handle))
         }
     }
@@ -3,9 +3,14 @@
 
 #[macro_use]
 extern crate libyal_rs_common;
+#[macro_use]
+extern crate libcerror_rs;
 
 pub mod handle;
+pub mod memory_range;
 mod io_handle;
 pub mod ffi_error;
 pub mod error;
-//mod libbfio;
\ No newline at end of file
+//mod libbfio;
+
+pub use crate::io_handle::IoHandle;
\ No newline at end of file
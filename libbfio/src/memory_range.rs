@@ -0,0 +1,182 @@
+//! Wraps libbfio's memory range handle, which reads from an in-memory buffer.
+//!
+//! libbfio does not copy the buffer, so the handle keeps it borrowed (or owns it) for as long as it lives.
+//! The inner `Handle` is only lent out by shared reference, so it can never be moved away from the buffer.
+use crate::error::Error;
+use crate::ffi_error::LibbfioErrorRefMut;
+use crate::handle::{
+    libbfio_handle_close, libbfio_handle_open, Handle, HandleRef, HandleRefMut,
+    LibbfioAccessFlags,
+};
+use libyal_rs_common::ffi::AsTypeRef;
+use log::error;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Deref;
+use std::os::raw::c_int;
+use std::ptr;
+
+extern "C" {
+    pub fn libbfio_memory_range_initialize(
+        handle: *mut HandleRefMut,
+        error: *mut LibbfioErrorRefMut,
+    ) -> c_int;
+    pub fn libbfio_memory_range_get(
+        handle: HandleRef,
+        range_start: *mut *mut u8,
+        range_size: *mut usize,
+        error: *mut LibbfioErrorRefMut,
+    ) -> c_int;
+    pub fn libbfio_memory_range_set(
+        handle: HandleRef,
+        range_start: *mut u8,
+        range_size: usize,
+        error: *mut LibbfioErrorRefMut,
+    ) -> c_int;
+}
+
+/// A read-only `Handle` over a buffer, opened for reading. Can be used anywhere a `&Handle` is
+/// accepted, and reads (and seeks) like one.
+pub struct MemoryRangeHandle<'a> {
+    // Declared first so it is dropped before the buffer it points into.
+    handle: Handle,
+    buffer: Cow<'a, [u8]>,
+}
+
+impl<'a> MemoryRangeHandle<'a> {
+    /// Create a handle reading from `buffer`, which must outlive the handle.
+    pub fn new(buffer: &'a [u8]) -> Result<Self, Error> {
+        Self::from_cow(Cow::Borrowed(buffer))
+    }
+
+    fn from_cow(buffer: Cow<'a, [u8]>) -> Result<Self, Error> {
+        let mut handle = ptr::null_mut();
+
+        try_ffi!(libbfio_memory_range_initialize(&mut handle));
+
+        let handle = Handle::wrap_ptr(handle);
+
+        // The handle is only ever opened for reading, so libbfio will not write through this pointer.
+        try_ffi!(libbfio_memory_range_set(
+            handle.as_type_ref(),
+            buffer.as_ptr() as *mut u8,
+            buffer.len(),
+        ));
+
+        try_ffi!(libbfio_handle_open(
+            handle.as_type_ref(),
+            LibbfioAccessFlags::Read.to_int(),
+        ));
+
+        Ok(MemoryRangeHandle { handle, buffer })
+    }
+
+    /// The buffer the handle reads from.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+impl MemoryRangeHandle<'static> {
+    /// Create a handle which owns `buffer`.
+    pub fn from_vec(buffer: Vec<u8>) -> Result<Self, Error> {
+        Self::from_cow(Cow::Owned(buffer))
+    }
+}
+
+impl<'a> Deref for MemoryRangeHandle<'a> {
+    type Target = Handle;
+
+    fn deref(&self) -> &Handle {
+        &self.handle
+    }
+}
+
+impl<'a> Read for MemoryRangeHandle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.handle.read(buf)
+    }
+}
+
+impl<'a> Seek for MemoryRangeHandle<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.handle.seek(pos)
+    }
+}
+
+/// Writes are always rejected, the handle is only opened for reading.
+impl<'a> Write for MemoryRangeHandle<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.handle.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.handle.flush()
+    }
+}
+
+impl<'a> Drop for MemoryRangeHandle<'a> {
+    fn drop(&mut self) {
+        let mut error = ptr::null_mut();
+
+        unsafe {
+            libbfio_handle_close(self.handle.as_type_ref(), &mut error);
+        }
+
+        if !error.is_null() {
+            match Error::try_from(error) {
+                Ok(e) => error!("`libbfio_handle_close` failed: {}", e),
+                Err(_) => error!("`libbfio_handle_close` failed!"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::handle::libbfio_handle_is_open;
+    use crate::memory_range::MemoryRangeHandle;
+    use libyal_rs_common::ffi::AsTypeRef;
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    const CONTENT: &[u8; 9] = b"some_data";
+
+    #[test]
+    fn test_read() {
+        let mut handle = MemoryRangeHandle::new(CONTENT).unwrap();
+        let mut buf = vec![];
+
+        handle.read_to_end(&mut buf).unwrap();
+
+        assert_eq!(buf, CONTENT);
+    }
+
+    #[test]
+    fn test_seek_owned() {
+        let mut handle = MemoryRangeHandle::from_vec(CONTENT.to_vec()).unwrap();
+        let mut buf = vec![];
+
+        handle.seek(SeekFrom::Start(5)).unwrap();
+        handle.read_to_end(&mut buf).unwrap();
+
+        assert_eq!(buf, b"data");
+    }
+
+    #[test]
+    fn test_is_open() {
+        let handle = MemoryRangeHandle::new(CONTENT).unwrap();
+        let mut error = std::ptr::null_mut();
+
+        let is_open = unsafe { libbfio_handle_is_open(handle.as_type_ref(), &mut error) };
+
+        assert_eq!(is_open, 1);
+    }
+
+    #[test]
+    fn test_write_is_rejected() {
+        let mut handle = MemoryRangeHandle::new(CONTENT).unwrap();
+
+        assert!(handle.write(b"Hello").is_err());
+    }
+}
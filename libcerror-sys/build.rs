@@ -1,36 +1,45 @@
 use failure::{bail, Error};
-use libyal_rs_common_build::{sync_and_build_lib, generate_bindings, get_lib_and_copy_to_out_dir};
+use libyal_rs_common_build::{
+    generate_bindings, get_lib_and_copy_to_out_dir, is_msvc_target, BuildError, LibBuilder,
+};
 use std::env;
 use std::path::PathBuf;
 
-fn build_and_link_static(lib_path: PathBuf) -> PathBuf {
-    if cfg!(target_os = "windows") {
+fn build_and_link_static(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    if is_msvc_target() {
         println!("cargo:rustc-link-lib=static=libcerror");
     } else {
         println!("cargo:rustc-link-lib=static=cerror");
     }
 
-    sync_and_build_lib(lib_path, false)
+    LibBuilder::new(lib_path).shared(false).sync_and_build()
 }
 
-fn build_and_link_dynamic(lib_path: PathBuf) -> PathBuf {
-    if cfg!(target_os = "windows") {
+fn build_and_link_dynamic(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    if is_msvc_target() {
         println!("cargo:rustc-link-lib=dylib=libcerror");
     } else {
         println!("cargo:rustc-link-lib=dylib=cerror");
     }
 
-    sync_and_build_lib(lib_path, true)
+    LibBuilder::new(lib_path).shared(true).sync_and_build()
 }
 
-fn main() {
-    let lib_path = get_lib_and_copy_to_out_dir("libcerror");
+fn build() -> Result<(), BuildError> {
+    let lib_path = get_lib_and_copy_to_out_dir("libcerror")?;
 
     let include_folder_path = if cfg!(feature = "dynamic_link") {
-        build_and_link_dynamic(lib_path)
+        build_and_link_dynamic(lib_path)?
     } else {
-        build_and_link_static(lib_path)
+        build_and_link_static(lib_path)?
     };
 
-    generate_bindings(&include_folder_path, "wrapper.h");
+    generate_bindings(&include_folder_path, "wrapper.h")
+}
+
+fn main() {
+    if let Err(e) = build() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,221 @@
+//! Error handling shared by the libyal wrappers.
+//!
+//! Every libyal library reports errors through a `libcerror_error_t **` out-param
+//! (`libfsntfs_error_t`, `libbfio_error_t`, .. are all the same libcerror structure),
+//! so they can all be owned and printed by `CError`.
+
+// Only linked against, the declarations below are used instead of the generated bindings.
+extern crate libcerror_sys;
+
+use log::trace;
+use std::ffi::CStr;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+#[repr(C)]
+pub struct __LibcerrorError(isize);
+
+pub type LibcerrorErrorRefMut = *mut __LibcerrorError;
+pub type LibcerrorErrorRef = *const __LibcerrorError;
+
+extern "C" {
+    pub fn libcerror_error_free(error: *mut LibcerrorErrorRefMut);
+    pub fn libcerror_error_sprint(
+        error: LibcerrorErrorRefMut,
+        string: *mut c_char,
+        size: usize,
+    ) -> c_int;
+    pub fn libcerror_error_backtrace_sprint(
+        error: LibcerrorErrorRefMut,
+        string: *mut c_char,
+        size: usize,
+    ) -> c_int;
+    pub fn libcerror_error_matches(
+        error: LibcerrorErrorRefMut,
+        error_domain: c_int,
+        error_code: c_int,
+    ) -> c_int;
+}
+
+/// The error domains of `libcerror_definitions.h`.
+pub mod domain {
+    use std::os::raw::c_int;
+
+    pub const ARGUMENTS: c_int = b'a' as c_int;
+    pub const CONVERSION: c_int = b'c' as c_int;
+    pub const COMPRESSION: c_int = b'C' as c_int;
+    pub const IO: c_int = b'I' as c_int;
+    pub const INPUT: c_int = b'i' as c_int;
+    pub const MEMORY: c_int = b'm' as c_int;
+    pub const OUTPUT: c_int = b'o' as c_int;
+    pub const RUNTIME: c_int = b'r' as c_int;
+}
+
+/// Codes of the `ARGUMENTS` domain.
+pub mod argument_error {
+    use std::os::raw::c_int;
+
+    pub const INVALID_VALUE: c_int = 1;
+    pub const VALUE_OUT_OF_BOUNDS: c_int = 7;
+    pub const UNSUPPORTED_VALUE: c_int = 8;
+}
+
+/// Codes of the `COMPRESSION` domain.
+pub mod compression_error {
+    use std::os::raw::c_int;
+
+    pub const GENERIC: c_int = 0;
+    pub const COMPRESS_FAILED: c_int = 1;
+    pub const DECOMPRESS_FAILED: c_int = 2;
+}
+
+/// Codes of the `IO` domain.
+pub mod io_error {
+    use std::os::raw::c_int;
+
+    pub const GENERIC: c_int = 0;
+    pub const OPEN_FAILED: c_int = 1;
+    pub const CLOSE_FAILED: c_int = 2;
+    pub const SEEK_FAILED: c_int = 3;
+    pub const READ_FAILED: c_int = 4;
+    pub const WRITE_FAILED: c_int = 5;
+    pub const ACCESS_DENIED: c_int = 6;
+    pub const INVALID_RESOURCE: c_int = 7;
+    pub const IOCTL_FAILED: c_int = 8;
+    pub const UNLINK_FAILED: c_int = 9;
+}
+
+/// Codes of the `INPUT` domain.
+pub mod input_error {
+    use std::os::raw::c_int;
+
+    pub const GENERIC: c_int = 0;
+    pub const INVALID_DATA: c_int = 1;
+    pub const SIGNATURE_MISMATCH: c_int = 2;
+    pub const CHECKSUM_MISMATCH: c_int = 3;
+    pub const VALUE_MISMATCH: c_int = 4;
+}
+
+/// Codes of the `RUNTIME` domain.
+pub mod runtime_error {
+    use std::os::raw::c_int;
+
+    pub const VALUE_MISSING: c_int = 1;
+    pub const UNSUPPORTED_VALUE: c_int = 14;
+    pub const ABORT_REQUESTED: c_int = 15;
+}
+
+/// Big enough for the backtraces libyal produces.
+const MESSAGE_BUFFER_SIZE: usize = 1024;
+
+/// An owned libcerror error, freed on drop.
+pub struct CError(LibcerrorErrorRefMut);
+
+impl CError {
+    /// Takes ownership of an error returned through a libyal `error` out-param.
+    ///
+    /// # Safety
+    /// `error` must be NULL or a valid libcerror error which is not owned by anything else.
+    pub unsafe fn from_raw<T>(error: *mut T) -> Self {
+        CError(error as LibcerrorErrorRefMut)
+    }
+
+    /// Gives up ownership of the error, the caller is responsible to free it.
+    pub fn into_raw(self) -> LibcerrorErrorRefMut {
+        let error = self.0;
+        std::mem::forget(self);
+        error
+    }
+
+    /// The error message (of the outermost error).
+    pub fn message(&self) -> String {
+        self.print_with(libcerror_error_sprint)
+    }
+
+    /// Whether the error was raised with `domain` and `code`. libcerror keeps the domain and
+    /// code of the first (innermost) error set, the messages added on the way up don't change it.
+    pub fn matches(&self, domain: c_int, code: c_int) -> bool {
+        !self.0.is_null() && unsafe { libcerror_error_matches(self.0, domain, code) } == 1
+    }
+
+    /// Whether the error was raised with `domain` and any of `codes`.
+    pub fn matches_any(&self, domain: c_int, codes: &[c_int]) -> bool {
+        codes.iter().any(|&code| self.matches(domain, code))
+    }
+
+    /// The message of the innermost error, which is usually the most specific one.
+    pub fn root_message(&self) -> String {
+        self.backtrace()
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_owned()
+    }
+
+    /// The error messages of the entire error chain, one per line.
+    pub fn backtrace(&self) -> String {
+        self.print_with(libcerror_error_backtrace_sprint)
+    }
+
+    fn print_with(
+        &self,
+        sprint: unsafe extern "C" fn(LibcerrorErrorRefMut, *mut c_char, usize) -> c_int,
+    ) -> String {
+        if self.0.is_null() {
+            return "Error pointer cannot be NULL".to_owned();
+        }
+
+        let mut buffer = vec![0 as c_char; MESSAGE_BUFFER_SIZE];
+
+        if unsafe { sprint(self.0, buffer.as_mut_ptr(), buffer.len()) } == -1 {
+            return "Failed to print error".to_owned();
+        }
+
+        let repr = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+        repr.to_string_lossy().trim_end().to_owned()
+    }
+}
+
+impl Display for CError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl Debug for CError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("CError").field(&self.backtrace()).finish()
+    }
+}
+
+impl Drop for CError {
+    fn drop(&mut self) {
+        if self.0.is_null() {
+            return;
+        }
+
+        trace!("Calling `libcerror_error_free`");
+
+        unsafe { libcerror_error_free(&mut self.0) };
+
+        self.0 = ptr::null_mut();
+    }
+}
+
+/// Calls a libyal function, passing a fresh `error` out-param as the last argument.
+/// Returns early with `Err(CError.into())` unless the function returned 1.
+///
+/// ```ignore
+/// try_ffi!(libfsntfs_volume_get_serial_number(self.as_type_ref(), &mut serial_number));
+/// ```
+#[macro_export]
+macro_rules! try_ffi {
+    ($func:ident($($arg:expr),* $(,)?)) => {{
+        let mut error = ::std::ptr::null_mut();
+
+        if unsafe { $func($($arg,)* &mut error) } != 1 {
+            return Err(unsafe { $crate::CError::from_raw(error) }.into());
+        }
+    }};
+}
@@ -0,0 +1 @@
+#include "libewf.h"
\ No newline at end of file
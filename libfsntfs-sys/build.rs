@@ -1,12 +1,16 @@
 use failure::{bail, Error};
-use libyal_rs_common_build::{build_lib, generate_bindings, sync_libs, get_lib_and_copy_to_out_dir};
+use libyal_rs_common_build::{
+    generate_runtime_loader, generate_split_bindings, get_lib_or_fetch, get_prebuilt_lib,
+    is_msvc_target, sync_libs, BuildError, LibBuilder,
+};
 use std::env;
 use std::fs::File;
-use std::io::{Write, Read};
+use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::process::Command;
 
-fn build_and_link_static(lib_path: PathBuf) -> PathBuf {
-    if cfg!(target_os = "windows") {
+fn link_static() {
+    if is_msvc_target() {
         println!("cargo:rustc-link-lib=static=libfsntfs");
 
         // Also static-link deps (otherwise we'll get missing symbols at link time).
@@ -35,51 +39,355 @@ fn build_and_link_static(lib_path: PathBuf) -> PathBuf {
     } else {
         println!("cargo:rustc-link-lib=static=fsntfs");
     }
-
-    build_lib(lib_path, false)
 }
 
-fn build_and_link_dynamic(lib_path: PathBuf) -> PathBuf {
-    if cfg!(target_os = "windows") {
+fn link_dynamic() {
+    if is_msvc_target() {
         println!("cargo:rustc-link-lib=dylib=libfsntfs");
     } else {
         println!("cargo:rustc-link-lib=dylib=fsntfs");
     }
+}
+
+fn build_and_link_static(builder: LibBuilder) -> Result<PathBuf, BuildError> {
+    link_static();
 
-    build_lib(lib_path, true)
+    builder.shared(false).build()
 }
 
-fn main() {
-    let lib_path = get_lib_and_copy_to_out_dir("libfsntfs");
+fn build_and_link_dynamic(builder: LibBuilder) -> Result<PathBuf, BuildError> {
+    link_dynamic();
+
+    builder.shared(true).build()
+}
+
+/// The C compiler `configure` uses, like the `cc` crate picks it.
+fn c_compiler() -> String {
+    let target = env::var("TARGET").unwrap();
+    let candidates = [
+        format!("CC_{}", target),
+        format!("CC_{}", target.replace('-', "_")),
+        "TARGET_CC".to_owned(),
+        "CC".to_owned(),
+    ];
+
+    candidates
+        .iter()
+        .filter_map(|candidate| env::var(candidate).ok())
+        .next()
+        .unwrap_or_else(|| "cc".to_owned())
+}
+
+/// What `compiler` prints for `arg`, `None` if it fails.
+fn compiler_output(compiler: &str, arg: &str) -> Option<String> {
+    Command::new(compiler)
+        .arg(arg)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// The sanitizer runtimes `compiler` links instrumented code with: gcc has separate ASan and
+/// UBSan runtimes, the ASan runtime of clang includes UBSan.
+fn sanitizer_runtimes(compiler: &str) -> Vec<PathBuf> {
+    // gcc (and clang when it uses the gcc runtimes) knows the path, otherwise it prints the
+    // name back.
+    let gcc_runtimes: Vec<PathBuf> = ["libasan.so", "libubsan.so"]
+        .iter()
+        .filter_map(|name| compiler_output(compiler, &format!("-print-file-name={}", name)))
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute() && path.exists())
+        .collect();
+
+    if gcc_runtimes.len() == 2 {
+        return gcc_runtimes;
+    }
+
+    let resource_dir = match compiler_output(compiler, "-print-resource-dir") {
+        Some(resource_dir) => PathBuf::from(resource_dir).join("lib"),
+        None => return vec![],
+    };
+
+    let target = env::var("TARGET").unwrap();
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+    let candidates = [
+        resource_dir
+            .join("darwin")
+            .join("libclang_rt.asan_osx_dynamic.dylib"),
+        resource_dir.join(&target).join("libclang_rt.asan.so"),
+        resource_dir
+            .join("linux")
+            .join(format!("libclang_rt.asan-{}.so", arch)),
+    ];
+
+    candidates
+        .iter()
+        .find(|candidate| candidate.exists())
+        .cloned()
+        .into_iter()
+        .collect()
+}
+
+/// Instrument the C sources with AddressSanitizer and UndefinedBehaviorSanitizer.
+///
+/// The sanitizer runtime has to be loaded before anything else, which linking it through
+/// cargo can not do. Either build the crates with `RUSTFLAGS=-Zsanitizer=address` (nightly),
+/// which links the runtime of rustc first, or run the binaries with the runtime of the C
+/// compiler preloaded (`LD_PRELOAD`, `DYLD_INSERT_LIBRARIES` on macOS), which the build prints.
+fn enable_sanitizers(builder: LibBuilder) -> LibBuilder {
+    if is_msvc_target() {
+        println!("cargo:warning=The `asan` feature is not supported for MSVC builds, ignoring it.");
+        return builder;
+    }
+
+    let sanitize_flags = "-g -fsanitize=address,undefined -fno-omit-frame-pointer";
+    let builder = builder
+        .flags("CFLAGS", sanitize_flags)
+        .flags("LDFLAGS", sanitize_flags);
+
+    println!("cargo:rerun-if-env-changed=RUSTFLAGS");
+    let rustc_sanitizer = env::var("RUSTFLAGS")
+        .map(|flags| flags.contains("-Zsanitizer=address"))
+        .unwrap_or(false);
+
+    if rustc_sanitizer {
+        return builder;
+    }
+
+    let compiler = c_compiler();
+    let runtimes = sanitizer_runtimes(&compiler);
+
+    if runtimes.is_empty() {
+        println!(
+            "cargo:warning=Could not find the sanitizer runtimes of `{}`, build with \
+             `RUSTFLAGS=-Zsanitizer=address` instead.",
+            compiler
+        );
+        return builder;
+    }
+
+    let preload_var = match env::var("CARGO_CFG_TARGET_OS").unwrap().as_str() {
+        "macos" | "ios" => "DYLD_INSERT_LIBRARIES",
+        _ => "LD_PRELOAD",
+    };
+
+    // Linked so the instrumented code resolves, but it only comes first when preloaded.
+    for runtime in &runtimes {
+        let file_stem = runtime.file_stem().unwrap().to_string_lossy();
+        println!(
+            "cargo:rustc-link-search=native={}",
+            runtime.parent().unwrap().display()
+        );
+        println!(
+            "cargo:rustc-link-lib=dylib={}",
+            file_stem.trim_start_matches("lib")
+        );
+    }
+
+    let preload: Vec<String> = runtimes
+        .iter()
+        .map(|runtime| runtime.display().to_string())
+        .collect();
+    println!(
+        "cargo:warning=Run the instrumented binaries with `{}={}`.",
+        preload_var,
+        preload.join(":")
+    );
+
+    builder
+}
+
+/// The release used when there is no local checkout of libfsntfs.
+const LIBFSNTFS_VERSION: &str = "20200921";
+/// The SHA-256 of the release package of `LIBFSNTFS_VERSION`. While it is not pinned, fetching
+/// the release is rejected unless the `LIBFSNTFS_SHA256` env var gives it.
+const LIBFSNTFS_SHA256: Option<&str> = None;
+
+/// The block of `libfcache_cache_value_set_value` the patch below removes: freeing the previous
+/// value, which libfsntfs still references. Compared line by line without the indentation.
+const LIBFCACHE_PATCHED_BLOCK: &str = r#"
+if( internal_cache_value->free_value(
+&( internal_cache_value->value ),
+error ) != 1 )
+{
+libcerror_error_set(
+error,
+LIBCERROR_ERROR_DOMAIN_RUNTIME,
+LIBCERROR_RUNTIME_ERROR_FINALIZE_FAILED,
+"%s: unable to free value.",
+function );
 
-    sync_libs(&lib_path);
+return( -1 );
+}
+"#;
 
-    // Patch libfcache to fix a segfault (See https://github.com/libyal/libfsntfs/issues/10).
+/// Patch libfcache to fix a segfault (See https://github.com/libyal/libfsntfs/issues/10).
+/// Applies to the libfcache synced into git checkouts and to the one vendored in release
+/// packages. Fails when the file does not hold the block exactly once (another libfcache
+/// revision), rather than removing other lines.
+fn patch_libfcache(lib_path: &PathBuf) -> Result<(), BuildError> {
     let patched_file_path = lib_path.join("libfcache").join("libfcache_cache_value.c");
     let mut org_file_content = String::new();
 
-    File::open(&patched_file_path)
-        .unwrap()
-        .read_to_string(&mut org_file_content)
-        .unwrap();
+    File::open(&patched_file_path)?.read_to_string(&mut org_file_content)?;
+
+    let lines: Vec<&str> = org_file_content.lines().collect();
+    let block: Vec<&str> = LIBFCACHE_PATCHED_BLOCK.trim_matches('\n').lines().collect();
 
-    let patched_file_lines: Vec<&str> = org_file_content.lines().enumerate()
-        .filter(|(line_idx, _line)| (line_idx + 1 < 477) || (489 < line_idx +1) )
-        .map(|(_line_idx, line)| line)
+    let matches_at = |start: usize| {
+        lines[start..start + block.len()]
+            .iter()
+            .zip(&block)
+            .all(|(line, block_line)| line.trim() == *block_line)
+    };
+
+    let starts: Vec<usize> = (0..(lines.len() + 1).saturating_sub(block.len()))
+        .filter(|&start| matches_at(start))
         .collect();
 
+    let start = match starts.as_slice() {
+        [start] => *start,
+        _ => {
+            return Err(BuildError::FailedToPatch {
+                path: patched_file_path.display().to_string(),
+                reason: format!(
+                    "expected the block to remove once, found it {} times (another libfcache \
+                     revision?)",
+                    starts.len()
+                ),
+            })
+        }
+    };
+
+    let mut patched_file_lines = lines[..start].to_vec();
+    patched_file_lines.extend_from_slice(&lines[start + block.len()..]);
+
     let patched_file_content = patched_file_lines.join("\n");
 
-    File::create(&patched_file_path)
-        .unwrap()
-        .write_all(&patched_file_content.as_bytes())
-        .unwrap();
+    File::create(&patched_file_path)?.write_all(&patched_file_content.as_bytes())?;
+
+    Ok(())
+}
+
+/// The modules of `src/lib.rs` the functions are split into.
+const BINDING_MODULES: &[&str] = &[
+    "attribute",
+    "data_stream",
+    "error",
+    "file_entry",
+    "other",
+    "usn",
+    "volume",
+];
+
+/// The libfsntfs objects which are attributes. Matched first, as some of them start like other
+/// objects (e.g. `libfsntfs_volume_name_attribute_*`).
+const ATTRIBUTE_OBJECTS: &[&str] = &[
+    "attribute_",
+    "file_name_attribute_",
+    "object_identifier_attribute_",
+    "reparse_point_attribute_",
+    "security_descriptor_attribute_",
+    "standard_information_attribute_",
+    "volume_information_attribute_",
+    "volume_name_attribute_",
+];
+
+fn binding_module(function: &str) -> &'static str {
+    let area = function.trim_start_matches("libfsntfs_");
+
+    if ATTRIBUTE_OBJECTS
+        .iter()
+        .any(|object| area.starts_with(object))
+    {
+        "attribute"
+    } else if area.starts_with("data_stream_") {
+        "data_stream"
+    } else if area.starts_with("error_") {
+        "error"
+    } else if area.starts_with("file_entry_") {
+        "file_entry"
+    } else if area.starts_with("usn_") {
+        "usn"
+    } else if area.starts_with("volume_") {
+        "volume"
+    } else {
+        "other"
+    }
+}
+
+fn build() -> Result<(), BuildError> {
+    // Built elsewhere, there is nothing to fetch or build.
+    if let Some(include_folder_path) = get_prebuilt_lib("libfsntfs")? {
+        if cfg!(feature = "runtime-loading") {
+            generate_runtime_loader(&include_folder_path, "wrapper.h", "libfsntfs_.*")?;
+        } else if cfg!(feature = "dynamic_link") {
+            link_dynamic();
+        } else if !cfg!(feature = "no-link") {
+            link_static();
+        }
+
+        return generate_split_bindings(
+            &include_folder_path,
+            "wrapper.h",
+            BINDING_MODULES,
+            binding_module,
+        );
+    }
+
+    let lib_path = get_lib_or_fetch("libfsntfs", LIBFSNTFS_VERSION, LIBFSNTFS_SHA256)?;
+
+    // Release packages come with their dependencies, only git checkouts need to be synced.
+    if lib_path.join("synclibs.sh").exists() {
+        sync_libs(&lib_path)?;
+    }
+
+    patch_libfcache(&lib_path)?;
+
+    let mut builder = LibBuilder::new(lib_path).share_sublibraries(true);
+
+    if cfg!(feature = "asan") && !cfg!(feature = "no-link") {
+        builder = enable_sanitizers(builder);
+    }
+
+    // Consumers which link libfsntfs themselves (or load it at runtime) only need the bindings.
+    if cfg!(feature = "no-link") {
+        let include_folder_path = builder.generate_headers()?;
+
+        if cfg!(feature = "runtime-loading") {
+            generate_runtime_loader(&include_folder_path, "wrapper.h", "libfsntfs_.*")?;
+        }
+
+        return generate_split_bindings(
+            &include_folder_path,
+            "wrapper.h",
+            BINDING_MODULES,
+            binding_module,
+        );
+    }
+
+    if cfg!(feature = "debug-output") {
+        builder = builder.configure_args(vec!["--enable-verbose-output", "--enable-debug-output"]);
+    }
 
     let include_folder_path = if cfg!(feature = "dynamic_link") {
-        build_and_link_dynamic(lib_path)
+        build_and_link_dynamic(builder)?
     } else {
-        build_and_link_static(lib_path)
+        build_and_link_static(builder)?
     };
 
-    generate_bindings(&include_folder_path, "wrapper.h");
+    generate_split_bindings(
+        &include_folder_path,
+        "wrapper.h",
+        BINDING_MODULES,
+        binding_module,
+    )
+}
+
+fn main() {
+    if let Err(e) = build() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
 }
@@ -1,5 +1,72 @@
+//! The bindings are split by area, every item is re-exported at the root as well.
 #![allow(non_upper_case_globals)]
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
-include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+/// Loading libfsntfs at runtime, instead of linking it:
+///
+/// ```ignore
+/// let libfsntfs = unsafe { loader::Library::open("libfsntfs.so")? };
+/// let mut volume = VolumePtr::null();
+/// let mut error = ErrorPtr::null();
+///
+/// unsafe { (libfsntfs.libfsntfs_volume_initialize)(volume.as_out_ptr(), error.as_out_ptr()) };
+/// ```
+#[cfg(feature = "runtime-loading")]
+pub mod loader {
+    use super::types::*;
+    include!(concat!(env!("OUT_DIR"), "/loader.rs"));
+}
+
+pub mod ptr;
+
+/// Types and constants.
+pub mod types {
+    include!(concat!(env!("OUT_DIR"), "/types.rs"));
+}
+
+pub mod attribute {
+    use super::types::*;
+    include!(concat!(env!("OUT_DIR"), "/attribute.rs"));
+}
+
+pub mod data_stream {
+    use super::types::*;
+    include!(concat!(env!("OUT_DIR"), "/data_stream.rs"));
+}
+
+pub mod error {
+    use super::types::*;
+    include!(concat!(env!("OUT_DIR"), "/error.rs"));
+}
+
+pub mod file_entry {
+    use super::types::*;
+    include!(concat!(env!("OUT_DIR"), "/file_entry.rs"));
+}
+
+/// Functions of the library itself (e.g. `libfsntfs_get_version`) and of the smaller areas.
+pub mod other {
+    use super::types::*;
+    include!(concat!(env!("OUT_DIR"), "/other.rs"));
+}
+
+pub mod usn {
+    use super::types::*;
+    include!(concat!(env!("OUT_DIR"), "/usn.rs"));
+}
+
+pub mod volume {
+    use super::types::*;
+    include!(concat!(env!("OUT_DIR"), "/volume.rs"));
+}
+
+pub use crate::attribute::*;
+pub use crate::data_stream::*;
+pub use crate::error::*;
+pub use crate::file_entry::*;
+pub use crate::other::*;
+pub use crate::ptr::*;
+pub use crate::types::*;
+pub use crate::usn::*;
+pub use crate::volume::*;
@@ -0,0 +1,108 @@
+//! Run with `cargo bench --features bench`.
+#[macro_use]
+extern crate criterion;
+
+use criterion::Criterion;
+use libfsntfs_rs::file_entry::FileEntry;
+use libfsntfs_rs::volume::{AccessMode, MftEntryIndex, Volume};
+use std::io::{Read, Seek, SeekFrom};
+
+fn sample_volume() -> Volume {
+    let sample = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/ntfs-img-kw-1.dd");
+    Volume::open(sample, AccessMode::Read).unwrap()
+}
+
+fn walk(entry: &FileEntry) -> usize {
+    let mut count = 1;
+
+    for sub_entry in entry.iter_sub_entries().unwrap().filter_map(|e| e.ok()) {
+        // Guards against directories listing themselves (the root directory).
+        if sub_entry.get_file_reference().ok() == entry.get_file_reference().ok() {
+            continue;
+        }
+
+        count += walk(&sub_entry);
+    }
+
+    count
+}
+
+fn largest_file(volume: &Volume) -> MftEntryIndex {
+    volume
+        .mft_entries()
+        .unwrap()
+        .filter_map(|(idx, entry)| {
+            let entry = entry.ok()?;
+
+            if entry.has_default_data_stream().ok()? {
+                Some((entry.get_size().ok()?, idx))
+            } else {
+                None
+            }
+        })
+        .max()
+        .map(|(_, idx)| idx)
+        .unwrap()
+}
+
+fn bench_tree_walk(c: &mut Criterion) {
+    let volume = sample_volume();
+
+    c.bench_function("tree walk", move |b| {
+        b.iter(|| walk(&volume.get_root_directory().unwrap()))
+    });
+}
+
+fn bench_mft_export(c: &mut Criterion) {
+    let volume = sample_volume();
+
+    c.bench_function("mft export", move |b| {
+        b.iter(|| {
+            volume
+                .mft_entries()
+                .unwrap()
+                .filter_map(|(idx, entry)| {
+                    let entry = entry.ok()?;
+
+                    Some((
+                        idx,
+                        entry.get_name().ok(),
+                        entry.get_size().ok(),
+                        entry.get_file_attribute_flags().ok(),
+                        entry.get_number_of_alternate_data_streams().ok(),
+                    ))
+                })
+                .count()
+        })
+    });
+}
+
+fn bench_sequential_read(c: &mut Criterion) {
+    let volume = sample_volume();
+    let idx = largest_file(&volume);
+
+    c.bench_function("sequential read of the largest file", move |b| {
+        let mut entry = volume.get_file_entry_by_mft_idx(idx).unwrap();
+        let mut buffer = vec![0; 64 * 1024];
+
+        b.iter(|| {
+            entry.seek(SeekFrom::Start(0)).unwrap();
+
+            let mut total = 0;
+            loop {
+                match entry.read(&mut buffer).unwrap() {
+                    0 => break total,
+                    read => total += read,
+                }
+            }
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_tree_walk,
+    bench_mft_export,
+    bench_sequential_read
+);
+criterion_main!(benches);
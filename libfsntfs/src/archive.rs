@@ -0,0 +1,346 @@
+//! Packages a directory subtree into a tar or zip archive, with the `archive` feature.
+//!
+//! Members are named by their path below the exported entry (which is the top level member,
+//! unless it is the root directory), with `/` separators. Alternate data streams, when
+//! included, are members of their own named `file:stream`.
+//!
+//! ```ignore
+//! let users = volume.get_file_entry_by_path("\\Users")?;
+//! let report = export_to_tar(&users, File::create("users.tar")?, &ArchiveOptions::new())?;
+//! ```
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::metadata_files;
+use crate::utils::{datetime_from_filetime, mft_entry_index};
+use chrono::{Datelike, Timelike};
+use std::collections::HashSet;
+use std::io::{self, Read, Seek, Write};
+
+/// Seconds between 1601-01-01 (FILETIME) and 1970-01-01 (Unix time).
+const FILETIME_UNIX_EPOCH_DIFFERENCE: u64 = 11_644_473_600;
+
+#[derive(Debug, Clone)]
+pub struct ArchiveOptions {
+    /// Adds the alternate data streams of every entry as members, `file:stream`.
+    pub include_ads: bool,
+    /// Sets the modification time of members from `$STANDARD_INFORMATION`.
+    pub preserve_timestamps: bool,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        ArchiveOptions {
+            include_ads: false,
+            preserve_timestamps: true,
+        }
+    }
+}
+
+impl ArchiveOptions {
+    pub fn new() -> Self {
+        ArchiveOptions::default()
+    }
+
+    pub fn include_ads(mut self, include_ads: bool) -> Self {
+        self.include_ads = include_ads;
+        self
+    }
+
+    pub fn preserve_timestamps(mut self, preserve_timestamps: bool) -> Self {
+        self.preserve_timestamps = preserve_timestamps;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveReport {
+    pub directories: u64,
+    pub files: u64,
+    pub streams: u64,
+    /// Bytes of file and stream data archived.
+    pub bytes: u64,
+}
+
+/// A member of the archive, `data` is `None` for directories.
+struct Member<'r> {
+    path: String,
+    /// As a FILETIME.
+    modified: Option<u64>,
+    size: u64,
+    data: Option<&'r mut dyn Read>,
+    is_stream: bool,
+}
+
+impl ArchiveReport {
+    fn add(&mut self, member: &Member) {
+        if member.data.is_none() {
+            self.directories += 1;
+        } else if member.is_stream {
+            self.streams += 1;
+        } else {
+            self.files += 1;
+        }
+
+        self.bytes += member.size;
+    }
+}
+
+fn join(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+/// Calls `on_member` for `entry` and everything below it, parents before their children.
+fn visit(
+    entry: &FileEntry,
+    options: &ArchiveOptions,
+    on_member: &mut dyn FnMut(Member) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let volume = entry.volume();
+    let idx = mft_entry_index(entry.get_file_reference()?);
+
+    // The entries are read through handles of their own, so `entry` is not moved.
+    let top_level = if idx == metadata_files::ROOT_DIRECTORY {
+        String::new()
+    } else {
+        entry.get_name()?
+    };
+    let mut stack = vec![(volume.get_file_entry_by_mft_idx(idx)?, top_level)];
+    let mut visited = HashSet::new();
+
+    while let Some((mut entry, path)) = stack.pop() {
+        let modified = if options.preserve_timestamps {
+            entry
+                .get_timestamp_report()?
+                .standard_information
+                .map(|times| times.modification)
+        } else {
+            None
+        };
+
+        if entry.has_directory_entries_index()? {
+            // Corrupt directories can list an ancestor.
+            if !visited.insert(mft_entry_index(entry.get_file_reference()?)) {
+                continue;
+            }
+
+            if !path.is_empty() {
+                on_member(Member {
+                    path: path.clone(),
+                    modified,
+                    size: 0,
+                    data: None,
+                    is_stream: false,
+                })?;
+            }
+
+            for sub_entry_index in (0..entry.get_number_of_sub_file_entries()?).rev() {
+                let sub_entry = entry.get_sub_file_entry(sub_entry_index)?;
+                let sub_path = join(&path, &sub_entry.get_name()?);
+
+                stack.push((sub_entry, sub_path));
+            }
+        } else {
+            let size = entry.get_size()?;
+
+            on_member(Member {
+                path: path.clone(),
+                modified,
+                size,
+                data: Some(&mut entry),
+                is_stream: false,
+            })?;
+        }
+
+        if options.include_ads && !path.is_empty() {
+            for stream_index in 0..entry.get_number_of_alternate_data_streams()? {
+                let mut stream = entry.get_alternate_data_stream(stream_index)?;
+                let stream_path = format!("{}:{}", path, stream.get_name()?);
+                let size = stream.get_size()?;
+
+                on_member(Member {
+                    path: stream_path,
+                    modified,
+                    size,
+                    data: Some(&mut stream),
+                    is_stream: true,
+                })?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn unix_seconds(filetime: u64) -> u64 {
+    (filetime / 10_000_000).saturating_sub(FILETIME_UNIX_EPOCH_DIFFERENCE)
+}
+
+/// Writes `entry` and everything below it to a tar archive.
+pub fn export_to_tar<W: Write>(
+    entry: &FileEntry,
+    writer: W,
+    options: &ArchiveOptions,
+) -> Result<ArchiveReport, Error> {
+    let mut builder = tar::Builder::new(writer);
+    let mut report = ArchiveReport::default();
+
+    visit(entry, options, &mut |member| {
+        report.add(&member);
+
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(member.modified.map_or(0, unix_seconds));
+
+        match member.data {
+            None => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(0o755);
+                header.set_size(0);
+                builder.append_data(&mut header, format!("{}/", member.path), io::empty())?;
+            }
+            Some(data) => {
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_mode(0o644);
+                header.set_size(member.size);
+
+                // A short read would misalign every member after this one.
+                let mut data = data.take(member.size);
+                builder.append_data(&mut header, &member.path, &mut data)?;
+
+                if data.limit() != 0 {
+                    return Err(Error::Other(format!(
+                        "{} is shorter than its size",
+                        member.path
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    })?;
+
+    builder.finish()?;
+
+    Ok(report)
+}
+
+fn zip_error(error: zip::result::ZipError) -> Error {
+    Error::Other(format!("zip: {}", error))
+}
+
+/// Zip timestamps only cover 1980 to 2107.
+fn zip_time(filetime: u64) -> Option<zip::DateTime> {
+    let time = datetime_from_filetime(filetime);
+
+    if time.year() < 1980 || time.year() > 2107 {
+        return None;
+    }
+
+    zip::DateTime::from_date_and_time(
+        time.year() as u16,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+}
+
+/// Writes `entry` and everything below it to a zip archive, deflating the data.
+pub fn export_to_zip<W: Write + Seek>(
+    entry: &FileEntry,
+    writer: W,
+    options: &ArchiveOptions,
+) -> Result<ArchiveReport, Error> {
+    let mut zip = zip::ZipWriter::new(writer);
+    let mut report = ArchiveReport::default();
+
+    visit(entry, options, &mut |member| {
+        report.add(&member);
+
+        let mut file_options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+        if let Some(time) = member.modified.and_then(zip_time) {
+            file_options = file_options.last_modified_time(time);
+        }
+
+        match member.data {
+            None => zip
+                .add_directory(format!("{}/", member.path), file_options)
+                .map_err(zip_error)?,
+            Some(data) => {
+                zip.start_file(member.path, file_options)
+                    .map_err(zip_error)?;
+                io::copy(data, &mut zip)?;
+            }
+        }
+
+        Ok(())
+    })?;
+
+    zip.finish().map_err(zip_error)?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_export_to_tar() {
+        let volume = sample_volume().unwrap();
+        let extend = volume.get_file_entry_by_path("\\$Extend").unwrap();
+
+        let mut archive = vec![];
+        let report = export_to_tar(&extend, &mut archive, &ArchiveOptions::new()).unwrap();
+
+        let mut tar = tar::Archive::new(Cursor::new(archive));
+        let paths: Vec<String> = tar
+            .entries()
+            .unwrap()
+            .map(|member| {
+                let member = member.unwrap();
+                member.path().unwrap().to_string_lossy().into_owned()
+            })
+            .collect();
+
+        assert_eq!(paths[0], "$Extend/");
+        assert!(paths.contains(&"$Extend/$Reparse".to_owned()));
+        assert_eq!(
+            paths.len() as u64,
+            report.directories + report.files + report.streams
+        );
+    }
+
+    #[test]
+    fn test_export_to_zip_with_ads() {
+        let volume = sample_volume().unwrap();
+        let secure = volume
+            .get_file_entry_by_mft_idx(metadata_files::SECURE)
+            .unwrap();
+
+        let mut archive = Cursor::new(vec![]);
+        let options = ArchiveOptions::new().include_ads(true);
+        let report = export_to_zip(&secure, &mut archive, &options).unwrap();
+
+        assert_eq!((report.files, report.streams), (1, 1));
+
+        let mut zip = zip::ZipArchive::new(archive).unwrap();
+        let sds = zip.by_name("$Secure:$SDS").unwrap();
+        assert_eq!(sds.size(), report.bytes);
+    }
+
+    #[test]
+    fn test_unix_seconds() {
+        // 2019-01-01 00:00:00
+        assert_eq!(unix_seconds(131_907_744_000_000_000), 1_546_300_800);
+    }
+}
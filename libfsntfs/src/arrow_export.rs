@@ -0,0 +1,564 @@
+//! Exports MFT entries as Arrow record batches, and with the `parquet` feature as Parquet files.
+//!
+//! The schema is stable: columns are only ever added at the end. Rows are in ascending MFT
+//! entry index order, and `record_id` identifies them across exports (see `record_id`).
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::filter::EntryFilter;
+use crate::paths::PathResolver;
+use crate::record_id::RecordId;
+use crate::timestamps::{MacbField, MacbTimes};
+use crate::utils::{mft_entry_index, sequence_number};
+use crate::volume::{MftEntries, MftEntryIndex, Volume};
+use arrow::array::{
+    ArrayRef, BooleanBuilder, StringBuilder, TimestampMicrosecondBuilder, UInt16Builder,
+    UInt32Builder, UInt64Builder,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use std::sync::Arc;
+
+/// FILETIME (100 nanoseconds since 1601-01-01) of the unix epoch.
+const FILETIME_UNIX_EPOCH: i64 = 116_444_736_000_000_000;
+
+const TIMESTAMP_FIELDS: [MacbField; 4] = [
+    MacbField::Creation,
+    MacbField::Modification,
+    MacbField::EntryModification,
+    MacbField::Access,
+];
+
+fn arrow_error(error: ArrowError) -> Error {
+    Error::Other(format!("arrow: {:?}", error))
+}
+
+/// Microseconds since the unix epoch, `None` when the timestamp is not set.
+fn filetime_to_micros(filetime: u64) -> Option<i64> {
+    match filetime {
+        0 => None,
+        filetime => Some((filetime as i64 - FILETIME_UNIX_EPOCH) / 10),
+    }
+}
+
+/// The schema of the batches returned by `Volume::record_batches`.
+pub fn entry_schema() -> Schema {
+    let timestamp = DataType::Timestamp(TimeUnit::Microsecond, None);
+
+    Schema::new(vec![
+        Field::new("entry_number", DataType::UInt64, false),
+        Field::new("sequence_number", DataType::UInt16, false),
+        Field::new("in_use", DataType::Boolean, false),
+        Field::new("parent_entry_number", DataType::UInt64, true),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("path", DataType::Utf8, false),
+        Field::new("is_directory", DataType::Boolean, false),
+        Field::new("file_size", DataType::UInt64, false),
+        Field::new("ads_count", DataType::UInt32, false),
+        Field::new("flags", DataType::UInt32, false),
+        Field::new("si_created", timestamp.clone(), true),
+        Field::new("si_modified", timestamp.clone(), true),
+        Field::new("si_entry_modified", timestamp.clone(), true),
+        Field::new("si_accessed", timestamp.clone(), true),
+        Field::new("fn_created", timestamp.clone(), true),
+        Field::new("fn_modified", timestamp.clone(), true),
+        Field::new("fn_entry_modified", timestamp.clone(), true),
+        Field::new("fn_accessed", timestamp, true),
+        Field::new("record_id", DataType::Utf8, false),
+    ])
+}
+
+/// One row of the export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryRecord {
+    pub entry_number: MftEntryIndex,
+    pub sequence_number: u16,
+    pub in_use: bool,
+    pub parent_entry_number: Option<MftEntryIndex>,
+    pub name: String,
+    pub path: String,
+    pub is_directory: bool,
+    pub file_size: u64,
+    pub ads_count: u32,
+    pub flags: u32,
+    pub standard_information: Option<MacbTimes>,
+    pub file_name: Option<MacbTimes>,
+}
+
+impl EntryRecord {
+    pub fn from_entry(
+        idx: MftEntryIndex,
+        entry: &FileEntry,
+        paths: &mut PathResolver,
+    ) -> Result<Self, Error> {
+        let (mut record, parent_file_reference) = EntryRecord::without_path(idx, entry)?;
+        record.path = paths.path_of(entry.volume(), idx, &record.name, parent_file_reference);
+
+        Ok(record)
+    }
+
+    /// The record with an empty `path`, and the parent reference to resolve it from.
+    fn without_path(idx: MftEntryIndex, entry: &FileEntry) -> Result<(Self, Option<u64>), Error> {
+        let is_directory = entry.has_directory_entries_index()?;
+        let report = entry.get_timestamp_report()?;
+        let parent_file_reference = entry.get_parent_file_reference().ok();
+
+        let record = EntryRecord {
+            entry_number: idx,
+            sequence_number: sequence_number(entry.get_file_reference()?),
+            in_use: entry.is_allocated()?,
+            parent_entry_number: parent_file_reference.map(mft_entry_index),
+            name: entry.get_name().unwrap_or_default(),
+            path: String::new(),
+            is_directory,
+            file_size: if is_directory { 0 } else { entry.get_size()? },
+            ads_count: entry.get_number_of_alternate_data_streams()? as u32,
+            flags: entry.get_file_attribute_flags()?.bits(),
+            standard_information: report.standard_information,
+            file_name: report.file_name,
+        };
+
+        Ok((record, parent_file_reference))
+    }
+
+    pub fn record_id(&self) -> RecordId {
+        RecordId {
+            entry_number: self.entry_number,
+            sequence_number: self.sequence_number,
+            stream_index: 0,
+        }
+    }
+
+    /// The eight timestamps, in the order of the schema.
+    fn timestamps(&self) -> Vec<Option<i64>> {
+        let mut timestamps = Vec::with_capacity(8);
+
+        for times in [self.standard_information, self.file_name].iter() {
+            for field in TIMESTAMP_FIELDS.iter() {
+                timestamps.push(times.and_then(|times| filetime_to_micros(times.get(*field))));
+            }
+        }
+
+        timestamps
+    }
+}
+
+/// Builds a record batch out of `records`.
+pub fn records_to_batch(records: &[EntryRecord]) -> Result<RecordBatch, Error> {
+    let capacity = records.len();
+
+    let mut entry_number = UInt64Builder::new(capacity);
+    let mut sequence_number = UInt16Builder::new(capacity);
+    let mut in_use = BooleanBuilder::new(capacity);
+    let mut parent_entry_number = UInt64Builder::new(capacity);
+    let mut name = StringBuilder::new(capacity);
+    let mut path = StringBuilder::new(capacity);
+    let mut is_directory = BooleanBuilder::new(capacity);
+    let mut file_size = UInt64Builder::new(capacity);
+    let mut ads_count = UInt32Builder::new(capacity);
+    let mut flags = UInt32Builder::new(capacity);
+    let mut timestamps: Vec<TimestampMicrosecondBuilder> = (0..8)
+        .map(|_| TimestampMicrosecondBuilder::new(capacity))
+        .collect();
+    let mut record_id = StringBuilder::new(capacity);
+
+    for record in records {
+        entry_number
+            .append_value(record.entry_number)
+            .map_err(arrow_error)?;
+        sequence_number
+            .append_value(record.sequence_number)
+            .map_err(arrow_error)?;
+        in_use.append_value(record.in_use).map_err(arrow_error)?;
+        match record.parent_entry_number {
+            Some(parent) => parent_entry_number.append_value(parent),
+            None => parent_entry_number.append_null(),
+        }
+        .map_err(arrow_error)?;
+        name.append_value(&record.name).map_err(arrow_error)?;
+        path.append_value(&record.path).map_err(arrow_error)?;
+        is_directory
+            .append_value(record.is_directory)
+            .map_err(arrow_error)?;
+        file_size
+            .append_value(record.file_size)
+            .map_err(arrow_error)?;
+        ads_count
+            .append_value(record.ads_count)
+            .map_err(arrow_error)?;
+        flags.append_value(record.flags).map_err(arrow_error)?;
+
+        for (builder, timestamp) in timestamps.iter_mut().zip(record.timestamps()) {
+            match timestamp {
+                Some(timestamp) => builder.append_value(timestamp),
+                None => builder.append_null(),
+            }
+            .map_err(arrow_error)?;
+        }
+
+        record_id
+            .append_value(&record.record_id().to_string())
+            .map_err(arrow_error)?;
+    }
+
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(entry_number.finish()),
+        Arc::new(sequence_number.finish()),
+        Arc::new(in_use.finish()),
+        Arc::new(parent_entry_number.finish()),
+        Arc::new(name.finish()),
+        Arc::new(path.finish()),
+        Arc::new(is_directory.finish()),
+        Arc::new(file_size.finish()),
+        Arc::new(ads_count.finish()),
+        Arc::new(flags.finish()),
+    ];
+
+    for builder in timestamps.iter_mut() {
+        columns.push(Arc::new(builder.finish()));
+    }
+
+    columns.push(Arc::new(record_id.finish()));
+
+    RecordBatch::try_new(Arc::new(entry_schema()), columns).map_err(arrow_error)
+}
+
+/// Reads the MFT in batches of `batch_size` entries, entries which cannot be read (or with
+/// fields which cannot be read) are skipped.
+///
+/// The paths of a batch are only resolved once all its entries were read, so the parents
+/// within the batch (and the batches before it) are never read again.
+pub struct RecordBatches<'a> {
+    volume: &'a Volume,
+    entries: MftEntries<'a>,
+    paths: PathResolver,
+    batch_size: usize,
+    schema: SchemaRef,
+    filter: EntryFilter,
+}
+
+impl<'a> RecordBatches<'a> {
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Only reads the entries matching `filter`, the others are skipped before their paths
+    /// are reconstructed.
+    pub fn with_filter(mut self, filter: EntryFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// The records of the next batch, empty once the MFT was read.
+    pub fn next_records(&mut self) -> Result<Vec<EntryRecord>, Error> {
+        let mut unresolved = Vec::with_capacity(self.batch_size);
+
+        while unresolved.len() < self.batch_size {
+            match self.entries.next() {
+                Some((idx, Ok(entry))) => {
+                    // Directories filtered out may still be the parents of records.
+                    self.paths.add_directory(idx, &entry);
+
+                    if self.filter.matches(&entry).unwrap_or(false) {
+                        if let Ok(record) = EntryRecord::without_path(idx, &entry) {
+                            unresolved.push(record);
+                        }
+                    }
+                }
+                Some((_, Err(_))) => continue,
+                None => break,
+            }
+        }
+
+        let mut records = Vec::with_capacity(unresolved.len());
+
+        for (mut record, parent_file_reference) in unresolved {
+            record.path = self.paths.path_of(
+                self.volume,
+                record.entry_number,
+                &record.name,
+                parent_file_reference,
+            );
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+}
+
+impl<'a> Iterator for RecordBatches<'a> {
+    type Item = Result<RecordBatch, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_records() {
+            Ok(ref records) if records.is_empty() => None,
+            Ok(records) => Some(records_to_batch(&records)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl Volume {
+    /// Reads the MFT as Arrow record batches of up to `batch_size` rows, see `entry_schema`.
+    /// `batch_size` must not be 0.
+    pub fn record_batches(&self, batch_size: usize) -> Result<RecordBatches, Error> {
+        if batch_size == 0 {
+            return Err(Error::Other("the batch size must not be 0".to_owned()));
+        }
+
+        Ok(RecordBatches {
+            volume: self,
+            entries: self.mft_entries()?,
+            paths: PathResolver::new(),
+            batch_size,
+            schema: Arc::new(entry_schema()),
+            filter: EntryFilter::default(),
+        })
+    }
+}
+
+#[cfg(feature = "parquet")]
+pub use self::parquet_export::*;
+
+#[cfg(feature = "parquet")]
+mod parquet_export {
+    use super::*;
+    use parquet::column::writer::{ColumnWriter, ColumnWriterImpl};
+    use parquet::data_type::{ByteArray, DataType as ParquetDataType};
+    use parquet::errors::ParquetError;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::{FileWriter, RowGroupWriter, SerializedFileWriter};
+    use parquet::schema::parser::parse_message_type;
+    use std::fs::File;
+    use std::rc::Rc;
+
+    /// `entry_schema` in Parquet terms. All columns but `record_id` are optional to keep the
+    /// writer uniform.
+    pub const PARQUET_SCHEMA: &str = "
+        message mft_entry {
+            OPTIONAL INT64 entry_number (UINT_64);
+            OPTIONAL INT32 sequence_number (UINT_16);
+            OPTIONAL BOOLEAN in_use;
+            OPTIONAL INT64 parent_entry_number (UINT_64);
+            OPTIONAL BYTE_ARRAY name (UTF8);
+            OPTIONAL BYTE_ARRAY path (UTF8);
+            OPTIONAL BOOLEAN is_directory;
+            OPTIONAL INT64 file_size (UINT_64);
+            OPTIONAL INT32 ads_count (UINT_32);
+            OPTIONAL INT32 flags (UINT_32);
+            OPTIONAL INT64 si_created (TIMESTAMP_MICROS);
+            OPTIONAL INT64 si_modified (TIMESTAMP_MICROS);
+            OPTIONAL INT64 si_entry_modified (TIMESTAMP_MICROS);
+            OPTIONAL INT64 si_accessed (TIMESTAMP_MICROS);
+            OPTIONAL INT64 fn_created (TIMESTAMP_MICROS);
+            OPTIONAL INT64 fn_modified (TIMESTAMP_MICROS);
+            OPTIONAL INT64 fn_entry_modified (TIMESTAMP_MICROS);
+            OPTIONAL INT64 fn_accessed (TIMESTAMP_MICROS);
+            REQUIRED BYTE_ARRAY record_id (UTF8);
+        }
+    ";
+
+    fn parquet_error(error: ParquetError) -> Error {
+        Error::Other(format!("parquet: {}", error))
+    }
+
+    enum ColumnValues {
+        Int32(Vec<Option<i32>>),
+        Int64(Vec<Option<i64>>),
+        Boolean(Vec<Option<bool>>),
+        Utf8(Vec<Option<ByteArray>>),
+        RequiredUtf8(Vec<ByteArray>),
+    }
+
+    /// The values of column `column_index` of `PARQUET_SCHEMA`.
+    fn column_values(records: &[EntryRecord], column_index: usize) -> ColumnValues {
+        let records = records.iter();
+
+        match column_index {
+            0 => ColumnValues::Int64(records.map(|r| Some(r.entry_number as i64)).collect()),
+            1 => ColumnValues::Int32(
+                records
+                    .map(|r| Some(i32::from(r.sequence_number)))
+                    .collect(),
+            ),
+            2 => ColumnValues::Boolean(records.map(|r| Some(r.in_use)).collect()),
+            3 => ColumnValues::Int64(
+                records
+                    .map(|r| r.parent_entry_number.map(|n| n as i64))
+                    .collect(),
+            ),
+            4 => ColumnValues::Utf8(
+                records
+                    .map(|r| Some(ByteArray::from(r.name.as_str())))
+                    .collect(),
+            ),
+            5 => ColumnValues::Utf8(
+                records
+                    .map(|r| Some(ByteArray::from(r.path.as_str())))
+                    .collect(),
+            ),
+            6 => ColumnValues::Boolean(records.map(|r| Some(r.is_directory)).collect()),
+            7 => ColumnValues::Int64(records.map(|r| Some(r.file_size as i64)).collect()),
+            8 => ColumnValues::Int32(records.map(|r| Some(r.ads_count as i32)).collect()),
+            9 => ColumnValues::Int32(records.map(|r| Some(r.flags as i32)).collect()),
+            18 => ColumnValues::RequiredUtf8(
+                records
+                    .map(|r| ByteArray::from(r.record_id().to_string().as_str()))
+                    .collect(),
+            ),
+            timestamp_index => ColumnValues::Int64(
+                records
+                    .map(|r| r.timestamps()[timestamp_index - 10])
+                    .collect(),
+            ),
+        }
+    }
+
+    fn write_optional<T: ParquetDataType>(
+        writer: &mut ColumnWriterImpl<T>,
+        values: Vec<Option<T::T>>,
+    ) -> Result<(), Error> {
+        let definition_levels: Vec<i16> = values
+            .iter()
+            .map(|value| if value.is_some() { 1 } else { 0 })
+            .collect();
+        let values: Vec<T::T> = values.into_iter().filter_map(|value| value).collect();
+
+        writer
+            .write_batch(&values, Some(&definition_levels), None)
+            .map_err(parquet_error)?;
+
+        Ok(())
+    }
+
+    fn write_required<T: ParquetDataType>(
+        writer: &mut ColumnWriterImpl<T>,
+        values: Vec<T::T>,
+    ) -> Result<(), Error> {
+        writer
+            .write_batch(&values, None, None)
+            .map_err(parquet_error)?;
+
+        Ok(())
+    }
+
+    fn write_row_group(
+        writer: &mut SerializedFileWriter<File>,
+        records: &[EntryRecord],
+    ) -> Result<(), Error> {
+        let mut row_group_writer = writer.next_row_group().map_err(parquet_error)?;
+        let mut column_index = 0;
+
+        while let Some(mut column_writer) = row_group_writer.next_column().map_err(parquet_error)? {
+            match (&mut column_writer, column_values(records, column_index)) {
+                (ColumnWriter::Int32ColumnWriter(w), ColumnValues::Int32(values)) => {
+                    write_optional(w, values)?
+                }
+                (ColumnWriter::Int64ColumnWriter(w), ColumnValues::Int64(values)) => {
+                    write_optional(w, values)?
+                }
+                (ColumnWriter::BoolColumnWriter(w), ColumnValues::Boolean(values)) => {
+                    write_optional(w, values)?
+                }
+                (ColumnWriter::ByteArrayColumnWriter(w), ColumnValues::Utf8(values)) => {
+                    write_optional(w, values)?
+                }
+                (ColumnWriter::ByteArrayColumnWriter(w), ColumnValues::RequiredUtf8(values)) => {
+                    write_required(w, values)?
+                }
+                _ => {
+                    return Err(Error::Other(format!(
+                        "parquet: unexpected writer for column {}",
+                        column_index
+                    )))
+                }
+            }
+
+            row_group_writer
+                .close_column(column_writer)
+                .map_err(parquet_error)?;
+            column_index += 1;
+        }
+
+        writer
+            .close_row_group(row_group_writer)
+            .map_err(parquet_error)
+    }
+
+    impl Volume {
+        /// Writes the MFT to `file` as Parquet, one row group per `row_group_size` entries.
+        /// `row_group_size` must not be 0.
+        /// Returns the number of rows written.
+        pub fn export_parquet(&self, file: File, row_group_size: usize) -> Result<u64, Error> {
+            self.export_parquet_filtered(file, row_group_size, EntryFilter::default())
+        }
+
+        /// Like `export_parquet`, only writing the entries matching `filter`.
+        pub fn export_parquet_filtered(
+            &self,
+            file: File,
+            row_group_size: usize,
+            filter: EntryFilter,
+        ) -> Result<u64, Error> {
+            let mut batches = self.record_batches(row_group_size)?.with_filter(filter);
+
+            let schema = Rc::new(parse_message_type(PARQUET_SCHEMA).map_err(parquet_error)?);
+            let properties = Rc::new(WriterProperties::builder().build());
+
+            let mut writer =
+                SerializedFileWriter::new(file, schema, properties).map_err(parquet_error)?;
+            let mut rows = 0;
+
+            loop {
+                let records = batches.next_records()?;
+
+                if records.is_empty() {
+                    break;
+                }
+
+                write_row_group(&mut writer, &records)?;
+                rows += records.len() as u64;
+            }
+
+            writer.close().map_err(parquet_error)?;
+
+            Ok(rows)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_filetime_to_micros() {
+        assert_eq!(filetime_to_micros(0), None);
+        assert_eq!(filetime_to_micros(FILETIME_UNIX_EPOCH as u64), Some(0));
+        // 2019-01-01 00:00:00.1234567
+        assert_eq!(
+            filetime_to_micros(131_907_744_001_234_567),
+            Some(1_546_300_800_123_456)
+        );
+    }
+
+    #[test]
+    fn test_record_batches() {
+        let volume = sample_volume().unwrap();
+
+        assert!(volume.record_batches(0).is_err());
+
+        let mut rows = 0;
+
+        for batch in volume.record_batches(100).unwrap() {
+            let batch = batch.unwrap();
+
+            assert!(batch.num_rows() <= 100);
+            assert_eq!(batch.num_columns(), entry_schema().fields().len());
+            rows += batch.num_rows();
+        }
+
+        assert!(rows > 0);
+    }
+}
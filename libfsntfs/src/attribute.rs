@@ -1,6 +1,7 @@
 use crate::error::Error;
 use crate::ffi_error::{LibfsntfsErrorRef, LibfsntfsErrorRefMut};
 use crate::file_entry::FileEntry;
+use crate::flags::{FileAttributeFlags, VolumeFlags};
 use chrono::{Date, DateTime, NaiveDateTime, Utc};
 use libfsntfs_sys::size64_t;
 use libyal_rs_common::ffi::AsTypeRef;
@@ -9,6 +10,7 @@ use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::os::raw::c_int;
+use std::str::FromStr;
 use std::{fmt, ptr};
 
 #[repr(C)]
@@ -79,7 +81,7 @@ impl<'a> Debug for Attribute<'a> {
     }
 }
 
-#[derive(PartialOrd, PartialEq, Debug, Clone)]
+#[derive(PartialOrd, PartialEq, Eq, Hash, Debug, Clone, Copy)]
 #[repr(C)]
 pub enum AttributeType {
     Unused = 0,
@@ -130,6 +132,80 @@ impl TryFrom<u32> for AttributeType {
     }
 }
 
+/// All the attribute types, in the order of their type codes.
+const ATTRIBUTE_TYPES: [AttributeType; 18] = [
+    AttributeType::Unused,
+    AttributeType::StandardInformation,
+    AttributeType::AttributeList,
+    AttributeType::FileName,
+    AttributeType::ObjectIdentifier,
+    AttributeType::SecurityDescriptor,
+    AttributeType::VolumeName,
+    AttributeType::VolumeInformation,
+    AttributeType::Data,
+    AttributeType::IndexRoot,
+    AttributeType::IndexAllocation,
+    AttributeType::Bitmap,
+    AttributeType::ReparsePoint,
+    AttributeType::ExtendedInformation,
+    AttributeType::Extended,
+    AttributeType::PropertySet,
+    AttributeType::LoggedUtilityStream,
+    AttributeType::EndOfAttributes,
+];
+
+impl AttributeType {
+    /// The attribute type code, as stored in the MFT entry.
+    pub fn as_u32(self) -> u32 {
+        self as u32
+    }
+
+    /// The canonical NTFS name of the type (e.g. `$STANDARD_INFORMATION`).
+    pub fn name(self) -> &'static str {
+        match self {
+            AttributeType::Unused => "$UNUSED",
+            AttributeType::StandardInformation => "$STANDARD_INFORMATION",
+            AttributeType::AttributeList => "$ATTRIBUTE_LIST",
+            AttributeType::FileName => "$FILE_NAME",
+            AttributeType::ObjectIdentifier => "$OBJECT_ID",
+            AttributeType::SecurityDescriptor => "$SECURITY_DESCRIPTOR",
+            AttributeType::VolumeName => "$VOLUME_NAME",
+            AttributeType::VolumeInformation => "$VOLUME_INFORMATION",
+            AttributeType::Data => "$DATA",
+            AttributeType::IndexRoot => "$INDEX_ROOT",
+            AttributeType::IndexAllocation => "$INDEX_ALLOCATION",
+            AttributeType::Bitmap => "$BITMAP",
+            AttributeType::ReparsePoint => "$REPARSE_POINT",
+            AttributeType::ExtendedInformation => "$EA_INFORMATION",
+            AttributeType::Extended => "$EA",
+            AttributeType::PropertySet => "$PROPERTY_SET",
+            AttributeType::LoggedUtilityStream => "$LOGGED_UTILITY_STREAM",
+            AttributeType::EndOfAttributes => "$END",
+        }
+    }
+}
+
+impl fmt::Display for AttributeType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for AttributeType {
+    type Err = Error;
+
+    /// Parses the canonical name of the type, the leading `$` is optional and case is ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim_start_matches('$');
+
+        ATTRIBUTE_TYPES
+            .iter()
+            .find(|attribute_type| attribute_type.name()[1..].eq_ignore_ascii_case(name))
+            .cloned()
+            .ok_or_else(|| Error::UnknownAttributeTypeName(s.to_owned()))
+    }
+}
+
 extern "C" {
     pub fn libfsntfs_attribute_free(
         attribute: *mut AttributeRefMut,
@@ -423,7 +499,7 @@ pub struct StandardInformation {
     pub modification_time: Option<DateTime<Utc>>,
     pub access_time: Option<DateTime<Utc>>,
     pub entry_modification_time: Option<DateTime<Utc>>,
-    pub file_attribute_flags: u32,
+    pub file_attribute_flags: FileAttributeFlags,
     pub owner_identifier: u32,
     pub security_descriptor_identifier: u32,
     pub update_sequence_number: u32,
@@ -437,7 +513,7 @@ pub struct FileName {
     pub modification_time: Option<DateTime<Utc>>,
     pub access_time: Option<DateTime<Utc>>,
     pub entry_modification_time: Option<DateTime<Utc>>,
-    pub file_attribute_flags: u32,
+    pub file_attribute_flags: FileAttributeFlags,
 }
 
 #[derive(Debug, Clone)]
@@ -451,9 +527,9 @@ pub struct Data {
 
 #[derive(Debug, Clone)]
 pub struct VolumeInformation {
-    pub version: u32,
-    // TODO: parse flags
-    pub flags: u16,
+    pub major_version: u8,
+    pub minor_version: u8,
+    pub flags: VolumeFlags,
 }
 
 #[derive(Debug, Clone)]
@@ -483,31 +559,42 @@ pub struct LoggedUtilityStream {}
 pub struct EndOfAttributes {}
 
 impl<'a> Attribute<'a> {
+    /// The name, decoded by the `Utf16Policy` of the volume.
     pub fn get_name(&self) -> Result<String, Error> {
-        get_sized_utf8_string!(
+        self.decode(get_sized_utf16_units!(
             self,
-            libfsntfs_attribute_get_utf8_name_size,
-            libfsntfs_attribute_get_utf8_name
-        )
+            libfsntfs_attribute_get_utf16_name_size,
+            libfsntfs_attribute_get_utf16_name
+        )?)
+    }
+
+    fn decode(&self, units: Vec<u16>) -> Result<String, Error> {
+        self.1
+            .volume()
+            .options()
+            .get_utf16_policy()
+            .decode(&units)
     }
 
     pub fn get_data(&self) -> Result<AttributeWithInformation, Error> {
-        match self.get_type()? {
+        let attribute_type = self.get_type()?;
+
+        match attribute_type {
             AttributeType::VolumeName => {
-                let volume_name = get_sized_utf8_string!(
+                let volume_name = self.decode(get_sized_utf16_units!(
                     self,
-                    libfsntfs_volume_name_attribute_get_utf8_name_size,
-                    libfsntfs_volume_name_attribute_get_utf8_name
-                )?;
+                    libfsntfs_volume_name_attribute_get_utf16_name_size,
+                    libfsntfs_volume_name_attribute_get_utf16_name
+                )?)?;
 
                 Ok(AttributeWithInformation::VolumeName(volume_name))
             }
             AttributeType::FileName => {
-                let name = get_sized_utf8_string!(
+                let name = self.decode(get_sized_utf16_units!(
                     self,
-                    libfsntfs_file_name_attribute_get_utf8_name_size,
-                    libfsntfs_file_name_attribute_get_utf8_name
-                )?;
+                    libfsntfs_file_name_attribute_get_utf16_name_size,
+                    libfsntfs_file_name_attribute_get_utf16_name
+                )?)?;
 
                 let creation_time =
                     get_date_field!(self, libfsntfs_file_name_attribute_get_creation_time)?;
@@ -525,6 +612,11 @@ impl<'a> Attribute<'a> {
                     libfsntfs_file_name_attribute_get_parent_file_reference
                 )?;
 
+                let file_attribute_flags = get_u32_field!(
+                    self,
+                    libfsntfs_file_name_attribute_get_file_attribute_flags
+                )?;
+
                 Ok(AttributeWithInformation::FileName(FileName {
                     name,
                     parent_file_reference,
@@ -532,7 +624,7 @@ impl<'a> Attribute<'a> {
                     modification_time,
                     access_time,
                     entry_modification_time,
-                    file_attribute_flags: 0,
+                    file_attribute_flags: FileAttributeFlags::from(file_attribute_flags),
                 }))
             }
             AttributeType::StandardInformation => {
@@ -552,6 +644,12 @@ impl<'a> Attribute<'a> {
                     self,
                     libfsntfs_standard_information_attribute_get_entry_modification_time
                 )?;
+                let file_attribute_flags = get_u32_field!(
+                    self,
+                    libfsntfs_standard_information_attribute_get_file_attribute_flags
+                )?;
+                let security_descriptor_identifier =
+                    self.get_security_descriptor_identifier()?.unwrap_or(0);
 
                 Ok(AttributeWithInformation::StandardInformation(
                     StandardInformation {
@@ -559,13 +657,24 @@ impl<'a> Attribute<'a> {
                         modification_time,
                         access_time,
                         entry_modification_time,
-                        file_attribute_flags: 0,
+                        file_attribute_flags: FileAttributeFlags::from(file_attribute_flags),
                         owner_identifier: 0,
-                        security_descriptor_identifier: 0,
+                        security_descriptor_identifier,
                         update_sequence_number: 0,
                     },
                 ))
             }
+            AttributeType::VolumeInformation => {
+                let (major_version, minor_version) = self.get_volume_version()?;
+
+                Ok(AttributeWithInformation::VolumeInformation(
+                    VolumeInformation {
+                        major_version,
+                        minor_version,
+                        flags: self.get_volume_flags()?,
+                    },
+                ))
+            }
             AttributeType::Data => Ok(AttributeWithInformation::Data(Data {
                 flags: 0,
                 vcn_range_first: 0,
@@ -585,12 +694,62 @@ impl<'a> Attribute<'a> {
                 ))
             }
             _ => Err(Error::Other(format!(
-                "Unimplemented data type: {:?}",
-                self.get_type().unwrap()
+                "Unimplemented data type: {}",
+                attribute_type
             ))),
         }
     }
 
+    /// Retrieves the tag of a `$REPARSE_POINT` attribute.
+    pub fn get_reparse_point_tag(&self) -> Result<u32, Error> {
+        get_u32_field!(self, libfsntfs_reparse_point_attribute_get_tag)
+    }
+
+    /// Retrieves the security id of a `$STANDARD_INFORMATION` attribute,
+    /// `None` for the short (pre NTFS 3.0) form of the attribute which does not have one.
+    pub fn get_security_descriptor_identifier(&self) -> Result<Option<u32>, Error> {
+        let mut security_descriptor_identifier = 0_u32;
+        let mut error = ptr::null_mut();
+
+        match unsafe {
+            libfsntfs_standard_information_attribute_get_security_descriptor_identifier(
+                self.as_type_ref(),
+                &mut security_descriptor_identifier,
+                &mut error,
+            )
+        } {
+            1 => Ok(Some(security_descriptor_identifier)),
+            0 => Ok(None),
+            _ => Err(Error::try_from(error)?),
+        }
+    }
+
+    /// Retrieves the NTFS version (major, minor) of a `$VOLUME_INFORMATION` attribute.
+    pub fn get_volume_version(&self) -> Result<(u8, u8), Error> {
+        let mut major_version = 0_u8;
+        let mut minor_version = 0_u8;
+
+        try_ffi!(libfsntfs_volume_information_attribute_get_version(
+            self.as_type_ref(),
+            &mut major_version,
+            &mut minor_version
+        ));
+
+        Ok((major_version, minor_version))
+    }
+
+    /// Retrieves the flags of a `$VOLUME_INFORMATION` attribute.
+    pub fn get_volume_flags(&self) -> Result<VolumeFlags, Error> {
+        let mut flags = 0_u16;
+
+        try_ffi!(libfsntfs_volume_information_attribute_get_flags(
+            self.as_type_ref(),
+            &mut flags
+        ));
+
+        Ok(VolumeFlags::from(flags))
+    }
+
     pub fn get_type(&self) -> Result<AttributeType, Error> {
         let mut type_as_num = 0_u32;
         let mut error = ptr::null_mut();
@@ -604,3 +763,31 @@ impl<'a> Attribute<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribute_type_display_round_trips() {
+        for attribute_type in ATTRIBUTE_TYPES.iter() {
+            let name = attribute_type.to_string();
+
+            assert_eq!(AttributeType::from_str(&name).unwrap(), *attribute_type);
+            assert_eq!(
+                AttributeType::try_from(attribute_type.as_u32()).unwrap(),
+                *attribute_type
+            );
+        }
+    }
+
+    #[test]
+    fn test_attribute_type_from_str() {
+        assert_eq!(
+            "file_name".parse::<AttributeType>().unwrap(),
+            AttributeType::FileName
+        );
+        assert_eq!(AttributeType::Data.to_string(), "$DATA");
+        assert!("$NOT_AN_ATTRIBUTE".parse::<AttributeType>().is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Records which byte ranges of the image a session read, e.g. to build a minimized copy of
+//! the evidence holding only the parsed regions.
+//!
+//! ```ignore
+//! let audit = ReadAudit::new();
+//! let options = VolumeOpenOptions::new().read_audit(audit.clone());
+//! let volume = Volume::open_with_options("image.dd", &options)?;
+//!
+//! // ... parse the volume ...
+//!
+//! audit.write_range_list(File::create("ranges.csv")?)?;
+//! audit.copy_ranges(&mut File::open("image.dd")?, &mut File::create("sparse.dd")?)?;
+//! ```
+use crate::intercept::ReadInterceptor;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Formatter};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+/// The byte ranges read from the image, clones share them.
+#[derive(Clone, Default)]
+pub struct ReadAudit(Arc<Mutex<BTreeMap<u64, u64>>>);
+
+impl ReadAudit {
+    pub fn new() -> Self {
+        ReadAudit::default()
+    }
+
+    /// Adds `range`, merging it with the ranges it overlaps or touches.
+    pub fn record(&self, range: Range<u64>) {
+        if range.start >= range.end {
+            return;
+        }
+
+        let mut ranges = match self.0.lock() {
+            Ok(ranges) => ranges,
+            Err(_) => return,
+        };
+
+        let mut start = range.start;
+        let mut end = range.end;
+
+        let merged: Vec<u64> = ranges
+            .range(..=end)
+            .rev()
+            .take_while(|(_, &other_end)| other_end >= start)
+            .map(|(&other_start, _)| other_start)
+            .collect();
+
+        for other_start in merged {
+            let other_end = ranges.remove(&other_start).unwrap();
+            start = start.min(other_start);
+            end = end.max(other_end);
+        }
+
+        ranges.insert(start, end);
+    }
+
+    /// The ranges read, sorted and merged.
+    pub fn ranges(&self) -> Vec<Range<u64>> {
+        self.0
+            .lock()
+            .map(|ranges| ranges.iter().map(|(&start, &end)| start..end).collect())
+            .unwrap_or_default()
+    }
+
+    /// How many bytes of the image were read, each counted once.
+    pub fn total_bytes(&self) -> u64 {
+        self.ranges()
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum()
+    }
+
+    /// Writes the ranges as `offset,length` lines, after a header line.
+    pub fn write_range_list<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "offset,length")?;
+
+        for range in self.ranges() {
+            writeln!(writer, "{},{}", range.start, range.end - range.start)?;
+        }
+
+        Ok(())
+    }
+
+    /// Copies the ranges from `image` to the same offsets of `output`, leaving the rest of
+    /// `output` as it is (unwritten, for a sparse file).
+    pub fn copy_ranges<R: Read + Seek, W: Write + Seek>(
+        &self,
+        image: &mut R,
+        output: &mut W,
+    ) -> io::Result<()> {
+        for range in self.ranges() {
+            image.seek(SeekFrom::Start(range.start))?;
+            output.seek(SeekFrom::Start(range.start))?;
+
+            io::copy(&mut image.take(range.end - range.start), output)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ReadInterceptor for ReadAudit {
+    fn after_read(&self, offset: u64, data: &[u8]) {
+        self.record(offset..offset + data.len() as u64);
+    }
+}
+
+impl Debug for ReadAudit {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("ReadAudit").field(&self.ranges()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::options::VolumeOpenOptions;
+    use crate::volume::Volume;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_merges_ranges() {
+        let audit = ReadAudit::new();
+        audit.record(10..20);
+        audit.record(40..50);
+        audit.record(20..25);
+        audit.record(5..12);
+        audit.record(60..70);
+        audit.record(45..65);
+
+        assert_eq!(audit.ranges(), [5..25, 40..70]);
+        assert_eq!(audit.total_bytes(), 50);
+
+        let mut list = vec![];
+        audit.write_range_list(&mut list).unwrap();
+        assert_eq!(
+            String::from_utf8(list).unwrap(),
+            "offset,length\n5,20\n40,30\n"
+        );
+    }
+
+    #[test]
+    fn test_copy_ranges() {
+        let audit = ReadAudit::new();
+        audit.record(2..4);
+
+        let mut output = Cursor::new(vec![0; 6]);
+        audit
+            .copy_ranges(&mut Cursor::new(vec![1, 2, 3, 4, 5, 6]), &mut output)
+            .unwrap();
+
+        assert_eq!(output.into_inner(), [0, 0, 3, 4, 0, 0]);
+    }
+
+    #[test]
+    fn test_audited_volume() {
+        let audit = ReadAudit::new();
+        let options = VolumeOpenOptions::new().read_audit(audit.clone());
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+
+        assert!(volume.get_root_directory().is_ok());
+        // The boot sector is always read.
+        assert_eq!(audit.ranges()[0].start, 0);
+    }
+}
@@ -0,0 +1,226 @@
+//! Opens NTFS volumes inside BitLocker encrypted partitions, decrypting them with libbde.
+//!
+//! The partition is read by libbde through a libbfio handle, and the decrypted volume is
+//! handed to libfsntfs as another libbfio handle.
+//!
+//! ```ignore
+//! let partition = Handle::open_file("bitlocker.dd", LibbfioAccessFlags::Read)?;
+//! let keys = [BitlockerKey::RecoveryPassword("471207-278498-..".to_owned())];
+//! let volume = SharedVolume::open_bitlocker(partition, &keys, &VolumeOpenOptions::new())?;
+//! ```
+use crate::error::Error;
+use crate::ffi_error::to_io_error;
+use crate::options::VolumeOpenOptions;
+use crate::shared::SharedVolume;
+use libbde_sys::{
+    libbde_error_t, libbde_volume_close, libbde_volume_free, libbde_volume_get_size,
+    libbde_volume_initialize, libbde_volume_is_locked, libbde_volume_read_buffer,
+    libbde_volume_read_startup_key, libbde_volume_seek_offset, libbde_volume_set_keys,
+    libbde_volume_set_utf8_password, libbde_volume_set_utf8_recovery_password, libbde_volume_t,
+    size_t, VolumePtr, LIBBDE_ACCESS_FLAGS_LIBBDE_ACCESS_FLAG_READ,
+};
+use libbfio_rs::handle::{Handle, HandleRef};
+use libcerror_rs::CError;
+use libfsntfs_sys::{SEEK_CUR, SEEK_END, SEEK_SET};
+use libyal_rs_common::ffi::AsTypeRef;
+use log::error;
+use std::ffi::CString;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::raw::{c_int, c_void};
+use std::path::PathBuf;
+use std::ptr;
+
+extern "C" {
+    // `libbde.h` only declares it when libbde was built with libbfio (`LIBBDE_HAVE_BFIO`).
+    // libbde-sys builds it against the libbfio of libbfio-sys, the one `Handle` comes from.
+    fn libbde_volume_open_file_io_handle(
+        volume: *mut libbde_volume_t,
+        file_io_handle: HandleRef,
+        access_flags: c_int,
+        error: *mut *mut libbde_error_t,
+    ) -> c_int;
+}
+
+/// What unlocks the volume, libbde tries every key it was given.
+#[derive(Clone)]
+pub enum BitlockerKey {
+    Password(String),
+    /// The 48 digit recovery password, e.g. `471207-278498-...`.
+    RecoveryPassword(String),
+    /// A `.BEK` startup key file.
+    StartupKey(PathBuf),
+    /// The full volume encryption key (and tweak key, for AES-CBC with the diffuser),
+    /// e.g. recovered from memory.
+    Keys {
+        fvek: Vec<u8>,
+        tweak: Vec<u8>,
+    },
+}
+
+/// The decrypted contents of a BitLocker partition.
+pub struct BitlockerVolume {
+    ptr: VolumePtr,
+    // libbde reads from the handle until the volume is closed.
+    source: Handle,
+    /// Whether the volume was opened, and has to be closed.
+    opened: bool,
+}
+
+// Both the libbde volume and the handle it reads from are owned by this value alone.
+unsafe impl Send for BitlockerVolume {}
+
+impl BitlockerVolume {
+    /// Unlocks the partition read through `source`, fails if none of `keys` unlocks it.
+    pub fn open(source: Handle, keys: &[BitlockerKey]) -> Result<Self, Error> {
+        let mut handle = VolumePtr::null();
+
+        try_ffi!(libbde_volume_initialize(handle.as_out_ptr()));
+
+        let mut volume = BitlockerVolume {
+            ptr: handle,
+            source,
+            opened: false,
+        };
+
+        for key in keys {
+            volume.set_key(key)?;
+        }
+
+        try_ffi!(libbde_volume_open_file_io_handle(
+            volume.ptr.as_ptr(),
+            volume.source.as_type_ref(),
+            LIBBDE_ACCESS_FLAGS_LIBBDE_ACCESS_FLAG_READ as c_int,
+        ));
+        volume.opened = true;
+
+        if volume.is_locked()? {
+            return Err(Error::Other(
+                "BitLocker volume is locked, none of the keys unlocked it".to_owned(),
+            ));
+        }
+
+        Ok(volume)
+    }
+
+    fn set_key(&self, key: &BitlockerKey) -> Result<(), Error> {
+        match key {
+            BitlockerKey::Password(password) => try_ffi!(libbde_volume_set_utf8_password(
+                self.ptr.as_ptr(),
+                password.as_ptr(),
+                password.len() as size_t,
+            )),
+            BitlockerKey::RecoveryPassword(password) => {
+                try_ffi!(libbde_volume_set_utf8_recovery_password(
+                    self.ptr.as_ptr(),
+                    password.as_ptr(),
+                    password.len() as size_t,
+                ))
+            }
+            BitlockerKey::StartupKey(path) => {
+                let filename = CString::new(path.to_string_lossy().into_owned())
+                    .map_err(Error::StringContainsNul)?;
+
+                try_ffi!(libbde_volume_read_startup_key(
+                    self.ptr.as_ptr(),
+                    filename.as_ptr(),
+                ))
+            }
+            BitlockerKey::Keys { fvek, tweak } => try_ffi!(libbde_volume_set_keys(
+                self.ptr.as_ptr(),
+                fvek.as_ptr(),
+                fvek.len() as size_t,
+                tweak.as_ptr(),
+                tweak.len() as size_t,
+            )),
+        }
+
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> Result<bool, Error> {
+        let mut error = ptr::null_mut();
+
+        match unsafe { libbde_volume_is_locked(self.ptr.as_ptr(), &mut error) } {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::from(unsafe { CError::from_raw(error) })),
+        }
+    }
+
+    /// Size of the decrypted volume, in bytes.
+    pub fn get_size(&self) -> Result<u64, Error> {
+        let mut size = 0;
+
+        try_ffi!(libbde_volume_get_size(self.ptr.as_ptr(), &mut size));
+
+        Ok(size)
+    }
+}
+
+impl Read for BitlockerVolume {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut error = ptr::null_mut();
+
+        let read_count = unsafe {
+            libbde_volume_read_buffer(
+                self.ptr.as_ptr(),
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len() as size_t,
+                &mut error,
+            )
+        };
+
+        if read_count <= -1 {
+            Err(to_io_error(error))
+        } else {
+            Ok(read_count as usize)
+        }
+    }
+}
+
+impl Seek for BitlockerVolume {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset as i64, SEEK_SET as c_int),
+            SeekFrom::Current(offset) => (offset, SEEK_CUR as c_int),
+            SeekFrom::End(offset) => (offset, SEEK_END as c_int),
+        };
+
+        let mut error = ptr::null_mut();
+
+        let position =
+            unsafe { libbde_volume_seek_offset(self.ptr.as_ptr(), offset, whence, &mut error) };
+
+        if position <= -1 {
+            Err(to_io_error(error))
+        } else {
+            Ok(position as u64)
+        }
+    }
+}
+
+impl Drop for BitlockerVolume {
+    fn drop(&mut self) {
+        let mut error = ptr::null_mut();
+
+        if self.opened && unsafe { libbde_volume_close(self.ptr.as_ptr(), &mut error) } != 0 {
+            error!("`libbde_volume_close` failed!");
+        }
+
+        if unsafe { libbde_volume_free(self.ptr.as_out_ptr(), &mut error) } != 1 {
+            error!("`libbde_volume_free` failed!");
+        }
+    }
+}
+
+impl SharedVolume {
+    /// Opens the NTFS volume inside the BitLocker partition read through `source`.
+    /// Clones of the volume share the decrypted partition.
+    pub fn open_bitlocker(
+        source: Handle,
+        keys: &[BitlockerKey],
+        options: &VolumeOpenOptions,
+    ) -> Result<Self, Error> {
+        SharedVolume::from_reader(BitlockerVolume::open(source, keys)?, options)
+    }
+}
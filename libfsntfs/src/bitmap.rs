@@ -0,0 +1,198 @@
+//! The cluster allocation bitmap of the volume (`$Bitmap`), one bit per cluster.
+use crate::error::Error;
+use crate::geometry::VolumeGeometry;
+use crate::metadata_files;
+use crate::volume::Volume;
+use std::io::Read;
+
+/// A run of consecutive clusters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClusterRun {
+    pub first_cluster: u64,
+    pub number_of_clusters: u64,
+}
+
+impl ClusterRun {
+    /// Offset of the run in the volume, in bytes.
+    pub fn offset(&self, cluster_size: u64) -> u64 {
+        self.first_cluster * cluster_size
+    }
+
+    /// Size of the run, in bytes.
+    pub fn size(&self, cluster_size: u64) -> u64 {
+        self.number_of_clusters * cluster_size
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClusterBitmap {
+    bitmap: Vec<u8>,
+    total_clusters: u64,
+    cluster_size: u64,
+}
+
+impl ClusterBitmap {
+    /// Reads `$Bitmap` of the volume.
+    pub fn read(volume: &Volume) -> Result<Self, Error> {
+        let geometry = VolumeGeometry::read(volume)?;
+
+        let mut bitmap = vec![];
+        volume
+            .get_file_entry_by_mft_idx(metadata_files::BITMAP)?
+            .read_to_end(&mut bitmap)?;
+
+        Ok(ClusterBitmap::from_bytes(
+            bitmap,
+            geometry.total_clusters,
+            geometry.cluster_size,
+        ))
+    }
+
+    /// `$Bitmap` is padded, so bits past `total_clusters` are ignored.
+    pub fn from_bytes(bitmap: Vec<u8>, total_clusters: u64, cluster_size: u64) -> Self {
+        // A truncated bitmap cannot say anything about the clusters it does not cover.
+        let total_clusters = total_clusters.min(bitmap.len() as u64 * 8);
+
+        ClusterBitmap {
+            bitmap,
+            total_clusters,
+            cluster_size,
+        }
+    }
+
+    pub fn total_clusters(&self) -> u64 {
+        self.total_clusters
+    }
+
+    /// Size of a cluster, in bytes.
+    pub fn cluster_size(&self) -> u64 {
+        self.cluster_size
+    }
+
+    pub fn is_allocated(&self, cluster: u64) -> bool {
+        cluster < self.total_clusters
+            && self.bitmap[(cluster / 8) as usize] & (1 << (cluster % 8)) != 0
+    }
+
+    /// Number of clusters in use.
+    pub fn allocated_clusters(&self) -> u64 {
+        let full_bytes = (self.total_clusters / 8) as usize;
+
+        let mut allocated_clusters: u64 = self.bitmap[..full_bytes]
+            .iter()
+            .map(|byte| u64::from(byte.count_ones()))
+            .sum();
+
+        let remaining_clusters = self.total_clusters % 8;
+
+        if remaining_clusters > 0 {
+            let mask = (1_u8 << remaining_clusters) - 1;
+            allocated_clusters += u64::from((self.bitmap[full_bytes] & mask).count_ones());
+        }
+
+        allocated_clusters
+    }
+
+    /// The runs of clusters which are not in use, in order.
+    pub fn unallocated_runs(&self) -> UnallocatedRuns {
+        UnallocatedRuns {
+            bitmap: self,
+            next_cluster: 0,
+        }
+    }
+}
+
+pub struct UnallocatedRuns<'a> {
+    bitmap: &'a ClusterBitmap,
+    next_cluster: u64,
+}
+
+impl<'a> Iterator for UnallocatedRuns<'a> {
+    type Item = ClusterRun;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let total_clusters = self.bitmap.total_clusters;
+
+        let mut cluster = self.next_cluster;
+
+        while cluster < total_clusters && self.bitmap.is_allocated(cluster) {
+            // Skip fully allocated bytes at once.
+            if cluster % 8 == 0 && self.bitmap.bitmap[(cluster / 8) as usize] == 0xff {
+                cluster += 8;
+            } else {
+                cluster += 1;
+            }
+        }
+
+        if cluster >= total_clusters {
+            self.next_cluster = total_clusters;
+            return None;
+        }
+
+        let first_cluster = cluster;
+
+        while cluster < total_clusters && !self.bitmap.is_allocated(cluster) {
+            if cluster % 8 == 0
+                && cluster + 8 <= total_clusters
+                && self.bitmap.bitmap[(cluster / 8) as usize] == 0
+            {
+                cluster += 8;
+            } else {
+                cluster += 1;
+            }
+        }
+
+        self.next_cluster = cluster;
+
+        Some(ClusterRun {
+            first_cluster,
+            number_of_clusters: cluster - first_cluster,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocated_clusters_ignores_padding() {
+        let bitmap = vec![0xff, 0b0000_0101, 0xff];
+
+        assert_eq!(
+            ClusterBitmap::from_bytes(bitmap.clone(), 11, 4096).allocated_clusters(),
+            9
+        );
+        assert_eq!(
+            ClusterBitmap::from_bytes(bitmap, 24, 4096).allocated_clusters(),
+            18
+        );
+    }
+
+    #[test]
+    fn test_unallocated_runs() {
+        // Clusters 8, 10, 12-15 and 16-19 (until the end of the volume) are free.
+        let bitmap = ClusterBitmap::from_bytes(vec![0xff, 0b0000_1010, 0x00], 20, 4096);
+
+        let runs: Vec<ClusterRun> = bitmap.unallocated_runs().collect();
+
+        assert_eq!(
+            runs,
+            vec![
+                ClusterRun {
+                    first_cluster: 8,
+                    number_of_clusters: 1
+                },
+                ClusterRun {
+                    first_cluster: 10,
+                    number_of_clusters: 1
+                },
+                ClusterRun {
+                    first_cluster: 12,
+                    number_of_clusters: 8
+                },
+            ]
+        );
+        assert_eq!(runs[2].offset(4096), 12 * 4096);
+    }
+}
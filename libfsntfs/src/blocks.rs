@@ -0,0 +1,243 @@
+//! Reads a stream a block of clusters at a time, following its layout on the volume, so each
+//! block can be processed (e.g. hashed) on its own.
+//!
+//! ```ignore
+//! let stream = entry.data_stream(None)?.unwrap();
+//!
+//! for block in stream.cluster_blocks()?.clusters_per_block(16) {
+//!     let block = block?;
+//!     println!("VCN {} at LCN {:?}, {} bytes", block.vcn, block.lcn, block.data.len());
+//! }
+//! ```
+use crate::data_stream::Stream;
+use crate::error::Error;
+use crate::file_entry::{Extent, EXTENT_FLAG_IS_COMPRESSED, EXTENT_FLAG_IS_SPARSE};
+use std::io::{Read, Seek, SeekFrom};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterBlock {
+    /// The first cluster of the block, counted from the start of the stream.
+    pub vcn: u64,
+    /// The first cluster of the block on the volume, `None` for sparse blocks and resident data.
+    pub lcn: Option<u64>,
+    /// The flags of the extent the block is in.
+    pub flags: u32,
+    /// The data of the block, decompressed. Shorter than the block past the end of the data.
+    pub data: Vec<u8>,
+}
+
+impl ClusterBlock {
+    pub fn is_sparse(&self) -> bool {
+        self.flags & EXTENT_FLAG_IS_SPARSE != 0
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.flags & EXTENT_FLAG_IS_COMPRESSED != 0
+    }
+}
+
+/// The blocks of a stream, in the order of their VCNs. Blocks do not span extents.
+pub struct ClusterBlocks<R> {
+    reader: R,
+    extents: Vec<Extent>,
+    size: u64,
+    cluster_size: u64,
+    clusters_per_block: u64,
+    /// The extent the next block is in.
+    extent: usize,
+    /// Offset of the next block in the stream, and in its extent.
+    offset: u64,
+    offset_in_extent: u64,
+}
+
+impl<R: Read + Seek> ClusterBlocks<R> {
+    /// Blocks of `reader`, a stream of `size` bytes laid out in `extents`. A stream without
+    /// extents is resident, it is a single block.
+    pub fn new(reader: R, extents: Vec<Extent>, size: u64, cluster_size: u64) -> Self {
+        ClusterBlocks {
+            reader,
+            extents,
+            size,
+            cluster_size: cluster_size.max(1),
+            clusters_per_block: 1,
+            extent: 0,
+            offset: 0,
+            offset_in_extent: 0,
+        }
+    }
+
+    pub fn clusters_per_block(mut self, clusters_per_block: u64) -> Self {
+        self.clusters_per_block = clusters_per_block.max(1);
+        self
+    }
+
+    fn read_block(&mut self, length: u64) -> Result<Vec<u8>, Error> {
+        let mut data = vec![0; length as usize];
+
+        self.reader.seek(SeekFrom::Start(self.offset))?;
+        self.reader.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+
+    fn next_block(&mut self) -> Result<Option<ClusterBlock>, Error> {
+        if self.offset >= self.size {
+            return Ok(None);
+        }
+
+        if self.extents.is_empty() {
+            let data = self.read_block(self.size)?;
+            self.offset = self.size;
+
+            return Ok(Some(ClusterBlock {
+                vcn: 0,
+                lcn: None,
+                flags: 0,
+                data,
+            }));
+        }
+
+        let extent = match self.extents.get(self.extent) {
+            Some(extent) => *extent,
+            None => {
+                return Err(Error::Other(format!(
+                    "the extents of the stream end at {} of its {} bytes",
+                    self.offset, self.size
+                )))
+            }
+        };
+
+        let block_size =
+            (extent.size - self.offset_in_extent).min(self.clusters_per_block * self.cluster_size);
+        let block = ClusterBlock {
+            vcn: self.offset / self.cluster_size,
+            lcn: if extent.is_sparse() {
+                None
+            } else {
+                Some((extent.offset + self.offset_in_extent) / self.cluster_size)
+            },
+            flags: extent.flags,
+            data: self.read_block(block_size.min(self.size - self.offset))?,
+        };
+
+        self.offset += block_size;
+        self.offset_in_extent += block_size;
+
+        if self.offset_in_extent >= extent.size {
+            self.extent += 1;
+            self.offset_in_extent = 0;
+        }
+
+        Ok(Some(block))
+    }
+}
+
+impl<R: Read + Seek> Iterator for ClusterBlocks<R> {
+    type Item = Result<ClusterBlock, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_block() {
+            Ok(block) => block.map(Ok),
+            Err(e) => {
+                // Stop after the first error, the position in the stream is lost.
+                self.offset = self.size;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<'a> Stream<'a> {
+    /// The blocks of the stream, see `ClusterBlocks`.
+    pub fn cluster_blocks(self) -> Result<ClusterBlocks<Self>, Error> {
+        let (number_of_extents, cluster_size) = match self {
+            Stream::Default(ref entry) => (
+                entry.get_number_of_extents()?,
+                entry.volume().get_cluster_block_size()?,
+            ),
+            Stream::Named(ref stream) => (
+                stream.get_number_of_extents()?,
+                stream.file_entry().volume().get_cluster_block_size()?,
+            ),
+        };
+
+        let extents = (0..number_of_extents)
+            .map(|extent_index| match self {
+                Stream::Default(ref entry) => entry.get_extent(extent_index),
+                Stream::Named(ref stream) => stream.get_extent(extent_index),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let size = self.get_size()?;
+
+        Ok(ClusterBlocks::new(self, extents, size, cluster_size as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use std::io::Cursor;
+
+    fn extent(offset: u64, size: u64, flags: u32) -> Extent {
+        Extent {
+            offset,
+            size,
+            flags,
+        }
+    }
+
+    #[test]
+    fn test_blocks_follow_extents() {
+        let data: Vec<u8> = (0..10 * 512).map(|i| (i / 512) as u8).collect();
+        let extents = vec![
+            extent(100 * 512, 3 * 512, 0),
+            extent(0, 4 * 512, EXTENT_FLAG_IS_SPARSE),
+            extent(200 * 512, 4 * 512, 0),
+        ];
+
+        let blocks: Vec<ClusterBlock> =
+            ClusterBlocks::new(Cursor::new(data), extents, 9 * 512 + 10, 512)
+                .clusters_per_block(2)
+                .map(Result::unwrap)
+                .collect();
+
+        let layout: Vec<(u64, Option<u64>, usize)> = blocks
+            .iter()
+            .map(|block| (block.vcn, block.lcn, block.data.len()))
+            .collect();
+
+        assert_eq!(
+            layout,
+            [
+                (0, Some(100), 1024),
+                (2, Some(102), 512),
+                (3, None, 1024),
+                (5, None, 1024),
+                (7, Some(200), 1024),
+                (9, Some(202), 10),
+            ]
+        );
+        assert!(blocks[2].is_sparse());
+        assert_eq!(blocks[4].data[0], 7);
+    }
+
+    #[test]
+    fn test_cluster_blocks_of_mft() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+        let size = entry.get_size().unwrap();
+
+        let stream = entry.data_stream(None).unwrap().unwrap();
+        let total = stream
+            .cluster_blocks()
+            .unwrap()
+            .map(Result::unwrap)
+            .fold(0, |total, block| {
+                assert!(block.lcn.is_some());
+                total + block.data.len() as u64
+            });
+
+        assert_eq!(total, size);
+    }
+}
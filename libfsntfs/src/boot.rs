@@ -0,0 +1,244 @@
+//! The boot sector (`$Boot`), as stored, and the backup of it which NTFS keeps in the last
+//! sector of the partition.
+//!
+//! ```ignore
+//! let options = VolumeOpenOptions::new().boot_sector_fallback(true);
+//! let volume = Volume::open_with_options("damaged.dd", &options)?;
+//!
+//! if volume.get_boot_sector_source() == BootSectorSource::Backup {
+//!     println!("opened from the backup boot sector");
+//! }
+//! ```
+use crate::error::Error;
+use crate::geometry::{VolumeGeometry, BOOT_SECTOR_SIZE};
+use crate::metadata_files;
+use crate::mft_record::{invalid, read_u16, read_u32, read_u64};
+use crate::volume::Volume;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Signature at the end of a valid boot sector.
+pub const BOOT_SECTOR_END_SIGNATURE: [u8; 2] = [0x55, 0xaa];
+
+/// Sector sizes the backup boot sector is looked for with, it starts the last sector.
+const BACKUP_SECTOR_SIZES: [u64; 2] = [512, 4096];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootSectorSource {
+    /// The boot sector at the start of the volume.
+    Primary,
+    /// The backup at the end of the partition, the primary is damaged.
+    Backup,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootSector {
+    /// The sector as stored.
+    pub data: Vec<u8>,
+    pub oem_identifier: [u8; 8],
+    pub media_descriptor: u8,
+    pub sectors_per_track: u16,
+    pub number_of_heads: u16,
+    /// Sectors before the volume on the disk, as recorded when it was formatted.
+    pub hidden_sectors: u32,
+    pub serial_number: u64,
+    pub checksum: u32,
+    pub geometry: VolumeGeometry,
+}
+
+impl BootSector {
+    /// Parses the first `BOOT_SECTOR_SIZE` bytes of `data`, which must end with the
+    /// `BOOT_SECTOR_END_SIGNATURE`.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let data = data
+            .get(..BOOT_SECTOR_SIZE)
+            .ok_or_else(|| invalid(format!("boot sector is {} bytes", data.len())))?;
+
+        if data[BOOT_SECTOR_SIZE - 2..] != BOOT_SECTOR_END_SIGNATURE[..] {
+            return Err(invalid("boot sector has no end signature".to_owned()));
+        }
+
+        let geometry = VolumeGeometry::from_boot_sector(data)?;
+        let mut oem_identifier = [0; 8];
+        oem_identifier.copy_from_slice(&data[3..11]);
+
+        Ok(BootSector {
+            data: data.to_vec(),
+            oem_identifier,
+            media_descriptor: data[0x15],
+            sectors_per_track: read_u16(data, 0x18)?,
+            number_of_heads: read_u16(data, 0x1a)?,
+            hidden_sectors: read_u32(data, 0x1c)?,
+            serial_number: read_u64(data, 0x48)?,
+            checksum: read_u32(data, 0x50)?,
+            geometry,
+        })
+    }
+
+    /// Reads `$Boot` of the volume, the backup for volumes which fell back to it.
+    pub fn read(volume: &Volume) -> Result<Self, Error> {
+        let mut data = vec![0; BOOT_SECTOR_SIZE];
+
+        volume
+            .get_file_entry_by_mft_idx(metadata_files::BOOT)?
+            .read_exact(&mut data)?;
+
+        BootSector::parse(&data)
+    }
+
+    /// Reads the boot sector at the start of `reader`, a partition.
+    pub fn read_primary<R: Read + Seek>(reader: &mut R) -> Result<Self, Error> {
+        BootSector::parse(&read_sector(reader, 0)?)
+    }
+
+    /// Reads the backup boot sector in the last sector of `reader`, a partition.
+    pub fn read_backup<R: Read + Seek>(reader: &mut R) -> Result<Self, Error> {
+        let size = reader.seek(SeekFrom::End(0))?;
+        let mut result = Err(invalid(
+            "partition is too small for a backup boot sector".to_owned(),
+        ));
+
+        for sector_size in &BACKUP_SECTOR_SIZES {
+            if size < 2 * sector_size {
+                break;
+            }
+
+            result =
+                read_sector(reader, size - sector_size).and_then(|data| BootSector::parse(&data));
+
+            if result.is_ok() {
+                break;
+            }
+        }
+
+        result
+    }
+}
+
+fn read_sector<R: Read + Seek>(reader: &mut R, offset: u64) -> Result<Vec<u8>, Error> {
+    let mut data = vec![0; BOOT_SECTOR_SIZE];
+
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut data)?;
+
+    Ok(data)
+}
+
+/// A partition with its boot sector replaced by the backup when the primary is damaged, so
+/// libfsntfs can open it.
+pub struct BootSectorFallback<R> {
+    inner: R,
+    backup: Option<Vec<u8>>,
+    position: u64,
+}
+
+impl<R: Read + Seek> BootSectorFallback<R> {
+    /// Fails with the error of the primary boot sector when the backup is damaged as well.
+    pub fn new(mut inner: R) -> Result<Self, Error> {
+        let backup = match BootSector::read_primary(&mut inner) {
+            Ok(_) => None,
+            Err(e) => match BootSector::read_backup(&mut inner) {
+                Ok(backup) => Some(backup.data),
+                Err(_) => return Err(e),
+            },
+        };
+
+        inner.seek(SeekFrom::Start(0))?;
+
+        Ok(BootSectorFallback {
+            inner,
+            backup,
+            position: 0,
+        })
+    }
+
+    pub fn source(&self) -> BootSectorSource {
+        match self.backup {
+            Some(_) => BootSectorSource::Backup,
+            None => BootSectorSource::Primary,
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for BootSectorFallback<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = match self.backup {
+            Some(ref backup) if self.position < backup.len() as u64 => {
+                let start = self.position as usize;
+                let length = buf.len().min(backup.len() - start);
+
+                buf[..length].copy_from_slice(&backup[start..start + length]);
+                self.inner.seek(SeekFrom::Start((start + length) as u64))?;
+
+                length
+            }
+            _ => self.inner.read(buf)?,
+        };
+
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for BootSectorFallback<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::geometry::NTFS_OEM_IDENTIFIER;
+    use std::io::Cursor;
+
+    /// A partition of 16 sectors, with only a backup boot sector.
+    fn damaged_partition() -> Vec<u8> {
+        let mut boot_sector = vec![0; BOOT_SECTOR_SIZE];
+        boot_sector[3..11].copy_from_slice(NTFS_OEM_IDENTIFIER);
+        boot_sector[0x0b..0x0d].copy_from_slice(&512_u16.to_le_bytes());
+        boot_sector[0x0d] = 1;
+        boot_sector[0x28..0x30].copy_from_slice(&15_u64.to_le_bytes());
+        boot_sector[0x48..0x50].copy_from_slice(&0x1234_u64.to_le_bytes());
+        boot_sector[BOOT_SECTOR_SIZE - 2..].copy_from_slice(&BOOT_SECTOR_END_SIGNATURE);
+
+        let mut partition = vec![0xff; 15 * BOOT_SECTOR_SIZE];
+        partition.extend_from_slice(&boot_sector);
+        partition
+    }
+
+    #[test]
+    fn test_falls_back_to_backup() {
+        let mut partition = Cursor::new(damaged_partition());
+        assert!(BootSector::read_primary(&mut partition).is_err());
+
+        let mut reader = BootSectorFallback::new(partition).unwrap();
+        assert_eq!(reader.source(), BootSectorSource::Backup);
+
+        let mut data = vec![0; 2 * BOOT_SECTOR_SIZE];
+        reader.read_exact(&mut data).unwrap();
+
+        let boot_sector = BootSector::parse(&data).unwrap();
+        assert_eq!(boot_sector.serial_number, 0x1234);
+        assert_eq!(boot_sector.geometry.total_sectors, 15);
+        assert_eq!(data[BOOT_SECTOR_SIZE], 0xff);
+    }
+
+    #[test]
+    fn test_read_boot_sector() {
+        let volume = sample_volume().unwrap();
+        let boot_sector = BootSector::read(&volume).unwrap();
+
+        assert_eq!(&boot_sector.oem_identifier, NTFS_OEM_IDENTIFIER);
+        assert_eq!(boot_sector.data.len(), BOOT_SECTOR_SIZE);
+        assert_eq!(boot_sector.geometry, volume.geometry().unwrap());
+        assert_eq!(
+            boot_sector.serial_number,
+            volume.get_serial_number().unwrap()
+        );
+        assert_eq!(volume.get_boot_sector_source(), BootSectorSource::Primary);
+    }
+}
@@ -0,0 +1,215 @@
+//! Lets an interrupted extraction resume where it stopped, see `ExtractOptions::checkpoint`.
+//!
+//! The checkpoint is a journal of lines appended as the extraction goes: `done <reference> <path>`
+//! once a file is extracted, and `partial <reference> <offset> <path>` as the data of large files
+//! is written. Files are keyed by their file reference (so a reused MFT entry is a new file) and
+//! the path they are extracted to (so every hard link of a file is extracted). A line torn by a
+//! crash (without its newline) is ignored. Opening the checkpoint compacts the journal.
+use crate::error::Error;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    journal: Mutex<File>,
+    /// The completed files, by their file reference and path.
+    completed: HashSet<(u64, String)>,
+    /// Bytes written of the files which were interrupted, by their file reference and path.
+    partial: HashMap<(u64, String), u64>,
+}
+
+fn parse_reference(value: &str) -> Option<u64> {
+    u64::from_str_radix(value, 16).ok()
+}
+
+/// Escapes the characters which would end a line of the journal (or be trimmed from it).
+fn escape_path(path: &str) -> String {
+    path.replace('%', "%25")
+        .replace('\n', "%0A")
+        .replace('\r', "%0D")
+}
+
+fn unescape_path(path: &str) -> String {
+    path.replace("%0D", "\r")
+        .replace("%0A", "\n")
+        .replace("%25", "%")
+}
+
+impl Checkpoint {
+    /// Opens the checkpoint at `path`, starting a new one if there is none.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut completed = HashSet::new();
+        let mut partial = HashMap::new();
+
+        let journal = match fs::read_to_string(&path) {
+            Ok(journal) => journal,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        // Only lines which were written up to their newline count.
+        let written = &journal[..journal.rfind('\n').map_or(0, |end| end + 1)];
+
+        for line in written.lines() {
+            // The path goes last, as it may have spaces.
+            let fields: Vec<&str> = line.splitn(4, ' ').collect();
+
+            match fields.as_slice() {
+                ["done", reference, path] => {
+                    if let Some(reference) = parse_reference(reference) {
+                        let key = (reference, unescape_path(path));
+                        partial.remove(&key);
+                        completed.insert(key);
+                    }
+                }
+                ["partial", reference, offset, path] => {
+                    if let (Some(reference), Ok(offset)) =
+                        (parse_reference(reference), offset.parse())
+                    {
+                        partial.insert((reference, unescape_path(path)), offset);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let journal = Checkpoint::compact(&path, &completed, &partial)?;
+
+        Ok(Checkpoint {
+            path,
+            journal: Mutex::new(journal),
+            completed,
+            partial,
+        })
+    }
+
+    /// Rewrites the journal with a line per file and opens it for appending.
+    fn compact(
+        path: &Path,
+        completed: &HashSet<(u64, String)>,
+        partial: &HashMap<(u64, String), u64>,
+    ) -> Result<File, Error> {
+        let compacted = path.with_extension("compacting");
+
+        {
+            let mut journal = io::BufWriter::new(File::create(&compacted)?);
+
+            for (reference, path) in completed {
+                writeln!(journal, "done {:x} {}", reference, escape_path(path))?;
+            }
+            for ((reference, path), offset) in partial {
+                writeln!(
+                    journal,
+                    "partial {:x} {} {}",
+                    reference,
+                    offset,
+                    escape_path(path)
+                )?;
+            }
+
+            journal.into_inner().map_err(io::Error::from)?.sync_all()?;
+        }
+
+        fs::rename(&compacted, path)?;
+
+        Ok(OpenOptions::new().append(true).open(path)?)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file at `file_reference` was extracted to `path` by an earlier run.
+    pub fn is_completed(&self, file_reference: u64, path: &str) -> bool {
+        self.completed.contains(&(file_reference, path.to_owned()))
+    }
+
+    /// Files extracted by earlier runs.
+    pub fn completed_files(&self) -> usize {
+        self.completed.len()
+    }
+
+    /// Bytes of the file at `file_reference` which an earlier run wrote to `path`, 0 if none.
+    pub fn resume_offset(&self, file_reference: u64, path: &str) -> u64 {
+        self.partial
+            .get(&(file_reference, path.to_owned()))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    fn append(&self, line: String) -> io::Result<()> {
+        let mut journal = self
+            .journal
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "the checkpoint lock is poisoned"))?;
+
+        journal.write_all(line.as_bytes())
+    }
+
+    /// Records that the first `offset` bytes of the file were written (and flushed).
+    pub(crate) fn record_progress(
+        &self,
+        file_reference: u64,
+        path: &str,
+        offset: u64,
+    ) -> io::Result<()> {
+        self.append(format!(
+            "partial {:x} {} {}\n",
+            file_reference,
+            offset,
+            escape_path(path)
+        ))
+    }
+
+    pub(crate) fn record_completed(&self, file_reference: u64, path: &str) -> io::Result<()> {
+        self.append(format!("done {:x} {}\n", file_reference, escape_path(path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_from_journal() {
+        let path = std::env::temp_dir().join("libfsntfs-rs-checkpoint");
+        let _ = fs::remove_file(&path);
+
+        let checkpoint = Checkpoint::open(&path).unwrap();
+        let done = (3 << 48) | 40;
+        let interrupted = (1 << 48) | 41;
+
+        checkpoint.record_progress(done, "\\a b", 4096).unwrap();
+        checkpoint.record_completed(done, "\\a b").unwrap();
+        checkpoint
+            .record_progress(interrupted, "\\c\n%0A", 4096)
+            .unwrap();
+        checkpoint
+            .record_progress(interrupted, "\\c\n%0A", 8192)
+            .unwrap();
+        drop(checkpoint);
+
+        // A line torn by a crash.
+        let mut journal = OpenOptions::new().append(true).open(&path).unwrap();
+        journal.write_all(b"done 1000000000000 \\d").unwrap();
+        drop(journal);
+
+        let checkpoint = Checkpoint::open(&path).unwrap();
+        assert!(checkpoint.is_completed(done, "\\a b"));
+        // Another hard link of the file, and a later file in the same MFT entry.
+        assert!(!checkpoint.is_completed(done, "\\e"));
+        assert!(!checkpoint.is_completed((4 << 48) | 40, "\\a b"));
+        assert!(!checkpoint.is_completed(interrupted, "\\c\n%0A"));
+        assert_eq!(checkpoint.completed_files(), 1);
+        assert_eq!(checkpoint.resume_offset(done, "\\a b"), 0);
+        assert_eq!(checkpoint.resume_offset(interrupted, "\\c\n%0A"), 8192);
+        assert_eq!(checkpoint.resume_offset(interrupted, "\\c"), 0);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
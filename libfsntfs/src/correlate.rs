@@ -0,0 +1,210 @@
+//! The history of a single file, joined from the places NTFS keeps traces of it: the
+//! timestamps of its MFT entry, the `$UsnJrnl` records of its changes and the entries left in
+//! the slack of the `$I30` indexes of its parent directories (e.g. older names and timestamps).
+//!
+//! ```ignore
+//! let events = correlate(&volume, file_reference, &mut File::open("image.dd")?)?;
+//!
+//! for event in events {
+//!     println!("{} {:?} {}", event.filetime, event.source, event.description);
+//! }
+//! ```
+use crate::attribute::AttributeType;
+use crate::error::Error;
+use crate::index::{carve_slack_entries, Index, SlackEntry};
+use crate::paths::PathResolver;
+use crate::timeline::timestamp_description;
+use crate::timestamps::{MacbField, MacbTimes};
+use crate::usn::{UsnJournal, UsnReason};
+use crate::utils::mft_entry_index;
+use crate::volume::Volume;
+use std::io::{Read, Seek};
+
+const MACB_FIELDS: [MacbField; 4] = [
+    MacbField::Modification,
+    MacbField::Access,
+    MacbField::EntryModification,
+    MacbField::Creation,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventSource {
+    /// A timestamp of `$STANDARD_INFORMATION` or `$FILE_NAME` of the MFT entry.
+    Mft(AttributeType),
+    /// A record of the change journal.
+    UsnJournal { usn: u64 },
+    /// An entry carved from the slack of the `$I30` index of the directory `parent` (a file
+    /// reference).
+    I30Slack { parent: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorrelatedEvent {
+    /// The timestamp as a FILETIME.
+    pub filetime: u64,
+    pub source: EventSource,
+    /// The timestamp fields which share this timestamp, empty for journal records.
+    pub fields: Vec<MacbField>,
+    /// The name of the file when the event was recorded (the full path for MFT events).
+    pub name: String,
+    /// Set for journal records.
+    pub usn_reason: Option<UsnReason>,
+    /// e.g. `Creation Time` or `FILE_CREATE | CLOSE`.
+    pub description: String,
+}
+
+/// The events of the entry `file_reference` points to, oldest first.
+/// Journal records and slack entries are matched on the MFT entry index, so they include the
+/// earlier files which used the same entry. Volumes without a change journal have no journal
+/// events.
+/// `image` is the raw volume, needed to read the `$INDEX_ALLOCATION` of the parents.
+pub fn correlate<R: Read + Seek>(
+    volume: &Volume,
+    file_reference: u64,
+    image: &mut R,
+) -> Result<Vec<CorrelatedEvent>, Error> {
+    let idx = mft_entry_index(file_reference);
+    let entry = volume.get_file_entry_by_mft_idx(idx)?;
+    let path = PathResolver::new().full_path(idx, &entry);
+
+    let mut events: Vec<CorrelatedEvent> = entry
+        .timeline_events(idx, &path)?
+        .into_iter()
+        .map(|event| CorrelatedEvent {
+            filetime: event.filetime,
+            source: EventSource::Mft(event.attribute_type),
+            description: event.timestamp_description(),
+            fields: event.fields,
+            name: event.path,
+            usn_reason: None,
+        })
+        .collect();
+
+    if let Ok(journal) = UsnJournal::open(volume) {
+        for record in journal.records_from(0)? {
+            let record = record?;
+
+            if mft_entry_index(record.file_reference) != idx {
+                continue;
+            }
+
+            events.push(CorrelatedEvent {
+                filetime: record.timestamp,
+                source: EventSource::UsnJournal { usn: record.usn },
+                fields: vec![],
+                name: record.name,
+                usn_reason: Some(record.reason),
+                description: format!("{:?}", record.reason),
+            });
+        }
+    }
+
+    let mut parents: Vec<u64> = entry
+        .get_file_names()?
+        .iter()
+        .map(|file_name| file_name.parent_file_reference)
+        .collect();
+    parents.sort();
+    parents.dedup();
+
+    let utf16_policy = volume.options().get_utf16_policy();
+
+    for parent in parents {
+        let index = Index::read_by_mft_idx(volume, mft_entry_index(parent), "$I30", image)?;
+        let nodes = Some(&index.root)
+            .into_iter()
+            .chain(index.records.iter().map(|record| &record.node));
+
+        for node in nodes {
+            for slack_entry in carve_slack_entries(&node.slack, utf16_policy) {
+                if mft_entry_index(slack_entry.file_reference) == idx {
+                    events.extend(slack_events(&slack_entry, parent));
+                }
+            }
+        }
+    }
+
+    events.sort_by_key(|event| event.filetime);
+
+    Ok(events)
+}
+
+/// Groups the fields of a slack entry by timestamp.
+fn slack_events(entry: &SlackEntry, parent: u64) -> Vec<CorrelatedEvent> {
+    let mut events: Vec<CorrelatedEvent> = vec![];
+
+    for field in MACB_FIELDS.iter() {
+        let filetime = entry.times.get(*field);
+
+        match events.iter_mut().find(|event| event.filetime == filetime) {
+            Some(event) => event.fields.push(*field),
+            None => events.push(CorrelatedEvent {
+                filetime,
+                source: EventSource::I30Slack { parent },
+                fields: vec![*field],
+                name: entry.name.clone(),
+                usn_reason: None,
+                description: String::new(),
+            }),
+        }
+    }
+
+    for event in events.iter_mut() {
+        let descriptions: Vec<&str> = event
+            .fields
+            .iter()
+            .map(|field| timestamp_description(*field))
+            .collect();
+        event.description = format!("$I30 slack: {}", descriptions.join("; "));
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_correlate_file() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+        let reference = entry.get_file_reference().unwrap();
+
+        let mut image = File::open(sample_volume_path()).unwrap();
+        let events = correlate(&volume, reference, &mut image).unwrap();
+
+        assert!(events
+            .iter()
+            .any(|event| event.source == EventSource::Mft(AttributeType::StandardInformation)));
+        assert!(events
+            .windows(2)
+            .all(|pair| pair[0].filetime <= pair[1].filetime));
+    }
+
+    #[test]
+    fn test_slack_events() {
+        let entry = SlackEntry {
+            offset: 0,
+            file_reference: 0x0001_0000_0000_0040,
+            parent_file_reference: 5,
+            name: "old.txt".to_owned(),
+            times: MacbTimes {
+                modification: 2,
+                access: 2,
+                entry_modification: 2,
+                creation: 1,
+            },
+            data_size: 0,
+        };
+
+        let events = slack_events(&entry, 5);
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].fields, [MacbField::Creation]);
+        assert_eq!(events[0].source, EventSource::I30Slack { parent: 5 });
+        assert_eq!(events[1].description, "$I30 slack: Creation Time");
+    }
+}
@@ -0,0 +1,241 @@
+//! Exports the MFT as CSV, one row per entry, with the column names used by MFTECmd.
+//!
+//! Rows are written as the MFT is read, only the paths of directories are kept in memory.
+//! They are in ascending MFT entry index order, each with the `RecordId` of the entry as the
+//! last column (see `record_id`).
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::filter::EntryFilter;
+use crate::paths::PathResolver;
+use crate::record_id::RecordId;
+use crate::timestamps::{MacbField, MacbTimes};
+use crate::utils::{datetime_from_filetime, mft_entry_index, sequence_number};
+use crate::volume::{MftEntryIndex, Volume};
+use std::io::Write;
+
+/// The columns of MFTECmd, followed by `RecordId`.
+pub const CSV_HEADER: [&str; 22] = [
+    "EntryNumber",
+    "SequenceNumber",
+    "InUse",
+    "ParentEntryNumber",
+    "ParentSequenceNumber",
+    "FileName",
+    "FullPath",
+    "FileSize",
+    "AllocatedSize",
+    "IsDirectory",
+    "HasAds",
+    "AdsCount",
+    "SiFlags",
+    "Created0x10",
+    "LastModified0x10",
+    "LastRecordChange0x10",
+    "LastAccess0x10",
+    "Created0x30",
+    "LastModified0x30",
+    "LastRecordChange0x30",
+    "LastAccess0x30",
+    "RecordId",
+];
+
+const TIMESTAMP_FIELDS: [MacbField; 4] = [
+    MacbField::Creation,
+    MacbField::Modification,
+    MacbField::EntryModification,
+    MacbField::Access,
+];
+
+/// Quotes `field` if it contains a separator, quote or line break.
+pub(crate) fn escape(field: &str) -> String {
+    if field.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Formats a FILETIME as `2019-01-01 00:00:00.1234567`, keeping its full precision.
+fn format_filetime(filetime: u64) -> String {
+    if filetime == 0 {
+        return String::new();
+    }
+
+    format!(
+        "{}.{:07}",
+        datetime_from_filetime(filetime).format("%Y-%m-%d %H:%M:%S"),
+        filetime % 10_000_000
+    )
+}
+
+pub struct MftCsvWriter<W: Write> {
+    writer: W,
+    paths: PathResolver,
+}
+
+impl<W: Write> MftCsvWriter<W> {
+    pub fn new(writer: W) -> Self {
+        MftCsvWriter {
+            writer,
+            paths: PathResolver::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    pub fn write_header(&mut self) -> Result<(), Error> {
+        writeln!(self.writer, "{}", CSV_HEADER.join(","))?;
+        Ok(())
+    }
+
+    /// Writes the row of `entry`, the entry at `idx` of the MFT.
+    /// Nothing is written when a field of the entry cannot be read.
+    pub fn write_entry(&mut self, idx: MftEntryIndex, entry: &FileEntry) -> Result<(), Error> {
+        let row = self.entry_row(idx, entry)?;
+
+        self.write_row(&row)
+    }
+
+    fn entry_row(&mut self, idx: MftEntryIndex, entry: &FileEntry) -> Result<String, Error> {
+        let file_reference = entry.get_file_reference()?;
+        let parent_file_reference = entry.get_parent_file_reference().ok();
+        let name = entry.get_name().unwrap_or_default();
+
+        let full_path = self.paths.full_path(idx, entry);
+
+        let is_directory = entry.has_directory_entries_index()?;
+
+        let file_size = if is_directory { 0 } else { entry.get_size()? };
+
+        let mut allocated_size = 0;
+        for extent_index in 0..entry.get_number_of_extents()? {
+            allocated_size += entry.get_extent(extent_index)?.size;
+        }
+
+        let ads_count = entry.get_number_of_alternate_data_streams()?;
+        let report = entry.get_timestamp_report()?;
+
+        let mut fields = vec![
+            idx.to_string(),
+            sequence_number(file_reference).to_string(),
+            entry.is_allocated()?.to_string(),
+            parent_file_reference
+                .map(|parent| mft_entry_index(parent).to_string())
+                .unwrap_or_default(),
+            parent_file_reference
+                .map(|parent| sequence_number(parent).to_string())
+                .unwrap_or_default(),
+            escape(&name),
+            escape(&full_path),
+            file_size.to_string(),
+            allocated_size.to_string(),
+            is_directory.to_string(),
+            (ads_count > 0).to_string(),
+            ads_count.to_string(),
+            escape(&entry.get_file_attribute_flags()?.to_string()),
+        ];
+
+        for times in [report.standard_information, report.file_name].iter() {
+            let times = times.unwrap_or_else(MacbTimes::default);
+
+            for field in TIMESTAMP_FIELDS.iter() {
+                fields.push(format_filetime(times.get(*field)));
+            }
+        }
+
+        fields.push(RecordId::for_entry(file_reference).to_string());
+
+        Ok(fields.join(","))
+    }
+
+    fn write_row(&mut self, row: &str) -> Result<(), Error> {
+        writeln!(self.writer, "{}", row)?;
+
+        Ok(())
+    }
+}
+
+impl Volume {
+    /// Writes one CSV row per MFT entry to `writer`, entries which cannot be read are skipped.
+    /// Returns the number of rows written.
+    pub fn export_mft_csv<W: Write>(&self, writer: W) -> Result<u64, Error> {
+        self.export_mft_csv_filtered(writer, &EntryFilter::default())
+    }
+
+    /// Like `export_mft_csv`, only writing the entries matching `filter`.
+    pub fn export_mft_csv_filtered<W: Write>(
+        &self,
+        writer: W,
+        filter: &EntryFilter,
+    ) -> Result<u64, Error> {
+        let mut csv_writer = MftCsvWriter::new(writer);
+        csv_writer.write_header()?;
+
+        let mut rows = 0;
+
+        for (idx, entry) in self.mft_entries()? {
+            if let Ok(entry) = entry {
+                if !filter.matches(&entry).unwrap_or(false) {
+                    continue;
+                }
+
+                // Entries with fields which cannot be read are skipped like unreadable entries,
+                // only failing to write aborts the export.
+                if let Ok(row) = csv_writer.entry_row(idx, &entry) {
+                    csv_writer.write_row(&row)?;
+                    rows += 1;
+                }
+            }
+        }
+
+        csv_writer.into_inner().flush()?;
+
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("file.txt"), "file.txt");
+        assert_eq!(escape("a, b"), "\"a, b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_format_filetime() {
+        assert_eq!(format_filetime(0), "");
+        assert_eq!(
+            format_filetime(131_907_744_001_234_567),
+            "2019-01-01 00:00:00.1234567"
+        );
+    }
+
+    #[test]
+    fn test_export_mft_csv() {
+        let volume = sample_volume().unwrap();
+        let mut output = vec![];
+
+        let rows = volume.export_mft_csv(&mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines();
+
+        assert_eq!(lines.next(), Some(CSV_HEADER.join(",").as_str()));
+        assert_eq!(lines.count() as u64, rows);
+        assert!(output.contains(",$MFT,\\$MFT,"));
+
+        let record_ids: Vec<RecordId> = output
+            .lines()
+            .skip(1)
+            .map(|line| line.rsplit(',').next().unwrap().parse().unwrap())
+            .collect();
+        assert!(record_ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
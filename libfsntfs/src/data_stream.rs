@@ -0,0 +1,471 @@
+use crate::error::Error;
+use crate::ffi_error::{LibfsntfsErrorRef, LibfsntfsErrorRefMut};
+use crate::file_entry::{Extent, FileEntry};
+use crate::instrument::Metric;
+use crate::mft_record::{AttributeForm, MftRecord};
+use crate::utils::mft_entry_index;
+use libfsntfs_sys::{off64_t, size64_t, SEEK_CUR, SEEK_END, SEEK_SET};
+use libyal_rs_common::ffi::AsTypeRef;
+use std::convert::TryFrom;
+use std::ffi::c_void;
+use std::fmt::{Debug, Formatter};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::{Deref, Range};
+use std::os::raw::c_int;
+use std::{fmt, io, ptr};
+
+#[repr(C)]
+pub struct __DataStream(isize);
+
+pub type DataStreamRefMut = *mut __DataStream;
+pub type DataStreamRef = *const __DataStream;
+
+/// A named (alternate) `$DATA` stream of a file entry.
+pub struct DataStream<'a> {
+    handle: DataStreamRefMut,
+    file_entry: &'a FileEntry<'a>,
+}
+
+impl<'a> AsTypeRef for DataStream<'a> {
+    type Ref = DataStreamRef;
+    type RefMut = DataStreamRefMut;
+
+    #[inline]
+    fn as_type_ref(&self) -> Self::Ref {
+        // https://users.rust-lang.org/t/is-it-ub-to-convert-t-to-mut-t/16238/4
+        self.handle as *const _
+    }
+
+    #[inline]
+    fn as_type_ref_mut(&mut self) -> Self::RefMut {
+        self.handle
+    }
+
+    #[inline]
+    fn as_raw(&mut self) -> *mut Self::RefMut {
+        &mut self.handle as *mut _
+    }
+}
+
+impl<'a> DataStream<'a> {
+    pub fn wrap_ptr(file_entry: &'a FileEntry<'a>, ptr: DataStreamRefMut) -> Self {
+        DataStream {
+            handle: ptr,
+            file_entry,
+        }
+    }
+
+    /// The entry the stream is of.
+    pub fn file_entry(&self) -> &'a FileEntry<'a> {
+        self.file_entry
+    }
+}
+
+impl<'a> Drop for DataStream<'a> {
+    fn drop(&mut self) {
+        use log::trace;
+
+        let mut error = ptr::null_mut();
+
+        trace!("Calling `libfsntfs_data_stream_free`");
+
+        unsafe {
+            libfsntfs_data_stream_free(&mut self.as_type_ref_mut() as *mut _, &mut error);
+        }
+
+        debug_assert!(error.is_null(), "`libfsntfs_data_stream_free` failed!");
+    }
+}
+
+impl<'a> Debug for DataStream<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("DataStream")
+            .field("Name", &self.get_name().unwrap_or_default())
+            .finish()
+    }
+}
+
+extern "C" {
+    pub fn libfsntfs_data_stream_free(
+        data_stream: *mut DataStreamRefMut,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> c_int;
+    pub fn libfsntfs_data_stream_get_utf8_name_size(
+        data_stream: DataStreamRef,
+        utf8_string_size: *mut usize,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> c_int;
+    pub fn libfsntfs_data_stream_get_utf8_name(
+        data_stream: DataStreamRef,
+        utf8_string: *mut u8,
+        utf8_string_size: usize,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> c_int;
+    pub fn libfsntfs_data_stream_get_utf16_name_size(
+        data_stream: DataStreamRef,
+        utf16_string_size: *mut usize,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> c_int;
+    pub fn libfsntfs_data_stream_get_utf16_name(
+        data_stream: DataStreamRef,
+        utf16_string: *mut u16,
+        utf16_string_size: usize,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> c_int;
+    pub fn libfsntfs_data_stream_read_buffer(
+        data_stream: DataStreamRef,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> isize;
+    pub fn libfsntfs_data_stream_read_buffer_at_offset(
+        data_stream: DataStreamRef,
+        buffer: *mut c_void,
+        buffer_size: usize,
+        offset: off64_t,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> isize;
+    pub fn libfsntfs_data_stream_seek_offset(
+        data_stream: DataStreamRef,
+        offset: off64_t,
+        whence: c_int,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> off64_t;
+    pub fn libfsntfs_data_stream_get_offset(
+        data_stream: DataStreamRef,
+        offset: *mut off64_t,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> c_int;
+    pub fn libfsntfs_data_stream_get_size(
+        data_stream: DataStreamRef,
+        size: *mut size64_t,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> c_int;
+    pub fn libfsntfs_data_stream_get_number_of_extents(
+        data_stream: DataStreamRef,
+        number_of_extents: *mut c_int,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> c_int;
+    pub fn libfsntfs_data_stream_get_extent_by_index(
+        data_stream: DataStreamRef,
+        extent_index: c_int,
+        extent_offset: *mut off64_t,
+        extent_size: *mut size64_t,
+        extent_flags: *mut u32,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> c_int;
+}
+
+fn io_error_from(error: LibfsntfsErrorRefMut) -> io::Error {
+    match Error::try_from(error) {
+        Ok(e) => io::Error::new(io::ErrorKind::Other, format!("{}", e)),
+        Err(_) => io::Error::new(
+            io::ErrorKind::Other,
+            "error while getting error information",
+        ),
+    }
+}
+
+impl<'a> Read for DataStream<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        let mut error = ptr::null_mut();
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::trace_span!(
+            "read_stream",
+            image = self
+                .file_entry
+                .volume()
+                .get_image_path()
+                .unwrap_or_default(),
+            name = self.file_entry.get_name().unwrap_or_default().as_str(),
+            stream = self.get_name().unwrap_or_default().as_str(),
+            length = buf.len()
+        );
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        let options = self.file_entry.volume().options();
+        options.record(Metric::FfiCall("libfsntfs_data_stream_read_buffer"));
+
+        let read_count = unsafe {
+            libfsntfs_data_stream_read_buffer(
+                self.as_type_ref(),
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                &mut error,
+            )
+        };
+
+        if read_count <= -1 {
+            Err(io_error_from(error))
+        } else {
+            options.record(Metric::BytesRead(read_count as u64));
+            Ok(read_count as usize)
+        }
+    }
+}
+
+impl<'a> Seek for DataStream<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, io::Error> {
+        let mut error = ptr::null_mut();
+
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset as i64, SEEK_SET),
+            SeekFrom::End(offset) => (offset, SEEK_END),
+            SeekFrom::Current(offset) => (offset, SEEK_CUR),
+        };
+
+        let seek_pos = unsafe {
+            libfsntfs_data_stream_seek_offset(
+                self.as_type_ref(),
+                offset,
+                whence as i32,
+                &mut error,
+            )
+        };
+
+        if seek_pos <= -1 {
+            Err(io_error_from(error))
+        } else {
+            Ok(seek_pos as u64)
+        }
+    }
+}
+
+/// The default or a named `$DATA` stream of a file entry, see `FileEntry::data_stream`.
+#[derive(Debug)]
+pub enum Stream<'a> {
+    /// The unnamed stream, read through a handle of the entry of its own.
+    Default(FileEntry<'a>),
+    Named(DataStream<'a>),
+}
+
+impl<'a> Stream<'a> {
+    /// The name of the stream, empty for the default stream.
+    pub fn get_name(&self) -> Result<String, Error> {
+        match self {
+            Stream::Default(_) => Ok(String::new()),
+            Stream::Named(stream) => stream.get_name(),
+        }
+    }
+
+    pub fn get_size(&self) -> Result<u64, Error> {
+        match self {
+            Stream::Default(entry) => entry.get_size(),
+            Stream::Named(stream) => stream.get_size(),
+        }
+    }
+}
+
+impl<'a> Read for Stream<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        match self {
+            Stream::Default(entry) => entry.read(buf),
+            Stream::Named(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl<'a> Seek for Stream<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, io::Error> {
+        match self {
+            Stream::Default(entry) => entry.seek(pos),
+            Stream::Named(stream) => stream.seek(pos),
+        }
+    }
+}
+
+impl<'a> DataStream<'a> {
+    /// The name, decoded by the `Utf16Policy` of the volume.
+    pub fn get_name(&self) -> Result<String, Error> {
+        self.file_entry()
+            .volume()
+            .options()
+            .get_utf16_policy()
+            .decode(&self.get_utf16_name()?)
+    }
+
+    /// The name as stored, which may not be valid UTF-16.
+    pub fn get_utf16_name(&self) -> Result<Vec<u16>, Error> {
+        get_sized_utf16_units!(
+            self,
+            libfsntfs_data_stream_get_utf16_name_size,
+            libfsntfs_data_stream_get_utf16_name
+        )
+    }
+
+    pub fn get_size(&self) -> Result<u64, Error> {
+        let mut size = 0;
+
+        try_ffi!(libfsntfs_data_stream_get_size(self.as_type_ref(), &mut size));
+
+        Ok(size)
+    }
+
+    pub fn get_number_of_extents(&self) -> Result<c_int, Error> {
+        let mut number_of_extents = 0;
+
+        try_ffi!(libfsntfs_data_stream_get_number_of_extents(
+            self.as_type_ref(),
+            &mut number_of_extents
+        ));
+
+        Ok(number_of_extents)
+    }
+
+    pub fn get_extent(&self, extent_index: c_int) -> Result<Extent, Error> {
+        let mut offset = 0;
+        let mut size = 0;
+        let mut flags = 0;
+
+        try_ffi!(libfsntfs_data_stream_get_extent_by_index(
+            self.as_type_ref(),
+            extent_index,
+            &mut offset,
+            &mut size,
+            &mut flags
+        ));
+
+        Ok(Extent {
+            offset: offset as u64,
+            size,
+            flags,
+        })
+    }
+
+    /// Whether the data is stored in the MFT record itself rather than in clusters, from the
+    /// non-resident flag of its `$DATA` attribute.
+    pub fn is_resident(&self) -> Result<bool, Error> {
+        match stored_data(self.file_entry(), &self.get_name()?)? {
+            StoredData::Resident(_) => Ok(true),
+            StoredData::NonResident => Ok(false),
+            StoredData::Elsewhere => Ok(self.get_number_of_extents()? == 0),
+        }
+    }
+
+    /// The data of a resident stream, `None` if the stream is not resident. The data is not
+    /// read through the stream (and does not move its offset), but taken from the MFT record.
+    pub fn resident_data(&self) -> Result<Option<ResidentData>, Error> {
+        match stored_data(self.file_entry(), &self.get_name()?)? {
+            StoredData::Resident(data) => Ok(Some(data)),
+            StoredData::NonResident => Ok(None),
+            StoredData::Elsewhere => {
+                let size = self.get_size()?;
+                let mft_entry_size = u64::from(self.file_entry().volume().get_mft_entry_size()?);
+
+                if self.get_number_of_extents()? != 0 || size > mft_entry_size {
+                    return Ok(None);
+                }
+
+                let mut data = vec![0; size as usize];
+                let mut error = ptr::null_mut();
+
+                let read_count = unsafe {
+                    libfsntfs_data_stream_read_buffer_at_offset(
+                        self.as_type_ref(),
+                        data.as_mut_ptr() as *mut c_void,
+                        data.len(),
+                        0,
+                        &mut error,
+                    )
+                };
+
+                if read_count <= -1 {
+                    return Err(Error::try_from(error)?);
+                }
+
+                data.truncate(read_count as usize);
+
+                Ok(Some(ResidentData::from_vec(data)))
+            }
+        }
+    }
+}
+
+/// The data of a resident `$DATA` attribute, see `DataStream::resident_data` and
+/// `FileEntry::resident_data`. Holds the MFT record it was found in, and derefs to the data
+/// in it.
+#[derive(Debug, Clone)]
+pub struct ResidentData {
+    buffer: Vec<u8>,
+    range: Range<usize>,
+}
+
+impl ResidentData {
+    pub(crate) fn from_vec(data: Vec<u8>) -> Self {
+        ResidentData {
+            range: 0..data.len(),
+            buffer: data,
+        }
+    }
+}
+
+impl Deref for ResidentData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[self.range.clone()]
+    }
+}
+
+impl AsRef<[u8]> for ResidentData {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+/// How a `$DATA` attribute is stored in the MFT record of its entry.
+pub(crate) enum StoredData {
+    Resident(ResidentData),
+    NonResident,
+    /// Moved to an extension record, which are not parsed.
+    Elsewhere,
+}
+
+/// How the `$DATA` attribute `name` (empty for the default stream) of `file_entry` is stored.
+pub(crate) fn stored_data(file_entry: &FileEntry, name: &str) -> Result<StoredData, Error> {
+    let idx = mft_entry_index(file_entry.get_file_reference()?);
+    let record = MftRecord::read(file_entry.volume(), idx)?;
+
+    let range = match record
+        .data_attributes()?
+        .into_iter()
+        .find(|attribute| attribute.name == name)
+    {
+        Some(attribute) => match attribute.form {
+            AttributeForm::Resident { data } => {
+                let start = data.as_ptr() as usize - record.data().as_ptr() as usize;
+                start..start + data.len()
+            }
+            AttributeForm::NonResident { .. } => return Ok(StoredData::NonResident),
+        },
+        None => return Ok(StoredData::Elsewhere),
+    };
+
+    Ok(StoredData::Resident(ResidentData {
+        buffer: record.into_data(),
+        range,
+    }))
+}
+
+pub struct IterDataStreams<'a> {
+    pub(crate) handle: &'a FileEntry<'a>,
+    pub(crate) num_data_streams: u32,
+    pub(crate) idx: u32,
+}
+
+impl<'a> Iterator for IterDataStreams<'a> {
+    type Item = Result<DataStream<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx < self.num_data_streams {
+            let data_stream = self.handle.get_alternate_data_stream(self.idx as c_int);
+            self.idx += 1;
+
+            return Some(data_stream);
+        }
+
+        None
+    }
+}
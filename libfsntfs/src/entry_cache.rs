@@ -0,0 +1,258 @@
+//! A least recently used cache of the names, parents and paths of MFT entries, shared by the
+//! walks, path resolvers and lookups of the volumes opened with it, so that the parents which
+//! are resolved over and over are only read from libfsntfs once.
+//!
+//! Entries are cached by MFT entry index, so a cache belongs to a single image: volumes opened
+//! with clones of it (e.g. the clones of a `SharedVolume`) must all be opened on that image.
+//!
+//! ```ignore
+//! let cache = EntryCache::new(100_000);
+//! let options = VolumeOpenOptions::new().entry_cache(cache.clone());
+//! let volume = Volume::open_with_options("image.dd", &options)?;
+//!
+//! for entry in volume.walk(WalkOptions::default())? { /* ... */ }
+//! println!("{} entries cached", cache.len());
+//! ```
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::instrument::Metric;
+use crate::utils::mft_entry_index;
+use crate::volume::{MftEntryIndex, Volume};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+/// What the cache holds of an MFT entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedEntry {
+    /// The reference of the entry itself, with its current sequence number.
+    pub file_reference: u64,
+    pub name: String,
+    pub parent_file_reference: u64,
+    pub allocated: bool,
+    pub is_directory: bool,
+    /// The full path of a directory, once a `PathResolver` resolved it.
+    pub path: Option<String>,
+}
+
+impl CachedEntry {
+    pub fn read(entry: &FileEntry) -> Result<Self, Error> {
+        Ok(CachedEntry {
+            file_reference: entry.get_file_reference()?,
+            name: entry.get_name()?,
+            parent_file_reference: entry.get_parent_file_reference()?,
+            allocated: entry.is_allocated()?,
+            is_directory: entry.has_directory_entries_index()?,
+            path: None,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Lru {
+    capacity: usize,
+    /// The entries, with the tick they were last used at.
+    entries: HashMap<MftEntryIndex, (CachedEntry, u64)>,
+    /// The entries by the tick they were last used at, least recently used first.
+    by_use: BTreeMap<u64, MftEntryIndex>,
+    tick: u64,
+}
+
+impl Lru {
+    /// Marks the entry at `idx` as the most recently used one.
+    fn touch(&mut self, idx: MftEntryIndex) -> Option<&mut CachedEntry> {
+        self.tick += 1;
+        let tick = self.tick;
+
+        let (entry, last_used) = self.entries.get_mut(&idx)?;
+        self.by_use.remove(last_used);
+        self.by_use.insert(tick, idx);
+        *last_used = tick;
+
+        Some(entry)
+    }
+
+    fn insert(&mut self, entry: CachedEntry) {
+        let idx = mft_entry_index(entry.file_reference);
+
+        if let Some((_, last_used)) = self.entries.remove(&idx) {
+            self.by_use.remove(&last_used);
+        }
+
+        while self.entries.len() >= self.capacity {
+            let least_recently_used = match self.by_use.iter().next() {
+                Some((&tick, &idx)) => (tick, idx),
+                None => return,
+            };
+
+            self.by_use.remove(&least_recently_used.0);
+            self.entries.remove(&least_recently_used.1);
+        }
+
+        self.tick += 1;
+        self.by_use.insert(self.tick, idx);
+        self.entries.insert(idx, (entry, self.tick));
+    }
+}
+
+/// Holds up to `capacity` entries, evicting the least recently used ones. Clones share the
+/// entries.
+#[derive(Clone)]
+pub struct EntryCache(Arc<Mutex<Lru>>);
+
+impl EntryCache {
+    pub fn new(capacity: usize) -> Self {
+        EntryCache(Arc::new(Mutex::new(Lru {
+            capacity,
+            ..Lru::default()
+        })))
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.0.lock().map(|lru| lru.capacity).unwrap_or(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().map(|lru| lru.entries.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The entry at the MFT entry index of `file_reference`. Its sequence number is not
+    /// checked, compare it with `CachedEntry::file_reference` to tell stale references.
+    pub fn get(&self, file_reference: u64) -> Option<CachedEntry> {
+        let mut lru = self.0.lock().ok()?;
+
+        lru.touch(mft_entry_index(file_reference)).cloned()
+    }
+
+    /// Adds `entry`, replacing what was cached for its MFT entry index.
+    pub fn insert(&self, entry: CachedEntry) {
+        if let Ok(mut lru) = self.0.lock() {
+            lru.insert(entry);
+        }
+    }
+
+    /// Remembers the full path of the directory `file_reference` points to, if it is cached
+    /// with that sequence number.
+    pub(crate) fn set_path(&self, file_reference: u64, path: &str) {
+        if let Ok(mut lru) = self.0.lock() {
+            if let Some(entry) = lru.touch(mft_entry_index(file_reference)) {
+                if entry.file_reference == file_reference {
+                    entry.path = Some(path.to_owned());
+                }
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut lru) = self.0.lock() {
+            lru.entries.clear();
+            lru.by_use.clear();
+        }
+    }
+}
+
+impl Debug for EntryCache {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("EntryCache")
+            .field("capacity", &self.capacity())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl Volume {
+    /// The name, parent and state of the entry at the MFT entry index of `file_reference`,
+    /// from the `EntryCache` of the volume when it has one.
+    pub fn get_cached_entry(&self, file_reference: u64) -> Result<CachedEntry, Error> {
+        let cache = match self.options().get_entry_cache() {
+            Some(cache) => cache,
+            None => {
+                return CachedEntry::read(
+                    &self.get_file_entry_by_mft_idx(mft_entry_index(file_reference))?,
+                )
+            }
+        };
+
+        if let Some(entry) = cache.get(file_reference) {
+            self.options().record(Metric::CacheHit("entries"));
+            return Ok(entry);
+        }
+
+        self.options().record(Metric::CacheMiss("entries"));
+
+        let entry =
+            CachedEntry::read(&self.get_file_entry_by_mft_idx(mft_entry_index(file_reference))?)?;
+        cache.insert(entry.clone());
+
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::metadata_files;
+    use crate::options::VolumeOpenOptions;
+    use crate::paths::PathResolver;
+
+    fn cached_entry(idx: MftEntryIndex) -> CachedEntry {
+        CachedEntry {
+            file_reference: idx | 1 << 48,
+            name: idx.to_string(),
+            parent_file_reference: 5,
+            allocated: true,
+            is_directory: true,
+            path: None,
+        }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = EntryCache::new(2);
+        cache.insert(cached_entry(40));
+        cache.insert(cached_entry(41));
+
+        assert!(cache.get(40).is_some());
+        cache.insert(cached_entry(42));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(41).is_none());
+        assert_eq!(cache.get(40).unwrap().name, "40");
+
+        cache.set_path(cached_entry(42).file_reference, "\\42");
+        cache.set_path(43 | 2 << 48, "\\43");
+        assert_eq!(cache.get(42).unwrap().path.unwrap(), "\\42");
+    }
+
+    #[test]
+    fn test_shared_by_resolvers() {
+        let cache = EntryCache::new(1000);
+        let options = VolumeOpenOptions::new().entry_cache(cache.clone());
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+
+        let extend = volume.get_file_entry_by_path("\\$Extend").unwrap();
+        let reference = extend.get_file_reference().unwrap();
+
+        assert_eq!(
+            PathResolver::new().directory_path(&volume, reference),
+            "\\$Extend"
+        );
+        assert_eq!(cache.get(reference).unwrap().path.unwrap(), "\\$Extend");
+
+        // Another resolver finds the path in the cache.
+        assert_eq!(
+            PathResolver::new().directory_path(&volume, reference),
+            "\\$Extend"
+        );
+
+        let root = volume
+            .get_cached_entry(metadata_files::ROOT_DIRECTORY)
+            .unwrap();
+        assert!(root.is_directory);
+    }
+}
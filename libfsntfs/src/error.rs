@@ -5,6 +5,7 @@ use failure::Fail;
 use std::convert::TryFrom;
 use std::ffi::{c_void, FromBytesWithNulError, NulError};
 use std::fmt::{self, Debug, Display, Formatter};
+use std::io;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::os::raw::c_char;
@@ -17,6 +18,8 @@ pub enum Error {
     FailedToConvertDate(#[cause] chrono::ParseError),
     #[fail(display = "AttributeType has no variant {}", _0)]
     UnknownAttributeEnumVariant(u32),
+    #[fail(display = "Unknown attribute type name {}", _0)]
+    UnknownAttributeTypeName(String),
     #[fail(display = "String is invalid UTF-8: {}", _0)]
     StringContainsInvalidUTF8(#[cause] FromUtf8Error),
     #[fail(display = "String is invalid UTF-8: {}", _0)]
@@ -25,6 +28,49 @@ pub enum Error {
     StringContainsNul(#[cause] NulError),
     #[fail(display = "An FFI error has occurred: {}", _0)]
     FFI(String),
+    #[fail(display = "An IO error has occurred: {}", _0)]
+    Io(#[cause] io::Error),
+    /// Data on the volume which could not be parsed, e.g. a bad MFT entry signature.
+    /// `offset` is only known when libfsntfs reported it.
+    #[fail(display = "Corrupt structure: {}", structure)]
+    CorruptStructure {
+        structure: String,
+        offset: Option<u64>,
+    },
+    #[fail(display = "Unsupported: {}", _0)]
+    Unsupported(String),
+    #[fail(display = "Not found: {}", _0)]
+    NotFound(String),
+    #[fail(display = "Operation was cancelled")]
+    Cancelled,
+    #[fail(display = "Reparse points loop while resolving {}", _0)]
+    ReparsePointLoop(String),
+    #[fail(display = "Followed more than {} reparse points", _0)]
+    TooManyReparsePoints(usize),
+    #[fail(display = "Invalid security descriptor: {}", _0)]
+    InvalidSecurityDescriptor(String),
+    /// A name holding unpaired surrogates, see `Utf16Policy::Strict`. Holds the name with
+    /// them replaced.
+    #[fail(display = "Name is invalid UTF-16: {}", _0)]
+    InvalidUtf16Name(String),
+    #[fail(display = "Invalid record: {}", _0)]
+    InvalidRecord(String),
+    /// Not a `RecordId` in the form of `<entry number>-<sequence number>:<stream index>`.
+    #[fail(display = "Invalid record ID: {}", _0)]
+    InvalidRecordId(String),
+    /// The change journal was deleted and created again since the USN was stored, so the USN
+    /// points into another journal.
+    #[fail(
+        display = "The change journal was recreated (ID {:x}, the USN is of {:x})",
+        actual, expected
+    )]
+    UsnJournalRecreated { expected: u64, actual: u64 },
     #[fail(display = "An unexpected error has occurred: {}", _0)]
     Other(String),
 }
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
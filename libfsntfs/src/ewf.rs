@@ -0,0 +1,151 @@
+//! Reads EnCase (E01, Ex01) images with libewf.
+//!
+//! ```ignore
+//! let disk = EwfImage::open("evidence.E01")?;
+//! let volume = SharedVolume::from_reader(disk, &VolumeOpenOptions::new())?;
+//! ```
+use crate::error::Error;
+use crate::ffi_error::to_io_error;
+use libewf_sys::{
+    libewf_glob, libewf_glob_free, libewf_handle_close, libewf_handle_free,
+    libewf_handle_get_media_size, libewf_handle_initialize, libewf_handle_open,
+    libewf_handle_read_buffer, libewf_handle_seek_offset, size_t, HandlePtr,
+    LIBEWF_ACCESS_FLAGS_LIBEWF_ACCESS_FLAG_READ, LIBEWF_FORMAT_LIBEWF_FORMAT_UNKNOWN,
+};
+use libfsntfs_sys::{SEEK_CUR, SEEK_END, SEEK_SET};
+use log::error;
+use std::ffi::CString;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::Path;
+use std::ptr;
+
+/// An EnCase image, reads return the contents of the imaged media.
+pub struct EwfImage {
+    ptr: HandlePtr,
+    /// Whether the segments were opened, and have to be closed.
+    opened: bool,
+}
+
+// The handle is owned by this value alone.
+unsafe impl Send for EwfImage {}
+
+impl EwfImage {
+    /// Opens the image of which `path` is the first segment (e.g. `.E01`), the other
+    /// segments (`.E02`, ..) are found next to it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let filename = CString::new(path.as_ref().to_string_lossy().into_owned())
+            .map_err(Error::StringContainsNul)?;
+
+        let mut filenames = ptr::null_mut();
+        let mut number_of_filenames = 0;
+
+        try_ffi!(libewf_glob(
+            filename.as_ptr(),
+            filename.as_bytes().len() as size_t,
+            LIBEWF_FORMAT_LIBEWF_FORMAT_UNKNOWN as u8,
+            &mut filenames,
+            &mut number_of_filenames,
+        ));
+
+        let image = EwfImage::open_segments(filenames, number_of_filenames);
+
+        let mut error = ptr::null_mut();
+
+        if unsafe { libewf_glob_free(filenames, number_of_filenames, &mut error) } != 1 {
+            error!("`libewf_glob_free` failed!");
+        }
+
+        image
+    }
+
+    fn open_segments(
+        filenames: *mut *mut c_char,
+        number_of_filenames: c_int,
+    ) -> Result<Self, Error> {
+        let mut handle = HandlePtr::null();
+
+        try_ffi!(libewf_handle_initialize(handle.as_out_ptr()));
+
+        let mut image = EwfImage {
+            ptr: handle,
+            opened: false,
+        };
+
+        try_ffi!(libewf_handle_open(
+            image.ptr.as_ptr(),
+            filenames as *const _,
+            number_of_filenames,
+            LIBEWF_ACCESS_FLAGS_LIBEWF_ACCESS_FLAG_READ as c_int,
+        ));
+        image.opened = true;
+
+        Ok(image)
+    }
+
+    /// Size of the imaged media, in bytes.
+    pub fn get_media_size(&self) -> Result<u64, Error> {
+        let mut size = 0;
+
+        try_ffi!(libewf_handle_get_media_size(self.ptr.as_ptr(), &mut size));
+
+        Ok(size)
+    }
+}
+
+impl Read for EwfImage {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut error = ptr::null_mut();
+
+        let read_count = unsafe {
+            libewf_handle_read_buffer(
+                self.ptr.as_ptr(),
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len() as size_t,
+                &mut error,
+            )
+        };
+
+        if read_count <= -1 {
+            Err(to_io_error(error))
+        } else {
+            Ok(read_count as usize)
+        }
+    }
+}
+
+impl Seek for EwfImage {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset as i64, SEEK_SET as c_int),
+            SeekFrom::Current(offset) => (offset, SEEK_CUR as c_int),
+            SeekFrom::End(offset) => (offset, SEEK_END as c_int),
+        };
+
+        let mut error = ptr::null_mut();
+
+        let position =
+            unsafe { libewf_handle_seek_offset(self.ptr.as_ptr(), offset, whence, &mut error) };
+
+        if position <= -1 {
+            Err(to_io_error(error))
+        } else {
+            Ok(position as u64)
+        }
+    }
+}
+
+impl Drop for EwfImage {
+    fn drop(&mut self) {
+        let mut error = ptr::null_mut();
+
+        // Closing a handle which was never opened fails.
+        if self.opened && unsafe { libewf_handle_close(self.ptr.as_ptr(), &mut error) } != 0 {
+            error!("`libewf_handle_close` failed!");
+        }
+
+        if unsafe { libewf_handle_free(self.ptr.as_out_ptr(), &mut error) } != 1 {
+            error!("`libewf_handle_free` failed!");
+        }
+    }
+}
@@ -0,0 +1,668 @@
+//! Extracts (and hashes) the files of a volume with a pool of worker threads.
+//!
+//! One thread walks the directory tree and filters the entries, the workers each open their
+//! own clone of the volume and copy the default data stream of the entries they are handed.
+//! Both channels are bounded, so the walk never runs far ahead of the workers.
+//!
+//! Symbolic links and junctions follow the `reparse_policy` of the walk options: links which
+//! are not followed are never extracted (they are listed in the report unless skipped), a
+//! followed link to a file is extracted with the data of its target.
+//!
+//! With a checkpoint (see `checkpoint`), an extraction which was interrupted can be run again:
+//! the files it completed are skipped, and those it was writing are continued.
+//!
+//! ```ignore
+//! let volume = SharedVolume::open("image.dd", &VolumeOpenOptions::new())?;
+//! let options = ExtractOptions::new().output_directory("out").workers(8);
+//!
+//! let report = extract(&volume, &options, |extracted| println!("{}", extracted.path))?;
+//! for failure in report.failures {
+//!     eprintln!("{:?}: {}", failure.path, failure.error);
+//! }
+//! ```
+use crate::checkpoint::Checkpoint;
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::filter::EntryFilter;
+#[cfg(any(feature = "md-5", feature = "sha-1", feature = "sha2"))]
+use crate::hashing::{Hashes, HashingReader};
+use crate::shared::SharedVolume;
+use crate::utils::mft_entry_index;
+use crate::volume::MftEntryIndex;
+use crate::walk::{Link, LinkOutcome, WalkEntry, WalkOptions};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Bytes written between the checkpoints of a file.
+const PROGRESS_INTERVAL: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    pub walk: WalkOptions,
+    pub filter: EntryFilter,
+    /// Files are written below this directory, at their path in the volume.
+    /// When `None`, streams are only read (and hashed).
+    pub output_directory: Option<PathBuf>,
+    pub workers: usize,
+    /// Entries which can be waiting for a worker.
+    pub queue_size: usize,
+    /// Where the progress of the extraction is recorded, and resumed from.
+    pub checkpoint: Option<PathBuf>,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            walk: WalkOptions::default(),
+            filter: EntryFilter::default(),
+            output_directory: None,
+            workers: 4,
+            queue_size: 256,
+            checkpoint: None,
+        }
+    }
+}
+
+impl ExtractOptions {
+    pub fn new() -> Self {
+        ExtractOptions::default()
+    }
+
+    pub fn walk(mut self, walk: WalkOptions) -> Self {
+        self.walk = walk;
+        self
+    }
+
+    pub fn filter(mut self, filter: EntryFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn output_directory(mut self, output_directory: impl Into<PathBuf>) -> Self {
+        self.output_directory = Some(output_directory.into());
+        self
+    }
+
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    pub fn queue_size(mut self, queue_size: usize) -> Self {
+        self.queue_size = queue_size;
+        self
+    }
+
+    pub fn checkpoint(mut self, checkpoint: impl Into<PathBuf>) -> Self {
+        self.checkpoint = Some(checkpoint.into());
+        self
+    }
+}
+
+/// A file which was extracted.
+#[derive(Debug, Clone)]
+pub struct Extracted {
+    pub path: String,
+    pub mft_entry_index: MftEntryIndex,
+    /// Bytes copied from the default data stream.
+    pub size: u64,
+    /// Where the file was written, if it was.
+    pub output_path: Option<PathBuf>,
+    #[cfg(any(feature = "md-5", feature = "sha-1", feature = "sha2"))]
+    pub hashes: Hashes,
+}
+
+/// A file (or, without a path, the walk itself) which failed.
+#[derive(Debug)]
+pub struct ExtractFailure {
+    pub path: Option<String>,
+    pub error: Error,
+}
+
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    pub extracted: u64,
+    pub bytes: u64,
+    /// Files the checkpoint has as extracted by an earlier run.
+    pub skipped: u64,
+    pub failures: Vec<ExtractFailure>,
+    /// The symbolic links and junctions the walk met, with their path.
+    pub links: Vec<(String, Link)>,
+}
+
+pub(crate) struct Job {
+    pub(crate) path: String,
+    pub(crate) mft_entry_index: MftEntryIndex,
+    /// Whoever queued the job (e.g. items of a manifest) it is done for.
+    pub(crate) tags: Vec<usize>,
+}
+
+pub(crate) struct Outcome {
+    pub(crate) tags: Vec<usize>,
+    /// `None` for files skipped as the checkpoint has them as extracted.
+    pub(crate) result: Result<Option<Extracted>, ExtractFailure>,
+}
+
+/// Hands jobs to the workers and failures to the calling thread, see `run`.
+pub(crate) struct Queue {
+    jobs: SyncSender<Job>,
+    outcomes: SyncSender<Outcome>,
+}
+
+impl Queue {
+    /// `false` once every worker is gone.
+    pub(crate) fn submit(&self, job: Job) -> bool {
+        self.jobs.send(job).is_ok()
+    }
+
+    pub(crate) fn fail(&self, tags: Vec<usize>, path: Option<String>, error: Error) {
+        // The receiver only goes away once every thread is done.
+        let _ = self.outcomes.send(Outcome {
+            tags,
+            result: Err(ExtractFailure { path, error }),
+        });
+    }
+}
+
+/// Extracts the files `options` select, calling `on_extracted` (on the calling thread) for
+/// each file as it is done. Files which fail are collected in the report, they do not stop
+/// the extraction; only failing to start does.
+pub fn extract<F>(
+    volume: &SharedVolume,
+    options: &ExtractOptions,
+    mut on_extracted: F,
+) -> Result<ExtractReport, Error>
+where
+    F: FnMut(&Extracted),
+{
+    let walk_options = options.clone();
+    let links = Arc::new(Mutex::new(vec![]));
+    let walk_links = links.clone();
+    let mut report = ExtractReport::default();
+
+    run(
+        volume,
+        options,
+        move |volume, queue| walk(volume, &walk_options, queue, &walk_links),
+        |outcome| match outcome.result {
+            Ok(Some(extracted)) => {
+                report.extracted += 1;
+                report.bytes += extracted.size;
+                on_extracted(&extracted);
+            }
+            Ok(None) => report.skipped += 1,
+            Err(failure) => report.failures.push(failure),
+        },
+    )?;
+
+    if let Ok(mut links) = links.lock() {
+        report.links = links.drain(..).collect();
+    }
+
+    Ok(report)
+}
+
+/// Runs `produce` on a thread of its own, with a clone of `volume`, and the workers on the jobs
+/// it queues. `on_outcome` is called on the calling thread, for every job and failure.
+pub(crate) fn run<P, F>(
+    volume: &SharedVolume,
+    options: &ExtractOptions,
+    produce: P,
+    mut on_outcome: F,
+) -> Result<(), Error>
+where
+    P: FnOnce(&SharedVolume, &Queue) + Send + 'static,
+    F: FnMut(Outcome),
+{
+    let (jobs, queue) = sync_channel::<Job>(options.queue_size);
+    let (outcomes, results) = sync_channel::<Outcome>(options.queue_size);
+    let queue = Arc::new(Mutex::new(queue));
+
+    let checkpoint = match options.checkpoint {
+        Some(ref path) => Some(Arc::new(Checkpoint::open(path)?)),
+        None => None,
+    };
+
+    let mut threads = Vec::with_capacity(options.workers + 1);
+
+    let producer_volume = volume.try_clone()?;
+    let producer_queue = Queue {
+        jobs,
+        outcomes: outcomes.clone(),
+    };
+    threads.push(thread::spawn(move || {
+        produce(&producer_volume, &producer_queue)
+    }));
+
+    for _ in 0..options.workers.max(1) {
+        let worker_volume = volume.try_clone()?;
+        let output_directory = options.output_directory.clone();
+        let checkpoint = checkpoint.clone();
+        let queue = queue.clone();
+        let outcomes = outcomes.clone();
+
+        threads.push(thread::spawn(move || {
+            work(
+                &worker_volume,
+                output_directory.as_ref(),
+                checkpoint.as_ref().map(|checkpoint| &**checkpoint),
+                &queue,
+                &outcomes,
+            )
+        }));
+    }
+
+    // Only the threads hold senders now, so `results` ends when they are all done.
+    drop(outcomes);
+
+    for outcome in results {
+        on_outcome(outcome);
+    }
+
+    for handle in threads {
+        if handle.join().is_err() {
+            on_outcome(Outcome {
+                tags: vec![],
+                result: Err(ExtractFailure {
+                    path: None,
+                    error: Error::Other("an extraction thread panicked".to_owned()),
+                }),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn walk(
+    volume: &SharedVolume,
+    options: &ExtractOptions,
+    queue: &Queue,
+    links: &Mutex<Vec<(String, Link)>>,
+) {
+    let walk = match volume.walk(options.walk.clone()) {
+        Ok(walk) => walk,
+        Err(e) => return queue.fail(vec![], None, e),
+    };
+
+    for entry in walk {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                queue.fail(vec![], None, e);
+                continue;
+            }
+        };
+
+        if let Some(ref link) = entry.link {
+            if let Ok(mut links) = links.lock() {
+                links.push((entry.path.clone(), link.clone()));
+            }
+        }
+
+        let mft_entry_index = match selected_walk_entry(&entry, &options.filter) {
+            Ok(Some(mft_entry_index)) => mft_entry_index,
+            Ok(None) => continue,
+            Err(e) => {
+                queue.fail(vec![], Some(entry.path), e);
+                continue;
+            }
+        };
+
+        let job = Job {
+            path: entry.path,
+            mft_entry_index,
+            tags: vec![],
+        };
+
+        if !queue.submit(job) {
+            // Every worker is gone.
+            return;
+        }
+    }
+}
+
+/// The MFT entry index of `entry`, if it is extracted. Directories have no data to extract.
+pub(crate) fn selected(
+    entry: &FileEntry,
+    filter: &EntryFilter,
+) -> Result<Option<MftEntryIndex>, Error> {
+    if entry.has_directory_entries_index()? || !filter.matches(entry)? {
+        return Ok(None);
+    }
+
+    Ok(Some(mft_entry_index(entry.get_file_reference()?)))
+}
+
+/// Like `selected`, for an entry of a walk: links are only extracted when they were followed
+/// to a file, with the data of the file.
+pub(crate) fn selected_walk_entry(
+    entry: &WalkEntry,
+    filter: &EntryFilter,
+) -> Result<Option<MftEntryIndex>, Error> {
+    match entry.link {
+        Some(ref link) => match (link.outcome, link.target_mft_entry_index) {
+            (LinkOutcome::Followed, Some(target_mft_entry_index)) => {
+                let target = entry
+                    .entry
+                    .volume()
+                    .get_file_entry_by_mft_idx(target_mft_entry_index)?;
+
+                selected(&target, filter)
+            }
+            _ => Ok(None),
+        },
+        None => selected(&entry.entry, filter),
+    }
+}
+
+fn work(
+    volume: &SharedVolume,
+    output_directory: Option<&PathBuf>,
+    checkpoint: Option<&Checkpoint>,
+    queue: &Mutex<Receiver<Job>>,
+    outcomes: &SyncSender<Outcome>,
+) {
+    loop {
+        let job = match queue.lock() {
+            Ok(queue) => match queue.recv() {
+                Ok(job) => job,
+                // The walk is done.
+                Err(_) => return,
+            },
+            Err(_) => return,
+        };
+
+        let result = match extract_one(volume, output_directory, checkpoint, &job) {
+            Ok(extracted) => Ok(extracted),
+            Err(error) => Err(ExtractFailure {
+                path: Some(job.path),
+                error,
+            }),
+        };
+        let outcome = Outcome {
+            tags: job.tags,
+            result,
+        };
+
+        if outcomes.send(outcome).is_err() {
+            return;
+        }
+    }
+}
+
+fn extract_one(
+    volume: &SharedVolume,
+    output_directory: Option<&PathBuf>,
+    checkpoint: Option<&Checkpoint>,
+    job: &Job,
+) -> Result<Option<Extracted>, Error> {
+    let mut entry = volume.get_file_entry_by_mft_idx(job.mft_entry_index)?;
+    let file_reference = entry.get_file_reference()?;
+
+    if let Some(checkpoint) = checkpoint {
+        if checkpoint.is_completed(file_reference, &job.path) {
+            return Ok(None);
+        }
+    }
+
+    let output_path = output_directory.map(|directory| output_path(directory, &job.path));
+
+    // The bytes an interrupted run already wrote, which are kept.
+    let resume_offset = match (checkpoint, &output_path) {
+        (Some(checkpoint), Some(output_path)) => checkpoint
+            .resume_offset(file_reference, &job.path)
+            .min(fs::metadata(output_path).map_or(0, |metadata| metadata.len())),
+        _ => 0,
+    };
+
+    let mut writer: Box<dyn Write> = match output_path {
+        Some(ref output_path) => {
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .open(output_path)?;
+            file.set_len(resume_offset)?;
+            file.seek(SeekFrom::Start(resume_offset))?;
+
+            Box::new(BufWriter::new(file))
+        }
+        None => Box::new(io::sink()),
+    };
+
+    entry.seek(SeekFrom::Start(resume_offset))?;
+
+    let mut progress = |offset| match (checkpoint, &output_path) {
+        (Some(checkpoint), Some(_)) => {
+            checkpoint.record_progress(file_reference, &job.path, offset)
+        }
+        _ => Ok(()),
+    };
+
+    #[cfg(any(feature = "md-5", feature = "sha-1", feature = "sha2"))]
+    let (size, hashes) = {
+        // The hashes cover the whole stream, so what was written before is read back.
+        let written: Box<dyn Read> = match output_path {
+            Some(ref output_path) if resume_offset > 0 => {
+                Box::new(File::open(output_path)?.take(resume_offset))
+            }
+            _ => Box::new(io::empty()),
+        };
+
+        let mut reader = HashingReader::new(written.chain(&mut entry));
+        io::copy(&mut (&mut reader).take(resume_offset), &mut io::sink())?;
+        let size = copy(&mut reader, &mut writer, resume_offset, &mut progress)?;
+        (size, reader.finish())
+    };
+    #[cfg(not(any(feature = "md-5", feature = "sha-1", feature = "sha2")))]
+    let size = copy(&mut entry, &mut writer, resume_offset, &mut progress)?;
+
+    writer.flush()?;
+    drop(writer);
+
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.record_completed(file_reference, &job.path)?;
+    }
+
+    Ok(Some(Extracted {
+        path: job.path.clone(),
+        mft_entry_index: job.mft_entry_index,
+        size,
+        output_path,
+        #[cfg(any(feature = "md-5", feature = "sha-1", feature = "sha2"))]
+        hashes,
+    }))
+}
+
+/// Copies `reader` to `writer`, which are at `offset`, and returns the offset they end at.
+/// Every `PROGRESS_INTERVAL` bytes, `writer` is flushed and `progress` called with the offset.
+fn copy(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    mut offset: u64,
+    progress: &mut dyn FnMut(u64) -> io::Result<()>,
+) -> io::Result<u64> {
+    let mut buf = vec![0; 64 * 1024];
+    let mut unrecorded = 0;
+
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => return Ok(offset),
+            Ok(read) => read,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        writer.write_all(&buf[..read])?;
+        offset += read as u64;
+        unrecorded += read as u64;
+
+        if unrecorded >= PROGRESS_INTERVAL {
+            writer.flush()?;
+            progress(offset)?;
+            unrecorded = 0;
+        }
+    }
+}
+
+/// Names Windows reserves for devices, in any directory and with any extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Where the file at `path` (in the volume) is written. Components which could escape
+/// `directory` (`..`, or names with separators or prefixes such as `C:` of the host) are
+/// sanitized, as are names Windows can not create (reserved characters and device names, or
+/// trailing dots and spaces), so the output is the same on every host.
+fn output_path(directory: &Path, path: &str) -> PathBuf {
+    let mut output_path = directory.to_path_buf();
+
+    for component in path.split('\\').filter(|c| !c.is_empty()) {
+        match component {
+            "." | ".." => output_path.push("_"),
+            _ => output_path.push(sanitize_name(component)),
+        }
+    }
+
+    output_path
+}
+
+fn sanitize_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '|' | '?' | '*' | '\0'..='\x1f' => '_',
+            c => c,
+        })
+        .collect();
+
+    if sanitized.ends_with('.') || sanitized.ends_with(' ') {
+        sanitized.push('_');
+    }
+
+    let stem = sanitized.split('.').next().unwrap_or("").trim_end();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        sanitized.insert(0, '_');
+    }
+
+    sanitized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::options::VolumeOpenOptions;
+    use crate::walk::{PathMatcher, ReparsePolicy};
+
+    #[test]
+    fn test_output_path() {
+        let directory = Path::new("out");
+
+        assert_eq!(
+            output_path(directory, "\\a\\b.txt"),
+            directory.join("a").join("b.txt")
+        );
+        assert_eq!(
+            output_path(directory, "\\..\\a/b"),
+            directory.join("_").join("a_b")
+        );
+        assert_eq!(
+            output_path(directory, "\\C:\\a:b\\?\\UNC"),
+            directory.join("C_").join("a_b").join("_").join("UNC")
+        );
+        assert_eq!(
+            output_path(directory, "\\con\\NUL.txt\\COM1 .log\\console"),
+            directory
+                .join("_con")
+                .join("_NUL.txt")
+                .join("_COM1 .log")
+                .join("console")
+        );
+        assert_eq!(
+            output_path(directory, "\\a.\\b \\c\x01"),
+            directory.join("a._").join("b _").join("c_")
+        );
+    }
+
+    #[test]
+    fn test_extract_without_output() {
+        let volume = SharedVolume::open(sample_volume_path(), &VolumeOpenOptions::new()).unwrap();
+        let options = ExtractOptions::new().workers(2).queue_size(4);
+
+        let mut paths = vec![];
+        let report = extract(&volume, &options, |extracted| {
+            paths.push(extracted.path.clone())
+        })
+        .unwrap();
+
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(report.extracted as usize, paths.len());
+        assert!(report.extracted > 0);
+    }
+
+    #[test]
+    fn test_extract_skipping_links() {
+        let volume = SharedVolume::open(sample_volume_path(), &VolumeOpenOptions::new()).unwrap();
+        let options = ExtractOptions::new().walk(WalkOptions {
+            reparse_policy: ReparsePolicy::Skip,
+            ..WalkOptions::default()
+        });
+
+        let report = extract(&volume, &options, |_| {}).unwrap();
+
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert!(report.links.is_empty());
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let directory = std::env::temp_dir().join("libfsntfs-rs-resume");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        let volume = SharedVolume::open(sample_volume_path(), &VolumeOpenOptions::new()).unwrap();
+        let mut log_file = volume.get_file_entry_by_path("\\$LogFile").unwrap();
+        let mut data = vec![];
+        log_file.read_to_end(&mut data).unwrap();
+
+        // An earlier run wrote 150 bytes of `$LogFile`, of which 100 made it to the checkpoint.
+        let output_directory = directory.join("out");
+        let checkpoint = directory.join("checkpoint");
+        fs::create_dir_all(&output_directory).unwrap();
+        fs::write(output_directory.join("$LogFile"), &data[..150]).unwrap();
+        Checkpoint::open(&checkpoint)
+            .unwrap()
+            .record_progress(log_file.get_file_reference().unwrap(), "\\$LogFile", 100)
+            .unwrap();
+
+        let options = ExtractOptions::new()
+            .walk(WalkOptions {
+                include: vec![PathMatcher::prefix("\\$LogFile")],
+                ..WalkOptions::default()
+            })
+            .output_directory(&output_directory)
+            .checkpoint(&checkpoint);
+
+        let report = extract(&volume, &options, |_| {}).unwrap();
+        assert_eq!((report.extracted, report.skipped), (1, 0));
+        assert_eq!(fs::read(output_directory.join("$LogFile")).unwrap(), data);
+
+        let report = extract(&volume, &options, |_| {}).unwrap();
+        assert_eq!((report.extracted, report.skipped), (0, 1));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
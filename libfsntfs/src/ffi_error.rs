@@ -1,10 +1,14 @@
 use crate::error::Error;
+use libcerror_rs::{
+    argument_error, compression_error, domain, input_error, io_error, runtime_error, CError,
+};
 use libyal_rs_common::ffi::AsTypeRef;
 use libfsntfs_sys::FILE;
 use log::trace;
 use std::convert::TryFrom;
-use std::ffi::{c_void, CStr};
+use std::ffi::c_void;
 use std::fmt::{self, Display, Formatter};
+use std::io;
 use std::mem;
 
 #[repr(C)]
@@ -74,33 +78,133 @@ impl TryFrom<*mut __LibfsntfsError> for Error {
             return Err(Error::Other("Error pointer cannot be NULL".to_owned()));
         }
 
-        let mut buffer = vec![0; 1024];
+        Ok(Error::from(unsafe { CError::from_raw(err) }))
+    }
+}
+
+/// The error of a failed `read_buffer` or `seek_offset` call, for `Read` and `Seek` impls.
+/// The errors of all libyal libraries are libcerror errors, so `err` may be of any of them.
+pub(crate) fn to_io_error<E>(err: *mut E) -> io::Error {
+    if err.is_null() {
+        return io::Error::new(
+            io::ErrorKind::Other,
+            "error while getting error information",
+        );
+    }
+
+    let e = Error::from(unsafe { CError::from_raw(err) });
+
+    io::Error::new(io::ErrorKind::Other, format!("{}", e))
+}
+
+/// Finds the offset in messages such as `invalid MFT entry signature at offset: 0x00004000`.
+fn parse_offset(message: &str) -> Option<u64> {
+    let start = message.find("offset: ")? + "offset: ".len();
+    let value: String = message[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+
+    if value.starts_with("0x") {
+        u64::from_str_radix(&value[2..], 16).ok()
+    } else {
+        value.parse().ok()
+    }
+}
 
-        let retcode =
-            unsafe { libfsntfs_error_backtrace_sprint(err as *const _, buffer.as_mut_ptr(), buffer.len()) };
+impl From<CError> for Error {
+    /// Categorizes the error by its libcerror domain and code, everything which does not fit
+    /// one of the categories stays an `Error::FFI`.
+    fn from(err: CError) -> Self {
+        if err.matches(domain::IO, io_error::INVALID_RESOURCE) {
+            return Error::NotFound(err.backtrace());
+        }
 
-        if retcode == -1 {
-            Err(Error::FFI("Failed to print error".to_owned()))
-        } else {
-            let repr = unsafe { CStr::from_ptr(buffer.as_ptr()) };
-            Ok(Error::FFI(repr.to_string_lossy().to_string()))
+        if err.matches(domain::IO, io_error::ACCESS_DENIED) {
+            return Error::Io(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                err.backtrace(),
+            ));
         }
+
+        if err.matches_any(
+            domain::IO,
+            &[
+                io_error::GENERIC,
+                io_error::OPEN_FAILED,
+                io_error::CLOSE_FAILED,
+                io_error::SEEK_FAILED,
+                io_error::READ_FAILED,
+                io_error::WRITE_FAILED,
+                io_error::IOCTL_FAILED,
+                io_error::UNLINK_FAILED,
+            ],
+        ) {
+            return Error::Io(io::Error::new(io::ErrorKind::Other, err.backtrace()));
+        }
+
+        if err.matches_any(
+            domain::INPUT,
+            &[
+                input_error::GENERIC,
+                input_error::INVALID_DATA,
+                input_error::SIGNATURE_MISMATCH,
+                input_error::CHECKSUM_MISMATCH,
+                input_error::VALUE_MISMATCH,
+            ],
+        ) || err.matches_any(
+            domain::COMPRESSION,
+            &[
+                compression_error::GENERIC,
+                compression_error::COMPRESS_FAILED,
+                compression_error::DECOMPRESS_FAILED,
+            ],
+        ) {
+            let structure = err.root_message();
+
+            return Error::CorruptStructure {
+                offset: parse_offset(&structure),
+                structure,
+            };
+        }
+
+        if err.matches(domain::RUNTIME, runtime_error::UNSUPPORTED_VALUE)
+            || err.matches(domain::ARGUMENTS, argument_error::UNSUPPORTED_VALUE)
+        {
+            return Error::Unsupported(err.root_message());
+        }
+
+        if err.matches(domain::RUNTIME, runtime_error::ABORT_REQUESTED) {
+            return Error::Cancelled;
+        }
+
+        Error::FFI(err.backtrace())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::error::Error;
+    use super::*;
     use crate::fixtures::*;
     use crate::volume::{AccessMode, Volume};
 
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(
+            parse_offset("invalid MFT entry signature at offset: 0x00004000."),
+            Some(0x4000)
+        );
+        assert_eq!(parse_offset("value at offset: 512 out of bounds."), Some(512));
+        assert_eq!(parse_offset("unable to read MFT entry: 5."), None);
+    }
+
     #[test]
     fn test_error() {
         let result = Volume::open("non-existent", AccessMode::Read);
         assert!(result.is_err());
 
         if let Err(e) = result {
-            if let Error::FFI(s) = e {
+            if let Error::NotFound(s) = e {
                 dbg!(&s);
                 assert!(
                     s.find("libfsntfs_volume_open").is_some(),
@@ -1,11 +1,20 @@
 use chrono::prelude::*;
 
-use crate::attribute::{Attribute, AttributeRef, AttributeRefMut};
+use crate::attribute::{
+    Attribute, AttributeRef, AttributeRefMut, AttributeType, AttributeWithInformation, FileName,
+};
+use crate::data_stream::{
+    stored_data, DataStream, DataStreamRefMut, IterDataStreams, ResidentData, StoredData, Stream,
+};
 use crate::error::Error;
+use crate::flags::FileAttributeFlags;
+use crate::instrument::Metric;
+use crate::security_descriptor::SecurityDescriptor;
+use crate::utils::mft_entry_index;
 use crate::ffi_error::{LibfsntfsError, LibfsntfsErrorRef, LibfsntfsErrorRefMut};
 use crate::volume::{Volume, VolumeRef};
 use libfsntfs_sys::{
-    libfsntfs_attribute_t, libfsntfs_data_stream_t, off64_t, size64_t, SEEK_CUR, SEEK_END, SEEK_SET,
+    libfsntfs_attribute_t, off64_t, size64_t, SEEK_CUR, SEEK_END, SEEK_SET,
 };
 use libyal_rs_common::ffi::AsTypeRef;
 use std::convert::TryFrom;
@@ -27,6 +36,27 @@ pub type FileEntryRef = *const __FileEntry;
 #[repr(C)]
 pub struct FileEntry<'a>(FileEntryRefMut, &'a Volume);
 
+/// The extent is sparse (has no data on disk).
+pub const EXTENT_FLAG_IS_SPARSE: u32 = 0x0000_0001;
+/// The extent is compressed.
+pub const EXTENT_FLAG_IS_COMPRESSED: u32 = 0x0000_0002;
+
+/// A run of clusters holding the data of the default data stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extent {
+    /// Offset of the extent in the volume, in bytes.
+    pub offset: u64,
+    /// Size of the extent, in bytes.
+    pub size: u64,
+    pub flags: u32,
+}
+
+impl Extent {
+    pub fn is_sparse(&self) -> bool {
+        self.flags & EXTENT_FLAG_IS_SPARSE != 0
+    }
+}
+
 impl<'a> AsTypeRef for FileEntry<'a> {
     type Ref = FileEntryRef;
     type RefMut = FileEntryRefMut;
@@ -52,6 +82,11 @@ impl<'a> FileEntry<'a> {
     pub fn wrap_ptr(volume: &'a Volume, ptr: FileEntryRefMut) -> Self {
         FileEntry(ptr, volume)
     }
+
+    /// The volume the entry belongs to.
+    pub fn volume(&self) -> &'a Volume {
+        self.1
+    }
 }
 
 impl<'a> Drop for FileEntry<'a> {
@@ -278,7 +313,7 @@ extern "C" {
     pub fn libfsntfs_file_entry_get_alternate_data_stream_by_index(
         file_entry: FileEntryRef,
         alternate_data_stream_index: c_int,
-        alternate_data_stream: *mut *mut libfsntfs_data_stream_t,
+        alternate_data_stream: *mut DataStreamRefMut,
         error: *mut LibfsntfsErrorRefMut,
     ) -> c_int;
     pub fn libfsntfs_file_entry_has_alternate_data_stream_by_utf8_name(
@@ -297,14 +332,14 @@ extern "C" {
         file_entry: FileEntryRef,
         utf8_string: *const u8,
         utf8_string_length: usize,
-        alternate_data_stream: *mut *mut libfsntfs_data_stream_t,
+        alternate_data_stream: *mut DataStreamRefMut,
         error: *mut LibfsntfsErrorRefMut,
     ) -> c_int;
     pub fn libfsntfs_file_entry_get_alternate_data_stream_by_utf16_name(
         file_entry: FileEntryRef,
         utf16_string: *const u16,
         utf16_string_length: usize,
-        alternate_data_stream: *mut *mut libfsntfs_data_stream_t,
+        alternate_data_stream: *mut DataStreamRefMut,
         error: *mut LibfsntfsErrorRefMut,
     ) -> c_int;
     pub fn libfsntfs_file_entry_get_number_of_sub_file_entries(
@@ -421,6 +456,20 @@ impl<'a: 'b, 'b> Iterator for IterSubEntries<'a, 'b> {
 impl<'a> Read for FileEntry<'a> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
         let mut error = ptr::null_mut();
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::trace_span!(
+            "read_stream",
+            image = self.volume().get_image_path().unwrap_or_default(),
+            name = self.get_name().unwrap_or_default().as_str(),
+            length = buf.len()
+        );
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        let options = self.volume().options();
+        options.record(Metric::FfiCall("libfsntfs_file_entry_read_buffer"));
+
         let read_count = unsafe {
             libfsntfs_file_entry_read_buffer(
                 self.as_type_ref(),
@@ -443,6 +492,7 @@ impl<'a> Read for FileEntry<'a> {
 
             Err(io_err)
         } else {
+            options.record(Metric::BytesRead(read_count as u64));
             Ok(read_count as usize)
         }
     }
@@ -516,13 +566,80 @@ impl<'a> FileEntry<'a> {
     }
 
     /// Retrieves a specific alternate data stream.
-    pub fn get_alternate_data_stream(&self, alternate_data_stream_index: isize) {
-        unimplemented!();
+    pub fn get_alternate_data_stream(
+        &self,
+        alternate_data_stream_index: c_int,
+    ) -> Result<DataStream, Error> {
+        let mut data_stream = ptr::null_mut();
+
+        try_ffi!(libfsntfs_file_entry_get_alternate_data_stream_by_index(
+            self.as_type_ref(),
+            alternate_data_stream_index,
+            &mut data_stream
+        ));
+
+        Ok(DataStream::wrap_ptr(self, data_stream))
     }
 
     /// Retrieves an alternate data stream specified by the name.
-    pub fn get_alternate_data_stream_by_name(&self, name: isize) {
-        unimplemented!();
+    pub fn get_alternate_data_stream_by_name(&self, name: &str) -> Result<DataStream, Error> {
+        let mut data_stream = ptr::null_mut();
+
+        try_ffi!(libfsntfs_file_entry_get_alternate_data_stream_by_utf8_name(
+            self.as_type_ref(),
+            name.as_ptr(),
+            name.len(),
+            &mut data_stream
+        ));
+
+        Ok(DataStream::wrap_ptr(self, data_stream))
+    }
+
+    /// The default stream (when `name` is `None` or empty) or the named stream, `None` if the
+    /// entry has no such stream. Reading the default stream does not move the offset of `self`.
+    /// Of several streams with the same name, this is the one libfsntfs finds first, see
+    /// `data_attributes` for all of them.
+    pub fn data_stream(&self, name: Option<&str>) -> Result<Option<Stream>, Error> {
+        let name = match name {
+            Some(name) if !name.is_empty() => name,
+            _ => {
+                if !self.has_default_data_stream()? {
+                    return Ok(None);
+                }
+
+                let idx = mft_entry_index(self.get_file_reference()?);
+                let entry = self.volume().get_file_entry_by_mft_idx(idx)?;
+
+                return Ok(Some(Stream::Default(entry)));
+            }
+        };
+
+        let mut data_stream = ptr::null_mut();
+        let mut error = ptr::null_mut();
+
+        match unsafe {
+            libfsntfs_file_entry_get_alternate_data_stream_by_utf8_name(
+                self.as_type_ref(),
+                name.as_ptr(),
+                name.len(),
+                &mut data_stream,
+                &mut error,
+            )
+        } {
+            1 => Ok(Some(Stream::Named(DataStream::wrap_ptr(self, data_stream)))),
+            0 => Ok(None),
+            _ => Err(Error::try_from(error)?),
+        }
+    }
+
+    pub fn iter_alternate_data_streams(&self) -> Result<IterDataStreams, Error> {
+        let number_of_data_streams = self.get_number_of_alternate_data_streams()? as u32;
+
+        Ok(IterDataStreams {
+            handle: self,
+            num_data_streams: number_of_data_streams,
+            idx: 0,
+        })
     }
 
     pub fn iter_attributes(&self) -> Result<IterAttributes, Error> {
@@ -582,11 +699,20 @@ impl<'a> FileEntry<'a> {
         }
     }
 
+    /// The name, decoded by the `Utf16Policy` of the volume.
     pub fn get_name(&self) -> Result<String, Error> {
-        get_sized_utf8_string!(
+        self.1
+            .options()
+            .get_utf16_policy()
+            .decode(&self.get_utf16_name()?)
+    }
+
+    /// The name as stored, which may not be valid UTF-16.
+    pub fn get_utf16_name(&self) -> Result<Vec<u16>, Error> {
+        get_sized_utf16_units!(
             self,
-            libfsntfs_file_entry_get_utf8_name_size,
-            libfsntfs_file_entry_get_utf8_name
+            libfsntfs_file_entry_get_utf16_name_size,
+            libfsntfs_file_entry_get_utf16_name
         )
     }
 
@@ -594,6 +720,10 @@ impl<'a> FileEntry<'a> {
         let mut sub_entry = ptr::null_mut();
         let mut error = ptr::null_mut();
 
+        self.1.options().record(Metric::FfiCall(
+            "libfsntfs_file_entry_get_sub_file_entry_by_index",
+        ));
+
         if unsafe {
             libfsntfs_file_entry_get_sub_file_entry_by_index(
                 self.as_type_ref(),
@@ -683,12 +813,40 @@ impl<'a> FileEntry<'a> {
         unimplemented!();
     }
 
-    pub fn get_extent(&self, extent_index: isize) {
-        unimplemented!();
+    /// Retrieves a specific extent of the default data stream.
+    pub fn get_extent(&self, extent_index: c_int) -> Result<Extent, Error> {
+        let mut offset = 0;
+        let mut size = 0;
+        let mut flags = 0;
+        let mut error = ptr::null_mut();
+
+        if unsafe {
+            libfsntfs_file_entry_get_extent_by_index(
+                self.as_type_ref(),
+                extent_index,
+                &mut offset,
+                &mut size,
+                &mut flags,
+                &mut error,
+            )
+        } != 1
+        {
+            Err(Error::try_from(error)?)
+        } else {
+            Ok(Extent {
+                offset: offset as u64,
+                size,
+                flags,
+            })
+        }
     }
 
-    pub fn get_file_attribute_flags(&self) {
-        unimplemented!();
+    /// Retrieves the file attribute flags (from `$STANDARD_INFORMATION`).
+    pub fn get_file_attribute_flags(&self) -> Result<FileAttributeFlags, Error> {
+        Ok(FileAttributeFlags::from(get_u32_field!(
+            self,
+            libfsntfs_file_entry_get_file_attribute_flags
+        )?))
     }
 
     pub fn get_journal_sequence_number(&self) {
@@ -711,44 +869,265 @@ impl<'a> FileEntry<'a> {
         unimplemented!();
     }
 
-    pub fn get_number_of_alternate_data_streams(&self) {
-        unimplemented!();
+    pub fn get_number_of_alternate_data_streams(&self) -> Result<c_int, Error> {
+        let mut number_of_data_streams = 0;
+
+        try_ffi!(libfsntfs_file_entry_get_number_of_alternate_data_streams(
+            self.as_type_ref(),
+            &mut number_of_data_streams
+        ));
+
+        Ok(number_of_data_streams)
     }
 
-    pub fn get_number_of_extents(&self) {
-        unimplemented!();
+    /// Retrieves the number of extents of the default data stream.
+    pub fn get_number_of_extents(&self) -> Result<c_int, Error> {
+        let mut number_of_extents = 0;
+        let mut error = ptr::null_mut();
+
+        if unsafe {
+            libfsntfs_file_entry_get_number_of_extents(
+                self.as_type_ref(),
+                &mut number_of_extents,
+                &mut error,
+            )
+        } != 1
+        {
+            Err(Error::try_from(error)?)
+        } else {
+            Ok(number_of_extents)
+        }
     }
 
     pub fn get_parent_file_reference_by_attribute_index(&self, attribute_index: isize) {
         unimplemented!();
     }
 
-    pub fn get_reparse_point_print_name(&self) {
-        unimplemented!();
+    /// Retrieves the name of the reparse point target meant for display, if the entry is a reparse
+    /// point, decoded by the `Utf16Policy` of the volume.
+    pub fn get_reparse_point_print_name(&self) -> Result<Option<String>, Error> {
+        match self.get_utf16_reparse_point_print_name()? {
+            Some(name) => Ok(Some(self.1.options().get_utf16_policy().decode(&name)?)),
+            None => Ok(None),
+        }
     }
 
-    pub fn get_reparse_point_substitute_name(&self) {
-        unimplemented!();
+    pub fn get_utf16_reparse_point_print_name(&self) -> Result<Option<Vec<u16>>, Error> {
+        get_optional_sized_utf16_units!(
+            self,
+            libfsntfs_file_entry_get_utf16_reparse_point_print_name_size,
+            libfsntfs_file_entry_get_utf16_reparse_point_print_name
+        )
     }
 
-    pub fn get_security_descriptor_data(&self) {
-        unimplemented!();
+    /// Retrieves the reparse point target (e.g. `\??\C:\target`), if the entry is a reparse
+    /// point, decoded by the `Utf16Policy` of the volume.
+    pub fn get_reparse_point_substitute_name(&self) -> Result<Option<String>, Error> {
+        match self.get_utf16_reparse_point_substitute_name()? {
+            Some(name) => Ok(Some(self.1.options().get_utf16_policy().decode(&name)?)),
+            None => Ok(None),
+        }
     }
 
-    pub fn has_alternate_data_stream_by_name(&self, name: isize) {
-        unimplemented!();
+    pub fn get_utf16_reparse_point_substitute_name(&self) -> Result<Option<Vec<u16>>, Error> {
+        get_optional_sized_utf16_units!(
+            self,
+            libfsntfs_file_entry_get_utf16_reparse_point_substitute_name_size,
+            libfsntfs_file_entry_get_utf16_reparse_point_substitute_name
+        )
     }
 
-    pub fn has_default_data_stream(&self) {
-        unimplemented!();
+    /// Retrieves the tag of the `$REPARSE_POINT` attribute, if the entry has one.
+    pub fn get_reparse_point_tag(&self) -> Result<Option<u32>, Error> {
+        for attribute in self.iter_attributes()? {
+            let attribute = attribute?;
+
+            if attribute.get_type()? == AttributeType::ReparsePoint {
+                return Ok(Some(attribute.get_reparse_point_tag()?));
+            }
+        }
+
+        Ok(None)
     }
 
-    pub fn has_directory_entries_index(&self) {
-        unimplemented!();
+    /// Retrieves every `$FILE_NAME` attribute: one per hard link, plus the DOS (8.3) names.
+    pub fn get_file_names(&self) -> Result<Vec<FileName>, Error> {
+        let mut file_names = vec![];
+
+        for attribute in self.iter_attributes()? {
+            let attribute = attribute?;
+
+            if attribute.get_type()? == AttributeType::FileName {
+                if let AttributeWithInformation::FileName(file_name) = attribute.get_data()? {
+                    file_names.push(file_name);
+                }
+            }
+        }
+
+        Ok(file_names)
     }
 
-    pub fn is_empty(&self) {
-        unimplemented!();
+    /// Retrieves the security descriptor of the entry (libfsntfs looks it up in `$Secure`),
+    /// `None` if the entry has none.
+    pub fn get_security_descriptor_data(&self) -> Result<Option<Vec<u8>>, Error> {
+        let data = get_sized_bytes!(
+            self,
+            libfsntfs_file_entry_get_security_descriptor_size,
+            libfsntfs_file_entry_get_security_descriptor
+        )?;
+
+        Ok(if data.is_empty() { None } else { Some(data) })
+    }
+
+    /// Retrieves and parses the security descriptor of the entry.
+    pub fn get_security_descriptor(&self) -> Result<Option<SecurityDescriptor>, Error> {
+        match self.get_security_descriptor_data()? {
+            Some(data) => Ok(Some(SecurityDescriptor::parse(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieves the security id from `$STANDARD_INFORMATION`, see `SecurityDescriptors`.
+    pub fn get_security_descriptor_identifier(&self) -> Result<Option<u32>, Error> {
+        for attribute in self.iter_attributes()? {
+            let attribute = attribute?;
+
+            if attribute.get_type()? == AttributeType::StandardInformation {
+                return attribute.get_security_descriptor_identifier();
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn has_alternate_data_stream_by_name(&self, name: &str) -> Result<bool, Error> {
+        let mut error = ptr::null_mut();
+
+        match unsafe {
+            libfsntfs_file_entry_has_alternate_data_stream_by_utf8_name(
+                self.as_type_ref(),
+                name.as_ptr(),
+                name.len(),
+                &mut error,
+            )
+        } {
+            1 => Ok(true),
+            0 => Ok(false),
+            _ => Err(Error::try_from(error)?),
+        }
+    }
+
+    /// Whether the entry has an unnamed `$DATA` attribute.
+    pub fn has_default_data_stream(&self) -> Result<bool, Error> {
+        let mut error = ptr::null_mut();
+
+        match unsafe { libfsntfs_file_entry_has_default_data_stream(self.as_type_ref(), &mut error) }
+        {
+            1 => Ok(true),
+            0 => Ok(false),
+            _ => Err(Error::try_from(error)?),
+        }
+    }
+
+    /// Whether the entry has named (alternate) data streams.
+    pub fn has_ads(&self) -> Result<bool, Error> {
+        Ok(self.get_number_of_alternate_data_streams()? > 0)
+    }
+
+    /// The number of `$DATA` streams, the default stream included.
+    pub fn data_stream_count(&self) -> Result<usize, Error> {
+        let default = if self.has_default_data_stream()? { 1 } else { 0 };
+
+        Ok(default + self.get_number_of_alternate_data_streams()? as usize)
+    }
+
+    /// Whether the default data stream is stored in the MFT record itself, see `DataStream::is_resident`.
+    pub fn is_resident(&self) -> Result<bool, Error> {
+        if !self.has_default_data_stream()? {
+            return Ok(false);
+        }
+
+        match stored_data(self, "")? {
+            StoredData::Resident(_) => Ok(true),
+            StoredData::NonResident => Ok(false),
+            StoredData::Elsewhere => Ok(self.get_number_of_extents()? == 0),
+        }
+    }
+
+    /// The data of the default data stream if it is resident, see `DataStream::resident_data`.
+    pub fn resident_data(&self) -> Result<Option<ResidentData>, Error> {
+        if !self.has_default_data_stream()? {
+            return Ok(None);
+        }
+
+        match stored_data(self, "")? {
+            StoredData::Resident(data) => Ok(Some(data)),
+            StoredData::NonResident => Ok(None),
+            StoredData::Elsewhere => {
+                let size = self.get_size()?;
+                let mft_entry_size = u64::from(self.volume().get_mft_entry_size()?);
+
+                if self.get_number_of_extents()? != 0 || size > mft_entry_size {
+                    return Ok(None);
+                }
+
+                let mut data = vec![0; size as usize];
+                let mut error = ptr::null_mut();
+
+                let read_count = unsafe {
+                    libfsntfs_file_entry_read_buffer_at_offset(
+                        self.as_type_ref(),
+                        data.as_mut_ptr() as *mut c_void,
+                        data.len(),
+                        0,
+                        &mut error,
+                    )
+                };
+
+                if read_count <= -1 {
+                    return Err(Error::try_from(error)?);
+                }
+
+                data.truncate(read_count as usize);
+
+                Ok(Some(ResidentData::from_vec(data)))
+            }
+        }
+    }
+
+    /// Whether the entry has a `$I30` index, i.e. it is a directory.
+    pub fn has_directory_entries_index(&self) -> Result<bool, Error> {
+        let mut error = ptr::null_mut();
+
+        match unsafe {
+            libfsntfs_file_entry_has_directory_entries_index(self.as_type_ref(), &mut error)
+        } {
+            1 => Ok(true),
+            0 => Ok(false),
+            _ => Err(Error::try_from(error)?),
+        }
+    }
+
+    /// Whether the MFT entry has no content (e.g. it was never used).
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        let mut error = ptr::null_mut();
+
+        match unsafe { libfsntfs_file_entry_is_empty(self.as_type_ref(), &mut error) } {
+            1 => Ok(true),
+            0 => Ok(false),
+            _ => Err(Error::try_from(error)?),
+        }
+    }
+
+    /// Whether the MFT entry is in use, deleted files are unallocated.
+    pub fn is_allocated(&self) -> Result<bool, Error> {
+        let mut error = ptr::null_mut();
+
+        match unsafe { libfsntfs_file_entry_is_allocated(self.as_type_ref(), &mut error) } {
+            1 => Ok(true),
+            0 => Ok(false),
+            _ => Err(Error::try_from(error)?),
+        }
     }
 }
 
@@ -756,6 +1135,7 @@ impl<'a> FileEntry<'a> {
 mod tests {
     use super::*;
     use crate::fixtures::*;
+    use crate::metadata_files;
     use log::{info, trace};
     use std::path::PathBuf;
 
@@ -774,6 +1154,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_get_file_attribute_flags() {
+        let volume = sample_volume().unwrap();
+        let mft = volume.get_file_entry_by_mft_idx(0).unwrap();
+
+        let flags = mft.get_file_attribute_flags().unwrap();
+
+        assert!(flags.contains(FileAttributeFlags::HIDDEN | FileAttributeFlags::SYSTEM));
+    }
+
     #[test]
     fn test_read() {
         let volume = sample_volume().unwrap();
@@ -807,4 +1197,43 @@ mod tests {
 
         assert_eq!(buf.len(), 75776);
     }
+
+    #[test]
+    fn test_alternate_data_streams() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+
+        let number_of_data_streams = entry.get_number_of_alternate_data_streams().unwrap();
+
+        assert_eq!(
+            entry.iter_alternate_data_streams().unwrap().count(),
+            number_of_data_streams as usize
+        );
+        assert!(!entry.has_alternate_data_stream_by_name("missing").unwrap());
+        assert!(!entry.is_resident().unwrap());
+        assert!(entry.resident_data().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_data_stream() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+
+        let mut buf = Vec::new();
+        let mut default = entry.data_stream(None).unwrap().unwrap();
+        default.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf.len(), 75776);
+        assert!(entry.data_stream(Some("missing")).unwrap().is_none());
+        assert!(entry.has_default_data_stream().unwrap());
+        assert!(!entry.has_ads().unwrap());
+        assert_eq!(entry.data_stream_count().unwrap(), 1);
+
+        let secure = volume
+            .get_file_entry_by_mft_idx(metadata_files::SECURE)
+            .unwrap();
+        let sds = secure.data_stream(Some("$SDS")).unwrap().unwrap();
+        assert_eq!(sds.get_name().unwrap(), "$SDS");
+        assert!(secure.has_ads().unwrap());
+        assert!(!secure.has_default_data_stream().unwrap());
+    }
 }
@@ -0,0 +1,222 @@
+//! Predicates on timestamps, size and flags of MFT entries, checked before the (more expensive)
+//! names and paths of the entries are looked up.
+use crate::attribute::AttributeType;
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::flags::FileAttributeFlags;
+use crate::timestamps::{MacbField, TimestampReport};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeRange {
+    /// `StandardInformation` or `FileName`.
+    pub attribute_type: AttributeType,
+    pub field: MacbField,
+    /// Inclusive.
+    pub after: Option<DateTime<Utc>>,
+    /// Exclusive.
+    pub before: Option<DateTime<Utc>>,
+}
+
+impl TimeRange {
+    /// Entries without the timestamp never match.
+    fn matches(&self, report: &TimestampReport) -> bool {
+        let times = match self.attribute_type {
+            AttributeType::StandardInformation => report.standard_information,
+            AttributeType::FileName => report.file_name,
+            _ => None,
+        };
+
+        match times.and_then(|times| times.get_datetime(self.field)) {
+            Some(datetime) => {
+                self.after.map_or(true, |after| datetime >= after)
+                    && self.before.map_or(true, |before| datetime < before)
+            }
+            None => false,
+        }
+    }
+}
+
+/// All conditions must hold for an entry to match, the default filter matches everything.
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    pub time_ranges: Vec<TimeRange>,
+    /// Logical size of the default data stream, in bytes.
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Flags which must all be set.
+    pub flags_set: FileAttributeFlags,
+    /// Flags which must all be clear.
+    pub flags_clear: FileAttributeFlags,
+    /// Whether the entry must (or must not) have named data streams.
+    pub has_ads: Option<bool>,
+}
+
+impl EntryFilter {
+    pub fn new() -> Self {
+        EntryFilter::default()
+    }
+
+    pub fn time_range(
+        mut self,
+        attribute_type: AttributeType,
+        field: MacbField,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.time_ranges.push(TimeRange {
+            attribute_type,
+            field,
+            after,
+            before,
+        });
+        self
+    }
+
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        self.min_size = Some(min_size);
+        self
+    }
+
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    pub fn with_flags(mut self, flags: FileAttributeFlags) -> Self {
+        self.flags_set |= flags;
+        self
+    }
+
+    pub fn without_flags(mut self, flags: FileAttributeFlags) -> Self {
+        self.flags_clear |= flags;
+        self
+    }
+
+    pub fn has_ads(mut self, has_ads: bool) -> Self {
+        self.has_ads = Some(has_ads);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time_ranges.is_empty()
+            && self.min_size.is_none()
+            && self.max_size.is_none()
+            && self.flags_set.is_empty()
+            && self.flags_clear.is_empty()
+            && self.has_ads.is_none()
+    }
+
+    /// Checks the cheapest conditions first, timestamps are only read when the rest matched.
+    pub fn matches(&self, entry: &FileEntry) -> Result<bool, Error> {
+        if self.is_empty() {
+            return Ok(true);
+        }
+
+        if !self.flags_set.is_empty() || !self.flags_clear.is_empty() {
+            let flags = entry.get_file_attribute_flags()?;
+
+            if !flags.contains(self.flags_set) || flags.intersects(self.flags_clear) {
+                return Ok(false);
+            }
+        }
+
+        if let Some(has_ads) = self.has_ads {
+            if entry.has_ads()? != has_ads {
+                return Ok(false);
+            }
+        }
+
+        if self.min_size.is_some() || self.max_size.is_some() {
+            let size = entry.get_size()?;
+
+            if self.min_size.map_or(false, |min_size| size < min_size)
+                || self.max_size.map_or(false, |max_size| size > max_size)
+            {
+                return Ok(false);
+            }
+        }
+
+        if !self.time_ranges.is_empty() {
+            let report = entry.get_timestamp_report()?;
+
+            if !self
+                .time_ranges
+                .iter()
+                .all(|time_range| time_range.matches(&report))
+            {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::timestamps::MacbTimes;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_time_range() {
+        // 2019-01-01 00:00:00.1234567
+        let times = MacbTimes {
+            creation: 131_907_744_001_234_567,
+            ..MacbTimes::default()
+        };
+        let report = TimestampReport::from_times(Some(times), None);
+
+        let range = |after, before| TimeRange {
+            attribute_type: AttributeType::StandardInformation,
+            field: MacbField::Creation,
+            after,
+            before,
+        };
+
+        assert!(range(Some(Utc.ymd(2019, 1, 1).and_hms(0, 0, 0)), None).matches(&report));
+        assert!(!range(None, Some(Utc.ymd(2018, 1, 1).and_hms(0, 0, 0))).matches(&report));
+
+        let file_name_range = TimeRange {
+            attribute_type: AttributeType::FileName,
+            ..range(None, None)
+        };
+        assert!(!file_name_range.matches(&report));
+    }
+
+    #[test]
+    fn test_entry_filter() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+        let size = entry.get_size().unwrap();
+
+        assert!(EntryFilter::new().matches(&entry).unwrap());
+        assert!(EntryFilter::new()
+            .min_size(size)
+            .max_size(size)
+            .matches(&entry)
+            .unwrap());
+        assert!(!EntryFilter::new()
+            .min_size(size + 1)
+            .matches(&entry)
+            .unwrap());
+
+        let flags = entry.get_file_attribute_flags().unwrap();
+        assert!(EntryFilter::new()
+            .with_flags(flags)
+            .matches(&entry)
+            .unwrap());
+
+        if !flags.is_empty() {
+            assert!(!EntryFilter::new()
+                .without_flags(flags)
+                .matches(&entry)
+                .unwrap());
+        }
+
+        assert!(EntryFilter::new().has_ads(false).matches(&entry).unwrap());
+        assert!(!EntryFilter::new().has_ads(true).matches(&entry).unwrap());
+    }
+}
@@ -0,0 +1,161 @@
+//! Flag types of NTFS attributes.
+use bitflags::bitflags;
+use std::fmt::{self, Display, Formatter};
+
+bitflags! {
+    /// The FAT-style file attribute flags, stored in `$STANDARD_INFORMATION` and `$FILE_NAME`.
+    #[derive(Default)]
+    pub struct FileAttributeFlags: u32 {
+        const READ_ONLY = 0x0000_0001;
+        const HIDDEN = 0x0000_0002;
+        const SYSTEM = 0x0000_0004;
+        const DIRECTORY = 0x0000_0010;
+        const ARCHIVE = 0x0000_0020;
+        const DEVICE = 0x0000_0040;
+        const NORMAL = 0x0000_0080;
+        const TEMPORARY = 0x0000_0100;
+        const SPARSE_FILE = 0x0000_0200;
+        const REPARSE_POINT = 0x0000_0400;
+        const COMPRESSED = 0x0000_0800;
+        const OFFLINE = 0x0000_1000;
+        const NOT_CONTENT_INDEXED = 0x0000_2000;
+        const ENCRYPTED = 0x0000_4000;
+        const INTEGRITY_STREAM = 0x0000_8000;
+        const VIRTUAL = 0x0001_0000;
+        const NO_SCRUB_DATA = 0x0002_0000;
+        /// Set in `$FILE_NAME` for directories (the entry has a `$I30` index).
+        const DUPLICATE_FILE_NAME_INDEX_PRESENT = 0x1000_0000;
+        /// The entry has a view index (e.g. `$Secure` or `$Quota`).
+        const VIEW_INDEX_PRESENT = 0x2000_0000;
+    }
+}
+
+bitflags! {
+    /// The volume flags, stored in `$VOLUME_INFORMATION` of `$Volume`.
+    pub struct VolumeFlags: u16 {
+        /// The volume was not cleanly unmounted (or chkdsk was scheduled).
+        const DIRTY = 0x0001;
+        const RESIZE_LOG_FILE = 0x0002;
+        const UPGRADE_ON_MOUNT = 0x0004;
+        const MOUNTED_ON_NT4 = 0x0008;
+        const DELETE_USN_UNDERWAY = 0x0010;
+        const REPAIR_OBJECT_IDS = 0x0020;
+        const CHKDSK_UNDERWAY = 0x4000;
+        const MODIFIED_BY_CHKDSK = 0x8000;
+    }
+}
+
+/// Flag names, in the order they are displayed.
+const FILE_ATTRIBUTE_FLAG_NAMES: [(FileAttributeFlags, &str); 19] = [
+    (FileAttributeFlags::READ_ONLY, "read-only"),
+    (FileAttributeFlags::HIDDEN, "hidden"),
+    (FileAttributeFlags::SYSTEM, "system"),
+    (FileAttributeFlags::DIRECTORY, "directory"),
+    (FileAttributeFlags::ARCHIVE, "archive"),
+    (FileAttributeFlags::DEVICE, "device"),
+    (FileAttributeFlags::NORMAL, "normal"),
+    (FileAttributeFlags::TEMPORARY, "temporary"),
+    (FileAttributeFlags::SPARSE_FILE, "sparse"),
+    (FileAttributeFlags::REPARSE_POINT, "reparse point"),
+    (FileAttributeFlags::COMPRESSED, "compressed"),
+    (FileAttributeFlags::OFFLINE, "offline"),
+    (FileAttributeFlags::NOT_CONTENT_INDEXED, "not content indexed"),
+    (FileAttributeFlags::ENCRYPTED, "encrypted"),
+    (FileAttributeFlags::INTEGRITY_STREAM, "integrity stream"),
+    (FileAttributeFlags::VIRTUAL, "virtual"),
+    (FileAttributeFlags::NO_SCRUB_DATA, "no scrub data"),
+    (
+        FileAttributeFlags::DUPLICATE_FILE_NAME_INDEX_PRESENT,
+        "directory index",
+    ),
+    (FileAttributeFlags::VIEW_INDEX_PRESENT, "view index"),
+];
+
+const VOLUME_FLAG_NAMES: [(VolumeFlags, &str); 8] = [
+    (VolumeFlags::DIRTY, "dirty"),
+    (VolumeFlags::RESIZE_LOG_FILE, "resize log file"),
+    (VolumeFlags::UPGRADE_ON_MOUNT, "upgrade on mount"),
+    (VolumeFlags::MOUNTED_ON_NT4, "mounted on NT4"),
+    (VolumeFlags::DELETE_USN_UNDERWAY, "delete USN underway"),
+    (VolumeFlags::REPAIR_OBJECT_IDS, "repair object ids"),
+    (VolumeFlags::CHKDSK_UNDERWAY, "chkdsk underway"),
+    (VolumeFlags::MODIFIED_BY_CHKDSK, "modified by chkdsk"),
+];
+
+fn fmt_flag_names<F: Copy>(
+    f: &mut Formatter,
+    flag_names: &[(F, &str)],
+    contains: impl Fn(F) -> bool,
+) -> fmt::Result {
+    let names: Vec<&str> = flag_names
+        .iter()
+        .filter(|(flag, _name)| contains(*flag))
+        .map(|(_flag, name)| *name)
+        .collect();
+
+    if names.is_empty() {
+        f.write_str("none")
+    } else {
+        f.write_str(&names.join(", "))
+    }
+}
+
+/// Unknown bits are dropped, use `FileAttributeFlags::bits` of the raw value if they matter.
+impl From<u32> for FileAttributeFlags {
+    fn from(flags: u32) -> Self {
+        FileAttributeFlags::from_bits_truncate(flags)
+    }
+}
+
+/// Comma separated flag names, e.g. `hidden, system, archive`.
+impl Display for FileAttributeFlags {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        fmt_flag_names(f, &FILE_ATTRIBUTE_FLAG_NAMES, |flag| self.contains(flag))
+    }
+}
+
+impl From<u16> for VolumeFlags {
+    fn from(flags: u16) -> Self {
+        VolumeFlags::from_bits_truncate(flags)
+    }
+}
+
+impl VolumeFlags {
+    /// Whether the volume was not cleanly unmounted.
+    pub fn is_dirty(&self) -> bool {
+        self.contains(VolumeFlags::DIRTY)
+    }
+}
+
+/// Comma separated flag names, e.g. `dirty, modified by chkdsk`.
+impl Display for VolumeFlags {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        fmt_flag_names(f, &VOLUME_FLAG_NAMES, |flag| self.contains(flag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let flags = FileAttributeFlags::from(0x0000_0026);
+
+        assert_eq!(
+            flags,
+            FileAttributeFlags::HIDDEN | FileAttributeFlags::SYSTEM | FileAttributeFlags::ARCHIVE
+        );
+        assert_eq!(flags.to_string(), "hidden, system, archive");
+        assert_eq!(FileAttributeFlags::empty().to_string(), "none");
+    }
+
+    #[test]
+    fn test_volume_flags() {
+        let flags = VolumeFlags::from(0x8001);
+
+        assert!(flags.is_dirty());
+        assert_eq!(flags.to_string(), "dirty, modified by chkdsk");
+        assert!(!VolumeFlags::empty().is_dirty());
+    }
+}
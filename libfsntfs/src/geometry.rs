@@ -0,0 +1,159 @@
+//! The layout of the volume, as recorded in the boot sector (`$Boot`).
+use crate::error::Error;
+use crate::metadata_files;
+use crate::mft_record::{invalid, read_u16, read_u64};
+use crate::volume::Volume;
+use std::io::Read;
+
+pub const BOOT_SECTOR_SIZE: usize = 512;
+pub const NTFS_OEM_IDENTIFIER: &[u8; 8] = b"NTFS    ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VolumeGeometry {
+    pub bytes_per_sector: u16,
+    pub sectors_per_cluster: u32,
+    /// Size of a cluster, in bytes.
+    pub cluster_size: u64,
+    /// Number of sectors in the volume, the backup boot sector after the last cluster is not included.
+    pub total_sectors: u64,
+    pub total_clusters: u64,
+    /// First cluster of `$MFT`.
+    pub mft_start_lcn: u64,
+    /// First cluster of `$MFTMirr`.
+    pub mft_mirror_lcn: u64,
+    /// Size of an MFT entry, in bytes.
+    pub mft_entry_size: u32,
+    /// Size of an index record (`INDX`), in bytes.
+    pub index_entry_size: u32,
+}
+
+/// Decodes the "clusters per record" bytes, negative values are the log2 of the size in bytes.
+fn record_size(value: u8, cluster_size: u64) -> u32 {
+    let value = value as i8;
+
+    if value < 0 {
+        1 << u32::from(value.wrapping_neg() as u8)
+    } else {
+        (value as u64 * cluster_size) as u32
+    }
+}
+
+impl VolumeGeometry {
+    /// Reads the boot sector of the volume.
+    pub fn read(volume: &Volume) -> Result<Self, Error> {
+        let mut boot_sector = [0; BOOT_SECTOR_SIZE];
+
+        volume
+            .get_file_entry_by_mft_idx(metadata_files::BOOT)?
+            .read_exact(&mut boot_sector)?;
+
+        VolumeGeometry::from_boot_sector(&boot_sector)
+    }
+
+    pub fn from_boot_sector(boot_sector: &[u8]) -> Result<Self, Error> {
+        if boot_sector.get(3..11) != Some(&NTFS_OEM_IDENTIFIER[..]) {
+            return Err(invalid("boot sector has no NTFS signature".to_owned()));
+        }
+
+        let bytes_per_sector = read_u16(boot_sector, 0x0b)?;
+
+        // Large clusters (Windows 10 1709 and later) store the log2 of the count.
+        let sectors_per_cluster = match boot_sector[0x0d] {
+            value if value > 0x80 => 1_u32 << (256 - u32::from(value)),
+            value => u32::from(value),
+        };
+
+        let cluster_size = u64::from(bytes_per_sector) * u64::from(sectors_per_cluster);
+
+        if cluster_size == 0 {
+            return Err(invalid("boot sector has a cluster size of 0".to_owned()));
+        }
+
+        let total_sectors = read_u64(boot_sector, 0x28)?;
+
+        Ok(VolumeGeometry {
+            bytes_per_sector,
+            sectors_per_cluster,
+            cluster_size,
+            total_sectors,
+            total_clusters: total_sectors * u64::from(bytes_per_sector) / cluster_size,
+            mft_start_lcn: read_u64(boot_sector, 0x30)?,
+            mft_mirror_lcn: read_u64(boot_sector, 0x38)?,
+            mft_entry_size: record_size(boot_sector[0x40], cluster_size),
+            index_entry_size: record_size(boot_sector[0x44], cluster_size),
+        })
+    }
+
+    /// Size of the volume, in bytes.
+    pub fn volume_size(&self) -> u64 {
+        self.total_sectors * u64::from(self.bytes_per_sector)
+    }
+
+    /// Offset of `$MFT` in the volume, in bytes.
+    pub fn mft_offset(&self) -> u64 {
+        self.mft_start_lcn * self.cluster_size
+    }
+
+    /// Offset of `$MFTMirr` in the volume, in bytes.
+    pub fn mft_mirror_offset(&self) -> u64 {
+        self.mft_mirror_lcn * self.cluster_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn boot_sector() -> Vec<u8> {
+        let mut boot_sector = vec![0; BOOT_SECTOR_SIZE];
+        boot_sector[3..11].copy_from_slice(NTFS_OEM_IDENTIFIER);
+        boot_sector[0x0b..0x0d].copy_from_slice(&512_u16.to_le_bytes());
+        boot_sector[0x0d] = 8;
+        boot_sector[0x28..0x30].copy_from_slice(&204_799_u64.to_le_bytes());
+        boot_sector[0x30..0x38].copy_from_slice(&4_u64.to_le_bytes());
+        boot_sector[0x38..0x40].copy_from_slice(&12_799_u64.to_le_bytes());
+        // 2^10 bytes per MFT entry.
+        boot_sector[0x40] = 0xf6;
+        boot_sector[0x44] = 1;
+        boot_sector
+    }
+
+    #[test]
+    fn test_from_boot_sector() {
+        let geometry = VolumeGeometry::from_boot_sector(&boot_sector()).unwrap();
+
+        assert_eq!(geometry.cluster_size, 4096);
+        assert_eq!(geometry.total_clusters, 25_599);
+        assert_eq!(geometry.mft_offset(), 4 * 4096);
+        assert_eq!(geometry.mft_entry_size, 1024);
+        assert_eq!(geometry.index_entry_size, 4096);
+    }
+
+    #[test]
+    fn test_large_clusters() {
+        let mut boot_sector = boot_sector();
+        // 2^(256 - 0xf7) = 512 sectors per cluster.
+        boot_sector[0x0d] = 0xf7;
+
+        let geometry = VolumeGeometry::from_boot_sector(&boot_sector).unwrap();
+
+        assert_eq!(geometry.sectors_per_cluster, 512);
+        assert_eq!(geometry.cluster_size, 256 * 1024);
+    }
+
+    #[test]
+    fn test_read_geometry() {
+        let volume = sample_volume().unwrap();
+        let geometry = volume.geometry().unwrap();
+
+        assert_eq!(
+            geometry.cluster_size,
+            volume.get_cluster_block_size().unwrap() as u64
+        );
+        assert_eq!(
+            geometry.mft_entry_size,
+            volume.get_mft_entry_size().unwrap()
+        );
+    }
+}
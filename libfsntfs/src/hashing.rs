@@ -0,0 +1,123 @@
+//! Hashes data while it is being read, so extraction only needs a single pass over each stream.
+//!
+//! Each algorithm is behind its own feature (`md-5`, `sha-1` and `sha2`),
+//! the hashes of algorithms which are not enabled are `None`.
+use std::io::{self, Read};
+
+#[cfg(feature = "md-5")]
+use md5::{Digest as _, Md5};
+#[cfg(feature = "sha-1")]
+use sha1::{Digest as _, Sha1};
+#[cfg(feature = "sha2")]
+use sha2::{Digest as _, Sha256};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hashes {
+    /// Lowercase hex digests.
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+}
+
+fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Wraps a reader (e.g. a `FileEntry`) and hashes everything read through it.
+pub struct HashingReader<R> {
+    inner: R,
+    #[cfg(feature = "md-5")]
+    md5: Md5,
+    #[cfg(feature = "sha-1")]
+    sha1: Sha1,
+    #[cfg(feature = "sha2")]
+    sha256: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> Self {
+        HashingReader {
+            inner,
+            #[cfg(feature = "md-5")]
+            md5: Md5::new(),
+            #[cfg(feature = "sha-1")]
+            sha1: Sha1::new(),
+            #[cfg(feature = "sha2")]
+            sha256: Sha256::new(),
+        }
+    }
+
+    /// The hashes of everything read so far.
+    pub fn finish(self) -> Hashes {
+        let mut hashes = Hashes::default();
+
+        #[cfg(feature = "md-5")]
+        {
+            hashes.md5 = Some(to_hex(&self.md5.result()));
+        }
+        #[cfg(feature = "sha-1")]
+        {
+            hashes.sha1 = Some(to_hex(&self.sha1.result()));
+        }
+        #[cfg(feature = "sha2")]
+        {
+            hashes.sha256 = Some(to_hex(&self.sha256.result()));
+        }
+
+        hashes
+    }
+
+    /// Reads the rest of the stream (discarding it) and returns the hashes.
+    pub fn hash_to_end(mut self) -> io::Result<Hashes> {
+        io::copy(&mut self, &mut io::sink())?;
+
+        Ok(self.finish())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let data = &buf[..read];
+
+        #[cfg(feature = "md-5")]
+        self.md5.input(data);
+        #[cfg(feature = "sha-1")]
+        self.sha1.input(data);
+        #[cfg(feature = "sha2")]
+        self.sha256.input(data);
+
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_hashes_everything_read() {
+        let mut reader = HashingReader::new(&b"abc"[..]);
+
+        let mut data = vec![];
+        reader.read_to_end(&mut data).unwrap();
+
+        assert_eq!(data, b"abc");
+
+        let hashes = reader.finish();
+
+        #[cfg(feature = "md-5")]
+        assert_eq!(hashes.md5.unwrap(), "900150983cd24fb0d6963f7d28e17f72");
+        #[cfg(feature = "sha-1")]
+        assert_eq!(
+            hashes.sha1.unwrap(),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        #[cfg(feature = "sha2")]
+        assert_eq!(
+            hashes.sha256.unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,440 @@
+//! Opens disk images of any supported format, and the NTFS volumes in them.
+//!
+//! ```ignore
+//! for volume in images::open_volumes("evidence.E01", &VolumeOpenOptions::new())? {
+//!     println!("{:?}", volume.get_name()?);
+//! }
+//! ```
+//!
+//! Formats other than raw need their feature: `ewf`, `vhdi` (VHD and VHDX) or `qcow`.
+use crate::error::Error;
+use crate::options::VolumeOpenOptions;
+use crate::partition::PartitionTable;
+use crate::shared::{ReadSeekSend, SharedReader, SharedVolume};
+use crate::utils::read_up_to;
+use std::cmp;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const SECTOR_SIZE: u64 = 512;
+const NTFS_SIGNATURE: &[u8] = b"NTFS    ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Raw,
+    /// A raw image split into numbered segments (`.001`, `.002`, ..).
+    SplitRaw,
+    Ewf,
+    Vhd,
+    Vhdx,
+    Qcow,
+}
+
+impl ImageFormat {
+    /// Detects the format from the start (`header`) and the last sector (`footer`) of the
+    /// first segment of the image.
+    pub fn from_signatures(header: &[u8], footer: &[u8]) -> ImageFormat {
+        let signatures: [(&[u8], ImageFormat); 5] = [
+            (b"EVF\x09\x0d\x0a\xff\x00", ImageFormat::Ewf),
+            (b"EVF2\x0d\x0a\x81\x00", ImageFormat::Ewf),
+            (b"vhdxfile", ImageFormat::Vhdx),
+            (b"conectix", ImageFormat::Vhd),
+            (b"QFI\xfb", ImageFormat::Qcow),
+        ];
+
+        for (magic, format) in signatures.iter() {
+            if header.starts_with(magic) {
+                return *format;
+            }
+        }
+
+        // Fixed size VHDs only have a footer.
+        if footer.starts_with(b"conectix") {
+            return ImageFormat::Vhd;
+        }
+
+        ImageFormat::Raw
+    }
+
+    pub fn detect(path: impl AsRef<Path>) -> Result<ImageFormat, Error> {
+        let path = path.as_ref();
+
+        if split_raw_segments(path).len() > 1 {
+            return Ok(ImageFormat::SplitRaw);
+        }
+
+        let mut file = File::open(path)?;
+
+        let mut header = vec![0; SECTOR_SIZE as usize];
+        let header_size = read_up_to(&mut file, &mut header)?;
+        header.truncate(header_size);
+
+        let mut footer = vec![0; SECTOR_SIZE as usize];
+        let size = file.seek(SeekFrom::End(0))?;
+
+        let footer_size = if size >= SECTOR_SIZE {
+            file.seek(SeekFrom::End(-(SECTOR_SIZE as i64)))?;
+            read_up_to(&mut file, &mut footer)?
+        } else {
+            0
+        };
+        footer.truncate(footer_size);
+
+        let format = ImageFormat::from_signatures(&header, &footer);
+
+        Ok(format)
+    }
+}
+
+/// The segments of a split raw image, starting with `path`. Images which are not split are
+/// their only segment.
+fn split_raw_segments(path: &Path) -> Vec<PathBuf> {
+    let mut segments = vec![path.to_path_buf()];
+
+    let extension = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.chars().all(|c| c.is_ascii_digit()) => extension,
+        _ => return segments,
+    };
+
+    let width = extension.len();
+    let mut number: u64 = match extension.parse() {
+        Ok(number) => number,
+        Err(_) => return segments,
+    };
+
+    loop {
+        number += 1;
+        let next = path.with_extension(format!("{:0width$}", number, width = width));
+
+        if !next.exists() {
+            return segments;
+        }
+
+        segments.push(next);
+    }
+}
+
+/// The segments of a split image read back to back, as one disk.
+pub struct ConcatReader<R> {
+    segments: Vec<(u64, R)>,
+    size: u64,
+    position: u64,
+}
+
+impl<R: Read + Seek> ConcatReader<R> {
+    pub fn new(segments: Vec<R>) -> io::Result<Self> {
+        let mut sized = Vec::with_capacity(segments.len());
+        let mut size = 0;
+
+        for mut segment in segments {
+            let segment_size = segment.seek(SeekFrom::End(0))?;
+            sized.push((segment_size, segment));
+            size += segment_size;
+        }
+
+        Ok(ConcatReader {
+            segments: sized,
+            size,
+            position: 0,
+        })
+    }
+}
+
+impl<R: Read + Seek> Read for ConcatReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut start = 0;
+
+        for (size, segment) in self.segments.iter_mut() {
+            if self.position < start + *size {
+                let offset = self.position - start;
+                let length = cmp::min(buf.len() as u64, *size - offset) as usize;
+
+                segment.seek(SeekFrom::Start(offset))?;
+                let read = segment.read(&mut buf[..length])?;
+
+                self.position += read as u64;
+                return Ok(read);
+            }
+
+            start += *size;
+        }
+
+        Ok(0)
+    }
+}
+
+impl<R: Read + Seek> Seek for ConcatReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+            SeekFrom::End(offset) => self.size as i64 + offset,
+        };
+
+        if position < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the stream",
+            ));
+        }
+
+        self.position = position as u64;
+        Ok(self.position)
+    }
+}
+
+/// A region (e.g. a partition) of a disk, reads and seeks are relative to its start.
+pub struct PartitionReader<R> {
+    inner: R,
+    offset: u64,
+    size: u64,
+    position: u64,
+}
+
+impl<R: Read + Seek> PartitionReader<R> {
+    pub fn new(inner: R, offset: u64, size: u64) -> Self {
+        PartitionReader {
+            inner,
+            offset,
+            size,
+            position: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Seek> Read for PartitionReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size {
+            return Ok(0);
+        }
+
+        let length = cmp::min(buf.len() as u64, self.size - self.position) as usize;
+
+        self.inner
+            .seek(SeekFrom::Start(self.offset + self.position))?;
+        let read = self.inner.read(&mut buf[..length])?;
+
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for PartitionReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+            SeekFrom::End(offset) => self.size as i64 + offset,
+        };
+
+        if position < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the stream",
+            ));
+        }
+
+        self.position = position as u64;
+        Ok(self.position)
+    }
+}
+
+/// A disk image, reads return the contents of the imaged disk.
+pub struct DiskImage {
+    format: ImageFormat,
+    reader: Box<dyn ReadSeekSend>,
+}
+
+/// Opens the image at `path` (the first segment of split images), detecting its format.
+pub fn open_any(path: impl AsRef<Path>) -> Result<DiskImage, Error> {
+    let path = path.as_ref();
+    let format = ImageFormat::detect(path)?;
+
+    let reader: Box<dyn ReadSeekSend> = match format {
+        ImageFormat::Raw => Box::new(File::open(path)?),
+        ImageFormat::SplitRaw => {
+            let segments = split_raw_segments(path)
+                .iter()
+                .map(File::open)
+                .collect::<io::Result<Vec<_>>>()?;
+
+            Box::new(ConcatReader::new(segments)?)
+        }
+        #[cfg(feature = "ewf")]
+        ImageFormat::Ewf => Box::new(crate::ewf::EwfImage::open(path)?),
+        #[cfg(feature = "vhdi")]
+        ImageFormat::Vhd | ImageFormat::Vhdx => Box::new(crate::vhdi::VhdiImage::open(path)?),
+        #[cfg(feature = "qcow")]
+        ImageFormat::Qcow => Box::new(crate::qcow::QcowImage::open(path)?),
+        #[allow(unreachable_patterns)]
+        _ => {
+            return Err(Error::Unsupported(format!(
+                "{:?} images, enable the `{}` feature",
+                format,
+                format.feature()
+            )))
+        }
+    };
+
+    Ok(DiskImage { format, reader })
+}
+
+impl ImageFormat {
+    fn feature(self) -> &'static str {
+        match self {
+            ImageFormat::Ewf => "ewf",
+            ImageFormat::Vhd | ImageFormat::Vhdx => "vhdi",
+            ImageFormat::Qcow => "qcow",
+            ImageFormat::Raw | ImageFormat::SplitRaw => "default",
+        }
+    }
+}
+
+/// Opens every NTFS volume in the image at `path`, see `DiskImage::ntfs_volumes`.
+pub fn open_volumes(
+    path: impl AsRef<Path>,
+    options: &VolumeOpenOptions,
+) -> Result<Vec<SharedVolume>, Error> {
+    open_any(path)?.ntfs_volumes(options)
+}
+
+impl DiskImage {
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    /// Opens the NTFS volumes in the image: the image itself if it holds a single volume,
+    /// otherwise every NTFS partition of its partition table (see `partition`).
+    /// The volumes share the image.
+    pub fn ntfs_volumes(mut self, options: &VolumeOpenOptions) -> Result<Vec<SharedVolume>, Error> {
+        let regions = ntfs_regions(&mut self.reader)?;
+        let disk = Arc::new(Mutex::new(self.reader));
+
+        regions
+            .into_iter()
+            .map(|(offset, size)| {
+                let partition = PartitionReader::new(SharedReader::new(disk.clone()), offset, size);
+                SharedVolume::from_reader(partition, options)
+            })
+            .collect()
+    }
+}
+
+impl Read for DiskImage {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Seek for DiskImage {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.reader.seek(pos)
+    }
+}
+
+fn is_ntfs_boot_sector<R: Read + Seek>(disk: &mut R, offset: u64) -> Result<bool, Error> {
+    let mut boot_sector = [0; 11];
+
+    disk.seek(SeekFrom::Start(offset))?;
+    let read = read_up_to(disk, &mut boot_sector)?;
+
+    Ok(read == boot_sector.len() && &boot_sector[3..] == NTFS_SIGNATURE)
+}
+
+/// The (offset, size) of every NTFS volume on the disk, in bytes.
+fn ntfs_regions<R: Read + Seek>(disk: &mut R) -> Result<Vec<(u64, u64)>, Error> {
+    let table = match PartitionTable::read(disk)? {
+        Some(table) => table,
+        None if is_ntfs_boot_sector(disk, 0)? => {
+            let disk_size = disk.seek(SeekFrom::End(0))?;
+            return Ok(vec![(0, disk_size)]);
+        }
+        None => return Ok(vec![]),
+    };
+
+    let mut regions = vec![];
+
+    for partition in table.partitions {
+        if is_ntfs_boot_sector(disk, partition.offset)? {
+            regions.push((partition.offset, partition.size));
+        }
+    }
+
+    Ok(regions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_from_signatures() {
+        assert_eq!(
+            ImageFormat::from_signatures(b"EVF\x09\x0d\x0a\xff\x00\x01", b""),
+            ImageFormat::Ewf
+        );
+        assert_eq!(
+            ImageFormat::from_signatures(b"vhdxfile", b""),
+            ImageFormat::Vhdx
+        );
+        assert_eq!(
+            ImageFormat::from_signatures(b"QFI\xfb\x00\x00\x00\x03", b""),
+            ImageFormat::Qcow
+        );
+        assert_eq!(
+            ImageFormat::from_signatures(b"\xebR\x90NTFS    ", b"conectix"),
+            ImageFormat::Vhd
+        );
+        assert_eq!(
+            ImageFormat::from_signatures(b"\xebR\x90NTFS    ", b""),
+            ImageFormat::Raw
+        );
+    }
+
+    #[test]
+    fn test_concat_reader() {
+        let segments = vec![Cursor::new(vec![1, 2, 3]), Cursor::new(vec![4, 5])];
+        let mut reader = ConcatReader::new(segments).unwrap();
+
+        reader.seek(SeekFrom::Start(2)).unwrap();
+        let mut data = vec![];
+        reader.read_to_end(&mut data).unwrap();
+
+        assert_eq!(data, vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn test_mbr_partition() {
+        let mut disk = vec![0; 8 * SECTOR_SIZE as usize];
+        // A partition of 4 sectors, starting at sector 2.
+        disk[446 + 4] = 0x07;
+        disk[446 + 8] = 2;
+        disk[446 + 12] = 4;
+        disk[510] = 0x55;
+        disk[511] = 0xaa;
+        disk[2 * SECTOR_SIZE as usize + 3..2 * SECTOR_SIZE as usize + 11]
+            .copy_from_slice(NTFS_SIGNATURE);
+
+        let regions = ntfs_regions(&mut Cursor::new(disk)).unwrap();
+
+        assert_eq!(regions, vec![(2 * SECTOR_SIZE, 4 * SECTOR_SIZE)]);
+    }
+
+    #[test]
+    fn test_open_volumes() {
+        let image = open_any(sample_volume_path()).unwrap();
+        assert_eq!(image.format(), ImageFormat::Raw);
+
+        let volumes = image.ntfs_volumes(&VolumeOpenOptions::new()).unwrap();
+        assert_eq!(volumes.len(), 1);
+        assert!(volumes[0].get_root_directory().is_ok());
+    }
+}
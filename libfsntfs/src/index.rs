@@ -0,0 +1,545 @@
+//! Reads the entries of an index: directories (`$I30`) or the view indexes
+//! of the `$Extend` metadata files (`$Q` and `$O` of `$Quota`, `$R` of `$Reparse` and so on).
+//!
+//! Besides the entries, the B-tree is kept as stored: the node in `$INDEX_ROOT` and the index
+//! records of `$INDEX_ALLOCATION`, which entries point to by VCN.
+//!
+//! ```ignore
+//! let sii = Index::read_by_mft_idx(&volume, metadata_files::SECURE, "$SII", &mut image)?;
+//!
+//! for entry in &sii.root.entries {
+//!     println!("{:?} -> {:?}", entry.key, entry.sub_node_vcn);
+//! }
+//! ```
+use crate::attribute::AttributeType;
+use crate::error::Error;
+use crate::mft_record::{
+    apply_fixups, invalid, read_u16, read_u32, read_u64, restore_fixups, FixupStatus, MftRecord,
+    FIXUP_STRIDE,
+};
+use crate::options::Utf16Policy;
+use crate::timestamps::MacbTimes;
+use crate::volume::{MftEntryIndex, Volume};
+use std::io::{Read, Seek};
+
+pub const INDEX_RECORD_SIGNATURE: &[u8; 4] = b"INDX";
+
+/// The entry points to a sub-node with smaller keys.
+pub const INDEX_ENTRY_FLAG_HAS_SUB_NODE: u16 = 0x0001;
+/// The entry terminates the node and has no key.
+pub const INDEX_ENTRY_FLAG_IS_LAST: u16 = 0x0002;
+
+/// The node has sub-nodes in `$INDEX_ALLOCATION`.
+pub const INDEX_NODE_FLAG_HAS_SUB_NODES: u32 = 0x0000_0001;
+
+const INDEX_ROOT_NODE_HEADER_OFFSET: usize = 0x10;
+const INDEX_RECORD_NODE_HEADER_OFFSET: usize = 0x18;
+const INDEX_ENTRY_HEADER_SIZE: usize = 0x10;
+/// Index records are a few KiB, larger sizes only come from corrupt index roots.
+const MAX_INDEX_RECORD_SIZE: u32 = 64 * 1024;
+
+/// Size of a `$FILE_NAME` key without the name.
+const FILE_NAME_KEY_SIZE: usize = 0x42;
+/// Timestamps of entries carved from slack are between 1980 and 2100.
+const SLACK_FILETIMES: std::ops::Range<u64> = 0x01a8_e79f_e1d5_8000..0x022f_7163_7764_0000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The file the entry refers to, only in indexes of attributes (e.g. `$I30`).
+    pub file_reference: Option<u64>,
+    pub flags: u16,
+    pub key: Vec<u8>,
+    /// The value of the entry, only in view indexes.
+    pub data: Vec<u8>,
+    /// The VCN of the index record with the keys smaller than this one.
+    pub sub_node_vcn: Option<u64>,
+}
+
+/// A node of the B-tree of an index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexNode {
+    /// Offset of the first entry, from the node header.
+    pub entries_offset: u32,
+    pub used_size: u32,
+    pub allocated_size: u32,
+    pub flags: u32,
+    /// The entries of the node, in key order, without the terminating entry.
+    pub entries: Vec<IndexEntry>,
+    /// The VCN of the index record with the keys greater than all entries of the node
+    /// (the sub-node of the terminating entry).
+    pub last_sub_node_vcn: Option<u64>,
+    /// The allocated bytes past the used size, which can hold entries removed from the node.
+    pub slack: Vec<u8>,
+}
+
+impl IndexNode {
+    pub fn has_sub_nodes(&self) -> bool {
+        self.flags & INDEX_NODE_FLAG_HAS_SUB_NODES != 0
+    }
+
+    /// The VCNs of the sub-nodes, in key order.
+    pub fn sub_node_vcns(&self) -> Vec<u64> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.sub_node_vcn)
+            .chain(self.last_sub_node_vcn)
+            .collect()
+    }
+}
+
+/// A `$FILE_NAME` entry of a directory index (`$I30`) recovered from the slack of a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlackEntry {
+    /// Offset of the entry in the slack.
+    pub offset: usize,
+    pub file_reference: u64,
+    pub parent_file_reference: u64,
+    pub name: String,
+    pub times: MacbTimes,
+    pub data_size: u64,
+}
+
+/// Carves the `$FILE_NAME` entries which are still intact from `slack` (see `IndexNode`).
+pub fn carve_slack_entries(slack: &[u8], utf16_policy: Utf16Policy) -> Vec<SlackEntry> {
+    let mut entries = vec![];
+    let mut offset = 0;
+
+    // Entries are 8 byte aligned.
+    while offset + INDEX_ENTRY_HEADER_SIZE + FILE_NAME_KEY_SIZE <= slack.len() {
+        match carve_slack_entry(&slack[offset..], utf16_policy) {
+            Some((mut entry, length)) => {
+                entry.offset = offset;
+                entries.push(entry);
+                offset += length;
+            }
+            None => offset += 8,
+        }
+    }
+
+    entries
+}
+
+/// The entry at the start of `data` and its length, if it looks intact.
+fn carve_slack_entry(data: &[u8], utf16_policy: Utf16Policy) -> Option<(SlackEntry, usize)> {
+    let length = read_u16(data, 0x08).ok()? as usize;
+    let key_length = read_u16(data, 0x0a).ok()? as usize;
+    let key = data.get(INDEX_ENTRY_HEADER_SIZE..INDEX_ENTRY_HEADER_SIZE + key_length)?;
+
+    let name_length = *key.get(0x40)? as usize;
+    let namespace = key[0x41];
+
+    if name_length == 0
+        || namespace > 3
+        || key_length != FILE_NAME_KEY_SIZE + name_length * 2
+        || length < INDEX_ENTRY_HEADER_SIZE + key_length
+        || length % 8 != 0
+    {
+        return None;
+    }
+
+    let times = MacbTimes {
+        creation: read_u64(key, 0x08).ok()?,
+        modification: read_u64(key, 0x10).ok()?,
+        entry_modification: read_u64(key, 0x18).ok()?,
+        access: read_u64(key, 0x20).ok()?,
+    };
+
+    let filetimes = [
+        times.creation,
+        times.modification,
+        times.entry_modification,
+        times.access,
+    ];
+
+    if !filetimes
+        .iter()
+        .all(|filetime| SLACK_FILETIMES.contains(filetime))
+    {
+        return None;
+    }
+
+    let name_utf16: Vec<u16> = key[FILE_NAME_KEY_SIZE..]
+        .chunks(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+
+    let entry = SlackEntry {
+        offset: 0,
+        file_reference: read_u64(data, 0x00).ok()?,
+        parent_file_reference: read_u64(key, 0x00).ok()?,
+        name: utf16_policy.decode(&name_utf16).ok()?,
+        times,
+        data_size: read_u64(key, 0x30).ok()?,
+    };
+
+    Some((entry, length))
+}
+
+/// An index record (`INDX`) of the `$INDEX_ALLOCATION` of an index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexRecord {
+    /// The position of the record in the allocation, in index records.
+    pub block: usize,
+    /// The VCN of the record, as stored in its header.
+    pub vcn: u64,
+    pub fixup_status: FixupStatus,
+    pub node: IndexNode,
+}
+
+#[derive(Debug, Clone)]
+pub struct Index {
+    pub name: String,
+    /// The type of the indexed attribute, 0 for view indexes.
+    pub indexed_attribute_type: u32,
+    pub collation_rule: u32,
+    pub index_entry_size: u32,
+    /// All entries of the index, in tree order per node but not globally sorted.
+    pub entries: Vec<IndexEntry>,
+    /// The node in `$INDEX_ROOT`.
+    pub root: IndexNode,
+    /// The index records in use, in allocation order. Torn ones are only read with
+    /// `VolumeOpenOptions::accept_torn_writes`.
+    pub records: Vec<IndexRecord>,
+}
+
+impl Index {
+    /// Reads the index `name` of MFT entry `mft_entry_index`, e.g. `$SII` of `$Secure`.
+    pub fn read_by_mft_idx<R: Read + Seek>(
+        volume: &Volume,
+        mft_entry_index: MftEntryIndex,
+        name: &str,
+        image: &mut R,
+    ) -> Result<Self, Error> {
+        let record = MftRecord::read(volume, mft_entry_index)?;
+
+        Index::read(volume, &record, name, image)
+    }
+
+    /// Reads the index `name` of `record`.
+    /// `image` is the raw volume, needed when the index has an `$INDEX_ALLOCATION`.
+    pub fn read<R: Read + Seek>(
+        volume: &Volume,
+        record: &MftRecord,
+        name: &str,
+        image: &mut R,
+    ) -> Result<Self, Error> {
+        let root = record
+            .find_attribute(AttributeType::IndexRoot, name)?
+            .ok_or_else(|| {
+                invalid(format!(
+                    "MFT entry {} has no index named {}",
+                    record.index(),
+                    name
+                ))
+            })?;
+
+        let cluster_size = volume.get_cluster_block_size()? as u64;
+        let root = root.read_data(image, cluster_size)?;
+
+        let indexed_attribute_type = read_u32(&root, 0x00)?;
+        let collation_rule = read_u32(&root, 0x04)?;
+        let index_entry_size = read_u32(&root, 0x08)?;
+        let is_view = indexed_attribute_type == 0;
+
+        if index_entry_size == 0
+            || index_entry_size as usize % FIXUP_STRIDE != 0
+            || index_entry_size > MAX_INDEX_RECORD_SIZE
+        {
+            return Err(invalid(format!(
+                "index {} of MFT entry {} has an invalid index record size {}",
+                name,
+                record.index(),
+                index_entry_size
+            )));
+        }
+
+        let mut records = vec![];
+        let root = parse_node(
+            root.get(INDEX_ROOT_NODE_HEADER_OFFSET..).unwrap_or(&[]),
+            is_view,
+        )?;
+        let mut entries = root.entries.clone();
+
+        if let Some(allocation) = record.find_attribute(AttributeType::IndexAllocation, name)? {
+            let allocation = allocation.read_data(image, cluster_size)?;
+
+            // Only the index records marked as in use in the bitmap are part of the index.
+            let bitmap = match record.find_attribute(AttributeType::Bitmap, name)? {
+                Some(bitmap) => bitmap.read_data(image, cluster_size)?,
+                None => vec![],
+            };
+
+            for (block_idx, block) in allocation
+                .chunks(index_entry_size as usize)
+                .enumerate()
+                .filter(|(block_idx, _)| {
+                    bitmap
+                        .get(block_idx / 8)
+                        .map_or(false, |byte| byte & (1 << (block_idx % 8)) != 0)
+                })
+            {
+                if block.get(..4) != Some(&INDEX_RECORD_SIGNATURE[..]) {
+                    return Err(invalid(format!(
+                        "index record {} of {} has no INDX signature",
+                        block_idx, name
+                    )));
+                }
+
+                let fixup_status = FixupStatus::check(block);
+                let mut block = block.to_vec();
+
+                if volume.options().accepts_torn_writes() {
+                    restore_fixups(&mut block)?;
+                } else {
+                    apply_fixups(&mut block)?;
+                }
+
+                let node = parse_node(
+                    block.get(INDEX_RECORD_NODE_HEADER_OFFSET..).unwrap_or(&[]),
+                    is_view,
+                )?;
+                entries.extend(node.entries.iter().cloned());
+
+                records.push(IndexRecord {
+                    block: block_idx,
+                    vcn: read_u64(&block, 0x10)?,
+                    fixup_status,
+                    node,
+                });
+            }
+        }
+
+        Ok(Index {
+            name: name.to_owned(),
+            indexed_attribute_type,
+            collation_rule,
+            index_entry_size,
+            entries,
+            root,
+            records,
+        })
+    }
+
+    pub fn is_view_index(&self) -> bool {
+        self.indexed_attribute_type == 0
+    }
+
+    /// The index record with the VCN `vcn`, which entries point to with `sub_node_vcn`.
+    pub fn record_by_vcn(&self, vcn: u64) -> Option<&IndexRecord> {
+        self.records.iter().find(|record| record.vcn == vcn)
+    }
+
+    /// Whether entries were read from index records which failed the fixup validation.
+    pub fn has_torn_records(&self) -> bool {
+        self.records
+            .iter()
+            .any(|record| !record.fixup_status.is_valid())
+    }
+}
+
+/// Parses the node starting with the node header at the start of `node`.
+fn parse_node(node: &[u8], is_view: bool) -> Result<IndexNode, Error> {
+    let entries_offset = read_u32(node, 0x00)?;
+    let used_size = read_u32(node, 0x04)?;
+    let allocated_size = read_u32(node, 0x08)?;
+    let flags = read_u32(node, 0x0c)?;
+
+    let end = (used_size as usize).min(node.len());
+    let slack = node
+        .get(end..(allocated_size as usize).min(node.len()))
+        .unwrap_or(&[])
+        .to_vec();
+    let mut offset = entries_offset as usize;
+    let mut entries = vec![];
+    let mut last_sub_node_vcn = None;
+
+    while offset + INDEX_ENTRY_HEADER_SIZE <= end {
+        let entry = &node[offset..end];
+
+        let length = read_u16(entry, 0x08)? as usize;
+        let key_length = read_u16(entry, 0x0a)? as usize;
+        let flags = read_u16(entry, 0x0c)?;
+
+        let entry = entry
+            .get(..length)
+            .filter(|entry| entry.len() >= INDEX_ENTRY_HEADER_SIZE)
+            .ok_or_else(|| invalid(format!("invalid index entry size {}", length)))?;
+
+        // Stored in the last 8 bytes of the entry.
+        let sub_node_vcn = if flags & INDEX_ENTRY_FLAG_HAS_SUB_NODE != 0 {
+            Some(read_u64(entry, length.saturating_sub(8))?)
+        } else {
+            None
+        };
+
+        if flags & INDEX_ENTRY_FLAG_IS_LAST != 0 {
+            last_sub_node_vcn = sub_node_vcn;
+            break;
+        }
+
+        let key = entry
+            .get(INDEX_ENTRY_HEADER_SIZE..INDEX_ENTRY_HEADER_SIZE + key_length)
+            .ok_or_else(|| invalid("index key out of bounds".to_owned()))?
+            .to_vec();
+
+        let (file_reference, data) = if is_view {
+            let data_offset = read_u16(entry, 0x00)? as usize;
+            let data_length = read_u16(entry, 0x02)? as usize;
+
+            let data = entry
+                .get(data_offset..data_offset + data_length)
+                .ok_or_else(|| invalid("index data out of bounds".to_owned()))?
+                .to_vec();
+
+            (None, data)
+        } else {
+            (Some(read_u64(entry, 0x00)?), vec![])
+        };
+
+        entries.push(IndexEntry {
+            file_reference,
+            flags,
+            key,
+            data,
+            sub_node_vcn,
+        });
+
+        offset += length;
+    }
+
+    Ok(IndexNode {
+        entries_offset,
+        used_size,
+        allocated_size,
+        flags,
+        entries,
+        last_sub_node_vcn,
+        slack,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::metadata_files;
+    use crate::options::VolumeOpenOptions;
+    use crate::volume::Volume;
+    use std::fs::File;
+
+    fn view_entry(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let data_offset = INDEX_ENTRY_HEADER_SIZE + key.len();
+        let length = data_offset + data.len();
+
+        let mut entry = vec![];
+        entry.extend_from_slice(&(data_offset as u16).to_le_bytes());
+        entry.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        entry.extend_from_slice(&[0; 4]);
+        entry.extend_from_slice(&(length as u16).to_le_bytes());
+        entry.extend_from_slice(&(key.len() as u16).to_le_bytes());
+        entry.extend_from_slice(&[0; 4]);
+        entry.extend_from_slice(key);
+        entry.extend_from_slice(data);
+        entry
+    }
+
+    #[test]
+    fn test_parse_view_node() {
+        let mut entries_data = view_entry(&[1, 0, 0, 0], &[0xaa; 6]);
+        entries_data.extend(view_entry(&[2, 0, 0, 0], &[0xbb; 2]));
+        // The terminating entry.
+        entries_data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0x10, 0, 0, 0, 0x02, 0, 0, 0]);
+
+        let mut node = vec![];
+        node.extend_from_slice(&16_u32.to_le_bytes());
+        node.extend_from_slice(&((16 + entries_data.len()) as u32).to_le_bytes());
+        node.extend_from_slice(&((16 + entries_data.len()) as u32).to_le_bytes());
+        node.extend_from_slice(&[0; 4]);
+        node.extend(entries_data);
+
+        let node = parse_node(&node, true).unwrap();
+        let entries = &node.entries;
+
+        assert!(!node.has_sub_nodes());
+        assert!(node.sub_node_vcns().is_empty());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].key, vec![1, 0, 0, 0]);
+        assert_eq!(entries[0].data, vec![0xaa; 6]);
+        assert_eq!(entries[1].data, vec![0xbb; 2]);
+        assert!(entries[1].file_reference.is_none());
+        assert!(node.slack.is_empty());
+    }
+
+    #[test]
+    fn test_carve_slack_entries() {
+        let name: Vec<u8> = "old.txt"
+            .encode_utf16()
+            .flat_map(|c| c.to_le_bytes().to_vec())
+            .collect();
+        let key_length = FILE_NAME_KEY_SIZE + name.len();
+        let length = (INDEX_ENTRY_HEADER_SIZE + key_length + 7) / 8 * 8;
+        // 2019-01-01.
+        let filetime = 0x01d4_a164_f03e_4000_u64;
+
+        let mut entry = vec![0; length];
+        entry[0x00..0x08].copy_from_slice(&0x0001_0000_0000_0040_u64.to_le_bytes());
+        entry[0x08..0x0a].copy_from_slice(&(length as u16).to_le_bytes());
+        entry[0x0a..0x0c].copy_from_slice(&(key_length as u16).to_le_bytes());
+        let key = &mut entry[INDEX_ENTRY_HEADER_SIZE..];
+        key[0x00..0x08].copy_from_slice(&5_u64.to_le_bytes());
+        for time_offset in (0x08..0x28).step_by(8) {
+            key[time_offset..time_offset + 8].copy_from_slice(&filetime.to_le_bytes());
+        }
+        key[0x30..0x38].copy_from_slice(&1234_u64.to_le_bytes());
+        key[0x40] = 7;
+        key[0x41] = 1;
+        key[FILE_NAME_KEY_SIZE..key_length].copy_from_slice(&name);
+
+        let mut slack = vec![0xff; 16];
+        slack.extend(entry);
+        slack.extend(vec![0; 64]);
+
+        let entries = carve_slack_entries(&slack, Utf16Policy::Lossy);
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].offset, 16);
+        assert_eq!(entries[0].name, "old.txt");
+        assert_eq!(entries[0].parent_file_reference, 5);
+        assert_eq!(entries[0].data_size, 1234);
+        assert_eq!(entries[0].times.creation, filetime);
+    }
+
+    #[test]
+    fn test_read_view_index() {
+        let volume = sample_volume().unwrap();
+        let mut image = File::open(sample_volume_path()).unwrap();
+        let sii =
+            Index::read_by_mft_idx(&volume, metadata_files::SECURE, "$SII", &mut image).unwrap();
+
+        assert!(sii.is_view_index());
+        assert!(!sii.entries.is_empty());
+        // Keyed by security id.
+        assert!(sii.entries.iter().all(|entry| entry.key.len() == 4));
+    }
+
+    #[test]
+    fn test_index_records() {
+        let options = VolumeOpenOptions::new().accept_torn_writes(true);
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+        let record = MftRecord::read(&volume, metadata_files::ROOT_DIRECTORY).unwrap();
+
+        let mut image = File::open(sample_volume_path()).unwrap();
+        let index = Index::read(&volume, &record, "$I30", &mut image).unwrap();
+
+        assert!(!index.has_torn_records());
+
+        for vcn in index.root.sub_node_vcns() {
+            assert!(index.record_by_vcn(vcn).is_some());
+        }
+
+        assert!(index
+            .records
+            .iter()
+            .all(|record| record.fixup_status == FixupStatus::Valid));
+    }
+}
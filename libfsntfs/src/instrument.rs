@@ -0,0 +1,180 @@
+//! Counters for monitoring what the parser does per image, see `VolumeOpenOptions::metrics`.
+//!
+//! ```ignore
+//! let counters = Arc::new(Counters::default());
+//! let options = VolumeOpenOptions::new().metrics(counters.clone());
+//!
+//! let volume = Volume::open_with_options("image.dd", &options)?;
+//! for entry in volume.walk(WalkOptions::default())? { /* ... */ }
+//!
+//! println!("walked {} entries", counters.get_entries_walked());
+//! ```
+//!
+//! Closures taking a `Metric` are metrics too, and with the `metrics` feature
+//! `MetricsFacade` forwards to the recorder installed for the `metrics` crate.
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Something the parser did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// Bytes read from the data of file entries and streams.
+    BytesRead(u64),
+    /// A call into libfsntfs which reads the image, by the name of the function.
+    FfiCall(&'static str),
+    /// A lookup in a cache, by the name of the cache, which was served from it.
+    CacheHit(&'static str),
+    /// A lookup in a cache which had to read the volume instead.
+    CacheMiss(&'static str),
+    /// An entry yielded by a walk of the directory tree.
+    EntryWalked,
+}
+
+/// Receives the metrics of the volumes opened with it, from any thread.
+pub trait Metrics: Send + Sync {
+    fn record(&self, metric: Metric);
+}
+
+impl<F: Fn(Metric) + Send + Sync> Metrics for F {
+    fn record(&self, metric: Metric) {
+        self(metric)
+    }
+}
+
+impl Debug for dyn Metrics {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("Metrics")
+    }
+}
+
+/// Sums up the metrics it receives.
+#[derive(Debug, Default)]
+pub struct Counters {
+    bytes_read: AtomicU64,
+    ffi_calls: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    entries_walked: AtomicU64,
+}
+
+impl Counters {
+    pub fn get_bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    pub fn get_ffi_calls(&self) -> u64 {
+        self.ffi_calls.load(Ordering::Relaxed)
+    }
+
+    pub fn get_cache_hits(&self) -> u64 {
+        self.cache_hits.load(Ordering::Relaxed)
+    }
+
+    pub fn get_cache_misses(&self) -> u64 {
+        self.cache_misses.load(Ordering::Relaxed)
+    }
+
+    pub fn get_entries_walked(&self) -> u64 {
+        self.entries_walked.load(Ordering::Relaxed)
+    }
+}
+
+impl Metrics for Counters {
+    fn record(&self, metric: Metric) {
+        let (counter, value) = match metric {
+            Metric::BytesRead(bytes) => (&self.bytes_read, bytes),
+            Metric::FfiCall(_) => (&self.ffi_calls, 1),
+            Metric::CacheHit(_) => (&self.cache_hits, 1),
+            Metric::CacheMiss(_) => (&self.cache_misses, 1),
+            Metric::EntryWalked => (&self.entries_walked, 1),
+        };
+
+        counter.fetch_add(value, Ordering::Relaxed);
+    }
+}
+
+/// Forwards metrics to the `metrics` crate, as `libfsntfs.*` counters labeled with the image.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone)]
+pub struct MetricsFacade {
+    image: String,
+}
+
+#[cfg(feature = "metrics")]
+impl MetricsFacade {
+    pub fn new(image: impl Into<String>) -> Self {
+        MetricsFacade {
+            image: image.into(),
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics for MetricsFacade {
+    fn record(&self, metric: Metric) {
+        let image = self.image.clone();
+
+        match metric {
+            Metric::BytesRead(bytes) => {
+                ::metrics::counter!("libfsntfs.bytes_read", bytes, "image" => image)
+            }
+            Metric::FfiCall(function) => {
+                ::metrics::counter!("libfsntfs.ffi_calls", 1, "image" => image, "function" => function)
+            }
+            Metric::CacheHit(cache) => {
+                ::metrics::counter!("libfsntfs.cache_hits", 1, "image" => image, "cache" => cache)
+            }
+            Metric::CacheMiss(cache) => {
+                ::metrics::counter!("libfsntfs.cache_misses", 1, "image" => image, "cache" => cache)
+            }
+            Metric::EntryWalked => {
+                ::metrics::counter!("libfsntfs.entries_walked", 1, "image" => image)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::options::VolumeOpenOptions;
+    use crate::volume::Volume;
+    use crate::walk::WalkOptions;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_counters() {
+        let counters = Arc::new(Counters::default());
+        let options = VolumeOpenOptions::new().metrics(counters.clone());
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+
+        let walked = volume.walk(WalkOptions::default()).unwrap().count() as u64;
+        assert_eq!(counters.get_entries_walked(), walked);
+
+        let mut data = vec![];
+        let mut entry = volume.get_file_entry_by_mft_idx(0).unwrap();
+        entry.read_to_end(&mut data).unwrap();
+
+        assert_eq!(counters.get_bytes_read(), data.len() as u64);
+        assert!(counters.get_ffi_calls() > 0);
+    }
+
+    #[test]
+    fn test_callback() {
+        let recorded = Arc::new(Mutex::new(vec![]));
+        let sink = recorded.clone();
+        let options = VolumeOpenOptions::new().metrics(Arc::new(move |metric: Metric| {
+            sink.lock().unwrap().push(metric);
+        }));
+
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+        volume.get_file_entry_by_mft_idx(5).unwrap();
+
+        assert_eq!(
+            recorded.lock().unwrap()[..],
+            [Metric::FfiCall("libfsntfs_volume_get_file_entry_by_index")]
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Hooks around every read libfsntfs makes from the image, e.g. for caching or auditing
+//! which byte ranges were touched.
+//!
+//! ```ignore
+//! struct Audit;
+//!
+//! impl ReadInterceptor for Audit {
+//!     fn after_read(&self, offset: u64, data: &[u8]) {
+//!         println!("read {} bytes at {}", data.len(), offset);
+//!     }
+//! }
+//!
+//! let options = VolumeOpenOptions::new().read_interceptor(Arc::new(Audit));
+//! let volume = Volume::open_with_options("image.dd", &options)?;
+//! ```
+use std::fmt::{self, Debug, Formatter};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+/// Sees the reads of the volumes opened with it, from any thread.
+pub trait ReadInterceptor: Send + Sync {
+    /// Called before `buf.len()` bytes are read at `offset` of the image.
+    /// Returning `Some(read)` answers the read with the first `read` bytes of `buf` instead of
+    /// reading the image (e.g. from a cache).
+    fn before_read(&self, _offset: u64, _buf: &mut [u8]) -> io::Result<Option<usize>> {
+        Ok(None)
+    }
+
+    /// Called with the bytes read at `offset`, whether from the image or `before_read`.
+    fn after_read(&self, _offset: u64, _data: &[u8]) {}
+}
+
+impl Debug for dyn ReadInterceptor {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("ReadInterceptor")
+    }
+}
+
+/// Passes the reads from `inner` through the interceptor.
+pub struct InterceptedReader<R> {
+    inner: R,
+    interceptor: Arc<dyn ReadInterceptor>,
+    position: u64,
+}
+
+impl<R: Read + Seek> InterceptedReader<R> {
+    pub fn new(mut inner: R, interceptor: Arc<dyn ReadInterceptor>) -> io::Result<Self> {
+        let position = inner.seek(SeekFrom::Current(0))?;
+
+        Ok(InterceptedReader {
+            inner,
+            interceptor,
+            position,
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Seek> Read for InterceptedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = match self.interceptor.before_read(self.position, buf)? {
+            Some(read) => {
+                let read = read.min(buf.len());
+
+                // Keep the image in step, the next read might not be answered.
+                self.inner
+                    .seek(SeekFrom::Start(self.position + read as u64))?;
+
+                read
+            }
+            None => self.inner.read(buf)?,
+        };
+
+        self.interceptor.after_read(self.position, &buf[..read]);
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for InterceptedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::options::VolumeOpenOptions;
+    use crate::volume::Volume;
+    use std::io::Cursor;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(u64, usize)>>);
+
+    impl ReadInterceptor for Recorder {
+        fn after_read(&self, offset: u64, data: &[u8]) {
+            self.0.lock().unwrap().push((offset, data.len()));
+        }
+    }
+
+    /// Answers every read with `0xaa`.
+    struct Constant;
+
+    impl ReadInterceptor for Constant {
+        fn before_read(&self, _offset: u64, buf: &mut [u8]) -> io::Result<Option<usize>> {
+            buf.iter_mut().for_each(|byte| *byte = 0xaa);
+
+            Ok(Some(buf.len()))
+        }
+    }
+
+    #[test]
+    fn test_intercepted_reader() {
+        let recorder = Arc::new(Recorder::default());
+        let mut reader =
+            InterceptedReader::new(Cursor::new(vec![0; 100]), recorder.clone()).unwrap();
+
+        let mut buf = [0; 10];
+        reader.seek(SeekFrom::Start(20)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+
+        assert_eq!(*recorder.0.lock().unwrap(), [(20, 10), (30, 10)]);
+
+        let mut reader =
+            InterceptedReader::new(Cursor::new(vec![0; 100]), Arc::new(Constant)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+
+        assert_eq!(buf, [0xaa; 10]);
+        assert_eq!(reader.into_inner().position(), 10);
+    }
+
+    #[test]
+    fn test_intercepted_volume() {
+        let recorder = Arc::new(Recorder::default());
+        let options = VolumeOpenOptions::new().read_interceptor(recorder.clone());
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+
+        assert!(volume.get_root_directory().is_ok());
+        assert!(!recorder.0.lock().unwrap().is_empty());
+    }
+}
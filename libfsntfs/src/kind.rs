@@ -0,0 +1,92 @@
+//! Classifies file entries by what they are, see `FileEntry::kind`.
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::flags::FileAttributeFlags;
+use crate::metadata_files::MetadataFile;
+use crate::resolve::{IO_REPARSE_TAG_MOUNT_POINT, IO_REPARSE_TAG_SYMLINK};
+use crate::utils::mft_entry_index;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    File,
+    Directory,
+    SymbolicLink,
+    /// A mount point, to a directory of this or another volume.
+    Junction,
+    /// A metadata file at a fixed index. The root directory is a `Directory`.
+    Special(MetadataFile),
+}
+
+impl Kind {
+    pub fn is_link(self) -> bool {
+        self == Kind::SymbolicLink || self == Kind::Junction
+    }
+}
+
+impl<'a> FileEntry<'a> {
+    /// What the entry is. Links are recognized by their reparse tag, entries with other reparse
+    /// points (e.g. deduplicated or cloud files) are files or directories.
+    pub fn kind(&self) -> Result<Kind, Error> {
+        if let Some(metadata_file) =
+            MetadataFile::from_index(mft_entry_index(self.get_file_reference()?))
+        {
+            return Ok(Kind::Special(metadata_file));
+        }
+
+        if self
+            .get_file_attribute_flags()?
+            .contains(FileAttributeFlags::REPARSE_POINT)
+        {
+            match self.get_reparse_point_tag()? {
+                Some(IO_REPARSE_TAG_SYMLINK) => return Ok(Kind::SymbolicLink),
+                Some(IO_REPARSE_TAG_MOUNT_POINT) => return Ok(Kind::Junction),
+                _ => {}
+            }
+        }
+
+        if self.has_directory_entries_index()? {
+            Ok(Kind::Directory)
+        } else {
+            Ok(Kind::File)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::metadata_files;
+
+    #[test]
+    fn test_kind() {
+        let volume = sample_volume().unwrap();
+
+        let mft = file_entry(&volume).unwrap();
+        assert_eq!(mft.kind().unwrap(), Kind::Special(MetadataFile::Mft));
+
+        let root = volume
+            .get_file_entry_by_mft_idx(metadata_files::ROOT_DIRECTORY)
+            .unwrap();
+        assert_eq!(root.kind().unwrap(), Kind::Directory);
+
+        let reparse = volume
+            .get_file_entry_by_path("\\$Extend\\$Reparse")
+            .unwrap();
+        assert_eq!(reparse.kind().unwrap(), Kind::File);
+    }
+
+    #[test]
+    fn test_metadata_file_index() {
+        for idx in 0..16 {
+            if let Some(metadata_file) = MetadataFile::from_index(idx) {
+                assert_eq!(metadata_file.index(), idx);
+            }
+        }
+
+        assert_eq!(
+            MetadataFile::from_index(metadata_files::ROOT_DIRECTORY),
+            None
+        );
+    }
+}
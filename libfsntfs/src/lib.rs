@@ -4,13 +4,89 @@
 
 #[macro_use]
 extern crate libyal_rs_common;
+#[macro_use]
+extern crate libcerror_rs;
 
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(feature = "arrow")]
+pub mod arrow_export;
 pub mod attribute;
+pub mod audit;
+#[cfg(feature = "bitlocker")]
+pub mod bitlocker;
+pub mod bitmap;
+pub mod blocks;
+pub mod boot;
+pub mod checkpoint;
+pub mod correlate;
+pub mod csv_export;
+pub mod data_stream;
+pub mod entry_cache;
 pub mod error;
+#[cfg(feature = "ewf")]
+pub mod ewf;
+pub mod extract;
 pub mod ffi_error;
 pub mod file_entry;
+pub mod filter;
+pub mod flags;
+pub mod geometry;
+#[cfg(any(feature = "md-5", feature = "sha-1", feature = "sha2"))]
+pub mod hashing;
+pub mod images;
+pub mod index;
+pub mod instrument;
+pub mod intercept;
+pub mod kind;
+pub mod manifest;
+pub mod memory;
+pub mod metadata_files;
+pub mod mft_record;
+#[cfg(feature = "mft-interop")]
+pub mod mft_interop;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "debug-output")]
+pub mod notify;
+pub mod options;
+pub mod owned;
+pub mod partition;
+pub mod paths;
+#[cfg(feature = "qcow")]
+pub mod qcow;
+pub mod quota;
+pub mod query;
+pub mod record_id;
+pub mod recovery;
+pub mod reference;
+pub mod reparse_index;
+pub mod resolve;
+pub mod salvage;
+pub mod scan;
+pub mod secure;
+pub mod shared;
+pub mod security_descriptor;
+pub mod sparse;
+pub mod stats;
+pub mod throttle;
+pub mod timeline;
+pub mod timestamps;
+pub mod txf;
+pub mod unallocated;
+#[cfg(all(windows, feature = "unbuffered"))]
+pub mod unbuffered;
+pub mod upcase;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
+pub mod usn;
 mod utils;
+pub mod verify;
+#[cfg(feature = "vhdi")]
+pub mod vhdi;
 pub mod volume;
+pub mod walk;
+pub mod windows_paths;
 
 #[cfg(test)]
 mod fixtures;
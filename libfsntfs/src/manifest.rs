@@ -0,0 +1,439 @@
+//! Extracts the artifacts a manifest lists (paths, globs and file references, as in the target
+//! lists of triage tools) in a single pass, reporting for every item what it extracted.
+//!
+//! ```ignore
+//! let manifest = Manifest::parse(
+//!     "# Registry hives
+//!      C:\\Windows\\System32\\config\\SAM
+//!      \\Users\\*\\NTUSER.DAT
+//!      ref:0x1000000000000",
+//! )?;
+//! let report = extract_manifest(&volume, &manifest, &ExtractOptions::new().output_directory("out"))?;
+//!
+//! for item in report.items.iter().filter(|item| !item.failures.is_empty()) {
+//!     eprintln!("{}: {:?}", item.item, item.failures);
+//! }
+//! ```
+use crate::error::Error;
+use crate::extract::{self, ExtractFailure, ExtractOptions, Extracted, Job, Queue};
+use crate::paths::PathResolver;
+use crate::shared::SharedVolume;
+use crate::utils::mft_entry_index;
+use crate::volume::MftEntryIndex;
+use crate::walk::PathMatcher;
+use crate::windows_paths::WindowsPath;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+/// Characters which make a line of a manifest a glob rather than a path.
+const GLOB_CHARACTERS: &[char] = &['*', '?', '[', '{'];
+
+#[derive(Debug, Clone)]
+pub enum ManifestItem {
+    /// A file, or a directory with everything below it.
+    Path(String),
+    Pattern(PathMatcher),
+    /// A file reference, of which a sequence number of 0 is not checked.
+    FileReference(u64),
+}
+
+impl Display for ManifestItem {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ManifestItem::Path(path) => write!(f, "{}", path),
+            ManifestItem::Pattern(matcher) => write!(f, "{:?}", matcher),
+            ManifestItem::FileReference(file_reference) => {
+                write!(f, "ref:{:#x}", file_reference)
+            }
+        }
+    }
+}
+
+/// The path of this crate `path` stands for, which can be a full Windows path as well.
+fn volume_path(path: &str) -> String {
+    match WindowsPath::parse(path) {
+        Some(windows_path) => windows_path.path,
+        None => format!("\\{}", path.trim_start_matches(|c| c == '\\' || c == '/')),
+    }
+}
+
+fn parse_file_reference(value: &str) -> Result<u64, Error> {
+    let parsed = if value.starts_with("0x") || value.starts_with("0X") {
+        u64::from_str_radix(&value[2..], 16)
+    } else {
+        value.parse()
+    };
+
+    parsed.map_err(|_| Error::Other(format!("invalid file reference `{}`", value)))
+}
+
+impl ManifestItem {
+    /// Parses a line of a manifest: `ref:` followed by a file reference (decimal or `0x` hex),
+    /// a glob if it contains any of `*?[{` (which needs the `globset` feature), a path otherwise.
+    /// Paths and globs can be full Windows paths, of which the drive is left out.
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let line = line.trim();
+
+        if line.starts_with("ref:") {
+            return parse_file_reference(line[4..].trim()).map(ManifestItem::FileReference);
+        }
+
+        let path = volume_path(line);
+
+        if !line.contains(GLOB_CHARACTERS) {
+            return Ok(match path.trim_end_matches('\\') {
+                "" => ManifestItem::Path(path),
+                trimmed => ManifestItem::Path(trimmed.to_owned()),
+            });
+        }
+
+        #[cfg(feature = "globset")]
+        return PathMatcher::glob(&path.replace('\\', "/")).map(ManifestItem::Pattern);
+
+        #[cfg(not(feature = "globset"))]
+        Err(Error::Unsupported(format!(
+            "the glob `{}` needs the `globset` feature",
+            line
+        )))
+    }
+}
+
+/// The items to extract, in the order they are reported in.
+#[derive(Debug, Clone, Default)]
+pub struct Manifest {
+    items: Vec<ManifestItem>,
+}
+
+impl Manifest {
+    pub fn new() -> Self {
+        Manifest::default()
+    }
+
+    pub fn item(mut self, item: ManifestItem) -> Self {
+        self.items.push(item);
+        self
+    }
+
+    /// One item per line, see `ManifestItem::parse`. Empty lines and lines starting with `#`
+    /// are skipped.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut manifest = Manifest::new();
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            manifest.items.push(ManifestItem::parse(line)?);
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Manifest::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn items(&self) -> &[ManifestItem] {
+        &self.items
+    }
+}
+
+/// What an item of the manifest extracted. A file listed by several items is extracted once,
+/// and reported for each of them.
+#[derive(Debug)]
+pub struct ItemReport {
+    pub item: ManifestItem,
+    pub extracted: Vec<Extracted>,
+    /// Files the checkpoint has as extracted by an earlier run.
+    pub skipped: u64,
+    /// An item which matched no file at all fails with `Error::NotFound`.
+    pub failures: Vec<ExtractFailure>,
+}
+
+impl ItemReport {
+    pub fn is_success(&self) -> bool {
+        (!self.extracted.is_empty() || self.skipped > 0) && self.failures.is_empty()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ManifestReport {
+    /// In the order of the manifest.
+    pub items: Vec<ItemReport>,
+    /// Files extracted, each counted once.
+    pub extracted: u64,
+    pub bytes: u64,
+    pub skipped: u64,
+    /// Failures which are not of an item, e.g. of the walk.
+    pub failures: Vec<ExtractFailure>,
+}
+
+/// Extracts what `manifest` lists, with the workers, filter and output directory of `options`.
+///
+/// Files listed by path and file reference are looked up directly, directories and globs are
+/// matched in a single walk (with the excludes and depth of `options.walk`). The files are then
+/// extracted once each, in the order of their MFT entries.
+pub fn extract_manifest(
+    volume: &SharedVolume,
+    manifest: &Manifest,
+    options: &ExtractOptions,
+) -> Result<ManifestReport, Error> {
+    let items = manifest.items.clone();
+
+    let mut report = ManifestReport {
+        items: manifest
+            .items
+            .iter()
+            .map(|item| ItemReport {
+                item: item.clone(),
+                extracted: vec![],
+                skipped: 0,
+                failures: vec![],
+            })
+            .collect(),
+        ..ManifestReport::default()
+    };
+
+    let producer_options = options.clone();
+
+    extract::run(
+        volume,
+        options,
+        move |volume, queue| select(volume, &items, &producer_options, queue),
+        |outcome| {
+            match outcome.result {
+                Ok(Some(ref extracted)) => {
+                    report.extracted += 1;
+                    report.bytes += extracted.size;
+                }
+                Ok(None) => report.skipped += 1,
+                Err(_) => {}
+            }
+
+            if outcome.tags.is_empty() {
+                if let Err(failure) = outcome.result {
+                    report.failures.push(failure);
+                }
+                return;
+            }
+
+            match outcome.result {
+                Ok(Some(extracted)) => {
+                    for &tag in &outcome.tags {
+                        report.items[tag].extracted.push(extracted.clone());
+                    }
+                }
+                Ok(None) => {
+                    for &tag in &outcome.tags {
+                        report.items[tag].skipped += 1;
+                    }
+                }
+                Err(failure) => {
+                    // Errors do not clone, the other items get its message.
+                    let (&first, others) = outcome.tags.split_first().unwrap();
+
+                    for &tag in others {
+                        report.items[tag].failures.push(ExtractFailure {
+                            path: failure.path.clone(),
+                            error: Error::Other(failure.error.to_string()),
+                        });
+                    }
+                    report.items[first].failures.push(failure);
+                }
+            }
+        },
+    )?;
+
+    for item in report.items.iter_mut() {
+        if item.extracted.is_empty() && item.skipped == 0 && item.failures.is_empty() {
+            item.failures.push(ExtractFailure {
+                path: None,
+                error: Error::NotFound(format!("{} matched no file", item.item)),
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Queues a job for every file the items select, tagged with the items.
+fn select(volume: &SharedVolume, items: &[ManifestItem], options: &ExtractOptions, queue: &Queue) {
+    let mut jobs: BTreeMap<MftEntryIndex, Job> = BTreeMap::new();
+    let mut add = |path: String, mft_entry_index: MftEntryIndex, tag: usize| {
+        let job = jobs.entry(mft_entry_index).or_insert_with(|| Job {
+            path,
+            mft_entry_index,
+            tags: vec![],
+        });
+
+        if !job.tags.contains(&tag) {
+            job.tags.push(tag);
+        }
+    };
+
+    let mut resolver = PathResolver::new();
+    // Directories listed by path, and globs, are matched against the paths of the walk.
+    let mut matchers = vec![];
+
+    for (tag, item) in items.iter().enumerate() {
+        match item {
+            ManifestItem::Path(path) => {
+                let entry = match volume.get_file_entry_by_path(path) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        queue.fail(vec![tag], Some(path.clone()), e);
+                        continue;
+                    }
+                };
+
+                match entry.has_directory_entries_index() {
+                    Ok(true) => matchers.push((tag, PathMatcher::prefix(path))),
+                    Ok(false) => match extract::selected(&entry, &options.filter) {
+                        Ok(Some(mft_entry_index)) => add(path.clone(), mft_entry_index, tag),
+                        Ok(None) => {}
+                        Err(e) => queue.fail(vec![tag], Some(path.clone()), e),
+                    },
+                    Err(e) => queue.fail(vec![tag], Some(path.clone()), e),
+                }
+            }
+            ManifestItem::Pattern(matcher) => matchers.push((tag, matcher.clone())),
+            ManifestItem::FileReference(file_reference) => {
+                let entry = match volume.get_file_entry_by_reference(*file_reference) {
+                    Ok((_, state)) if state.is_stale() => {
+                        queue.fail(
+                            vec![tag],
+                            None,
+                            Error::NotFound(format!(
+                                "ref:{:#x}, its MFT entry was reused",
+                                file_reference
+                            )),
+                        );
+                        continue;
+                    }
+                    Ok((entry, _)) => entry,
+                    Err(e) => {
+                        queue.fail(vec![tag], None, e);
+                        continue;
+                    }
+                };
+
+                let idx = mft_entry_index(*file_reference);
+                let path = resolver.full_path(idx, &entry);
+
+                match extract::selected(&entry, &options.filter) {
+                    Ok(Some(mft_entry_index)) => add(path, mft_entry_index, tag),
+                    Ok(None) => {}
+                    Err(e) => queue.fail(vec![tag], Some(path), e),
+                }
+            }
+        }
+    }
+
+    if !matchers.is_empty() {
+        let mut walk_options = options.walk.clone();
+        walk_options.deduplicate_hard_links = true;
+
+        match volume.walk(walk_options) {
+            Ok(walk) => {
+                for entry in walk {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(e) => {
+                            queue.fail(vec![], None, e);
+                            continue;
+                        }
+                    };
+
+                    let tags: Vec<usize> = matchers
+                        .iter()
+                        .filter(|(_, matcher)| {
+                            matcher.matches(&entry.path)
+                                || entry.hard_links.iter().any(|path| matcher.matches(path))
+                        })
+                        .map(|(tag, _)| *tag)
+                        .collect();
+
+                    if tags.is_empty() {
+                        continue;
+                    }
+
+                    match extract::selected_walk_entry(&entry, &options.filter) {
+                        Ok(Some(mft_entry_index)) => {
+                            for tag in tags {
+                                add(entry.path.clone(), mft_entry_index, tag);
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => queue.fail(tags, Some(entry.path), e),
+                    }
+                }
+            }
+            Err(e) => queue.fail(matchers.iter().map(|(tag, _)| *tag).collect(), None, e),
+        }
+    }
+
+    for (_, job) in jobs {
+        if !queue.submit(job) {
+            // Every worker is gone.
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::metadata_files;
+    use crate::options::VolumeOpenOptions;
+
+    #[test]
+    fn test_parse() {
+        let manifest = Manifest::parse(
+            "# hives
+             C:\\Windows\\System32\\config\\SAM
+
+             $MFT
+             ref:0x10000000000000",
+        )
+        .unwrap();
+
+        match manifest.items() {
+            [ManifestItem::Path(sam), ManifestItem::Path(mft), ManifestItem::FileReference(reference)] =>
+            {
+                assert_eq!(sam, "\\Windows\\System32\\config\\SAM");
+                assert_eq!(mft, "\\$MFT");
+                assert_eq!(*reference, 1 << 52);
+            }
+            items => panic!("unexpected items {:?}", items),
+        }
+
+        assert!(Manifest::parse("ref:nope").is_err());
+    }
+
+    #[test]
+    fn test_extract_manifest() {
+        let volume = SharedVolume::open(sample_volume_path(), &VolumeOpenOptions::new()).unwrap();
+        let manifest = Manifest::new()
+            .item(ManifestItem::Path("\\$Extend".to_owned()))
+            .item(ManifestItem::FileReference(metadata_files::LOG_FILE))
+            .item(ManifestItem::Path("\\$LogFile".to_owned()))
+            .item(ManifestItem::Path("\\does_not_exist".to_owned()));
+
+        let report =
+            extract_manifest(&volume, &manifest, &ExtractOptions::new().workers(2)).unwrap();
+
+        assert!(report.items[0].is_success(), "{:?}", report.items[0]);
+        assert!(report.items[1].is_success(), "{:?}", report.items[1]);
+        assert_eq!(report.items[1].extracted[0].path, "\\$LogFile");
+        assert!(report.items[2].is_success(), "{:?}", report.items[2]);
+        assert!(!report.items[3].is_success());
+
+        // `$LogFile` is listed twice, but extracted once.
+        let listed: usize = report.items.iter().map(|item| item.extracted.len()).sum();
+        assert_eq!(report.extracted as usize, listed - 1);
+    }
+}
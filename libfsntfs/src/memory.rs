@@ -0,0 +1,213 @@
+//! Bounds the memory the caches of volumes use, for services which keep many volumes open.
+//!
+//! Volumes opened with clones of a budget share it, so a single budget caps the caches of
+//! every volume of a service; giving each volume its own budget caps them per volume instead.
+//! Caches which are over the budget stop remembering, and read the volume again instead.
+//!
+//! ```ignore
+//! let budget = MemoryBudget::new(256 << 20);
+//! let options = VolumeOpenOptions::new().memory_budget(budget.clone());
+//!
+//! for image in images {
+//!     let volume = Volume::open_with_options(image, &options)?;
+//!     // ...
+//! }
+//! println!("{} bytes cached", budget.used());
+//! ```
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+struct Accounting {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+/// A limit in bytes on what caches hold, clones share it.
+#[derive(Clone)]
+pub struct MemoryBudget(Arc<Accounting>);
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        MemoryBudget(Arc::new(Accounting {
+            limit,
+            used: AtomicUsize::new(0),
+        }))
+    }
+
+    pub fn limit(&self) -> usize {
+        self.0.limit
+    }
+
+    /// Bytes currently accounted for by every cache using the budget.
+    pub fn used(&self) -> usize {
+        self.0.used.load(Ordering::Relaxed)
+    }
+
+    pub fn available(&self) -> usize {
+        self.limit().saturating_sub(self.used())
+    }
+
+    /// Accounts for `bytes` if they fit in the budget, returns whether they did.
+    /// Reserved bytes must be given back with `release`.
+    pub fn try_reserve(&self, bytes: usize) -> bool {
+        let mut used = self.used();
+
+        loop {
+            let reserved = match used.checked_add(bytes) {
+                Some(reserved) if reserved <= self.0.limit => reserved,
+                _ => return false,
+            };
+
+            match self.0.used.compare_exchange_weak(
+                used,
+                reserved,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(current) => used = current,
+            }
+        }
+    }
+
+    pub fn release(&self, bytes: usize) {
+        let mut used = self.used();
+
+        while let Err(current) = self.0.used.compare_exchange_weak(
+            used,
+            used.saturating_sub(bytes),
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        ) {
+            used = current;
+        }
+    }
+
+    /// Accounts for `bytes` even if they do not fit, e.g. for a copy of a cache.
+    fn reserve(&self, bytes: usize) {
+        self.0.used.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+impl Debug for MemoryBudget {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("MemoryBudget")
+            .field("limit", &self.limit())
+            .field("used", &self.used())
+            .finish()
+    }
+}
+
+/// What a cache reserved from a budget, released when the cache is dropped.
+/// Without a budget, every reservation succeeds.
+#[derive(Debug, Default)]
+pub(crate) struct Charge {
+    budget: Option<MemoryBudget>,
+    bytes: usize,
+}
+
+impl Charge {
+    pub(crate) fn new(budget: Option<MemoryBudget>) -> Self {
+        Charge { budget, bytes: 0 }
+    }
+
+    pub(crate) fn has_budget(&self) -> bool {
+        self.budget.is_some()
+    }
+
+    pub(crate) fn try_reserve(&mut self, bytes: usize) -> bool {
+        if let Some(ref budget) = self.budget {
+            if !budget.try_reserve(bytes) {
+                return false;
+            }
+        }
+
+        self.bytes += bytes;
+        true
+    }
+
+    pub(crate) fn release(&mut self, bytes: usize) {
+        let bytes = bytes.min(self.bytes);
+
+        if let Some(ref budget) = self.budget {
+            budget.release(bytes);
+        }
+
+        self.bytes -= bytes;
+    }
+}
+
+impl Clone for Charge {
+    fn clone(&self) -> Self {
+        if let Some(ref budget) = self.budget {
+            budget.reserve(self.bytes);
+        }
+
+        Charge {
+            budget: self.budget.clone(),
+            bytes: self.bytes,
+        }
+    }
+}
+
+impl Drop for Charge {
+    fn drop(&mut self) {
+        let bytes = self.bytes;
+        self.release(bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::options::VolumeOpenOptions;
+    use crate::paths::PathResolver;
+    use crate::utils::mft_entry_index;
+    use crate::volume::Volume;
+
+    #[test]
+    fn test_memory_budget() {
+        let budget = MemoryBudget::new(100);
+
+        assert!(budget.try_reserve(60));
+        assert!(!budget.try_reserve(60));
+        assert_eq!(budget.available(), 40);
+
+        budget.release(60);
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[test]
+    fn test_charge_released_on_drop() {
+        let budget = MemoryBudget::new(100);
+
+        {
+            let mut charge = Charge::new(Some(budget.clone()));
+            assert!(charge.try_reserve(30));
+
+            let copy = charge.clone();
+            assert_eq!(budget.used(), 60);
+            drop(copy);
+        }
+
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[test]
+    fn test_resolver_within_budget() {
+        let budget = MemoryBudget::new(0);
+        let options = VolumeOpenOptions::new().memory_budget(budget.clone());
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+
+        let extend = volume.get_file_entry_by_path("\\$Extend").unwrap();
+        let idx = mft_entry_index(extend.get_file_reference().unwrap());
+
+        // Nothing fits, paths are still resolved by reading the directories.
+        let mut resolver = PathResolver::new();
+        assert_eq!(resolver.directory_path(&volume, idx), "\\$Extend");
+        assert_eq!(resolver.directory_path(&volume, idx), "\\$Extend");
+        assert_eq!(budget.used(), 0);
+    }
+}
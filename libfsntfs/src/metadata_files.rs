@@ -0,0 +1,85 @@
+//! MFT entry indices of the NTFS metadata files, which are always at the start of the MFT.
+use crate::volume::MftEntryIndex;
+
+pub const MFT: MftEntryIndex = 0;
+pub const MFT_MIRROR: MftEntryIndex = 1;
+pub const LOG_FILE: MftEntryIndex = 2;
+pub const VOLUME: MftEntryIndex = 3;
+pub const ATTRIBUTE_DEFINITIONS: MftEntryIndex = 4;
+pub const ROOT_DIRECTORY: MftEntryIndex = 5;
+pub const BITMAP: MftEntryIndex = 6;
+pub const BOOT: MftEntryIndex = 7;
+pub const BAD_CLUSTERS: MftEntryIndex = 8;
+pub const SECURE: MftEntryIndex = 9;
+pub const UPCASE: MftEntryIndex = 10;
+pub const EXTEND: MftEntryIndex = 11;
+
+/// One of the metadata files at a fixed index, except the root directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetadataFile {
+    Mft,
+    MftMirror,
+    LogFile,
+    Volume,
+    AttributeDefinitions,
+    Bitmap,
+    Boot,
+    BadClusters,
+    Secure,
+    UpCase,
+    Extend,
+}
+
+impl MetadataFile {
+    pub fn from_index(idx: MftEntryIndex) -> Option<Self> {
+        let metadata_file = match idx {
+            MFT => MetadataFile::Mft,
+            MFT_MIRROR => MetadataFile::MftMirror,
+            LOG_FILE => MetadataFile::LogFile,
+            VOLUME => MetadataFile::Volume,
+            ATTRIBUTE_DEFINITIONS => MetadataFile::AttributeDefinitions,
+            BITMAP => MetadataFile::Bitmap,
+            BOOT => MetadataFile::Boot,
+            BAD_CLUSTERS => MetadataFile::BadClusters,
+            SECURE => MetadataFile::Secure,
+            UPCASE => MetadataFile::UpCase,
+            EXTEND => MetadataFile::Extend,
+            _ => return None,
+        };
+
+        Some(metadata_file)
+    }
+
+    pub fn index(self) -> MftEntryIndex {
+        match self {
+            MetadataFile::Mft => MFT,
+            MetadataFile::MftMirror => MFT_MIRROR,
+            MetadataFile::LogFile => LOG_FILE,
+            MetadataFile::Volume => VOLUME,
+            MetadataFile::AttributeDefinitions => ATTRIBUTE_DEFINITIONS,
+            MetadataFile::Bitmap => BITMAP,
+            MetadataFile::Boot => BOOT,
+            MetadataFile::BadClusters => BAD_CLUSTERS,
+            MetadataFile::Secure => SECURE,
+            MetadataFile::UpCase => UPCASE,
+            MetadataFile::Extend => EXTEND,
+        }
+    }
+
+    /// The name of the file in the root directory, e.g. `$MFT`.
+    pub fn name(self) -> &'static str {
+        match self {
+            MetadataFile::Mft => "$MFT",
+            MetadataFile::MftMirror => "$MFTMirr",
+            MetadataFile::LogFile => "$LogFile",
+            MetadataFile::Volume => "$Volume",
+            MetadataFile::AttributeDefinitions => "$AttrDef",
+            MetadataFile::Bitmap => "$Bitmap",
+            MetadataFile::Boot => "$Boot",
+            MetadataFile::BadClusters => "$BadClus",
+            MetadataFile::Secure => "$Secure",
+            MetadataFile::UpCase => "$UpCase",
+            MetadataFile::Extend => "$Extend",
+        }
+    }
+}
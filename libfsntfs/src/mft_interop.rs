@@ -0,0 +1,101 @@
+//! Conversions into the record types of the `mft` crate, so code written against it
+//! can read entries through libfsntfs.
+use crate::attribute::{Attribute, AttributeType};
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::mft_record::MftRecord;
+use crate::utils::mft_entry_index;
+use mft::attribute::{MftAttribute, MftAttributeType};
+use mft::MftEntry;
+use num_traits::FromPrimitive;
+use std::convert::TryFrom;
+
+fn mft_error(error: mft::err::Error) -> Error {
+    Error::Other(format!("mft: {}", error))
+}
+
+impl TryFrom<AttributeType> for MftAttributeType {
+    type Error = Error;
+
+    fn try_from(attribute_type: AttributeType) -> Result<Self, Self::Error> {
+        MftAttributeType::from_u32(attribute_type.as_u32())
+            .ok_or_else(|| Error::UnknownAttributeEnumVariant(attribute_type.as_u32()))
+    }
+}
+
+impl<'a> TryFrom<&FileEntry<'a>> for MftEntry {
+    type Error = Error;
+
+    /// Re-reads the MFT record of the entry and parses it with the `mft` crate.
+    fn try_from(file_entry: &FileEntry<'a>) -> Result<Self, Self::Error> {
+        let index = mft_entry_index(file_entry.get_file_reference()?);
+        let data = MftRecord::read_raw(file_entry.volume(), index)?;
+
+        MftEntry::from_buffer(data, index).map_err(mft_error)
+    }
+}
+
+impl<'a> FileEntry<'a> {
+    /// Parses the MFT record of the entry with the `mft` crate.
+    pub fn to_mft_entry(&self) -> Result<MftEntry, Error> {
+        MftEntry::try_from(self)
+    }
+
+    /// The attributes of the MFT record, as parsed by the `mft` crate.
+    pub fn to_mft_attributes(&self) -> Result<Vec<MftAttribute>, Error> {
+        self.to_mft_entry()?
+            .iter_attributes()
+            .map(|attribute| attribute.map_err(mft_error))
+            .collect()
+    }
+}
+
+impl<'a> Attribute<'a> {
+    pub fn get_mft_attribute_type(&self) -> Result<MftAttributeType, Error> {
+        MftAttributeType::try_from(self.get_type()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_to_mft_entry() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+
+        let mft_entry = entry.to_mft_entry().unwrap();
+
+        assert_eq!(
+            mft_entry.header.record_number,
+            mft_entry_index(entry.get_file_reference().unwrap())
+        );
+        assert_eq!(
+            mft_entry.find_best_name_attribute().unwrap().name,
+            entry.get_name().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_mft_attribute_types_match() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+
+        let attribute_types: Vec<MftAttributeType> = entry
+            .iter_attributes()
+            .unwrap()
+            .map(|attribute| attribute.unwrap().get_mft_attribute_type().unwrap())
+            .collect();
+
+        let mft_attribute_types: Vec<MftAttributeType> = entry
+            .to_mft_attributes()
+            .unwrap()
+            .into_iter()
+            .map(|attribute| attribute.header.type_code)
+            .collect();
+
+        assert_eq!(attribute_types, mft_attribute_types);
+    }
+}
@@ -0,0 +1,1052 @@
+//! Raw MFT records, for the metadata libfsntfs does not expose (index contents, attribute data).
+//!
+//! Only the base record is parsed, attributes moved to extension records by an
+//! `$ATTRIBUTE_LIST` are not followed.
+use crate::attribute::AttributeType;
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::metadata_files;
+use crate::options::Utf16Policy;
+use crate::utils::mft_entry_index;
+use crate::volume::{MftEntryIndex, Volume};
+use std::convert::{TryFrom, TryInto};
+use std::io::{Read, Seek, SeekFrom};
+
+pub const FILE_RECORD_SIGNATURE: &[u8; 4] = b"FILE";
+/// The signature chkdsk writes over records it found corrupt.
+pub const BAD_RECORD_SIGNATURE: &[u8; 4] = b"BAAD";
+
+/// The update sequence (fixup) stride, independent of the actual sector size.
+pub(crate) const FIXUP_STRIDE: usize = 512;
+
+const ATTRIBUTES_END_MARKER: u32 = 0xffff_ffff;
+
+/// The namespace of a `$FILE_NAME` which holds the DOS (8.3) name Windows generates next to
+/// a long name, e.g. `PROGRA~1`.
+pub const FILE_NAME_NAMESPACE_DOS: u8 = 2;
+/// The largest non-resident value `RawAttribute::read_data` reads into memory, the data size
+/// comes from the record and must not decide how much is allocated.
+pub const MAX_ATTRIBUTE_DATA_SIZE: u64 = 1024 * 1024 * 1024;
+
+pub(crate) fn invalid(reason: String) -> Error {
+    Error::InvalidRecord(reason)
+}
+
+pub(crate) fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| invalid(format!("truncated at offset {}", offset)))
+}
+
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| invalid(format!("truncated at offset {}", offset)))
+}
+
+pub(crate) fn read_u64(data: &[u8], offset: usize) -> Result<u64, Error> {
+    data.get(offset..offset + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| invalid(format!("truncated at offset {}", offset)))
+}
+
+/// Whether the update sequence (fixups) of a record as stored checks out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixupStatus {
+    Valid,
+    /// The record has no update sequence array.
+    Absent,
+    /// The update sequence array does not fit in the record.
+    OutOfBounds,
+    /// The end of `sector` does not hold the update sequence number, it was only partly written.
+    Mismatch {
+        sector: usize,
+    },
+}
+
+impl FixupStatus {
+    /// Checks `data`, a record as stored (without the fixups applied).
+    pub fn check(data: &[u8]) -> Self {
+        let update_sequence = match update_sequence(data) {
+            Ok(Some(update_sequence)) => update_sequence,
+            Ok(None) => return FixupStatus::Absent,
+            Err(_) => return FixupStatus::OutOfBounds,
+        };
+
+        for stride in 1..update_sequence.len() / 2 {
+            let end = stride * FIXUP_STRIDE;
+
+            if end > data.len() {
+                break;
+            }
+
+            if data[end - 2..end] != update_sequence[..2] {
+                return FixupStatus::Mismatch { sector: stride - 1 };
+            }
+        }
+
+        FixupStatus::Valid
+    }
+
+    pub fn is_valid(self) -> bool {
+        self == FixupStatus::Valid || self == FixupStatus::Absent
+    }
+}
+
+/// The update sequence array of `data`, `None` when it has none.
+fn update_sequence(data: &[u8]) -> Result<Option<&[u8]>, Error> {
+    let update_sequence_offset = read_u16(data, 0x04)? as usize;
+    let update_sequence_count = read_u16(data, 0x06)? as usize;
+
+    if update_sequence_count == 0 {
+        return Ok(None);
+    }
+
+    data.get(update_sequence_offset..update_sequence_offset + update_sequence_count * 2)
+        .map(Some)
+        .ok_or_else(|| invalid("update sequence array out of bounds".to_owned()))
+}
+
+/// Replaces the last two bytes of every 512 byte stride with the saved values,
+/// after checking they hold the update sequence number (which proves the write was complete).
+pub(crate) fn apply_fixups(data: &mut [u8]) -> Result<(), Error> {
+    if let FixupStatus::Mismatch { sector } = FixupStatus::check(data) {
+        return Err(invalid(format!(
+            "fixup mismatch in sector {} (torn write)",
+            sector
+        )));
+    }
+
+    restore_fixups(data)
+}
+
+/// Like `apply_fixups`, without checking the update sequence number, for records which are
+/// parsed even though their fixups do not match.
+pub(crate) fn restore_fixups(data: &mut [u8]) -> Result<(), Error> {
+    let update_sequence = match update_sequence(data)? {
+        Some(update_sequence) => update_sequence.to_vec(),
+        None => return Ok(()),
+    };
+
+    for stride in 1..update_sequence.len() / 2 {
+        let end = stride * FIXUP_STRIDE;
+
+        if end > data.len() {
+            break;
+        }
+
+        data[end - 2..end].copy_from_slice(&update_sequence[stride * 2..stride * 2 + 2]);
+    }
+
+    Ok(())
+}
+
+/// The header of an MFT record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordHeader {
+    pub signature: [u8; 4],
+    pub update_sequence_offset: u16,
+    pub update_sequence_count: u16,
+    /// The `$LogFile` sequence number of the last change to the record.
+    pub lsn: u64,
+    /// Incremented each time the entry is reused, part of the file references to it.
+    pub sequence_number: u16,
+    pub hard_link_count: u16,
+    pub first_attribute_offset: u16,
+    /// `0x01` in use, `0x02` directory.
+    pub flags: u16,
+    pub used_size: u32,
+    pub allocated_size: u32,
+    /// The file reference of the base record, 0 for base records.
+    pub base_record_reference: u64,
+    pub next_attribute_identifier: u16,
+    /// The index of the entry, only stored since NTFS 3.1.
+    pub record_number: Option<u32>,
+    pub fixup_status: FixupStatus,
+}
+
+impl RecordHeader {
+    /// Parses the header of `data`, a record as stored (without the fixups applied).
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let mut signature = [0; 4];
+        signature.copy_from_slice(
+            data.get(..4)
+                .ok_or_else(|| invalid("record has no signature".to_owned()))?,
+        );
+
+        let update_sequence_offset = read_u16(data, 0x04)?;
+
+        // Older headers end with the update sequence array at 0x2a.
+        let record_number = if update_sequence_offset >= 0x30 {
+            Some(read_u32(data, 0x2c)?)
+        } else {
+            None
+        };
+
+        Ok(RecordHeader {
+            signature,
+            update_sequence_offset,
+            update_sequence_count: read_u16(data, 0x06)?,
+            lsn: read_u64(data, 0x08)?,
+            sequence_number: read_u16(data, 0x10)?,
+            hard_link_count: read_u16(data, 0x12)?,
+            first_attribute_offset: read_u16(data, 0x14)?,
+            flags: read_u16(data, 0x16)?,
+            used_size: read_u32(data, 0x18)?,
+            allocated_size: read_u32(data, 0x1c)?,
+            base_record_reference: read_u64(data, 0x20)?,
+            next_attribute_identifier: read_u16(data, 0x28)?,
+            record_number,
+            fixup_status: FixupStatus::check(data),
+        })
+    }
+}
+
+/// A run of clusters of a non-resident attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataRun {
+    /// First cluster of the run in the volume, `None` for sparse runs.
+    pub lcn: Option<u64>,
+    pub number_of_clusters: u64,
+}
+
+/// Decodes the mapping pairs of a non-resident attribute.
+pub fn decode_data_runs(mut data: &[u8]) -> Result<Vec<DataRun>, Error> {
+    let mut runs = vec![];
+    let mut lcn: i64 = 0;
+
+    while let Some((&header, rest)) = data.split_first() {
+        if header == 0 {
+            break;
+        }
+
+        let length_size = (header & 0x0f) as usize;
+        let offset_size = (header >> 4) as usize;
+
+        if length_size == 0
+            || length_size > 8
+            || offset_size > 8
+            || rest.len() < length_size + offset_size
+        {
+            return Err(invalid("invalid data run".to_owned()));
+        }
+
+        let number_of_clusters = rest[..length_size]
+            .iter()
+            .rev()
+            .fold(0_u64, |value, byte| (value << 8) | u64::from(*byte));
+
+        let run_lcn = if offset_size == 0 {
+            None
+        } else {
+            let offset_bytes = &rest[length_size..length_size + offset_size];
+            // Sign extend from the most significant byte.
+            let mut offset: i64 = if offset_bytes[offset_size - 1] & 0x80 != 0 {
+                -1
+            } else {
+                0
+            };
+
+            for byte in offset_bytes.iter().rev() {
+                offset = (offset << 8) | i64::from(*byte);
+            }
+
+            lcn += offset;
+
+            if lcn < 0 {
+                return Err(invalid("data run before the start of the volume".to_owned()));
+            }
+
+            Some(lcn as u64)
+        };
+
+        runs.push(DataRun {
+            lcn: run_lcn,
+            number_of_clusters,
+        });
+
+        data = &rest[length_size + offset_size..];
+    }
+
+    Ok(runs)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeForm<'a> {
+    Resident {
+        data: &'a [u8],
+    },
+    NonResident {
+        data_runs: Vec<DataRun>,
+        allocated_size: u64,
+        data_size: u64,
+        initialized_size: u64,
+    },
+}
+
+/// An attribute as stored in an MFT record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawAttribute<'a> {
+    pub type_code: u32,
+    pub name: String,
+    pub flags: u16,
+    pub identifier: u16,
+    /// Offset of the attribute in the record.
+    pub offset: usize,
+    /// Length of the attribute in the record, header included.
+    pub length: usize,
+    pub form: AttributeForm<'a>,
+}
+
+impl<'a> RawAttribute<'a> {
+    fn parse(record: &'a [u8], offset: usize, utf16_policy: Utf16Policy) -> Result<Self, Error> {
+        let type_code = read_u32(record, offset)?;
+        let length = read_u32(record, offset + 0x04)? as usize;
+
+        let attribute = record
+            .get(offset..offset + length)
+            .filter(|attribute| attribute.len() >= 0x18)
+            .ok_or_else(|| invalid(format!("invalid attribute size at offset {}", offset)))?;
+
+        let non_resident = attribute[0x08] != 0;
+        let name_length = attribute[0x09] as usize;
+        let name_offset = read_u16(attribute, 0x0a)? as usize;
+        let flags = read_u16(attribute, 0x0c)?;
+        let identifier = read_u16(attribute, 0x0e)?;
+
+        let name_bytes = attribute
+            .get(name_offset..name_offset + name_length * 2)
+            .ok_or_else(|| invalid("attribute name out of bounds".to_owned()))?;
+        let name_utf16: Vec<u16> = name_bytes
+            .chunks(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        let name = utf16_policy.decode(&name_utf16)?;
+
+        let form = if non_resident {
+            let data_runs_offset = read_u16(attribute, 0x20)? as usize;
+
+            AttributeForm::NonResident {
+                data_runs: decode_data_runs(attribute.get(data_runs_offset..).unwrap_or(&[]))?,
+                allocated_size: read_u64(attribute, 0x28)?,
+                data_size: read_u64(attribute, 0x30)?,
+                initialized_size: read_u64(attribute, 0x38)?,
+            }
+        } else {
+            let value_length = read_u32(attribute, 0x10)? as usize;
+            let value_offset = read_u16(attribute, 0x14)? as usize;
+
+            AttributeForm::Resident {
+                data: attribute
+                    .get(value_offset..value_offset + value_length)
+                    .ok_or_else(|| invalid("resident value out of bounds".to_owned()))?,
+            }
+        };
+
+        Ok(RawAttribute {
+            type_code,
+            name,
+            flags,
+            identifier,
+            offset,
+            length,
+            form,
+        })
+    }
+
+    pub fn attribute_type(&self) -> Result<AttributeType, Error> {
+        AttributeType::try_from(self.type_code)
+    }
+
+    pub fn is_resident(&self) -> bool {
+        match self.form {
+            AttributeForm::Resident { .. } => true,
+            AttributeForm::NonResident { .. } => false,
+        }
+    }
+
+    /// The value of the attribute, read from `image` (the raw volume) when it is not resident.
+    /// Sparse runs and the part past the initialized size read as zeroes.
+    pub fn read_data<R: Read + Seek>(
+        &self,
+        image: &mut R,
+        cluster_size: u64,
+    ) -> Result<Vec<u8>, Error> {
+        let (data_runs, data_size, initialized_size) = match &self.form {
+            AttributeForm::Resident { data } => return Ok(data.to_vec()),
+            AttributeForm::NonResident {
+                data_runs,
+                data_size,
+                initialized_size,
+                ..
+            } => (data_runs, *data_size, *initialized_size),
+        };
+
+        if data_size > MAX_ATTRIBUTE_DATA_SIZE {
+            return Err(invalid(format!(
+                "attribute data size {} is larger than {}",
+                data_size, MAX_ATTRIBUTE_DATA_SIZE
+            )));
+        }
+
+        let initialized_size = initialized_size.min(data_size);
+        let overflow = || invalid("data run past the end of the volume".to_owned());
+
+        let mut data = vec![0; data_size as usize];
+        let mut offset = 0_u64;
+
+        for run in data_runs {
+            if offset >= initialized_size {
+                break;
+            }
+
+            let run_size = run
+                .number_of_clusters
+                .checked_mul(cluster_size)
+                .ok_or_else(overflow)?;
+            let to_read = run_size.min(initialized_size - offset) as usize;
+
+            if let Some(lcn) = run.lcn {
+                let start = lcn.checked_mul(cluster_size).ok_or_else(overflow)?;
+
+                image.seek(SeekFrom::Start(start))?;
+                image.read_exact(&mut data[offset as usize..offset as usize + to_read])?;
+            }
+
+            offset = offset.checked_add(run_size).ok_or_else(overflow)?;
+        }
+
+        Ok(data)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MftRecord {
+    index: MftEntryIndex,
+    header: RecordHeader,
+    /// The record with the fixups applied.
+    data: Vec<u8>,
+    utf16_policy: Utf16Policy,
+}
+
+impl MftRecord {
+    /// Reads the record of MFT entry `index` through `$MFT`.
+    /// Torn records are only read with `VolumeOpenOptions::accept_torn_writes`.
+    pub fn read(volume: &Volume, index: MftEntryIndex) -> Result<Self, Error> {
+        let data = MftRecord::read_raw(volume, index)?;
+
+        let record = if volume.options().accepts_torn_writes() {
+            MftRecord::from_bytes_with_torn_writes(index, data)?
+        } else {
+            MftRecord::from_bytes(index, data)?
+        };
+
+        Ok(record.utf16_policy(volume.options().get_utf16_policy()))
+    }
+
+    /// Reads the record of MFT entry `index` as stored, without applying the fixups.
+    pub fn read_raw(volume: &Volume, index: MftEntryIndex) -> Result<Vec<u8>, Error> {
+        let record_size = u64::from(volume.get_mft_entry_size()?);
+        let mut data = vec![0; record_size as usize];
+
+        let mut mft = volume.get_file_entry_by_mft_idx(metadata_files::MFT)?;
+        mft.seek(SeekFrom::Start(index * record_size))?;
+        mft.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+
+    /// Reads the header of the record of MFT entry `index`, which does not need to be intact.
+    pub fn read_header(volume: &Volume, index: MftEntryIndex) -> Result<RecordHeader, Error> {
+        RecordHeader::parse(&MftRecord::read_raw(volume, index)?)
+    }
+
+    /// Fails for records whose fixups do not match (torn writes).
+    pub fn from_bytes(index: MftEntryIndex, data: Vec<u8>) -> Result<Self, Error> {
+        MftRecord::parse(index, data, false)
+    }
+
+    /// Like `from_bytes`, but parses torn records as well, see `fixup_status`.
+    pub fn from_bytes_with_torn_writes(index: MftEntryIndex, data: Vec<u8>) -> Result<Self, Error> {
+        MftRecord::parse(index, data, true)
+    }
+
+    fn parse(
+        index: MftEntryIndex,
+        mut data: Vec<u8>,
+        accept_torn_writes: bool,
+    ) -> Result<Self, Error> {
+        if data.get(..4) != Some(&FILE_RECORD_SIGNATURE[..]) {
+            return Err(invalid(format!("MFT entry {} has no FILE signature", index)));
+        }
+
+        let header = RecordHeader::parse(&data)?;
+
+        if accept_torn_writes {
+            restore_fixups(&mut data)?;
+        } else {
+            apply_fixups(&mut data)?;
+        }
+
+        Ok(MftRecord {
+            index,
+            header,
+            data,
+            utf16_policy: Utf16Policy::default(),
+        })
+    }
+
+    /// How attribute names are decoded, records read from a volume use the policy of the volume.
+    pub fn utf16_policy(mut self, utf16_policy: Utf16Policy) -> Self {
+        self.utf16_policy = utf16_policy;
+        self
+    }
+
+    pub fn index(&self) -> MftEntryIndex {
+        self.index
+    }
+
+    pub fn header(&self) -> &RecordHeader {
+        &self.header
+    }
+
+    /// Whether the record passed the fixup validation, only records parsed with
+    /// `from_bytes_with_torn_writes` (or `accept_torn_writes`) can fail it.
+    pub fn fixup_status(&self) -> FixupStatus {
+        self.header.fixup_status
+    }
+
+    /// The record, with the fixups applied.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn attributes(&self) -> Result<Vec<RawAttribute>, Error> {
+        let used_size = (read_u32(&self.data, 0x18)? as usize).min(self.data.len());
+        let record = &self.data[..used_size];
+
+        let mut attributes = vec![];
+        let mut offset = read_u16(record, 0x14)? as usize;
+
+        while offset + 4 <= record.len() && read_u32(record, offset)? != ATTRIBUTES_END_MARKER {
+            let attribute = RawAttribute::parse(record, offset, self.utf16_policy)?;
+            offset += read_u32(record, offset + 0x04)? as usize;
+            attributes.push(attribute);
+        }
+
+        Ok(attributes)
+    }
+
+    /// The first attribute of the given type and name.
+    pub fn find_attribute(
+        &self,
+        attribute_type: AttributeType,
+        name: &str,
+    ) -> Result<Option<RawAttribute>, Error> {
+        Ok(self.attributes()?.into_iter().find(|attribute| {
+            attribute.type_code == attribute_type.as_u32() && attribute.name == name
+        }))
+    }
+
+    /// Every `$DATA` attribute of the record, ordered by their instance identifier.
+    /// Corrupt or crafted records can have several with the same name, all of them are kept.
+    pub fn data_attributes(&self) -> Result<Vec<RawAttribute>, Error> {
+        let mut attributes: Vec<RawAttribute> = self
+            .attributes()?
+            .into_iter()
+            .filter(|attribute| attribute.type_code == AttributeType::Data.as_u32())
+            .collect();
+
+        // Stable, so attributes which share an identifier keep the order of the record.
+        attributes.sort_by_key(|attribute| attribute.identifier);
+
+        Ok(attributes)
+    }
+
+    /// Every resident `$FILE_NAME` attribute of the record, with its namespace.
+    pub fn file_names(&self) -> Result<Vec<RecordFileName>, Error> {
+        let mut file_names = vec![];
+
+        for attribute in self.attributes()? {
+            if attribute.type_code != AttributeType::FileName.as_u32() {
+                continue;
+            }
+
+            // Always resident, anything else is corrupt.
+            let value = match attribute.form {
+                AttributeForm::Resident { data } => data,
+                AttributeForm::NonResident { .. } => continue,
+            };
+
+            let (name_length, namespace) = match value.get(0x40..0x42) {
+                Some(bytes) => (bytes[0] as usize, bytes[1]),
+                None => {
+                    return Err(invalid(format!(
+                        "truncated $FILE_NAME at offset {}",
+                        attribute.offset
+                    )))
+                }
+            };
+
+            let name_utf16 = (0..name_length)
+                .map(|i| read_u16(value, 0x42 + i * 2))
+                .collect::<Result<Vec<u16>, Error>>()?;
+
+            file_names.push(RecordFileName {
+                parent_file_reference: read_u64(value, 0x00)?,
+                name: self.utf16_policy.decode(&name_utf16)?,
+                namespace,
+            });
+        }
+
+        Ok(file_names)
+    }
+}
+
+/// A `$FILE_NAME` attribute of the record of an entry, see `FileEntry::record_file_names`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordFileName {
+    pub parent_file_reference: u64,
+    pub name: String,
+    /// 0 for POSIX, 1 for Win32, 2 for DOS and 3 for names valid in both.
+    pub namespace: u8,
+}
+
+/// A `$DATA` attribute of the record of an entry, see `FileEntry::data_attributes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataAttribute {
+    /// Empty for the default data stream.
+    pub name: String,
+    /// The instance identifier of the attribute in the record.
+    pub instance: u16,
+    pub is_resident: bool,
+    pub size: u64,
+    /// Whether another `$DATA` attribute of the record has the same name.
+    pub is_duplicate: bool,
+}
+
+/// How much of an MFT record as stored can be trusted, see `FileEntry::get_record_health`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordHealth {
+    Intact,
+    /// Never used, all zeros.
+    Empty,
+    /// Marked corrupt by chkdsk (`BAAD`).
+    MarkedBad,
+    /// Neither `FILE` nor `BAAD`, e.g. overwritten.
+    NoSignature,
+    /// The fixups do not match, the record was only partly written.
+    TornWrite,
+    /// The attributes cannot be parsed.
+    Malformed,
+}
+
+impl RecordHealth {
+    /// Checks `data`, a record as stored (without the fixups applied).
+    pub fn check(data: &[u8]) -> Self {
+        if data.iter().all(|&byte| byte == 0) {
+            return RecordHealth::Empty;
+        }
+
+        match data.get(..4) {
+            Some(signature) if signature == &FILE_RECORD_SIGNATURE[..] => {}
+            Some(signature) if signature == &BAD_RECORD_SIGNATURE[..] => {
+                return RecordHealth::MarkedBad
+            }
+            _ => return RecordHealth::NoSignature,
+        }
+
+        let header = match RecordHeader::parse(data) {
+            Ok(header) => header,
+            Err(_) => return RecordHealth::Malformed,
+        };
+
+        let mut data = data.to_vec();
+
+        if apply_fixups(&mut data).is_err() {
+            return RecordHealth::TornWrite;
+        }
+
+        let record = MftRecord {
+            index: 0,
+            header,
+            data,
+            utf16_policy: Utf16Policy::Lossy,
+        };
+
+        if record.attributes().is_err() {
+            return RecordHealth::Malformed;
+        }
+
+        RecordHealth::Intact
+    }
+
+    pub fn is_intact(self) -> bool {
+        self == RecordHealth::Intact
+    }
+}
+
+impl<'a> FileEntry<'a> {
+    /// Checks the MFT record of the entry as stored. libfsntfs reads what it can of damaged
+    /// records, so what it returns for entries which are not intact is less reliable.
+    pub fn get_record_health(&self) -> Result<RecordHealth, Error> {
+        let idx = mft_entry_index(self.get_file_reference()?);
+
+        Ok(RecordHealth::check(&MftRecord::read_raw(self.volume(), idx)?))
+    }
+
+    /// The header of the MFT record of the entry as stored.
+    pub fn get_record_header(&self) -> Result<RecordHeader, Error> {
+        let idx = mft_entry_index(self.get_file_reference()?);
+
+        MftRecord::read_header(self.volume(), idx)
+    }
+
+    pub fn is_based_on_corrupt_record(&self) -> Result<bool, Error> {
+        Ok(!self.get_record_health()?.is_intact())
+    }
+
+    fn record(&self) -> Result<MftRecord, Error> {
+        let idx = mft_entry_index(self.get_file_reference()?);
+
+        MftRecord::read(self.volume(), idx)
+    }
+
+    /// The `$FILE_NAME` attributes in the base record of the entry, with their namespace.
+    pub fn record_file_names(&self) -> Result<Vec<RecordFileName>, Error> {
+        self.record()?.file_names()
+    }
+
+    /// The `$DATA` attributes in the base record of the entry, ordered by instance identifier.
+    ///
+    /// libfsntfs gives access to the first stream of a name only, this lists all of them,
+    /// duplicates included (see `read_data_attribute`).
+    pub fn data_attributes(&self) -> Result<Vec<DataAttribute>, Error> {
+        let attributes = self.record()?.data_attributes()?;
+
+        Ok(attributes
+            .iter()
+            .map(|attribute| DataAttribute {
+                name: attribute.name.clone(),
+                instance: attribute.identifier,
+                is_resident: attribute.is_resident(),
+                size: match attribute.form {
+                    AttributeForm::Resident { data } => data.len() as u64,
+                    AttributeForm::NonResident { data_size, .. } => data_size,
+                },
+                is_duplicate: attributes
+                    .iter()
+                    .any(|other| !std::ptr::eq(other, attribute) && other.name == attribute.name),
+            })
+            .collect())
+    }
+
+    /// The data of the `$DATA` attribute with the instance identifier `instance`, read from
+    /// `image` (the raw volume) when it is not resident.
+    pub fn read_data_attribute<R: Read + Seek>(
+        &self,
+        instance: u16,
+        image: &mut R,
+    ) -> Result<Vec<u8>, Error> {
+        let record = self.record()?;
+        let attribute = record
+            .data_attributes()?
+            .into_iter()
+            .find(|attribute| attribute.identifier == instance)
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "$DATA attribute {} of MFT entry {}",
+                    instance,
+                    record.index()
+                ))
+            })?;
+
+        attribute.read_data(image, self.volume().get_cluster_block_size()? as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_decode_data_runs() {
+        // 0x18 clusters at 0x5634, 0x10 sparse clusters, 0x08 clusters 0x100 clusters before the first run.
+        let mapping_pairs = [0x21, 0x18, 0x34, 0x56, 0x01, 0x10, 0x21, 0x08, 0x00, 0xff, 0x00];
+
+        assert_eq!(
+            decode_data_runs(&mapping_pairs).unwrap(),
+            vec![
+                DataRun {
+                    lcn: Some(0x5634),
+                    number_of_clusters: 0x18
+                },
+                DataRun {
+                    lcn: None,
+                    number_of_clusters: 0x10
+                },
+                DataRun {
+                    lcn: Some(0x5534),
+                    number_of_clusters: 0x08
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_data_of_crafted_attribute() {
+        let attribute = |data_runs, data_size, initialized_size| RawAttribute {
+            type_code: 0x80,
+            name: String::new(),
+            flags: 0,
+            identifier: 0,
+            offset: 0,
+            length: 0,
+            form: AttributeForm::NonResident {
+                data_runs,
+                allocated_size: data_size,
+                data_size,
+                initialized_size,
+            },
+        };
+        let mut image = Cursor::new(vec![0xaa_u8; 4096]);
+
+        // The initialized size is past the data size.
+        let data = attribute(
+            vec![DataRun {
+                lcn: Some(0),
+                number_of_clusters: 2,
+            }],
+            100,
+            8192,
+        )
+        .read_data(&mut image, 4096)
+        .unwrap();
+        assert_eq!(data, vec![0xaa; 100]);
+
+        assert!(attribute(vec![], u64::max_value(), 0)
+            .read_data(&mut image, 4096)
+            .is_err());
+        assert!(attribute(
+            vec![DataRun {
+                lcn: Some(u64::max_value() / 2),
+                number_of_clusters: 1,
+            }],
+            100,
+            100,
+        )
+        .read_data(&mut image, 4096)
+        .is_err());
+    }
+
+    #[test]
+    fn test_apply_fixups() {
+        let mut data = vec![0; 1024];
+        data[0x04..0x06].copy_from_slice(&0x30_u16.to_le_bytes());
+        data[0x06..0x08].copy_from_slice(&3_u16.to_le_bytes());
+        data[0x30..0x36].copy_from_slice(&[0x07, 0x00, 0xaa, 0xbb, 0xcc, 0xdd]);
+        data[510..512].copy_from_slice(&[0x07, 0x00]);
+        data[1022..1024].copy_from_slice(&[0x07, 0x00]);
+
+        apply_fixups(&mut data).unwrap();
+
+        assert_eq!(data[510..512], [0xaa, 0xbb]);
+        assert_eq!(data[1022..1024], [0xcc, 0xdd]);
+
+        data[510..512].copy_from_slice(&[0x08, 0x00]);
+        data[0x30..0x32].copy_from_slice(&[0x08, 0x00]);
+        data[1022..1024].copy_from_slice(&[0x00, 0x00]);
+
+        assert!(apply_fixups(&mut data).is_err());
+    }
+
+    #[test]
+    fn test_record_health() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+
+        assert_eq!(entry.get_record_health().unwrap(), RecordHealth::Intact);
+        assert!(!entry.is_based_on_corrupt_record().unwrap());
+
+        let mut data = MftRecord::read_raw(&volume, metadata_files::MFT).unwrap();
+        let update_sequence_offset = read_u16(&data, 0x04).unwrap() as usize;
+        data[update_sequence_offset] ^= 0xff;
+        assert_eq!(RecordHealth::check(&data), RecordHealth::TornWrite);
+        assert_eq!(
+            RecordHeader::parse(&data).unwrap().fixup_status,
+            FixupStatus::Mismatch { sector: 0 }
+        );
+
+        data[..4].copy_from_slice(BAD_RECORD_SIGNATURE);
+        assert_eq!(RecordHealth::check(&data), RecordHealth::MarkedBad);
+
+        assert_eq!(RecordHealth::check(&[0; 1024]), RecordHealth::Empty);
+    }
+
+    /// A resident `$DATA` attribute named `name`, with the instance identifier `identifier`.
+    fn data_attribute(name: &str, identifier: u16, value: &[u8]) -> Vec<u8> {
+        let name: Vec<u8> = name
+            .encode_utf16()
+            .flat_map(|c| c.to_le_bytes().to_vec())
+            .collect();
+        let value_offset = (0x18 + name.len() + 7) / 8 * 8;
+        let length = (value_offset + value.len() + 7) / 8 * 8;
+
+        let mut attribute = vec![0; length];
+        attribute[0x00..0x04].copy_from_slice(&AttributeType::Data.as_u32().to_le_bytes());
+        attribute[0x04..0x08].copy_from_slice(&(length as u32).to_le_bytes());
+        attribute[0x09] = (name.len() / 2) as u8;
+        attribute[0x0a..0x0c].copy_from_slice(&0x18_u16.to_le_bytes());
+        attribute[0x0e..0x10].copy_from_slice(&identifier.to_le_bytes());
+        attribute[0x10..0x14].copy_from_slice(&(value.len() as u32).to_le_bytes());
+        attribute[0x14..0x16].copy_from_slice(&(value_offset as u16).to_le_bytes());
+        attribute[0x18..0x18 + name.len()].copy_from_slice(&name);
+        attribute[value_offset..value_offset + value.len()].copy_from_slice(value);
+        attribute
+    }
+
+    #[test]
+    fn test_duplicate_data_attributes() {
+        let mut data = vec![0; 1024];
+        data[..4].copy_from_slice(FILE_RECORD_SIGNATURE);
+        data[0x14..0x16].copy_from_slice(&0x38_u16.to_le_bytes());
+
+        let mut attributes = vec![];
+        attributes.extend(data_attribute("ads", 3, b"second"));
+        attributes.extend(data_attribute("", 0, b"default"));
+        attributes.extend(data_attribute("ads", 2, b"first"));
+        attributes.extend(&ATTRIBUTES_END_MARKER.to_le_bytes());
+
+        let used_size = 0x38 + attributes.len();
+        data[0x38..used_size].copy_from_slice(&attributes);
+        data[0x18..0x1c].copy_from_slice(&(used_size as u32).to_le_bytes());
+
+        let record = MftRecord::from_bytes(40, data).unwrap();
+        let streams: Vec<(String, u16)> = record
+            .data_attributes()
+            .unwrap()
+            .into_iter()
+            .map(|attribute| (attribute.name, attribute.identifier))
+            .collect();
+
+        assert_eq!(
+            streams,
+            [
+                ("".to_owned(), 0),
+                ("ads".to_owned(), 2),
+                ("ads".to_owned(), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_file_names() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+
+        let record_file_names = entry.record_file_names().unwrap();
+        let file_names = entry.get_file_names().unwrap();
+        assert_eq!(record_file_names.len(), file_names.len());
+
+        for (record_file_name, file_name) in record_file_names.iter().zip(file_names.iter()) {
+            assert_eq!(record_file_name.name, file_name.name);
+            assert_eq!(
+                record_file_name.parent_file_reference,
+                file_name.parent_file_reference
+            );
+            assert!(record_file_name.namespace <= 3);
+        }
+    }
+
+    #[test]
+    fn test_data_attributes_of_entry() {
+        let volume = sample_volume().unwrap();
+        let secure = volume
+            .get_file_entry_by_mft_idx(metadata_files::SECURE)
+            .unwrap();
+
+        let attributes = secure.data_attributes().unwrap();
+        let sds = attributes
+            .iter()
+            .find(|attribute| attribute.name == "$SDS")
+            .unwrap();
+        assert!(!sds.is_duplicate);
+
+        let mut image = File::open(sample_volume_path()).unwrap();
+        let data = secure
+            .read_data_attribute(sds.instance, &mut image)
+            .unwrap();
+        assert_eq!(data.len() as u64, sds.size);
+    }
+
+    #[test]
+    fn test_torn_record() {
+        let volume = sample_volume().unwrap();
+        let mut data = MftRecord::read_raw(&volume, metadata_files::MFT).unwrap();
+
+        // The end of the second sector no longer holds the update sequence number.
+        data[1022] ^= 0xff;
+
+        assert!(MftRecord::from_bytes(0, data.clone()).is_err());
+
+        let record = MftRecord::from_bytes_with_torn_writes(0, data).unwrap();
+        assert_eq!(record.fixup_status(), FixupStatus::Mismatch { sector: 1 });
+        assert!(record
+            .find_attribute(AttributeType::Data, "")
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_record_header() {
+        let volume = sample_volume().unwrap();
+        let record = MftRecord::read(&volume, metadata_files::MFT).unwrap();
+        let header = record.header();
+
+        assert_eq!(&header.signature, FILE_RECORD_SIGNATURE);
+        assert_eq!(header.fixup_status, FixupStatus::Valid);
+        assert_eq!(header.hard_link_count, 1);
+        assert_eq!(header.base_record_reference, 0);
+        assert_eq!(header.record_number, Some(0));
+        assert_eq!(header.allocated_size, volume.get_mft_entry_size().unwrap());
+        assert!(header.used_size <= header.allocated_size);
+
+        let attributes = record.attributes().unwrap();
+        assert_eq!(attributes[0].offset, header.first_attribute_offset as usize);
+        for pair in attributes.windows(2) {
+            assert_eq!(pair[0].offset + pair[0].length, pair[1].offset);
+        }
+
+        let entry = file_entry(&volume).unwrap();
+        let entry_header = entry.get_record_header().unwrap();
+        assert_eq!(
+            u64::from(entry_header.sequence_number),
+            entry.get_file_reference().unwrap() >> 48
+        );
+    }
+
+    #[test]
+    fn test_read_mft_record() {
+        let volume = sample_volume().unwrap();
+        let record = MftRecord::read(&volume, metadata_files::MFT).unwrap();
+
+        let data = record
+            .find_attribute(AttributeType::Data, "")
+            .unwrap()
+            .unwrap();
+
+        assert!(!data.is_resident());
+        assert!(record
+            .find_attribute(AttributeType::StandardInformation, "")
+            .unwrap()
+            .unwrap()
+            .is_resident());
+    }
+}
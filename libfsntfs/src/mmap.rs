@@ -0,0 +1,101 @@
+//! Reads images through a memory map, see `IoBackend::Mmap`.
+use memmap2::Mmap;
+use std::cmp;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+/// A reader over a memory mapped file, clones share the map.
+#[derive(Clone)]
+pub struct MmapReader {
+    map: Arc<Mmap>,
+    position: u64,
+}
+
+impl MmapReader {
+    /// Maps all of `file`, which must not be truncated while it is mapped.
+    pub fn new(file: &File) -> io::Result<Self> {
+        let map = unsafe { Mmap::map(file)? };
+
+        Ok(MmapReader {
+            map: Arc::new(map),
+            position: 0,
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.map.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl Read for MmapReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.len() {
+            return Ok(0);
+        }
+
+        let start = self.position as usize;
+        let length = cmp::min(buf.len(), self.map.len() - start);
+
+        buf[..length].copy_from_slice(&self.map[start..start + length]);
+        self.position += length as u64;
+
+        Ok(length)
+    }
+}
+
+impl Seek for MmapReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+            SeekFrom::End(offset) => self.len() as i64 + offset,
+        };
+
+        if position < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the stream",
+            ));
+        }
+
+        self.position = position as u64;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::options::{IoBackend, VolumeOpenOptions};
+    use crate::volume::Volume;
+
+    #[test]
+    fn test_mmap_reader() {
+        let mut file = File::open(sample_volume_path()).unwrap();
+        let mut reader = MmapReader::new(&file).unwrap();
+
+        let mut expected = [0; 512];
+        file.read_exact(&mut expected).unwrap();
+
+        let mut sector = [0; 512];
+        reader.read_exact(&mut sector).unwrap();
+        assert_eq!(&sector[..], &expected[..]);
+
+        assert_eq!(reader.seek(SeekFrom::End(0)).unwrap(), reader.len());
+        assert_eq!(reader.read(&mut sector).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_mmap_volume() {
+        let options = VolumeOpenOptions::new().io_backend(IoBackend::Mmap);
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+
+        assert!(volume.get_root_directory().is_ok());
+    }
+}
@@ -0,0 +1,47 @@
+//! Controls libfsntfs notifications.
+//!
+//! Only useful when libfsntfs is built with verbose and debug output (the `debug-output` feature),
+//! otherwise the library has nothing to report.
+use crate::error::Error;
+use crate::ffi_error::LibfsntfsErrorRefMut;
+use libfsntfs_sys::FILE;
+use std::convert::TryFrom;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+extern "C" {
+    pub fn libfsntfs_notify_set_verbose(verbose: c_int);
+    pub fn libfsntfs_notify_set_stream(stream: *mut FILE, error: *mut LibfsntfsErrorRefMut)
+        -> c_int;
+    pub fn libfsntfs_notify_stream_open(
+        filename: *const c_char,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> c_int;
+    pub fn libfsntfs_notify_stream_close(error: *mut LibfsntfsErrorRefMut) -> c_int;
+
+    #[cfg_attr(target_os = "windows", link_name = "_fdopen")]
+    fn fdopen(fd: c_int, mode: *const c_char) -> *mut FILE;
+}
+
+const STDERR_FILENO: c_int = 2;
+
+/// Enables (or disables) verbose notifications, which are written to stderr.
+pub fn set_verbose_notifications(verbose: bool) -> Result<(), Error> {
+    if verbose {
+        let stderr = unsafe { fdopen(STDERR_FILENO, b"w\0".as_ptr() as *const c_char) };
+
+        if stderr.is_null() {
+            return Err(Error::Other("Failed to open stderr stream".to_owned()));
+        }
+
+        let mut error = ptr::null_mut();
+
+        if unsafe { libfsntfs_notify_set_stream(stderr, &mut error) } != 1 {
+            return Err(Error::try_from(error)?);
+        }
+    }
+
+    unsafe { libfsntfs_notify_set_verbose(verbose as c_int) };
+
+    Ok(())
+}
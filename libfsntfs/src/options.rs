@@ -0,0 +1,479 @@
+//! Options for opening a volume, see `Volume::open_with_options`.
+use crate::audit::ReadAudit;
+use crate::entry_cache::EntryCache;
+use crate::error::Error;
+use crate::instrument::{Metric, Metrics};
+use crate::intercept::ReadInterceptor;
+use crate::memory::MemoryBudget;
+use crate::throttle::Throttle;
+use crate::volume::AccessMode;
+use std::sync::Arc;
+
+/// Where entries whose parent directories cannot be resolved (deleted, reused or looping)
+/// are placed.
+pub const DEFAULT_ORPHAN_ROOT: &str = "\\$Orphan";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrphanPolicy {
+    /// Orphans get paths under this virtual directory (e.g. `\$Orphan\file.txt`)
+    /// and are yielded by walks after the directory tree.
+    VirtualRoot(String),
+    /// Orphans are left out of walks, paths still place them under `DEFAULT_ORPHAN_ROOT`.
+    Exclude,
+}
+
+impl Default for OrphanPolicy {
+    fn default() -> Self {
+        OrphanPolicy::VirtualRoot(DEFAULT_ORPHAN_ROOT.to_owned())
+    }
+}
+
+impl OrphanPolicy {
+    /// The path orphans are placed under.
+    pub fn root(&self) -> &str {
+        match self {
+            OrphanPolicy::VirtualRoot(root) => root.as_str(),
+            OrphanPolicy::Exclude => DEFAULT_ORPHAN_ROOT,
+        }
+    }
+}
+
+/// How volumes opened by filename read their image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoBackend {
+    /// Reads of the file, by libbfio.
+    File,
+    /// A memory map of the file, faster for the random reads of MFT entries on local storage.
+    /// Needs the `mmap` feature.
+    Mmap,
+    /// Reads bypassing the Windows file cache (`FILE_FLAG_NO_BUFFERING`), for live volumes
+    /// (`\\.\C:`). Needs the `unbuffered` feature.
+    Unbuffered,
+    /// io_uring, with large reads submitted in batches. Experimental, needs the `io-uring`
+    /// feature and Linux 5.6 or later.
+    IoUring,
+}
+
+impl Default for IoBackend {
+    fn default() -> Self {
+        IoBackend::File
+    }
+}
+
+/// The codepage of names stored in single byte strings, such as the 8.3 names of volumes
+/// formatted by old versions of Windows in non-Latin locales. Supported by libfsntfs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codepage {
+    Ascii,
+    Iso8859_1,
+    Iso8859_2,
+    Iso8859_3,
+    Iso8859_4,
+    Iso8859_5,
+    Iso8859_6,
+    Iso8859_7,
+    Iso8859_8,
+    Iso8859_9,
+    Iso8859_10,
+    Iso8859_11,
+    Iso8859_13,
+    Iso8859_14,
+    Iso8859_15,
+    Iso8859_16,
+    Koi8R,
+    Koi8U,
+    /// Thai.
+    Windows874,
+    /// Japanese (Shift-JIS).
+    Windows932,
+    /// Simplified Chinese (GBK).
+    Windows936,
+    /// Korean.
+    Windows949,
+    /// Traditional Chinese (Big5).
+    Windows950,
+    Windows1250,
+    Windows1251,
+    Windows1252,
+    Windows1253,
+    Windows1254,
+    Windows1255,
+    Windows1256,
+    Windows1257,
+    Windows1258,
+}
+
+impl Default for Codepage {
+    /// What libfsntfs uses unless told otherwise.
+    fn default() -> Self {
+        Codepage::Windows1252
+    }
+}
+
+impl Codepage {
+    /// The number of the codepage, as Windows and libfsntfs know it.
+    pub fn number(self) -> u32 {
+        match self {
+            Codepage::Ascii => 20127,
+            Codepage::Iso8859_1 => 28591,
+            Codepage::Iso8859_2 => 28592,
+            Codepage::Iso8859_3 => 28593,
+            Codepage::Iso8859_4 => 28594,
+            Codepage::Iso8859_5 => 28595,
+            Codepage::Iso8859_6 => 28596,
+            Codepage::Iso8859_7 => 28597,
+            Codepage::Iso8859_8 => 28598,
+            Codepage::Iso8859_9 => 28599,
+            Codepage::Iso8859_10 => 28600,
+            Codepage::Iso8859_11 => 28601,
+            Codepage::Iso8859_13 => 28603,
+            Codepage::Iso8859_14 => 28604,
+            Codepage::Iso8859_15 => 28605,
+            Codepage::Iso8859_16 => 28606,
+            Codepage::Koi8R => 20866,
+            Codepage::Koi8U => 21866,
+            Codepage::Windows874 => 874,
+            Codepage::Windows932 => 932,
+            Codepage::Windows936 => 936,
+            Codepage::Windows949 => 949,
+            Codepage::Windows950 => 950,
+            Codepage::Windows1250 => 1250,
+            Codepage::Windows1251 => 1251,
+            Codepage::Windows1252 => 1252,
+            Codepage::Windows1253 => 1253,
+            Codepage::Windows1254 => 1254,
+            Codepage::Windows1255 => 1255,
+            Codepage::Windows1256 => 1256,
+            Codepage::Windows1257 => 1257,
+            Codepage::Windows1258 => 1258,
+        }
+    }
+}
+
+/// Where `Utf16Policy::Raw` places unpaired surrogates: U+10D800 to U+10DFFF, in the
+/// Supplementary Private Use Area-B.
+const RAW_SURROGATE_BASE: u32 = 0x10_0000;
+
+fn raw_escape(surrogate: u16) -> char {
+    std::char::from_u32(RAW_SURROGATE_BASE + u32::from(surrogate))
+        .expect("surrogates map to valid characters")
+}
+
+fn is_raw_escape(c: char) -> bool {
+    let value = c as u32;
+    value >= RAW_SURROGATE_BASE + 0xD800 && value <= RAW_SURROGATE_BASE + 0xDFFF
+}
+
+/// How names which are not valid UTF-16 are decoded. NTFS stores names as arbitrary 16-bit
+/// units, so names can hold unpaired surrogates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Utf16Policy {
+    /// Such names are an `Error::InvalidUtf16Name`.
+    Strict,
+    /// Unpaired surrogates are replaced with U+FFFD, so different names can decode the same.
+    Lossy,
+    /// Unpaired surrogates are kept as U+10D800 to U+10DFFF. Names which really hold those
+    /// characters have both of their surrogates kept that way (a high surrogate followed by a
+    /// low one is never unpaired), so names stay distinct and `encode` gives back the name as
+    /// stored.
+    Raw,
+}
+
+impl Default for Utf16Policy {
+    fn default() -> Self {
+        Utf16Policy::Lossy
+    }
+}
+
+impl Utf16Policy {
+    pub fn decode(self, units: &[u16]) -> Result<String, Error> {
+        let mut name = String::with_capacity(units.len());
+
+        for c in std::char::decode_utf16(units.iter().cloned()) {
+            match (c, self) {
+                (Ok(c), Utf16Policy::Raw) if is_raw_escape(c) => {
+                    let mut buffer = [0; 2];
+                    for &surrogate in c.encode_utf16(&mut buffer).iter() {
+                        name.push(raw_escape(surrogate));
+                    }
+                }
+                (Ok(c), _) => name.push(c),
+                (Err(_), Utf16Policy::Strict) => {
+                    return Err(Error::InvalidUtf16Name(String::from_utf16_lossy(units)))
+                }
+                (Err(_), Utf16Policy::Lossy) => name.push(std::char::REPLACEMENT_CHARACTER),
+                (Err(e), Utf16Policy::Raw) => name.push(raw_escape(e.unpaired_surrogate())),
+            }
+        }
+
+        Ok(name)
+    }
+
+    /// The UTF-16 units of `name`, the units it was decoded from for `Raw`.
+    pub fn encode(self, name: &str) -> Vec<u16> {
+        let mut units = Vec::with_capacity(name.len());
+
+        for c in name.chars() {
+            if self == Utf16Policy::Raw && is_raw_escape(c) {
+                units.push((c as u32 - RAW_SURROGATE_BASE) as u16);
+            } else {
+                let mut buffer = [0; 2];
+                units.extend_from_slice(c.encode_utf16(&mut buffer));
+            }
+        }
+
+        units
+    }
+}
+
+/// Settings applied when opening a volume.
+///
+/// ```ignore
+/// let options = VolumeOpenOptions::new().case_sensitive(true);
+/// let volume = Volume::open_with_options("image.dd", &options)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct VolumeOpenOptions {
+    pub(crate) access_mode: AccessMode,
+    pub(crate) case_sensitive: bool,
+    pub(crate) orphan_policy: OrphanPolicy,
+    pub(crate) throttle: Option<Throttle>,
+    pub(crate) io_backend: IoBackend,
+    pub(crate) memory_budget: Option<MemoryBudget>,
+    pub(crate) metrics: Option<Arc<dyn Metrics>>,
+    pub(crate) codepage: Codepage,
+    pub(crate) utf16_policy: Utf16Policy,
+    pub(crate) boot_sector_fallback: bool,
+    pub(crate) mft_mirror_fallback: bool,
+    pub(crate) accept_torn_writes: bool,
+    pub(crate) read_interceptor: Option<Arc<dyn ReadInterceptor>>,
+    pub(crate) read_audit: Option<ReadAudit>,
+    pub(crate) entry_cache: Option<EntryCache>,
+}
+
+impl Default for VolumeOpenOptions {
+    fn default() -> Self {
+        VolumeOpenOptions {
+            access_mode: AccessMode::Read,
+            case_sensitive: false,
+            orphan_policy: OrphanPolicy::default(),
+            throttle: None,
+            io_backend: IoBackend::default(),
+            memory_budget: None,
+            metrics: None,
+            codepage: Codepage::default(),
+            utf16_policy: Utf16Policy::default(),
+            boot_sector_fallback: false,
+            mft_mirror_fallback: false,
+            accept_torn_writes: false,
+            read_interceptor: None,
+            read_audit: None,
+            entry_cache: None,
+        }
+    }
+}
+
+impl VolumeOpenOptions {
+    pub fn new() -> Self {
+        VolumeOpenOptions::default()
+    }
+
+    pub fn access_mode(mut self, access_mode: AccessMode) -> Self {
+        self.access_mode = access_mode;
+        self
+    }
+
+    /// Whether path lookups must match the case of every name on the path.
+    /// NTFS (and libfsntfs) match names case-insensitively, which is the default.
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    pub fn orphan_policy(mut self, orphan_policy: OrphanPolicy) -> Self {
+        self.orphan_policy = orphan_policy;
+        self
+    }
+
+    /// Limits how fast the image is read, volumes opened with clones of the throttle share
+    /// its rate. Volumes opened from a `Handle` (`open_file_object`) are not throttled.
+    pub fn throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Only applies to volumes opened by filename, which must be opened for reading.
+    pub fn io_backend(mut self, io_backend: IoBackend) -> Self {
+        self.io_backend = io_backend;
+        self
+    }
+
+    /// Caps what the caches of the volume (e.g. the directory paths a walk remembers) hold,
+    /// volumes opened with clones of the budget share it.
+    pub fn memory_budget(mut self, memory_budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
+
+    /// Where the volume reports what it does, see `Metric`. Volumes opened with the same
+    /// metrics report to them together.
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn codepage(mut self, codepage: Codepage) -> Self {
+        self.codepage = codepage;
+        self
+    }
+
+    /// How names are decoded, applied to every name the volume returns (and so to paths and
+    /// exports).
+    pub fn utf16_policy(mut self, utf16_policy: Utf16Policy) -> Self {
+        self.utf16_policy = utf16_policy;
+        self
+    }
+
+    /// Whether volumes with a damaged boot sector are opened from the backup boot sector in
+    /// the last sector of the partition, see `boot::BootSectorFallback`. Volumes opened from a
+    /// `Handle` (`open_file_object`) do not fall back.
+    pub fn boot_sector_fallback(mut self, boot_sector_fallback: bool) -> Self {
+        self.boot_sector_fallback = boot_sector_fallback;
+        self
+    }
+
+    /// Whether damaged records of `$MFT`, `$MFTMirr`, `$LogFile` and `$Volume` are read from
+    /// `$MFTMirr`, see `recovery::MftMirrorFallback`. Like `boot_sector_fallback`, not applied
+    /// to volumes opened from a `Handle`.
+    pub fn mft_mirror_fallback(mut self, mft_mirror_fallback: bool) -> Self {
+        self.mft_mirror_fallback = mft_mirror_fallback;
+        self
+    }
+
+    /// Whether MFT and index records which fail the fixup validation (torn writes) are parsed
+    /// anyway instead of rejected, flagged by their `FixupStatus`. Only applies to the records
+    /// this crate parses (`MftRecord` and `Index`), libfsntfs itself still rejects them.
+    pub fn accept_torn_writes(mut self, accept_torn_writes: bool) -> Self {
+        self.accept_torn_writes = accept_torn_writes;
+        self
+    }
+
+    /// Sees (and can answer) every read of the image, see `intercept`. Like the throttle, not
+    /// applied to volumes opened from a `Handle`.
+    pub fn read_interceptor(mut self, read_interceptor: Arc<dyn ReadInterceptor>) -> Self {
+        self.read_interceptor = Some(read_interceptor);
+        self
+    }
+
+    /// Records the byte ranges read from the image into `read_audit`, see `audit`. Volumes
+    /// opened with clones of the audit record into it together. Reads answered by the
+    /// `read_interceptor` are recorded as well.
+    pub fn read_audit(mut self, read_audit: ReadAudit) -> Self {
+        self.read_audit = Some(read_audit);
+        self
+    }
+
+    /// Keeps the names, parents and paths of the entries the volume resolves in `entry_cache`,
+    /// see `entry_cache`. Volumes opened with clones of the cache (on the same image) share it.
+    pub fn entry_cache(mut self, entry_cache: EntryCache) -> Self {
+        self.entry_cache = Some(entry_cache);
+        self
+    }
+
+    pub fn get_access_mode(&self) -> AccessMode {
+        self.access_mode
+    }
+
+    pub fn is_case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    pub fn get_orphan_policy(&self) -> &OrphanPolicy {
+        &self.orphan_policy
+    }
+
+    pub fn get_throttle(&self) -> Option<&Throttle> {
+        self.throttle.as_ref()
+    }
+
+    pub fn get_io_backend(&self) -> IoBackend {
+        self.io_backend
+    }
+
+    pub fn get_memory_budget(&self) -> Option<&MemoryBudget> {
+        self.memory_budget.as_ref()
+    }
+
+    pub fn get_codepage(&self) -> Codepage {
+        self.codepage
+    }
+
+    pub fn get_utf16_policy(&self) -> Utf16Policy {
+        self.utf16_policy
+    }
+
+    pub fn has_boot_sector_fallback(&self) -> bool {
+        self.boot_sector_fallback
+    }
+
+    pub fn has_mft_mirror_fallback(&self) -> bool {
+        self.mft_mirror_fallback
+    }
+
+    pub fn accepts_torn_writes(&self) -> bool {
+        self.accept_torn_writes
+    }
+
+    pub fn get_read_interceptor(&self) -> Option<&Arc<dyn ReadInterceptor>> {
+        self.read_interceptor.as_ref()
+    }
+
+    pub fn get_read_audit(&self) -> Option<&ReadAudit> {
+        self.read_audit.as_ref()
+    }
+
+    pub fn get_entry_cache(&self) -> Option<&EntryCache> {
+        self.entry_cache.as_ref()
+    }
+
+    pub fn get_metrics(&self) -> Option<&Arc<dyn Metrics>> {
+        self.metrics.as_ref()
+    }
+
+    pub(crate) fn record(&self, metric: Metric) {
+        if let Some(ref metrics) = self.metrics {
+            metrics.record(metric);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_policy() {
+        // "a", an unpaired high surrogate, "b".
+        let units = [0x61, 0xD800, 0x62];
+
+        assert!(Utf16Policy::Strict.decode(&units).is_err());
+        assert_eq!(Utf16Policy::Lossy.decode(&units).unwrap(), "a\u{FFFD}b");
+
+        let raw = Utf16Policy::Raw.decode(&units).unwrap();
+        assert_eq!(raw, "a\u{10D800}b");
+        assert_eq!(Utf16Policy::Raw.encode(&raw), units);
+
+        // A name holding the character the unpaired surrogate is kept as.
+        let escape: Vec<u16> = "a\u{10D800}b".encode_utf16().collect();
+        let raw_escape = Utf16Policy::Raw.decode(&escape).unwrap();
+        assert_ne!(raw_escape, raw);
+        assert_eq!(raw_escape, "a\u{10DBF6}\u{10DC00}b");
+        assert_eq!(Utf16Policy::Raw.encode(&raw_escape), escape);
+
+        let valid: Vec<u16> = "\u{1F600}.txt".encode_utf16().collect();
+        for policy in &[Utf16Policy::Strict, Utf16Policy::Lossy, Utf16Policy::Raw] {
+            assert_eq!(policy.decode(&valid).unwrap(), "\u{1F600}.txt");
+            assert_eq!(policy.encode("\u{1F600}.txt"), valid);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! File entries which own (a handle to) their volume instead of borrowing it, so they can be
+//! stored in collections, returned from functions and, with a `SharedVolume`, sent to other
+//! threads.
+//!
+//! ```ignore
+//! let volume = Arc::new(Volume::open("image.dd", AccessMode::Read)?);
+//! let entries: Vec<OwnedFileEntry> = indexes
+//!     .into_iter()
+//!     .map(|idx| OwnedFileEntry::by_mft_idx(volume.clone(), idx))
+//!     .collect::<Result<_, _>>()?;
+//! ```
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::shared::SharedVolume;
+use crate::volume::{MftEntryIndex, Volume};
+use std::fmt::{self, Debug, Formatter};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::mem;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// `V` is what keeps the volume open: an `Arc<Volume>` shared by many entries, or a
+/// `SharedVolume` clone owned by the entry alone, which makes the entry `Send`.
+pub struct OwnedFileEntry<V: Deref<Target = Volume> = Arc<Volume>> {
+    // Borrows `volume`, so must be dropped first.
+    entry: FileEntry<'static>,
+    // Boxed so the volume stays at the same address when the entry is moved.
+    volume: Box<V>,
+}
+
+// The volume is not shared with anything else, so it is only ever used from one thread.
+unsafe impl Send for OwnedFileEntry<SharedVolume> {}
+
+impl<V: Deref<Target = Volume>> OwnedFileEntry<V> {
+    fn new<F>(volume: V, open: F) -> Result<Self, Error>
+    where
+        F: for<'v> FnOnce(&'v Volume) -> Result<FileEntry<'v>, Error>,
+    {
+        let volume = Box::new(volume);
+        let entry = open(&**volume)?;
+
+        // The entry never outlives the volume (see the drop order above), and is only handed
+        // out with the lifetime of a borrow of `self`.
+        let entry = unsafe { mem::transmute::<FileEntry, FileEntry<'static>>(entry) };
+
+        Ok(OwnedFileEntry { entry, volume })
+    }
+
+    pub fn by_mft_idx(volume: V, idx: MftEntryIndex) -> Result<Self, Error> {
+        OwnedFileEntry::new(volume, |volume| volume.get_file_entry_by_mft_idx(idx))
+    }
+
+    pub fn by_path(volume: V, path: impl AsRef<str>) -> Result<Self, Error> {
+        OwnedFileEntry::new(volume, |volume| {
+            volume.get_file_entry_by_path(path.as_ref())
+        })
+    }
+
+    pub fn entry(&self) -> &FileEntry {
+        &self.entry
+    }
+
+    pub fn volume(&self) -> &V {
+        &self.volume
+    }
+}
+
+impl<V: Deref<Target = Volume>> Read for OwnedFileEntry<V> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.entry.read(buf)
+    }
+}
+
+impl<V: Deref<Target = Volume>> Seek for OwnedFileEntry<V> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.entry.seek(pos)
+    }
+}
+
+impl<V: Deref<Target = Volume>> Debug for OwnedFileEntry<V> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("OwnedFileEntry").field(self.entry()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::options::VolumeOpenOptions;
+    use std::thread;
+
+    fn open_extend(volume: Arc<Volume>) -> OwnedFileEntry {
+        OwnedFileEntry::by_path(volume, "\\$Extend").unwrap()
+    }
+
+    #[test]
+    fn test_outlives_the_volume_binding() {
+        let entry = open_extend(Arc::new(sample_volume().unwrap()));
+
+        assert_eq!(entry.entry().get_name().unwrap(), "$Extend");
+    }
+
+    #[test]
+    fn test_send_to_thread() {
+        let volume = SharedVolume::open(sample_volume_path(), &VolumeOpenOptions::new()).unwrap();
+        let mut entry = OwnedFileEntry::by_mft_idx(volume, 0).unwrap();
+
+        let read = thread::spawn(move || {
+            let mut header = [0; 4];
+            entry.read_exact(&mut header).unwrap();
+            header
+        });
+
+        assert_eq!(&read.join().unwrap(), b"FILE");
+    }
+}
@@ -0,0 +1,433 @@
+//! Lists the partitions of a disk from its MBR or GPT partition table.
+//!
+//! ```ignore
+//! let mut disk = File::open("disk.dd")?;
+//!
+//! if let Some(table) = PartitionTable::read(&mut disk)? {
+//!     for partition in table.partitions.iter().filter(|p| p.partition_type.is_ntfs()) {
+//!         let volume = SharedVolume::open_with_offset("disk.dd", partition.offset, &options)?;
+//!     }
+//! }
+//! ```
+use crate::error::Error;
+use crate::utils::read_up_to;
+use std::convert::TryInto;
+use std::fmt::{self, Display, Formatter};
+use std::io::{Read, Seek, SeekFrom};
+
+/// Extended partitions are chained, longer chains are assumed to loop.
+const MAX_LOGICAL_PARTITIONS: usize = 128;
+/// GPT headers are looked for at LBA 1 of disks with these sector sizes.
+const GPT_SECTOR_SIZES: [u64; 2] = [512, 4096];
+const MBR_SECTOR_SIZE: u64 = 512;
+
+/// Partition type GUIDs, in their on-disk byte order.
+const GPT_BASIC_DATA: [u8; 16] = [
+    0xa2, 0xa0, 0xd0, 0xeb, 0xe5, 0xb9, 0x33, 0x44, 0x87, 0xc0, 0x68, 0xb6, 0xb7, 0x26, 0x99, 0xc7,
+];
+const GPT_WINDOWS_RECOVERY: [u8; 16] = [
+    0xa4, 0xbb, 0x94, 0xde, 0xd1, 0x06, 0x40, 0x4d, 0xa1, 0x6a, 0xbf, 0xd5, 0x01, 0x79, 0xd6, 0xac,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionScheme {
+    Mbr,
+    Gpt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionType {
+    /// The type byte of an MBR partition entry.
+    Mbr(u8),
+    /// The type GUID of a GPT partition entry, in its on-disk byte order.
+    Gpt([u8; 16]),
+}
+
+impl PartitionType {
+    /// Whether partitions of this type usually hold NTFS volumes. The types are shared with
+    /// other file systems (e.g. exFAT), check the boot sector to be sure.
+    pub fn is_ntfs(self) -> bool {
+        match self {
+            // Regular, and hidden by boot managers.
+            PartitionType::Mbr(0x07) | PartitionType::Mbr(0x17) | PartitionType::Mbr(0x27) => true,
+            PartitionType::Gpt(guid) => guid == GPT_BASIC_DATA || guid == GPT_WINDOWS_RECOVERY,
+            _ => false,
+        }
+    }
+}
+
+impl Display for PartitionType {
+    /// MBR types in hex, GPT types as GUIDs.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PartitionType::Mbr(kind) => write!(f, "0x{:02x}", kind),
+            PartitionType::Gpt(guid) => write!(
+                f,
+                "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+                u32::from_le_bytes([guid[0], guid[1], guid[2], guid[3]]),
+                u16::from_le_bytes([guid[4], guid[5]]),
+                u16::from_le_bytes([guid[6], guid[7]]),
+                guid[8],
+                guid[9],
+                guid[10],
+                guid[11],
+                guid[12],
+                guid[13],
+                guid[14],
+                guid[15]
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partition {
+    /// Numbered from 1 in table order, logical MBR partitions are numbered from 5.
+    pub number: usize,
+    /// Offset from the start of the disk, in bytes.
+    pub offset: u64,
+    /// In bytes.
+    pub size: u64,
+    pub partition_type: PartitionType,
+    /// The name of GPT partitions.
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionTable {
+    pub scheme: PartitionScheme,
+    pub sector_size: u64,
+    pub partitions: Vec<Partition>,
+}
+
+impl PartitionTable {
+    /// Reads the partition table of `disk`, `None` if it has none (e.g. it is an image of a
+    /// single volume).
+    pub fn read<R: Read + Seek>(disk: &mut R) -> Result<Option<PartitionTable>, Error> {
+        let mbr = read_at(disk, 0, MBR_SECTOR_SIZE as usize)?;
+
+        if !has_boot_signature(&mbr) {
+            return Ok(None);
+        }
+
+        // Volume boot sectors end with the same signature.
+        if mbr.get(3..11) == Some(&b"NTFS    "[..]) {
+            return Ok(None);
+        }
+
+        // A protective MBR, with a single partition spanning the disk.
+        if (0..4).any(|i| mbr[446 + i * 16 + 4] == 0xee) {
+            for &sector_size in GPT_SECTOR_SIZES.iter() {
+                if let Some(partitions) = gpt_partitions(disk, sector_size)? {
+                    return Ok(Some(PartitionTable {
+                        scheme: PartitionScheme::Gpt,
+                        sector_size,
+                        partitions,
+                    }));
+                }
+            }
+
+            return Err(Error::CorruptStructure {
+                structure: "protective MBR without a GPT header".to_owned(),
+                offset: Some(0),
+            });
+        }
+
+        Ok(Some(PartitionTable {
+            scheme: PartitionScheme::Mbr,
+            sector_size: MBR_SECTOR_SIZE,
+            partitions: mbr_partitions(disk, &mbr)?,
+        }))
+    }
+}
+
+fn read_at<R: Read + Seek>(disk: &mut R, offset: u64, size: usize) -> Result<Vec<u8>, Error> {
+    let mut data = vec![0; size];
+
+    disk.seek(SeekFrom::Start(offset))?;
+    let read = read_up_to(disk, &mut data)?;
+    data.truncate(read);
+
+    Ok(data)
+}
+
+fn has_boot_signature(sector: &[u8]) -> bool {
+    sector.len() >= 512 && sector[510..512] == [0x55, 0xaa]
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u64 {
+    u64::from(u32::from_le_bytes(
+        data[offset..offset + 4].try_into().expect("4 bytes"),
+    ))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().expect("8 bytes"))
+}
+
+/// `sectors` in bytes, overflows are corrupt `structure`s at `offset`.
+fn sectors_in_bytes(
+    sectors: u64,
+    sector_size: u64,
+    structure: &str,
+    offset: u64,
+) -> Result<u64, Error> {
+    sectors
+        .checked_mul(sector_size)
+        .ok_or_else(|| Error::CorruptStructure {
+            structure: structure.to_owned(),
+            offset: Some(offset),
+        })
+}
+
+fn mbr_partitions<R: Read + Seek>(disk: &mut R, mbr: &[u8]) -> Result<Vec<Partition>, Error> {
+    let mut partitions = vec![];
+    let mut logical = vec![];
+
+    for i in 0..4 {
+        let entry = &mbr[446 + i * 16..446 + (i + 1) * 16];
+        let (kind, start, sectors) = (entry[4], u32_at(entry, 8), u32_at(entry, 12));
+
+        match kind {
+            0x00 => {}
+            0x05 | 0x0f | 0x85 => logical.extend(logical_partitions(disk, start)?),
+            _ => partitions.push(Partition {
+                number: i + 1,
+                offset: sectors_in_bytes(start, MBR_SECTOR_SIZE, "MBR partition entry", 0)?,
+                size: sectors_in_bytes(sectors, MBR_SECTOR_SIZE, "MBR partition entry", 0)?,
+                partition_type: PartitionType::Mbr(kind),
+                name: None,
+            }),
+        }
+    }
+
+    for (i, mut partition) in logical.into_iter().enumerate() {
+        partition.number = 5 + i;
+        partitions.push(partition);
+    }
+
+    Ok(partitions)
+}
+
+/// Follows the chain of extended boot records of the extended partition at `extended_start`.
+fn logical_partitions<R: Read + Seek>(
+    disk: &mut R,
+    extended_start: u64,
+) -> Result<Vec<Partition>, Error> {
+    let mut partitions = vec![];
+    let mut ebr_start = extended_start;
+
+    for _ in 0..MAX_LOGICAL_PARTITIONS {
+        let ebr_offset = sectors_in_bytes(ebr_start, MBR_SECTOR_SIZE, "EBR chain", 0)?;
+        let ebr = read_at(disk, ebr_offset, MBR_SECTOR_SIZE as usize)?;
+
+        if !has_boot_signature(&ebr) {
+            break;
+        }
+
+        let (kind, start, sectors) = (ebr[446 + 4], u32_at(&ebr, 446 + 8), u32_at(&ebr, 446 + 12));
+
+        if kind != 0 && sectors != 0 {
+            let corrupt = || Error::CorruptStructure {
+                structure: "EBR partition entry".to_owned(),
+                offset: Some(ebr_offset),
+            };
+
+            partitions.push(Partition {
+                number: 0,
+                offset: ebr_start
+                    .checked_add(start)
+                    .and_then(|start| start.checked_mul(MBR_SECTOR_SIZE))
+                    .ok_or_else(corrupt)?,
+                size: sectors.checked_mul(MBR_SECTOR_SIZE).ok_or_else(corrupt)?,
+                partition_type: PartitionType::Mbr(kind),
+                name: None,
+            });
+        }
+
+        // The next EBR is relative to the start of the extended partition.
+        let next = u32_at(&ebr, 462 + 8);
+        if next == 0 {
+            break;
+        }
+
+        ebr_start = extended_start
+            .checked_add(next)
+            .ok_or_else(|| Error::CorruptStructure {
+                structure: "EBR chain".to_owned(),
+                offset: Some(ebr_offset),
+            })?;
+    }
+
+    Ok(partitions)
+}
+
+/// The partitions of the GPT with its header at LBA 1, `None` if there is no header there.
+fn gpt_partitions<R: Read + Seek>(
+    disk: &mut R,
+    sector_size: u64,
+) -> Result<Option<Vec<Partition>>, Error> {
+    let header = read_at(disk, sector_size, 92)?;
+
+    if !header.starts_with(b"EFI PART") {
+        return Ok(None);
+    }
+
+    if header.len() < 92 {
+        return Err(Error::CorruptStructure {
+            structure: "GPT header".to_owned(),
+            offset: Some(sector_size),
+        });
+    }
+
+    let entries_start =
+        sectors_in_bytes(u64_at(&header, 72), sector_size, "GPT header", sector_size)?;
+    let number_of_entries = u32_at(&header, 80);
+    let entry_size = u32_at(&header, 84);
+
+    if entry_size < 128 || entry_size > 4096 || entry_size % 8 != 0 || number_of_entries > 4096 {
+        return Err(Error::CorruptStructure {
+            structure: "GPT header".to_owned(),
+            offset: Some(sector_size),
+        });
+    }
+
+    let entries = read_at(
+        disk,
+        entries_start,
+        (number_of_entries * entry_size) as usize,
+    )?;
+
+    let partitions = entries
+        .chunks_exact(entry_size as usize)
+        .enumerate()
+        .filter(|(_, entry)| entry[..16].iter().any(|&byte| byte != 0))
+        .map(|(i, entry)| {
+            let entry_offset = entries_start + (i as u64) * entry_size;
+            let (first, last) = (u64_at(entry, 32), u64_at(entry, 40));
+            let mut partition_type = [0; 16];
+            partition_type.copy_from_slice(&entry[..16]);
+
+            let sectors = last
+                .checked_add(1)
+                .ok_or_else(|| Error::CorruptStructure {
+                    structure: "GPT partition entry".to_owned(),
+                    offset: Some(entry_offset),
+                })?
+                .saturating_sub(first);
+
+            Ok(Partition {
+                number: i + 1,
+                offset: sectors_in_bytes(first, sector_size, "GPT partition entry", entry_offset)?,
+                size: sectors_in_bytes(sectors, sector_size, "GPT partition entry", entry_offset)?,
+                partition_type: PartitionType::Gpt(partition_type),
+                name: gpt_name(&entry[56..128]),
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+
+    Ok(Some(partitions))
+}
+
+/// GPT names are UTF-16, padded with nul characters.
+fn gpt_name(data: &[u8]) -> Option<String> {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+
+    if units.is_empty() {
+        None
+    } else {
+        Some(String::from_utf16_lossy(&units))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    fn mbr_entry(disk: &mut [u8], offset: usize, kind: u8, start: u32, sectors: u32) {
+        disk[offset + 4] = kind;
+        disk[offset + 8..offset + 12].copy_from_slice(&start.to_le_bytes());
+        disk[offset + 12..offset + 16].copy_from_slice(&sectors.to_le_bytes());
+        disk[offset - offset % 512 + 510] = 0x55;
+        disk[offset - offset % 512 + 511] = 0xaa;
+    }
+
+    #[test]
+    fn test_mbr() {
+        let mut disk = vec![0; 32 * 512];
+        mbr_entry(&mut disk, 446, 0x07, 2, 4);
+        // An extended partition at sector 8, with one logical partition.
+        mbr_entry(&mut disk, 462, 0x05, 8, 16);
+        mbr_entry(&mut disk, 8 * 512 + 446, 0x07, 2, 6);
+
+        let table = PartitionTable::read(&mut Cursor::new(disk))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(table.scheme, PartitionScheme::Mbr);
+        assert_eq!(
+            table
+                .partitions
+                .iter()
+                .map(|p| (p.number, p.offset, p.size))
+                .collect::<Vec<_>>(),
+            vec![(1, 2 * 512, 4 * 512), (5, 10 * 512, 6 * 512)]
+        );
+        assert!(table.partitions[0].partition_type.is_ntfs());
+    }
+
+    #[test]
+    fn test_gpt() {
+        let mut disk = vec![0; 40 * 512];
+        mbr_entry(&mut disk, 446, 0xee, 1, 39);
+
+        disk[512..520].copy_from_slice(b"EFI PART");
+        disk[512 + 72..512 + 80].copy_from_slice(&2_u64.to_le_bytes());
+        disk[512 + 80..512 + 84].copy_from_slice(&4_u32.to_le_bytes());
+        disk[512 + 84..512 + 88].copy_from_slice(&128_u32.to_le_bytes());
+
+        let entry = 2 * 512;
+        disk[entry..entry + 16].copy_from_slice(&GPT_BASIC_DATA);
+        disk[entry + 32..entry + 40].copy_from_slice(&34_u64.to_le_bytes());
+        disk[entry + 40..entry + 48].copy_from_slice(&39_u64.to_le_bytes());
+        disk[entry + 56..entry + 62].copy_from_slice(b"O\x00S\x00\x00\x00");
+
+        let table = PartitionTable::read(&mut Cursor::new(&disk))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(table.scheme, PartitionScheme::Gpt);
+        assert_eq!(
+            table.partitions,
+            vec![Partition {
+                number: 1,
+                offset: 34 * 512,
+                size: 6 * 512,
+                partition_type: PartitionType::Gpt(GPT_BASIC_DATA),
+                name: Some("OS".to_owned()),
+            }]
+        );
+        assert_eq!(
+            table.partitions[0].partition_type.to_string(),
+            "ebd0a0a2-b9e5-4433-87c0-68b6b72699c7"
+        );
+
+        // A partition ending at the last LBA there is.
+        disk[entry + 40..entry + 48].copy_from_slice(&u64::max_value().to_le_bytes());
+        assert!(PartitionTable::read(&mut Cursor::new(&disk)).is_err());
+    }
+
+    #[test]
+    fn test_volume_image_has_no_table() {
+        let mut file = File::open(sample_volume_path()).unwrap();
+
+        assert_eq!(PartitionTable::read(&mut file).unwrap(), None);
+    }
+}
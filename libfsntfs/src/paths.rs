@@ -0,0 +1,341 @@
+//! Reconstructs full paths of MFT entries from their parent references.
+use crate::entry_cache::CachedEntry;
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::instrument::Metric;
+use crate::memory::Charge;
+use crate::metadata_files;
+use crate::mft_record::FILE_NAME_NAMESPACE_DOS;
+use crate::reference::ReferenceState;
+use crate::utils::{mft_entry_index, sequence_number};
+use crate::volume::{MftEntryIndex, Volume};
+use std::collections::HashMap;
+
+/// The path orphans are placed under, see `OrphanPolicy`.
+fn orphan_root(volume: &Volume) -> String {
+    volume.options().get_orphan_policy().root().to_owned()
+}
+
+/// Directories nested deeper than this are treated as a loop.
+const MAX_PATH_DEPTH: usize = 512;
+
+/// What a cached directory costs besides its path (or name), in bytes.
+const CACHED_ENTRY_OVERHEAD: usize = 64;
+
+/// A resolved directory, with what is needed to check references to it.
+#[derive(Debug, Clone)]
+struct Directory {
+    sequence_number: u16,
+    allocated: bool,
+    path: String,
+}
+
+impl Directory {
+    fn cost(&self) -> usize {
+        CACHED_ENTRY_OVERHEAD + self.path.len()
+    }
+
+    fn is_referenced_by(&self, file_reference: u64) -> bool {
+        !ReferenceState::new(
+            sequence_number(file_reference),
+            self.sequence_number,
+            self.allocated,
+        )
+        .is_stale()
+    }
+}
+
+/// A directory which was seen but not resolved yet, see `PathResolver::add_directory`.
+#[derive(Debug, Clone)]
+struct DirectoryLink {
+    name: String,
+    parent_file_reference: u64,
+    sequence_number: u16,
+    allocated: bool,
+}
+
+impl DirectoryLink {
+    fn cost(&self) -> usize {
+        CACHED_ENTRY_OVERHEAD + self.name.len()
+    }
+
+    fn read(entry: &FileEntry) -> Result<Self, Error> {
+        Ok(DirectoryLink {
+            name: entry.get_name()?,
+            parent_file_reference: entry.get_parent_file_reference()?,
+            sequence_number: sequence_number(entry.get_file_reference()?),
+            allocated: entry.is_allocated()?,
+        })
+    }
+
+    fn from_cached(entry: CachedEntry) -> Self {
+        DirectoryLink {
+            name: entry.name,
+            parent_file_reference: entry.parent_file_reference,
+            sequence_number: sequence_number(entry.file_reference),
+            allocated: entry.allocated,
+        }
+    }
+}
+
+/// The directory at `idx` as resolved by any resolver of the volume, from its `EntryCache`.
+fn shared_directory(volume: &Volume, idx: MftEntryIndex) -> Option<Directory> {
+    let entry = volume.options().get_entry_cache()?.get(idx)?;
+
+    Some(Directory {
+        sequence_number: sequence_number(entry.file_reference),
+        allocated: entry.allocated,
+        path: entry.path?,
+    })
+}
+
+/// Remembers the full paths of the directories it resolved, so walking the MFT in order
+/// reads every directory at most once. Directories added up front are not read at all.
+///
+/// Parent references are checked against the sequence numbers of the directories,
+/// files whose parent entry was reused are orphans, placed under the root of the `OrphanPolicy`
+/// the volume was opened with.
+///
+/// What is remembered counts against the `MemoryBudget` the volume was opened with, once it
+/// is used up directories are read again instead.
+#[derive(Debug, Clone, Default)]
+pub struct PathResolver {
+    directories: HashMap<MftEntryIndex, Directory>,
+    links: HashMap<MftEntryIndex, DirectoryLink>,
+    charge: Charge,
+}
+
+impl PathResolver {
+    pub fn new() -> Self {
+        PathResolver::default()
+    }
+
+    /// Remembers the name and parent of `entry` (the entry at `idx`) if it is a directory,
+    /// so resolving paths below it later on does not need to read it again.
+    /// Batches add all their entries first, and then resolve the paths of the batch.
+    pub fn add_directory(&mut self, idx: MftEntryIndex, entry: &FileEntry) {
+        if self.directories.contains_key(&idx)
+            || !entry.has_directory_entries_index().unwrap_or(false)
+        {
+            return;
+        }
+
+        if let Ok(link) = DirectoryLink::read(entry) {
+            if let Some(previous) = self.links.remove(&idx) {
+                self.charge.release(previous.cost());
+            }
+
+            if self.charge(entry.volume()).try_reserve(link.cost()) {
+                self.links.insert(idx, link);
+            }
+        }
+    }
+
+    /// The charge of the resolver, against the budget of the first volume it is used with.
+    fn charge(&mut self, volume: &Volume) -> &mut Charge {
+        if !self.charge.has_budget() {
+            if let Some(budget) = volume.options().get_memory_budget() {
+                self.charge = Charge::new(Some(budget.clone()));
+            }
+        }
+
+        &mut self.charge
+    }
+
+    fn remember(&mut self, volume: &Volume, idx: MftEntryIndex, directory: Directory) {
+        if let Some(cache) = volume.options().get_entry_cache() {
+            let file_reference = idx | u64::from(directory.sequence_number) << 48;
+            cache.set_path(file_reference, &directory.path);
+        }
+
+        if let Some(previous) = self.directories.remove(&idx) {
+            self.charge.release(previous.cost());
+        }
+
+        if self.charge(volume).try_reserve(directory.cost()) {
+            self.directories.insert(idx, directory);
+        }
+    }
+
+    /// The full path of the entry at `idx`, given its name and parent reference.
+    pub fn path_of(
+        &mut self,
+        volume: &Volume,
+        idx: MftEntryIndex,
+        name: &str,
+        parent_file_reference: Option<u64>,
+    ) -> String {
+        if idx == metadata_files::ROOT_DIRECTORY {
+            return "\\".to_owned();
+        }
+
+        let parent_path = match parent_file_reference {
+            Some(parent) => self.directory_path(volume, parent),
+            None => orphan_root(volume),
+        };
+
+        format!("{}\\{}", parent_path, name)
+    }
+
+    /// The full path of `entry`, the entry at `idx` of the MFT.
+    pub fn full_path(&mut self, idx: MftEntryIndex, entry: &FileEntry) -> String {
+        if idx == metadata_files::ROOT_DIRECTORY {
+            return "\\".to_owned();
+        }
+
+        let full_path = self.path_of(
+            entry.volume(),
+            idx,
+            &entry.get_name().unwrap_or_default(),
+            entry.get_parent_file_reference().ok(),
+        );
+
+        if entry.has_directory_entries_index().unwrap_or(false) {
+            if let (Ok(file_reference), Ok(allocated)) =
+                (entry.get_file_reference(), entry.is_allocated())
+            {
+                self.remember(
+                    entry.volume(),
+                    idx,
+                    Directory {
+                        sequence_number: sequence_number(file_reference),
+                        allocated,
+                        path: full_path.clone(),
+                    },
+                );
+            }
+        }
+
+        full_path
+    }
+
+    /// Every path of `entry`, one per hard link, without the DOS names of the links.
+    pub fn link_paths(&mut self, entry: &FileEntry) -> Result<Vec<String>, Error> {
+        // libfsntfs does not give the namespace of the names, names in extension records are
+        // not found in the base record and are kept.
+        let record_file_names = entry.record_file_names()?;
+        let mut paths = vec![];
+
+        for file_name in entry.get_file_names()? {
+            let is_dos_name = record_file_names.iter().any(|record_file_name| {
+                record_file_name.namespace == FILE_NAME_NAMESPACE_DOS
+                    && record_file_name.parent_file_reference == file_name.parent_file_reference
+                    && record_file_name.name == file_name.name
+            });
+
+            if is_dos_name {
+                continue;
+            }
+
+            let parent_path = self.directory_path(entry.volume(), file_name.parent_file_reference);
+            paths.push(format!("{}\\{}", parent_path, file_name.name));
+        }
+
+        Ok(paths)
+    }
+
+    /// Resolves the full path of the directory `file_reference` points to by walking up its
+    /// parents, remembering every directory on the way. The sequence number of a bare MFT entry
+    /// index is 0, which is not checked.
+    pub fn directory_path(&mut self, volume: &Volume, file_reference: u64) -> String {
+        let mut chain = vec![];
+        let mut current = file_reference;
+
+        let mut path = loop {
+            let idx = mft_entry_index(current);
+
+            if idx == metadata_files::ROOT_DIRECTORY {
+                break String::new();
+            }
+
+            if let Some(directory) = self.directories.get(&idx) {
+                volume.options().record(Metric::CacheHit("paths"));
+
+                if directory.is_referenced_by(current) {
+                    break directory.path.clone();
+                }
+
+                break orphan_root(volume);
+            }
+
+            if let Some(directory) = shared_directory(volume, idx) {
+                volume.options().record(Metric::CacheHit("paths"));
+
+                if directory.is_referenced_by(current) {
+                    break directory.path;
+                }
+
+                break orphan_root(volume);
+            }
+
+            if chain.len() >= MAX_PATH_DEPTH || chain.iter().any(|(i, _, _)| *i == idx) {
+                break orphan_root(volume);
+            }
+
+            let parent = match self.links.remove(&idx) {
+                Some(link) => {
+                    self.charge.release(link.cost());
+                    Ok(link)
+                }
+                None => {
+                    volume.options().record(Metric::CacheMiss("paths"));
+                    volume.get_cached_entry(idx).map(DirectoryLink::from_cached)
+                }
+            };
+
+            match parent {
+                Ok(link) => {
+                    let directory = Directory {
+                        sequence_number: link.sequence_number,
+                        allocated: link.allocated,
+                        path: String::new(),
+                    };
+
+                    if !directory.is_referenced_by(current) {
+                        break orphan_root(volume);
+                    }
+
+                    chain.push((idx, link.name, directory));
+                    current = link.parent_file_reference;
+                }
+                Err(_) => break orphan_root(volume),
+            }
+        };
+
+        for (directory_idx, name, mut directory) in chain.into_iter().rev() {
+            path = format!("{}\\{}", path, name);
+            directory.path = path.clone();
+            self.remember(volume, directory_idx, directory);
+        }
+
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_directory_path() {
+        let volume = sample_volume().unwrap();
+        let mut resolver = PathResolver::new();
+
+        let extend = volume.get_file_entry_by_path("\\$Extend").unwrap();
+        let idx = mft_entry_index(extend.get_file_reference().unwrap());
+
+        assert_eq!(resolver.directory_path(&volume, idx), "\\$Extend");
+
+        let mut batch_resolver = PathResolver::new();
+        batch_resolver.add_directory(idx, &extend);
+        assert_eq!(
+            batch_resolver.path_of(&volume, 42, "$Reparse", Some(idx)),
+            "\\$Extend\\$Reparse"
+        );
+        assert_eq!(
+            resolver.directory_path(&volume, metadata_files::ROOT_DIRECTORY),
+            ""
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Reads QCOW (version 1, 2 and 3) images with libqcow.
+//!
+//! Images with a backing file are opened together with it, which is looked up by the path
+//! stored in the image (see `utils::parent_image_path`).
+//!
+//! ```ignore
+//! let disk = QcowImage::open("overlay.qcow2")?;
+//! let volume = SharedVolume::from_reader(disk, &VolumeOpenOptions::new())?;
+//! ```
+use crate::error::Error;
+use crate::ffi_error::to_io_error;
+use crate::utils::parent_image_path;
+use libcerror_rs::CError;
+use libfsntfs_sys::{SEEK_CUR, SEEK_END, SEEK_SET};
+use libqcow_sys::{
+    libqcow_file_close, libqcow_file_free, libqcow_file_get_media_size,
+    libqcow_file_get_utf8_backing_filename, libqcow_file_get_utf8_backing_filename_size,
+    libqcow_file_initialize, libqcow_file_open, libqcow_file_read_buffer, libqcow_file_seek_offset,
+    libqcow_file_set_parent_file, size_t, FilePtr, LIBQCOW_ACCESS_FLAGS_LIBQCOW_ACCESS_FLAG_READ,
+};
+use log::error;
+use std::ffi::CString;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::raw::{c_int, c_void};
+use std::path::Path;
+use std::ptr;
+
+/// Backing file chains longer than this are assumed to loop.
+const MAX_PARENT_DEPTH: usize = 64;
+
+/// A QCOW image, reads return the contents of the virtual disk.
+pub struct QcowImage {
+    ptr: FilePtr,
+    /// Whether the file was opened, and has to be closed.
+    opened: bool,
+    // The backing file, read from by this image, so freed after it (`Drop::drop` runs before fields are dropped).
+    parent: Option<Box<QcowImage>>,
+}
+
+// The image and its backing files are owned by this value alone.
+unsafe impl Send for QcowImage {}
+
+impl QcowImage {
+    /// Opens the image at `path`, and its backing files.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        QcowImage::open_chain(path.as_ref(), 0)
+    }
+
+    fn open_chain(path: &Path, depth: usize) -> Result<Self, Error> {
+        if depth > MAX_PARENT_DEPTH {
+            return Err(Error::Other(format!(
+                "more than {} backing files, the chain of {} loops",
+                MAX_PARENT_DEPTH,
+                path.display()
+            )));
+        }
+
+        let mut handle = FilePtr::null();
+
+        let filename =
+            CString::new(path.to_string_lossy().into_owned()).map_err(Error::StringContainsNul)?;
+
+        try_ffi!(libqcow_file_initialize(handle.as_out_ptr()));
+
+        let mut image = QcowImage {
+            ptr: handle,
+            opened: false,
+            parent: None,
+        };
+
+        try_ffi!(libqcow_file_open(
+            image.ptr.as_ptr(),
+            filename.as_ptr(),
+            LIBQCOW_ACCESS_FLAGS_LIBQCOW_ACCESS_FLAG_READ as c_int,
+        ));
+        image.opened = true;
+
+        if let Some(backing_filename) = image.get_backing_filename()? {
+            let parent =
+                QcowImage::open_chain(&parent_image_path(path, &backing_filename), depth + 1)?;
+
+            try_ffi!(libqcow_file_set_parent_file(
+                image.ptr.as_ptr(),
+                parent.ptr.as_ptr(),
+            ));
+
+            image.parent = Some(Box::new(parent));
+        }
+
+        Ok(image)
+    }
+
+    /// Size of the virtual disk, in bytes.
+    pub fn get_media_size(&self) -> Result<u64, Error> {
+        let mut size = 0;
+
+        try_ffi!(libqcow_file_get_media_size(self.ptr.as_ptr(), &mut size));
+
+        Ok(size)
+    }
+
+    /// The path of the backing file, as stored in the image.
+    pub fn get_backing_filename(&self) -> Result<Option<String>, Error> {
+        let mut size: size_t = 0;
+        let mut error = ptr::null_mut();
+
+        match unsafe {
+            libqcow_file_get_utf8_backing_filename_size(self.ptr.as_ptr(), &mut size, &mut error)
+        } {
+            1 => {}
+            0 => return Ok(None),
+            _ => return Err(Error::from(unsafe { CError::from_raw(error) })),
+        }
+
+        if size == 0 {
+            return Ok(Some(String::new()));
+        }
+
+        let mut filename = vec![0; size as usize];
+
+        try_ffi!(libqcow_file_get_utf8_backing_filename(
+            self.ptr.as_ptr(),
+            filename.as_mut_ptr(),
+            size,
+        ));
+
+        // Without the nul terminator.
+        filename.pop();
+
+        String::from_utf8(filename)
+            .map(Some)
+            .map_err(Error::StringContainsInvalidUTF8)
+    }
+
+    /// The backing file, with the contents this image does not overwrite.
+    pub fn parent(&self) -> Option<&QcowImage> {
+        self.parent.as_ref().map(|parent| &**parent)
+    }
+}
+
+impl Read for QcowImage {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut error = ptr::null_mut();
+
+        let read_count = unsafe {
+            libqcow_file_read_buffer(
+                self.ptr.as_ptr(),
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len() as size_t,
+                &mut error,
+            )
+        };
+
+        if read_count <= -1 {
+            Err(to_io_error(error))
+        } else {
+            Ok(read_count as usize)
+        }
+    }
+}
+
+impl Seek for QcowImage {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset as i64, SEEK_SET as c_int),
+            SeekFrom::Current(offset) => (offset, SEEK_CUR as c_int),
+            SeekFrom::End(offset) => (offset, SEEK_END as c_int),
+        };
+
+        let mut error = ptr::null_mut();
+
+        let position =
+            unsafe { libqcow_file_seek_offset(self.ptr.as_ptr(), offset, whence, &mut error) };
+
+        if position <= -1 {
+            Err(to_io_error(error))
+        } else {
+            Ok(position as u64)
+        }
+    }
+}
+
+impl Drop for QcowImage {
+    fn drop(&mut self) {
+        let mut error = ptr::null_mut();
+
+        if self.opened && unsafe { libqcow_file_close(self.ptr.as_ptr(), &mut error) } != 0 {
+            error!("`libqcow_file_close` failed!");
+        }
+
+        if unsafe { libqcow_file_free(self.ptr.as_out_ptr(), &mut error) } != 1 {
+            error!("`libqcow_file_free` failed!");
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! A fluent API over the MFT, e.g.
+//!
+//! ```ignore
+//! let matches = volume
+//!     .query()
+//!     .modified_after(Utc.ymd(2019, 1, 1).and_hms(0, 0, 0))
+//!     .extension("exe")
+//!     .has_ads()
+//!     .deleted(false)
+//!     .run()?;
+//! ```
+use crate::attribute::AttributeType;
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::filter::EntryFilter;
+use crate::flags::FileAttributeFlags;
+use crate::paths::PathResolver;
+use crate::timestamps::MacbField;
+use crate::volume::{MftEntries, MftEntryIndex, Volume};
+use crate::walk::PathMatcher;
+use chrono::{DateTime, Utc};
+
+/// Conditions are combined with AND, except for `extension` and `path` which match any of
+/// the values given. Timestamps are the `$STANDARD_INFORMATION` ones.
+#[derive(Clone)]
+pub struct Query<'a> {
+    volume: &'a Volume,
+    filter: EntryFilter,
+    extensions: Vec<String>,
+    paths: Vec<PathMatcher>,
+    deleted: Option<bool>,
+}
+
+impl<'a> Query<'a> {
+    pub fn new(volume: &'a Volume) -> Self {
+        Query {
+            volume,
+            filter: EntryFilter::default(),
+            extensions: vec![],
+            paths: vec![],
+            deleted: None,
+        }
+    }
+
+    fn time_range(
+        mut self,
+        field: MacbField,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.filter = self
+            .filter
+            .time_range(AttributeType::StandardInformation, field, after, before);
+        self
+    }
+
+    pub fn modified_after(self, datetime: DateTime<Utc>) -> Self {
+        self.time_range(MacbField::Modification, Some(datetime), None)
+    }
+
+    pub fn modified_before(self, datetime: DateTime<Utc>) -> Self {
+        self.time_range(MacbField::Modification, None, Some(datetime))
+    }
+
+    pub fn created_after(self, datetime: DateTime<Utc>) -> Self {
+        self.time_range(MacbField::Creation, Some(datetime), None)
+    }
+
+    pub fn created_before(self, datetime: DateTime<Utc>) -> Self {
+        self.time_range(MacbField::Creation, None, Some(datetime))
+    }
+
+    pub fn accessed_after(self, datetime: DateTime<Utc>) -> Self {
+        self.time_range(MacbField::Access, Some(datetime), None)
+    }
+
+    pub fn accessed_before(self, datetime: DateTime<Utc>) -> Self {
+        self.time_range(MacbField::Access, None, Some(datetime))
+    }
+
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        self.filter = self.filter.min_size(min_size);
+        self
+    }
+
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.filter = self.filter.max_size(max_size);
+        self
+    }
+
+    pub fn with_flags(mut self, flags: FileAttributeFlags) -> Self {
+        self.filter = self.filter.with_flags(flags);
+        self
+    }
+
+    pub fn without_flags(mut self, flags: FileAttributeFlags) -> Self {
+        self.filter = self.filter.without_flags(flags);
+        self
+    }
+
+    /// Adds to the conditions of `filter`. A `has_ads` condition of `filter` replaces the one of
+    /// the query, like calling `has_ads` again does.
+    pub fn filter(mut self, filter: EntryFilter) -> Self {
+        self.filter.time_ranges.extend(filter.time_ranges);
+        self.filter.min_size = self.filter.min_size.max(filter.min_size);
+        self.filter.max_size = match (self.filter.max_size, filter.max_size) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.filter.flags_set |= filter.flags_set;
+        self.filter.flags_clear |= filter.flags_clear;
+        self.filter.has_ads = filter.has_ads.or(self.filter.has_ads);
+        self
+    }
+
+    /// The extension of the name, without the dot and ignoring case.
+    pub fn extension(mut self, extension: impl AsRef<str>) -> Self {
+        self.extensions.push(
+            extension
+                .as_ref()
+                .trim_start_matches('.')
+                .to_lowercase(),
+        );
+        self
+    }
+
+    pub fn path(mut self, matcher: PathMatcher) -> Self {
+        self.paths.push(matcher);
+        self
+    }
+
+    pub fn has_ads(mut self) -> Self {
+        self.filter = self.filter.has_ads(true);
+        self
+    }
+
+    /// `true` for entries which are not allocated anymore, `false` for the ones in use.
+    pub fn deleted(mut self, deleted: bool) -> Self {
+        self.deleted = Some(deleted);
+        self
+    }
+
+    pub fn run(self) -> Result<QueryResults<'a>, Error> {
+        Ok(QueryResults {
+            entries: self.volume.mft_entries()?,
+            resolver: PathResolver::new(),
+            query: self,
+        })
+    }
+
+    fn matches_extension(&self, name: &str) -> bool {
+        if self.extensions.is_empty() {
+            return true;
+        }
+
+        match name.rfind('.') {
+            Some(dot) => {
+                let extension = name[dot + 1..].to_lowercase();
+                self.extensions.iter().any(|e| *e == extension)
+            }
+            None => false,
+        }
+    }
+
+    /// Cheap conditions first, the path is only reconstructed for entries matching the rest.
+    fn matches(
+        &self,
+        idx: MftEntryIndex,
+        entry: &FileEntry,
+        resolver: &mut PathResolver,
+    ) -> Result<Option<String>, Error> {
+        if let Some(deleted) = self.deleted {
+            if entry.is_allocated()? == deleted {
+                return Ok(None);
+            }
+        }
+
+        if !self.filter.matches(entry)? {
+            return Ok(None);
+        }
+
+        if !self.extensions.is_empty() && !self.matches_extension(&entry.get_name()?) {
+            return Ok(None);
+        }
+
+        let path = resolver.full_path(idx, entry);
+
+        if !self.paths.is_empty() && !self.paths.iter().any(|matcher| matcher.matches(&path)) {
+            return Ok(None);
+        }
+
+        Ok(Some(path))
+    }
+}
+
+#[derive(Debug)]
+pub struct QueryMatch<'a> {
+    pub mft_entry_index: MftEntryIndex,
+    pub path: String,
+    pub entry: FileEntry<'a>,
+}
+
+/// The entries matching a query, in MFT order. Entries which cannot be read are skipped.
+pub struct QueryResults<'a> {
+    query: Query<'a>,
+    entries: MftEntries<'a>,
+    resolver: PathResolver,
+}
+
+impl<'a> Iterator for QueryResults<'a> {
+    type Item = QueryMatch<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (idx, entry) = self.entries.next()?;
+
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+
+            if let Ok(Some(path)) = self.query.matches(idx, &entry, &mut self.resolver) {
+                return Some(QueryMatch {
+                    mft_entry_index: idx,
+                    path,
+                    entry,
+                });
+            }
+        }
+    }
+}
+
+impl Volume {
+    /// Starts a query over the MFT entries of the volume, see `Query`.
+    pub fn query(&self) -> Query {
+        Query::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_matches_extension() {
+        let volume = sample_volume().unwrap();
+        let query = volume.query().extension(".EXE").extension("dll");
+
+        assert!(query.matches_extension("cmd.exe"));
+        assert!(query.matches_extension("KERNEL32.DLL"));
+        assert!(!query.matches_extension("exe"));
+        assert!(!query.matches_extension("notes.txt"));
+    }
+
+    #[test]
+    fn test_query() {
+        let volume = sample_volume().unwrap();
+
+        let paths: Vec<String> = volume
+            .query()
+            .path(PathMatcher::prefix("\\$Extend"))
+            .deleted(false)
+            .run()
+            .unwrap()
+            .map(|m| m.path)
+            .collect();
+
+        assert!(paths.iter().any(|path| path == "\\$Extend\\$Reparse"));
+        assert!(paths.iter().all(|path| path.starts_with("\\$Extend")));
+    }
+
+    #[test]
+    fn test_query_with_filter() {
+        let volume = sample_volume().unwrap();
+
+        let paths: Vec<String> = volume
+            .query()
+            .filter(EntryFilter::new().has_ads(true))
+            .run()
+            .unwrap()
+            .map(|m| m.path)
+            .collect();
+
+        assert!(paths.iter().any(|path| path == "\\$Secure"));
+        assert!(!paths.iter().any(|path| path == "\\$MFT"));
+    }
+}
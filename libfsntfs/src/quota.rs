@@ -0,0 +1,125 @@
+//! Per-user disk quotas from the `$Q` index of `$Extend\$Quota`.
+use crate::error::Error;
+use crate::index::Index;
+use crate::mft_record::{read_u32, read_u64, MftRecord};
+use crate::security_descriptor::Sid;
+use crate::utils::{datetime_from_filetime, mft_entry_index};
+use crate::volume::Volume;
+use chrono::{DateTime, Utc};
+use std::io::{Read, Seek};
+
+pub const QUOTA_PATH: &str = "\\$Extend\\$Quota";
+/// The index of quota entries by owner id.
+pub const QUOTA_INDEX_NAME: &str = "$Q";
+
+/// The entry with this owner id holds the defaults for new users, it has no SID.
+pub const DEFAULT_QUOTA_OWNER_ID: u32 = 1;
+
+const QUOTA_DATA_SID_OFFSET: usize = 0x30;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaEntry {
+    /// The owner id, as stored in `$STANDARD_INFORMATION` of the files of the owner.
+    pub owner_id: u32,
+    pub version: u32,
+    pub flags: u32,
+    /// Bytes charged to the owner.
+    pub bytes_used: u64,
+    /// FILETIME of the last change of the entry.
+    pub change_time: u64,
+    /// The threshold at which a warning is logged.
+    pub warning_limit: u64,
+    pub hard_limit: u64,
+    /// FILETIME of when the threshold was exceeded, 0 if it was not.
+    pub exceeded_time: u64,
+    /// `None` for the default quota entry.
+    pub sid: Option<Sid>,
+}
+
+impl QuotaEntry {
+    /// Parses an entry of the `$Q` index (the key is the owner id).
+    pub fn parse(key: &[u8], data: &[u8]) -> Result<Self, Error> {
+        let sid = match data.get(QUOTA_DATA_SID_OFFSET..) {
+            Some(sid) if !sid.is_empty() => Some(Sid::parse(sid)?),
+            _ => None,
+        };
+
+        Ok(QuotaEntry {
+            owner_id: read_u32(key, 0x00)?,
+            version: read_u32(data, 0x00)?,
+            flags: read_u32(data, 0x04)?,
+            bytes_used: read_u64(data, 0x08)?,
+            change_time: read_u64(data, 0x10)?,
+            warning_limit: read_u64(data, 0x18)?,
+            hard_limit: read_u64(data, 0x20)?,
+            exceeded_time: read_u64(data, 0x28)?,
+            sid,
+        })
+    }
+
+    pub fn get_change_time(&self) -> Option<DateTime<Utc>> {
+        match self.change_time {
+            0 => None,
+            filetime => Some(datetime_from_filetime(filetime)),
+        }
+    }
+
+    pub fn get_exceeded_time(&self) -> Option<DateTime<Utc>> {
+        match self.exceeded_time {
+            0 => None,
+            filetime => Some(datetime_from_filetime(filetime)),
+        }
+    }
+}
+
+/// Reads all quota entries of the volume, `image` is the raw volume.
+pub(crate) fn read_quota_entries<R: Read + Seek>(
+    volume: &Volume,
+    image: &mut R,
+) -> Result<Vec<QuotaEntry>, Error> {
+    let quota = volume.get_file_entry_by_path(QUOTA_PATH)?;
+    let record = MftRecord::read(volume, mft_entry_index(quota.get_file_reference()?))?;
+
+    Index::read(volume, &record, QUOTA_INDEX_NAME, image)?
+        .entries
+        .iter()
+        .map(|entry| QuotaEntry::parse(&entry.key, &entry.data))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quota_entry() {
+        let mut data = vec![];
+        data.extend_from_slice(&2_u32.to_le_bytes());
+        data.extend_from_slice(&0x1_u32.to_le_bytes());
+        data.extend_from_slice(&4096_u64.to_le_bytes());
+        data.extend_from_slice(&131_907_744_001_234_567_u64.to_le_bytes());
+        data.extend_from_slice(&u64::max_value().to_le_bytes());
+        data.extend_from_slice(&u64::max_value().to_le_bytes());
+        data.extend_from_slice(&0_u64.to_le_bytes());
+        // S-1-5-18
+        data.extend_from_slice(&[1, 1, 0, 0, 0, 0, 0, 5, 18, 0, 0, 0]);
+
+        let entry = QuotaEntry::parse(&0x100_u32.to_le_bytes(), &data).unwrap();
+
+        assert_eq!(entry.owner_id, 0x100);
+        assert_eq!(entry.bytes_used, 4096);
+        assert_eq!(entry.sid.unwrap().to_string(), "S-1-5-18");
+        assert!(entry.get_change_time().is_some());
+        assert!(entry.get_exceeded_time().is_none());
+    }
+
+    #[test]
+    fn test_default_quota_entry_has_no_sid() {
+        let data = vec![0; QUOTA_DATA_SID_OFFSET];
+
+        let entry =
+            QuotaEntry::parse(&DEFAULT_QUOTA_OWNER_ID.to_le_bytes(), &data).unwrap();
+
+        assert!(entry.sid.is_none());
+    }
+}
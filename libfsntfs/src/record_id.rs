@@ -0,0 +1,113 @@
+//! Stable identifiers of the rows of the exports, for databases which upsert the rows of an
+//! image exported again.
+//!
+//! Exports (`export_mft_csv`, `record_batches`, `export_parquet`) write their rows in
+//! ascending MFT entry index order, so exporting the same image twice (with the same filter)
+//! gives the same rows, in the same order, with the same IDs.
+//!
+//! A `RecordId` is the file reference of an entry and the index of one of its streams:
+//! - 0 for the entry itself and its default data stream,
+//! - `n` for the `n`-th alternate data stream, in the order libfsntfs lists them (the order of
+//!   the `$DATA` attributes in the MFT record).
+//!
+//! The exports write a row per entry, so the IDs of their rows always have stream index 0; the
+//! other stream indices are for consumers which list the streams of an entry themselves.
+//!
+//! The sequence number is part of the ID, so an MFT entry which was reused for another file
+//! gets new IDs. IDs are written as `<entry number>-<sequence number>:<stream index>`,
+//! e.g. `37-2:0`.
+use crate::error::Error;
+use crate::utils::{mft_entry_index, sequence_number};
+use crate::volume::MftEntryIndex;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Ordered like the rows of the exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RecordId {
+    pub entry_number: MftEntryIndex,
+    pub sequence_number: u16,
+    pub stream_index: u32,
+}
+
+impl RecordId {
+    pub fn new(file_reference: u64, stream_index: u32) -> Self {
+        RecordId {
+            entry_number: mft_entry_index(file_reference),
+            sequence_number: sequence_number(file_reference),
+            stream_index,
+        }
+    }
+
+    /// The ID of the entry itself (and its default data stream).
+    pub fn for_entry(file_reference: u64) -> Self {
+        RecordId::new(file_reference, 0)
+    }
+
+    pub fn file_reference(&self) -> u64 {
+        self.entry_number | u64::from(self.sequence_number) << 48
+    }
+}
+
+impl Display for RecordId {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}:{}",
+            self.entry_number, self.sequence_number, self.stream_index
+        )
+    }
+}
+
+impl FromStr for RecordId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidRecordId(s.to_owned());
+
+        let mut parts = s.splitn(2, ':');
+        let file_reference = parts.next().ok_or_else(invalid)?;
+        let stream_index = parts.next().ok_or_else(invalid)?;
+
+        let mut parts = file_reference.splitn(2, '-');
+        let entry_number: MftEntryIndex = parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(invalid)?;
+        let sequence_number = parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(invalid)?;
+
+        if entry_number > mft_entry_index(u64::max_value()) {
+            return Err(invalid());
+        }
+
+        Ok(RecordId {
+            entry_number,
+            sequence_number,
+            stream_index: stream_index.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_id() {
+        let id = RecordId::new(0x0002_0000_0000_0025, 1);
+
+        assert_eq!(id.to_string(), "37-2:1");
+        assert_eq!("37-2:1".parse::<RecordId>().unwrap(), id);
+        assert_eq!(id.file_reference(), 0x0002_0000_0000_0025);
+
+        assert!(RecordId::for_entry(0x0002_0000_0000_0025) < id);
+        assert!(id < RecordId::for_entry(0x0001_0000_0000_0026));
+
+        assert!("37-2".parse::<RecordId>().is_err());
+        assert!("37:1".parse::<RecordId>().is_err());
+        assert!("37-70000:1".parse::<RecordId>().is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Opening damaged volumes from the copies NTFS keeps of its critical structures: the backup
+//! boot sector (see `boot`) and `$MFTMirr`, which mirrors the first records of `$MFT`.
+//!
+//! ```ignore
+//! let options = VolumeOpenOptions::new()
+//!     .boot_sector_fallback(true)
+//!     .mft_mirror_fallback(true);
+//! let volume = Volume::open_with_options("damaged.dd", &options)?;
+//!
+//! for idx in &volume.get_recovery().mirrored_records {
+//!     println!("MFT entry {} was read from $MFTMirr", idx);
+//! }
+//! ```
+use crate::boot::{BootSector, BootSectorSource};
+use crate::error::Error;
+use crate::mft_record::RecordHealth;
+use crate::volume::MftEntryIndex;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Records of `$MFT` which `$MFTMirr` holds copies of: `$MFT`, `$MFTMirr`, `$LogFile` and
+/// `$Volume`.
+pub const MFT_MIRROR_RECORDS: MftEntryIndex = 4;
+
+/// What the volume was opened from, besides the structures in their usual place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovery {
+    pub boot_sector: BootSectorSource,
+    /// MFT entries whose records were damaged and read from `$MFTMirr` instead.
+    pub mirrored_records: Vec<MftEntryIndex>,
+}
+
+impl Default for Recovery {
+    fn default() -> Self {
+        Recovery {
+            boot_sector: BootSectorSource::Primary,
+            mirrored_records: vec![],
+        }
+    }
+}
+
+impl Recovery {
+    pub fn is_recovered(&self) -> bool {
+        self.boot_sector != BootSectorSource::Primary || !self.mirrored_records.is_empty()
+    }
+}
+
+/// A volume with the damaged records among the first `MFT_MIRROR_RECORDS` of `$MFT` replaced
+/// by their intact copies in `$MFTMirr`, so libfsntfs can open it.
+pub struct MftMirrorFallback<R> {
+    inner: R,
+    /// The records read from the mirror, by their offset in the volume.
+    patches: Vec<(u64, Vec<u8>)>,
+    mirrored_records: Vec<MftEntryIndex>,
+    position: u64,
+}
+
+impl<R: Read + Seek> MftMirrorFallback<R> {
+    /// Finds the records from the boot sector of `inner`, which must be intact (see
+    /// `boot::BootSectorFallback`).
+    pub fn new(mut inner: R) -> Result<Self, Error> {
+        let geometry = BootSector::read_primary(&mut inner)?.geometry;
+        let entry_size = u64::from(geometry.mft_entry_size);
+
+        let mut patches = vec![];
+        let mut mirrored_records = vec![];
+
+        for idx in 0..MFT_MIRROR_RECORDS {
+            let offset = geometry.mft_offset() + idx * entry_size;
+            let record = read_record(&mut inner, offset, entry_size)?;
+
+            if RecordHealth::check(&record) == RecordHealth::Intact {
+                continue;
+            }
+
+            let mirror = read_record(
+                &mut inner,
+                geometry.mft_mirror_offset() + idx * entry_size,
+                entry_size,
+            )?;
+
+            if RecordHealth::check(&mirror) == RecordHealth::Intact {
+                patches.push((offset, mirror));
+                mirrored_records.push(idx);
+            }
+        }
+
+        inner.seek(SeekFrom::Start(0))?;
+
+        Ok(MftMirrorFallback {
+            inner,
+            patches,
+            mirrored_records,
+            position: 0,
+        })
+    }
+
+    pub fn mirrored_records(&self) -> &[MftEntryIndex] {
+        &self.mirrored_records
+    }
+}
+
+/// Reads `size` bytes at `offset`, zeros where the image cannot be read (e.g. ends).
+fn read_record<R: Read + Seek>(reader: &mut R, offset: u64, size: u64) -> Result<Vec<u8>, Error> {
+    let mut record = vec![0; size as usize];
+
+    reader.seek(SeekFrom::Start(offset))?;
+    if reader.read_exact(&mut record).is_err() {
+        record.iter_mut().for_each(|byte| *byte = 0);
+    }
+
+    Ok(record)
+}
+
+impl<R: Read + Seek> Read for MftMirrorFallback<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let position = self.position;
+        let patch = self
+            .patches
+            .iter()
+            .find(|(offset, data)| position >= *offset && position < offset + data.len() as u64);
+
+        let read = match patch {
+            Some((offset, data)) => {
+                let start = (position - offset) as usize;
+                let length = buf.len().min(data.len() - start);
+
+                buf[..length].copy_from_slice(&data[start..start + length]);
+                self.inner.seek(SeekFrom::Start(position + length as u64))?;
+
+                length
+            }
+            None => {
+                // Stop at the next patch, it is read from the mirror.
+                let length = self
+                    .patches
+                    .iter()
+                    .map(|(offset, _)| *offset)
+                    .filter(|offset| *offset > position)
+                    .map(|offset| (offset - position).min(buf.len() as u64) as usize)
+                    .min()
+                    .unwrap_or_else(|| buf.len());
+
+                self.inner.read(&mut buf[..length])?
+            }
+        };
+
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for MftMirrorFallback<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::geometry::BOOT_SECTOR_SIZE;
+    use crate::options::VolumeOpenOptions;
+    use crate::shared::SharedVolume;
+    use std::fs;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_falls_back_to_mirror() {
+        let mut image = fs::read(sample_volume_path()).unwrap();
+        let geometry = BootSector::parse(&image[..BOOT_SECTOR_SIZE])
+            .unwrap()
+            .geometry;
+
+        // Overwrite the signature of the record of $MFTMirr.
+        let offset = geometry.mft_offset() as usize + geometry.mft_entry_size as usize;
+        image[offset..offset + 4].copy_from_slice(b"XXXX");
+
+        let reader = MftMirrorFallback::new(Cursor::new(image.clone())).unwrap();
+        assert_eq!(reader.mirrored_records(), [1]);
+
+        let options = VolumeOpenOptions::new().mft_mirror_fallback(true);
+        let volume = SharedVolume::from_reader(Cursor::new(image), &options).unwrap();
+
+        assert_eq!(volume.get_recovery().mirrored_records, [1]);
+        assert!(
+            volume
+                .get_file_entry_by_mft_idx(1)
+                .unwrap()
+                .get_size()
+                .unwrap()
+                > 0
+        );
+    }
+
+    #[test]
+    fn test_intact_volume() {
+        let options = VolumeOpenOptions::new()
+            .boot_sector_fallback(true)
+            .mft_mirror_fallback(true);
+        let volume = SharedVolume::open(sample_volume_path(), &options).unwrap();
+
+        assert!(!volume.get_recovery().is_recovered());
+    }
+}
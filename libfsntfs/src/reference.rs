@@ -0,0 +1,101 @@
+//! Checks file references (parent references, `$I30` entries, ..) against the sequence number
+//! of the MFT entry they point to, as entries are reused once the file they held was deleted.
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::utils::{mft_entry_index, sequence_number};
+use crate::volume::Volume;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceState {
+    /// The entry still holds the file which was referenced.
+    Current,
+    /// The referenced file was deleted, but its entry was not reused yet.
+    Deleted,
+    /// The entry was reused for another file since it was referenced.
+    Stale { expected: u16, actual: u16 },
+}
+
+impl ReferenceState {
+    /// `expected` is the sequence number of the reference, `actual` the one of the entry.
+    /// References without a sequence number (0) are not checked.
+    pub fn new(expected: u16, actual: u16, allocated: bool) -> Self {
+        if expected == 0 {
+            return ReferenceState::Current;
+        }
+
+        // Windows increments the sequence number when an entry is freed.
+        match (allocated, actual == expected, actual == expected.wrapping_add(1)) {
+            (true, true, _) => ReferenceState::Current,
+            (false, true, _) | (false, _, true) => ReferenceState::Deleted,
+            _ => ReferenceState::Stale { expected, actual },
+        }
+    }
+
+    pub fn is_stale(self) -> bool {
+        match self {
+            ReferenceState::Stale { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl<'a> FileEntry<'a> {
+    /// Whether `file_reference`, a reference to this entry, still refers to it.
+    pub fn reference_state(&self, file_reference: u64) -> Result<ReferenceState, Error> {
+        Ok(ReferenceState::new(
+            sequence_number(file_reference),
+            sequence_number(self.get_file_reference()?),
+            self.is_allocated()?,
+        ))
+    }
+
+    /// The parent directory and whether the parent reference is still valid.
+    pub fn get_parent(&self) -> Result<(FileEntry<'a>, ReferenceState), Error> {
+        self.volume()
+            .get_file_entry_by_reference(self.get_parent_file_reference()?)
+    }
+}
+
+impl Volume {
+    /// The entry `file_reference` points to and whether the reference is still valid.
+    pub fn get_file_entry_by_reference(
+        &self,
+        file_reference: u64,
+    ) -> Result<(FileEntry, ReferenceState), Error> {
+        let entry = self.get_file_entry_by_mft_idx(mft_entry_index(file_reference))?;
+        let state = entry.reference_state(file_reference)?;
+
+        Ok((entry, state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_reference_state() {
+        assert_eq!(ReferenceState::new(3, 3, true), ReferenceState::Current);
+        assert_eq!(ReferenceState::new(3, 4, false), ReferenceState::Deleted);
+        assert_eq!(ReferenceState::new(3, 3, false), ReferenceState::Deleted);
+        assert_eq!(
+            ReferenceState::new(3, 4, true),
+            ReferenceState::Stale {
+                expected: 3,
+                actual: 4
+            }
+        );
+        assert!(ReferenceState::new(3, 6, false).is_stale());
+        assert_eq!(ReferenceState::new(0, 6, true), ReferenceState::Current);
+    }
+
+    #[test]
+    fn test_get_parent() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+
+        let (_, state) = entry.get_parent().unwrap();
+        assert_eq!(state, ReferenceState::Current);
+    }
+}
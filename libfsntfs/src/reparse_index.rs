@@ -0,0 +1,94 @@
+//! Every reparse point of the volume, from the `$R` index of `$Extend\$Reparse`.
+use crate::error::Error;
+use crate::index::Index;
+use crate::mft_record::{read_u32, read_u64, MftRecord};
+use crate::utils::mft_entry_index;
+use crate::volume::{MftEntryIndex, Volume};
+use std::io::{Read, Seek};
+
+pub const REPARSE_PATH: &str = "\\$Extend\\$Reparse";
+/// The index of reparse points by tag and file reference.
+pub const REPARSE_INDEX_NAME: &str = "$R";
+
+/// Windows Overlay Filter (compressed system files).
+pub const IO_REPARSE_TAG_WOF: u32 = 0x8000_0017;
+/// Data deduplication.
+pub const IO_REPARSE_TAG_DEDUP: u32 = 0x8000_0013;
+
+/// Set on tags owned by Microsoft.
+const REPARSE_TAG_MICROSOFT: u32 = 0x8000_0000;
+/// Set on tags which redirect to another file or directory (symbolic links, junctions).
+const REPARSE_TAG_NAME_SURROGATE: u32 = 0x2000_0000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReparseIndexEntry {
+    pub tag: u32,
+    /// Reference (MFT entry index and sequence number) of the file carrying the reparse point.
+    pub file_reference: u64,
+}
+
+impl ReparseIndexEntry {
+    /// Parses the key of an entry of the `$R` index, the entries have no data.
+    pub fn parse(key: &[u8]) -> Result<Self, Error> {
+        Ok(ReparseIndexEntry {
+            tag: read_u32(key, 0x00)?,
+            file_reference: read_u64(key, 0x04)?,
+        })
+    }
+
+    pub fn mft_entry_index(&self) -> MftEntryIndex {
+        mft_entry_index(self.file_reference)
+    }
+
+    pub fn is_microsoft(&self) -> bool {
+        self.tag & REPARSE_TAG_MICROSOFT != 0
+    }
+
+    /// Whether the reparse point points to another file (symbolic links and junctions).
+    pub fn is_name_surrogate(&self) -> bool {
+        self.tag & REPARSE_TAG_NAME_SURROGATE != 0
+    }
+}
+
+/// Reads the `$R` index, `image` is the raw volume.
+pub(crate) fn read_reparse_points<R: Read + Seek>(
+    volume: &Volume,
+    image: &mut R,
+) -> Result<Vec<ReparseIndexEntry>, Error> {
+    let reparse = volume.get_file_entry_by_path(REPARSE_PATH)?;
+    let record = MftRecord::read(volume, mft_entry_index(reparse.get_file_reference()?))?;
+
+    Index::read(volume, &record, REPARSE_INDEX_NAME, image)?
+        .entries
+        .iter()
+        .map(|entry| ReparseIndexEntry::parse(&entry.key))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolve::IO_REPARSE_TAG_SYMLINK;
+
+    #[test]
+    fn test_parse_reparse_index_entry() {
+        let mut key = vec![];
+        key.extend_from_slice(&IO_REPARSE_TAG_SYMLINK.to_le_bytes());
+        key.extend_from_slice(&0x0003_0000_0000_002a_u64.to_le_bytes());
+        key.extend_from_slice(&[0; 4]);
+
+        let entry = ReparseIndexEntry::parse(&key).unwrap();
+
+        assert_eq!(entry.tag, IO_REPARSE_TAG_SYMLINK);
+        assert_eq!(entry.mft_entry_index(), 0x2a);
+        assert!(entry.is_microsoft());
+        assert!(entry.is_name_surrogate());
+
+        let wof = ReparseIndexEntry {
+            tag: IO_REPARSE_TAG_WOF,
+            ..entry
+        };
+
+        assert!(!wof.is_name_surrogate());
+    }
+}
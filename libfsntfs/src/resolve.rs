@@ -0,0 +1,217 @@
+//! Resolves paths through symbolic links and mount points (junctions) inside a volume.
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::volume::Volume;
+use std::collections::{HashSet, VecDeque};
+
+pub const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+pub const IO_REPARSE_TAG_SYMLINK: u32 = 0xA000_000C;
+
+/// Prefix of absolute (NT namespace) reparse point targets.
+const NT_PATH_PREFIX: &str = "\\??\\";
+
+#[derive(Debug, Clone)]
+pub struct ResolveOptions {
+    /// How many reparse points can be followed before giving up.
+    pub max_depth: usize,
+    /// The drive letter the volume was mounted as.
+    /// Targets on other drives are outside of the volume,
+    /// when `None` every drive letter is assumed to be this volume.
+    pub drive_letter: Option<char>,
+}
+
+impl Default for ResolveOptions {
+    fn default() -> Self {
+        ResolveOptions {
+            // Windows itself gives up after 63 reparse points.
+            max_depth: 63,
+            drive_letter: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Resolved<'a> {
+    /// The path resolved to an entry of this volume.
+    Entry(FileEntry<'a>),
+    /// A reparse point along the path points to another volume (or a network share).
+    OutsideVolume { target: String },
+}
+
+/// Where a reparse point leads.
+enum Target {
+    /// Path components from the root of the volume.
+    Absolute(Vec<String>),
+    /// Path components from the directory containing the reparse point.
+    Relative(Vec<String>),
+    OutsideVolume(String),
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.split(|c| c == '\\' || c == '/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .map(|component| component.to_owned())
+        .collect()
+}
+
+fn parse_target(substitute_name: &str, options: &ResolveOptions) -> Target {
+    if !substitute_name.starts_with(NT_PATH_PREFIX) {
+        return Target::Relative(split_path(substitute_name));
+    }
+
+    let nt_path = &substitute_name[NT_PATH_PREFIX.len()..];
+    let mut chars = nt_path.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(drive_letter), Some(':')) if drive_letter.is_ascii_alphabetic() => {
+            let same_volume = options.drive_letter.map_or(true, |volume_drive_letter| {
+                volume_drive_letter.eq_ignore_ascii_case(&drive_letter)
+            });
+
+            if same_volume {
+                Target::Absolute(split_path(&nt_path[2..]))
+            } else {
+                Target::OutsideVolume(substitute_name.to_owned())
+            }
+        }
+        // `Volume{GUID}`, `UNC\server\share` and so on.
+        _ => Target::OutsideVolume(substitute_name.to_owned()),
+    }
+}
+
+fn join_path(components: &[String]) -> String {
+    format!("\\{}", components.join("\\"))
+}
+
+/// The target of `entry` if it is a symbolic link or a mount point.
+fn link_target(entry: &FileEntry, options: &ResolveOptions) -> Result<Option<Target>, Error> {
+    match entry.get_reparse_point_tag()? {
+        Some(IO_REPARSE_TAG_SYMLINK) | Some(IO_REPARSE_TAG_MOUNT_POINT) => {}
+        _ => return Ok(None),
+    }
+
+    Ok(entry
+        .get_reparse_point_substitute_name()?
+        .map(|substitute_name| parse_target(&substitute_name, options)))
+}
+
+pub(crate) fn resolve<'a>(
+    volume: &'a Volume,
+    path: &str,
+    options: &ResolveOptions,
+) -> Result<Resolved<'a>, Error> {
+    let mut pending: VecDeque<String> = split_path(path).into_iter().collect();
+    let mut resolved: Vec<String> = vec![];
+
+    let mut followed = 0;
+    // A link reached again with the same remaining path will never resolve.
+    let mut seen = HashSet::new();
+
+    while let Some(component) = pending.pop_front() {
+        if component == ".." {
+            resolved.pop();
+            continue;
+        }
+
+        resolved.push(component);
+
+        let entry = volume.get_file_entry_by_path(join_path(&resolved))?;
+
+        let target = match link_target(&entry, options)? {
+            Some(target) => target,
+            None => continue,
+        };
+
+        followed += 1;
+
+        if followed > options.max_depth {
+            return Err(Error::TooManyReparsePoints(options.max_depth));
+        }
+
+        let remaining: Vec<String> = pending.iter().cloned().collect();
+
+        if !seen.insert((entry.get_file_reference()?, remaining)) {
+            return Err(Error::ReparsePointLoop(path.to_owned()));
+        }
+
+        let target_components = match target {
+            Target::Absolute(components) => {
+                resolved.clear();
+                components
+            }
+            Target::Relative(components) => {
+                resolved.pop();
+                components
+            }
+            Target::OutsideVolume(target) => return Ok(Resolved::OutsideVolume { target }),
+        };
+
+        for component in target_components.into_iter().rev() {
+            pending.push_front(component);
+        }
+    }
+
+    if resolved.is_empty() {
+        Ok(Resolved::Entry(volume.get_root_directory()?))
+    } else {
+        Ok(Resolved::Entry(volume.get_file_entry_by_path(join_path(
+            &resolved,
+        ))?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn absolute(target: Target) -> Vec<String> {
+        match target {
+            Target::Absolute(components) => components,
+            _ => panic!("expected an absolute target"),
+        }
+    }
+
+    #[test]
+    fn test_parse_target() {
+        let options = ResolveOptions {
+            drive_letter: Some('C'),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            absolute(parse_target("\\??\\c:\\Users\\Public", &options)),
+            vec!["Users", "Public"]
+        );
+
+        match parse_target("..\\sibling\\file.txt", &options) {
+            Target::Relative(components) => {
+                assert_eq!(components, vec!["..", "sibling", "file.txt"])
+            }
+            _ => panic!("expected a relative target"),
+        }
+
+        let outside_targets = [
+            "\\??\\D:\\data",
+            "\\??\\Volume{1234}\\",
+            "\\??\\UNC\\server\\share",
+        ];
+
+        for outside in outside_targets.iter() {
+            match parse_target(outside, &options) {
+                Target::OutsideVolume(target) => assert_eq!(target, *outside),
+                _ => panic!("expected {} to be outside of the volume", outside),
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_plain_path() {
+        let volume = sample_volume().unwrap();
+
+        match volume.resolve("\\$MFT", &ResolveOptions::default()).unwrap() {
+            Resolved::Entry(entry) => assert_eq!(entry.get_name().unwrap(), "$MFT"),
+            Resolved::OutsideVolume { target } => panic!("unexpected target {}", target),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Reading the data of damaged media: blocks which cannot be read are zero-filled (or filled by
+//! the caller) and reported as bad ranges, instead of failing the whole read.
+use std::fmt::{self, Debug, Formatter};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Size of the blocks which are given up on, the cluster size of most volumes.
+pub const DEFAULT_BLOCK_SIZE: u64 = 4096;
+
+/// A range of the stream which could not be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BadRange {
+    /// Offset from the start of the stream, in bytes.
+    pub offset: u64,
+    pub size: u64,
+}
+
+type FillFn<'r> = Box<dyn FnMut(u64, &mut [u8]) + 'r>;
+
+/// Wraps a stream (e.g. a `FileEntry` or `DataStream`), which must be seekable to skip over
+/// unreadable blocks.
+///
+/// ```ignore
+/// let mut reader = SalvagingReader::new(&mut file_entry)?;
+/// let bad_ranges = reader.copy_to(&mut output)?;
+/// ```
+pub struct SalvagingReader<'r, R> {
+    inner: R,
+    position: u64,
+    size: u64,
+    block_size: u64,
+    fill: FillFn<'r>,
+    bad_ranges: Vec<BadRange>,
+}
+
+impl<'r, R: Read + Seek> SalvagingReader<'r, R> {
+    /// Reads from the current position of `inner` to its end.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let position = inner.seek(SeekFrom::Current(0))?;
+        let size = inner.seek(SeekFrom::End(0))?;
+        inner.seek(SeekFrom::Start(position))?;
+
+        Ok(SalvagingReader {
+            inner,
+            position,
+            size,
+            block_size: DEFAULT_BLOCK_SIZE,
+            fill: Box::new(|_, buf: &mut [u8]| {
+                for byte in buf.iter_mut() {
+                    *byte = 0;
+                }
+            }),
+            bad_ranges: vec![],
+        })
+    }
+
+    pub fn block_size(mut self, block_size: u64) -> Self {
+        assert!(block_size > 0, "block size must not be zero");
+        self.block_size = block_size;
+        self
+    }
+
+    /// Fills the bytes of bad blocks, given their offset in the stream, instead of zeros.
+    pub fn fill_with(mut self, fill: impl FnMut(u64, &mut [u8]) + 'r) -> Self {
+        self.fill = Box::new(fill);
+        self
+    }
+
+    /// The ranges which could not be read so far, adjacent ranges are merged.
+    pub fn bad_ranges(&self) -> &[BadRange] {
+        &self.bad_ranges
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Copies the rest of the stream to `writer`, returning the bad ranges.
+    pub fn copy_to(mut self, writer: &mut impl Write) -> io::Result<Vec<BadRange>> {
+        io::copy(&mut self, writer)?;
+
+        Ok(self.bad_ranges)
+    }
+
+    fn add_bad_range(&mut self, offset: u64, size: u64) {
+        if let Some(last) = self.bad_ranges.last_mut() {
+            if last.offset + last.size == offset {
+                last.size += size;
+                return;
+            }
+        }
+
+        self.bad_ranges.push(BadRange { offset, size });
+    }
+
+    /// Bytes until the end of the current block (or the stream).
+    fn block_remaining(&self) -> u64 {
+        let block_end = (self.position / self.block_size + 1) * self.block_size;
+        block_end.min(self.size) - self.position
+    }
+}
+
+impl<'r, R: Read + Seek> Read for SalvagingReader<'r, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.size {
+            return Ok(0);
+        }
+
+        match self.inner.read(buf) {
+            Ok(read) => {
+                self.position += read as u64;
+                return Ok(read);
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {
+                return Err(io::Error::from(io::ErrorKind::Interrupted));
+            }
+            Err(_) => {}
+        }
+
+        // The error may be anywhere in `buf`, so only the current block is given up on
+        // when it fails on its own.
+        let length = (self.block_remaining() as usize).min(buf.len());
+        let block = &mut buf[..length];
+
+        self.inner.seek(SeekFrom::Start(self.position))?;
+
+        let read = match self.inner.read(block) {
+            Ok(read) => read,
+            Err(_) => {
+                (self.fill)(self.position, block);
+                self.add_bad_range(self.position, length as u64);
+                self.inner
+                    .seek(SeekFrom::Start(self.position + length as u64))?;
+                length
+            }
+        };
+
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<'r, R> Debug for SalvagingReader<'r, R> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("SalvagingReader")
+            .field("position", &self.position)
+            .field("size", &self.size)
+            .field("block_size", &self.block_size)
+            .field("bad_ranges", &self.bad_ranges)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Fails every read touching `bad`.
+    struct DamagedReader {
+        data: Cursor<Vec<u8>>,
+        bad: std::ops::Range<u64>,
+    }
+
+    impl Read for DamagedReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let start = self.data.position();
+            let end = start + buf.len() as u64;
+
+            if start < self.bad.end && self.bad.start < end {
+                return Err(io::Error::new(io::ErrorKind::Other, "bad sector"));
+            }
+
+            self.data.read(buf)
+        }
+    }
+
+    impl Seek for DamagedReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.data.seek(pos)
+        }
+    }
+
+    fn damaged(bad: std::ops::Range<u64>) -> DamagedReader {
+        DamagedReader {
+            data: Cursor::new(vec![0xff; 10]),
+            bad,
+        }
+    }
+
+    #[test]
+    fn test_zero_fills_bad_blocks() {
+        let mut output = vec![];
+        let bad_ranges = SalvagingReader::new(damaged(4..6))
+            .unwrap()
+            .block_size(2)
+            .copy_to(&mut output)
+            .unwrap();
+
+        assert_eq!(output, [0xff, 0xff, 0xff, 0xff, 0, 0, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(bad_ranges, vec![BadRange { offset: 4, size: 2 }]);
+    }
+
+    #[test]
+    fn test_merges_adjacent_bad_ranges() {
+        let mut output = vec![];
+        let bad_ranges = SalvagingReader::new(damaged(3..7))
+            .unwrap()
+            .block_size(2)
+            .fill_with(|_, buf| {
+                for byte in buf.iter_mut() {
+                    *byte = 0xaa;
+                }
+            })
+            .copy_to(&mut output)
+            .unwrap();
+
+        assert_eq!(
+            output,
+            [0xff, 0xff, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xff, 0xff]
+        );
+        assert_eq!(bad_ranges, vec![BadRange { offset: 2, size: 6 }]);
+    }
+}
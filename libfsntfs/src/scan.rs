@@ -0,0 +1,222 @@
+//! Finds data streams by their content, matching the first bytes against magic-byte signatures.
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::volume::{MftEntries, MftEntryIndex, Volume};
+use std::collections::VecDeque;
+use std::io::Read;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub name: String,
+    /// Offset of `magic` from the start of the stream.
+    pub offset: usize,
+    pub magic: Vec<u8>,
+}
+
+impl Signature {
+    pub fn new(name: impl Into<String>, offset: usize, magic: impl Into<Vec<u8>>) -> Self {
+        Signature {
+            name: name.into(),
+            offset,
+            magic: magic.into(),
+        }
+    }
+
+    pub fn matches(&self, header: &[u8]) -> bool {
+        header.get(self.offset..self.offset + self.magic.len()) == Some(&self.magic[..])
+    }
+
+    /// Signatures of common file formats.
+    pub fn common() -> Vec<Signature> {
+        vec![
+            Signature::new("pe", 0, &b"MZ"[..]),
+            Signature::new("elf", 0, &b"\x7fELF"[..]),
+            Signature::new("zip", 0, &b"PK\x03\x04"[..]),
+            Signature::new("sqlite", 0, &b"SQLite format 3\x00"[..]),
+            Signature::new("pdf", 0, &b"%PDF-"[..]),
+            Signature::new("ole", 0, &b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1"[..]),
+            Signature::new("png", 0, &b"\x89PNG\r\n\x1a\n"[..]),
+            Signature::new("jpeg", 0, &b"\xff\xd8\xff"[..]),
+            Signature::new("gzip", 0, &b"\x1f\x8b"[..]),
+            Signature::new("7z", 0, &b"7z\xbc\xaf\x27\x1c"[..]),
+            Signature::new("evtx", 0, &b"ElfFile\x00"[..]),
+            Signature::new("registry", 0, &b"regf"[..]),
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// How many bytes of each stream are read, signatures past that are never matched.
+    pub header_size: usize,
+    /// Scan the streams of entries which are not allocated (deleted files) as well.
+    pub include_deleted: bool,
+    pub include_alternate_data_streams: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            header_size: 512,
+            include_deleted: false,
+            include_alternate_data_streams: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureMatch {
+    pub mft_entry_index: MftEntryIndex,
+    /// The name of the alternate data stream, `None` for the default one.
+    pub stream_name: Option<String>,
+    /// Index of the matching signature in the signatures given to the scanner.
+    pub signature_index: usize,
+    /// Size of the stream, in bytes.
+    pub size: u64,
+}
+
+fn read_header(stream: impl Read, header_size: usize) -> Result<Vec<u8>, Error> {
+    let mut header = Vec::with_capacity(header_size);
+    stream.take(header_size as u64).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+/// Yields a match for every stream and every signature it matches, in MFT order.
+/// Streams which cannot be read are skipped.
+pub struct SignatureScanner<'a> {
+    entries: MftEntries<'a>,
+    signatures: Vec<Signature>,
+    options: ScanOptions,
+    pending: VecDeque<SignatureMatch>,
+}
+
+impl<'a> SignatureScanner<'a> {
+    pub fn new(
+        volume: &'a Volume,
+        signatures: Vec<Signature>,
+        options: ScanOptions,
+    ) -> Result<Self, Error> {
+        Ok(SignatureScanner {
+            entries: volume.mft_entries()?,
+            signatures,
+            options,
+            pending: VecDeque::new(),
+        })
+    }
+
+    pub fn signatures(&self) -> &[Signature] {
+        &self.signatures
+    }
+
+    fn push_matches(
+        &mut self,
+        idx: MftEntryIndex,
+        stream_name: Option<String>,
+        header: &[u8],
+        size: u64,
+    ) {
+        for (signature_index, signature) in self.signatures.iter().enumerate() {
+            if signature.matches(header) {
+                self.pending.push_back(SignatureMatch {
+                    mft_entry_index: idx,
+                    stream_name: stream_name.clone(),
+                    signature_index,
+                    size,
+                });
+            }
+        }
+    }
+
+    fn scan_entry(&mut self, idx: MftEntryIndex, mut entry: FileEntry) -> Result<(), Error> {
+        if !self.options.include_deleted && !entry.is_allocated()? {
+            return Ok(());
+        }
+
+        let header_size = self.options.header_size;
+
+        if entry.has_default_data_stream()? {
+            let size = entry.get_size()?;
+            let header = read_header(&mut entry, header_size)?;
+            self.push_matches(idx, None, &header, size);
+        }
+
+        if self.options.include_alternate_data_streams {
+            for data_stream in entry.iter_alternate_data_streams()? {
+                let mut data_stream = data_stream?;
+
+                let name = data_stream.get_name()?;
+                let size = data_stream.get_size()?;
+                let header = read_header(&mut data_stream, header_size)?;
+                self.push_matches(idx, Some(name), &header, size);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for SignatureScanner<'a> {
+    type Item = SignatureMatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(signature_match) = self.pending.pop_front() {
+                return Some(signature_match);
+            }
+
+            let (idx, entry) = self.entries.next()?;
+
+            if let Ok(entry) = entry {
+                // A stream failing halfway still keeps the matches of the streams before it.
+                let _ = self.scan_entry(idx, entry);
+            }
+        }
+    }
+}
+
+impl Volume {
+    /// Scans the data streams of the volume for `signatures`, see `SignatureScanner`.
+    pub fn scan_signatures(
+        &self,
+        signatures: Vec<Signature>,
+        options: ScanOptions,
+    ) -> Result<SignatureScanner, Error> {
+        SignatureScanner::new(self, signatures, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_signature_matches() {
+        let sqlite = Signature::new("sqlite", 0, &b"SQLite format 3\x00"[..]);
+        let tar = Signature::new("tar", 257, &b"ustar"[..]);
+
+        assert!(sqlite.matches(b"SQLite format 3\x00\x10\x00"));
+        assert!(!sqlite.matches(b"SQLite"));
+
+        let mut header = vec![0; 512];
+        header[257..262].copy_from_slice(b"ustar");
+        assert!(tar.matches(&header));
+        assert!(!tar.matches(&header[..260]));
+    }
+
+    #[test]
+    fn test_scan_signatures() {
+        let volume = sample_volume().unwrap();
+        let signatures = vec![Signature::new("anything", 0, vec![])];
+
+        let matches: Vec<SignatureMatch> = volume
+            .scan_signatures(signatures, ScanOptions::default())
+            .unwrap()
+            .collect();
+
+        // The empty signature matches every stream, including `$MFT`.
+        assert!(matches
+            .iter()
+            .any(|m| m.mft_entry_index == 0 && m.stream_name.is_none()));
+    }
+}
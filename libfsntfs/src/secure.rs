@@ -0,0 +1,189 @@
+//! The security descriptors of the volume, stored once in the `$SDS` stream of `$Secure`
+//! and referenced by files through the security id in `$STANDARD_INFORMATION`.
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::metadata_files;
+use crate::security_descriptor::SecurityDescriptor;
+use crate::volume::Volume;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io::Read;
+
+pub const SDS_STREAM_NAME: &str = "$SDS";
+
+/// `$SDS` is written in blocks of 256 KiB, each followed by a mirror copy of itself.
+const SDS_BLOCK_SIZE: usize = 0x40000;
+const SDS_ENTRY_HEADER_SIZE: usize = 20;
+const SDS_ENTRY_ALIGNMENT: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityDescriptorEntry {
+    pub hash: u32,
+    pub security_id: u32,
+    /// Offset of the entry in `$SDS`.
+    pub offset: u64,
+    /// The self-relative security descriptor.
+    pub data: Vec<u8>,
+}
+
+impl SecurityDescriptorEntry {
+    pub fn parse(&self) -> Result<SecurityDescriptor, Error> {
+        SecurityDescriptor::parse(&self.data)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SecurityDescriptors {
+    entries: Vec<SecurityDescriptorEntry>,
+    by_security_id: HashMap<u32, usize>,
+}
+
+impl SecurityDescriptors {
+    /// Reads and parses `$Secure:$SDS`.
+    pub fn read(volume: &Volume) -> Result<Self, Error> {
+        let secure = volume.get_file_entry_by_mft_idx(metadata_files::SECURE)?;
+
+        let mut sds = vec![];
+        secure
+            .get_alternate_data_stream_by_name(SDS_STREAM_NAME)?
+            .read_to_end(&mut sds)?;
+
+        Ok(SecurityDescriptors::from_sds(&sds))
+    }
+
+    /// Parses the content of a `$SDS` stream, skipping the mirror blocks.
+    pub fn from_sds(sds: &[u8]) -> Self {
+        let mut descriptors = SecurityDescriptors::default();
+
+        for block_start in (0..sds.len()).step_by(2 * SDS_BLOCK_SIZE) {
+            let block_end = (block_start + SDS_BLOCK_SIZE).min(sds.len());
+            let mut offset = block_start;
+
+            while let Some(entry) = parse_entry(&sds[..block_end], offset) {
+                // The size does not include the alignment padding.
+                offset += align(SDS_ENTRY_HEADER_SIZE + entry.data.len(), SDS_ENTRY_ALIGNMENT);
+
+                let idx = descriptors.entries.len();
+                descriptors
+                    .by_security_id
+                    .entry(entry.security_id)
+                    .or_insert(idx);
+                descriptors.entries.push(entry);
+            }
+        }
+
+        descriptors
+    }
+
+    pub fn get(&self, security_id: u32) -> Option<&SecurityDescriptorEntry> {
+        self.by_security_id
+            .get(&security_id)
+            .map(|&idx| &self.entries[idx])
+    }
+
+    /// The descriptor which applies to `file_entry`, `None` for entries without a security id
+    /// (volumes formatted before NTFS 3.0 store the descriptor in the entry itself).
+    pub fn get_for_entry(
+        &self,
+        file_entry: &FileEntry,
+    ) -> Result<Option<&SecurityDescriptorEntry>, Error> {
+        Ok(file_entry
+            .get_security_descriptor_identifier()?
+            .and_then(|security_id| self.get(security_id)))
+    }
+
+    /// All descriptors, in the order they appear in `$SDS`.
+    pub fn iter(&self) -> impl Iterator<Item = &SecurityDescriptorEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn align(value: usize, alignment: usize) -> usize {
+    (value + alignment - 1) / alignment * alignment
+}
+
+/// Parses the entry at `offset`, `None` at the end of the used part of a block.
+fn parse_entry(block: &[u8], offset: usize) -> Option<SecurityDescriptorEntry> {
+    let header = block.get(offset..offset + SDS_ENTRY_HEADER_SIZE)?;
+
+    let hash = u32::from_le_bytes(header[0..4].try_into().unwrap());
+    let security_id = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    let entry_offset = u64::from_le_bytes(header[8..16].try_into().unwrap());
+    let size = u32::from_le_bytes(header[16..20].try_into().unwrap()) as usize;
+
+    // Every entry records its own offset, anything else is padding or stale data.
+    if entry_offset != offset as u64 || size <= SDS_ENTRY_HEADER_SIZE {
+        return None;
+    }
+
+    let data = block.get(offset + SDS_ENTRY_HEADER_SIZE..offset + size)?;
+
+    Some(SecurityDescriptorEntry {
+        hash,
+        security_id,
+        offset: entry_offset,
+        data: data.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    fn sds_entry(security_id: u32, offset: usize, descriptor: &[u8]) -> Vec<u8> {
+        let mut entry = vec![];
+        entry.extend_from_slice(&0xdead_beef_u32.to_le_bytes());
+        entry.extend_from_slice(&security_id.to_le_bytes());
+        entry.extend_from_slice(&(offset as u64).to_le_bytes());
+        entry.extend_from_slice(&((SDS_ENTRY_HEADER_SIZE + descriptor.len()) as u32).to_le_bytes());
+        entry.extend_from_slice(descriptor);
+        entry.resize(align(entry.len(), SDS_ENTRY_ALIGNMENT), 0);
+        entry
+    }
+
+    #[test]
+    fn test_from_sds_skips_mirror_blocks() {
+        let mut sds = sds_entry(0x100, 0, &[1, 0, 4, 0x80]);
+        sds.extend(sds_entry(0x101, sds.len(), &[1; 13]));
+        sds.resize(SDS_BLOCK_SIZE, 0);
+        // The mirror of the first block.
+        sds.extend(sds.clone());
+        sds.extend(sds_entry(0x102, sds.len(), &[2; 16]));
+
+        let descriptors = SecurityDescriptors::from_sds(&sds);
+
+        let security_ids: Vec<u32> = descriptors.iter().map(|entry| entry.security_id).collect();
+        assert_eq!(security_ids, vec![0x100, 0x101, 0x102]);
+
+        assert_eq!(descriptors.get(0x101).unwrap().data, vec![1; 13]);
+        assert_eq!(
+            descriptors.get(0x102).unwrap().offset,
+            2 * SDS_BLOCK_SIZE as u64
+        );
+        assert!(descriptors.get(0x103).is_none());
+    }
+
+    #[test]
+    fn test_read_security_descriptors() {
+        let volume = sample_volume().unwrap();
+        let descriptors = volume.get_security_descriptors().unwrap();
+
+        assert!(!descriptors.is_empty());
+
+        let mft = volume.get_file_entry_by_mft_idx(metadata_files::MFT).unwrap();
+        let descriptor = descriptors.get_for_entry(&mft).unwrap().unwrap();
+
+        // Revision 1 of a self-relative security descriptor.
+        assert_eq!(descriptor.data[0], 1);
+        assert!(descriptor.parse().unwrap().dacl_aces().count() > 0);
+    }
+}
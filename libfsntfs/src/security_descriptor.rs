@@ -0,0 +1,375 @@
+//! Parses self-relative security descriptors (as stored in `$SDS` or `$SECURITY_DESCRIPTOR`)
+//! into their owner, group and access control lists.
+use crate::error::Error;
+use bitflags::bitflags;
+use std::convert::TryInto;
+use std::fmt;
+
+const SECURITY_DESCRIPTOR_HEADER_SIZE: usize = 20;
+const ACL_HEADER_SIZE: usize = 8;
+const ACE_HEADER_SIZE: usize = 4;
+
+/// Control flag marking the SACL as present.
+const SE_SACL_PRESENT: u16 = 0x0010;
+/// Control flag marking the DACL as present.
+const SE_DACL_PRESENT: u16 = 0x0004;
+
+/// Object ACEs carry an object type GUID.
+const ACE_OBJECT_TYPE_PRESENT: u32 = 0x1;
+/// Object ACEs carry an inherited object type GUID.
+const ACE_INHERITED_OBJECT_TYPE_PRESENT: u32 = 0x2;
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidSecurityDescriptor(reason.to_owned())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| invalid("truncated"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| invalid("truncated"))
+}
+
+/// The data at the offset stored at `field_offset`, `None` when the offset is 0.
+fn at(data: &[u8], field_offset: usize) -> Result<Option<&[u8]>, Error> {
+    match read_u32(data, field_offset)? as usize {
+        0 => Ok(None),
+        offset => data
+            .get(offset..)
+            .map(Some)
+            .ok_or_else(|| invalid("offset out of bounds")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sid {
+    pub revision: u8,
+    /// 48-bit identifier authority, e.g. 5 for `NT AUTHORITY`.
+    pub identifier_authority: u64,
+    pub sub_authorities: Vec<u32>,
+}
+
+impl Sid {
+    /// Parses a SID from the start of `data`.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let header = data.get(..8).ok_or_else(|| invalid("truncated SID"))?;
+
+        let revision = header[0];
+        let number_of_sub_authorities = header[1] as usize;
+        // The identifier authority is big-endian.
+        let identifier_authority = header[2..8]
+            .iter()
+            .fold(0_u64, |authority, byte| (authority << 8) | u64::from(*byte));
+
+        let mut sub_authorities = Vec::with_capacity(number_of_sub_authorities);
+
+        for idx in 0..number_of_sub_authorities {
+            sub_authorities.push(read_u32(data, 8 + idx * 4)?);
+        }
+
+        Ok(Sid {
+            revision,
+            identifier_authority,
+            sub_authorities,
+        })
+    }
+}
+
+impl fmt::Display for Sid {
+    /// Formats the SID as `S-1-5-21-...`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "S-{}-{}", self.revision, self.identifier_authority)?;
+
+        for sub_authority in &self.sub_authorities {
+            write!(f, "-{}", sub_authority)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AceType {
+    AccessAllowed,
+    AccessDenied,
+    SystemAudit,
+    SystemAlarm,
+    AccessAllowedObject,
+    AccessDeniedObject,
+    SystemAuditObject,
+    SystemAlarmObject,
+    SystemMandatoryLabel,
+    Unknown(u8),
+}
+
+impl AceType {
+    fn is_object(self) -> bool {
+        match self {
+            AceType::AccessAllowedObject
+            | AceType::AccessDeniedObject
+            | AceType::SystemAuditObject
+            | AceType::SystemAlarmObject => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<u8> for AceType {
+    fn from(ace_type: u8) -> Self {
+        match ace_type {
+            0x00 => AceType::AccessAllowed,
+            0x01 => AceType::AccessDenied,
+            0x02 => AceType::SystemAudit,
+            0x03 => AceType::SystemAlarm,
+            0x05 => AceType::AccessAllowedObject,
+            0x06 => AceType::AccessDeniedObject,
+            0x07 => AceType::SystemAuditObject,
+            0x08 => AceType::SystemAlarmObject,
+            0x11 => AceType::SystemMandatoryLabel,
+            other => AceType::Unknown(other),
+        }
+    }
+}
+
+bitflags! {
+    pub struct AceFlags: u8 {
+        const OBJECT_INHERIT = 0x01;
+        const CONTAINER_INHERIT = 0x02;
+        const NO_PROPAGATE_INHERIT = 0x04;
+        const INHERIT_ONLY = 0x08;
+        const INHERITED = 0x10;
+        const SUCCESSFUL_ACCESS = 0x40;
+        const FAILED_ACCESS = 0x80;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ace {
+    pub ace_type: AceType,
+    pub flags: AceFlags,
+    pub access_mask: u32,
+    /// `None` for ACE types this crate does not know the layout of.
+    pub sid: Option<Sid>,
+}
+
+impl Ace {
+    /// Parses an ACE from the start of `data`, returning it with its size.
+    fn parse(data: &[u8]) -> Result<(Self, usize), Error> {
+        let header = data.get(..ACE_HEADER_SIZE).ok_or_else(|| invalid("truncated ACE"))?;
+
+        let ace_type = AceType::from(header[0]);
+        let flags = AceFlags::from_bits_truncate(header[1]);
+        let size = read_u16(header, 2)? as usize;
+
+        let ace = data
+            .get(..size)
+            .filter(|ace| ace.len() >= ACE_HEADER_SIZE + 4)
+            .ok_or_else(|| invalid("invalid ACE size"))?;
+
+        let access_mask = read_u32(ace, ACE_HEADER_SIZE)?;
+
+        let sid = match ace_type {
+            AceType::Unknown(_) => None,
+            ace_type if ace_type.is_object() => {
+                let object_flags = read_u32(ace, ACE_HEADER_SIZE + 4)?;
+                let mut sid_offset = ACE_HEADER_SIZE + 8;
+
+                if object_flags & ACE_OBJECT_TYPE_PRESENT != 0 {
+                    sid_offset += 16;
+                }
+                if object_flags & ACE_INHERITED_OBJECT_TYPE_PRESENT != 0 {
+                    sid_offset += 16;
+                }
+
+                Some(Sid::parse(ace.get(sid_offset..).unwrap_or(&[]))?)
+            }
+            _ => Some(Sid::parse(&ace[ACE_HEADER_SIZE + 4..])?),
+        };
+
+        Ok((
+            Ace {
+                ace_type,
+                flags,
+                access_mask,
+                sid,
+            },
+            size,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Acl {
+    pub revision: u8,
+    pub aces: Vec<Ace>,
+}
+
+impl Acl {
+    fn parse(data: &[u8]) -> Result<Self, Error> {
+        let header = data.get(..ACL_HEADER_SIZE).ok_or_else(|| invalid("truncated ACL"))?;
+
+        let revision = header[0];
+        let size = read_u16(header, 2)? as usize;
+        let number_of_aces = read_u16(header, 4)?;
+
+        let acl = data.get(..size).ok_or_else(|| invalid("invalid ACL size"))?;
+
+        let mut aces = Vec::with_capacity(number_of_aces as usize);
+        let mut offset = ACL_HEADER_SIZE;
+
+        for _ in 0..number_of_aces {
+            let (ace, ace_size) = Ace::parse(acl.get(offset..).unwrap_or(&[]))?;
+
+            aces.push(ace);
+            offset += ace_size;
+        }
+
+        Ok(Acl { revision, aces })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityDescriptor {
+    pub revision: u8,
+    /// The `SE_*` control flags.
+    pub control: u16,
+    pub owner: Option<Sid>,
+    pub group: Option<Sid>,
+    /// Audit entries.
+    pub sacl: Option<Acl>,
+    /// Access entries, a missing DACL grants everyone full access.
+    pub dacl: Option<Acl>,
+}
+
+impl SecurityDescriptor {
+    /// Parses a self-relative security descriptor.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < SECURITY_DESCRIPTOR_HEADER_SIZE {
+            return Err(invalid("truncated header"));
+        }
+
+        let revision = data[0];
+        let control = read_u16(data, 2)?;
+
+        let owner = at(data, 4)?.map(Sid::parse).transpose()?;
+        let group = at(data, 8)?.map(Sid::parse).transpose()?;
+
+        let sacl = if control & SE_SACL_PRESENT != 0 {
+            at(data, 12)?.map(Acl::parse).transpose()?
+        } else {
+            None
+        };
+        let dacl = if control & SE_DACL_PRESENT != 0 {
+            at(data, 16)?.map(Acl::parse).transpose()?
+        } else {
+            None
+        };
+
+        Ok(SecurityDescriptor {
+            revision,
+            control,
+            owner,
+            group,
+            sacl,
+            dacl,
+        })
+    }
+
+    /// The entries of the DACL, empty if there is none.
+    pub fn dacl_aces(&self) -> impl Iterator<Item = &Ace> {
+        self.dacl.iter().flat_map(|acl| acl.aces.iter())
+    }
+
+    /// The entries of the SACL, empty if there is none.
+    pub fn sacl_aces(&self) -> impl Iterator<Item = &Ace> {
+        self.sacl.iter().flat_map(|acl| acl.aces.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // S-1-5-18 (SYSTEM)
+    const SYSTEM_SID: [u8; 12] = [1, 1, 0, 0, 0, 0, 0, 5, 18, 0, 0, 0];
+    // S-1-5-32-544 (Administrators)
+    const ADMINISTRATORS_SID: [u8; 16] = [1, 2, 0, 0, 0, 0, 0, 5, 32, 0, 0, 0, 32, 2, 0, 0];
+
+    fn ace(ace_type: u8, flags: u8, access_mask: u32, sid: &[u8]) -> Vec<u8> {
+        let mut ace = vec![ace_type, flags];
+        ace.extend_from_slice(&((ACE_HEADER_SIZE + 4 + sid.len()) as u16).to_le_bytes());
+        ace.extend_from_slice(&access_mask.to_le_bytes());
+        ace.extend_from_slice(sid);
+        ace
+    }
+
+    fn security_descriptor() -> Vec<u8> {
+        let aces = [
+            ace(0x00, 0x13, 0x001f_01ff, &SYSTEM_SID),
+            ace(0x01, 0x00, 0x0001_0000, &ADMINISTRATORS_SID),
+        ];
+        let aces_size: usize = aces.iter().map(|ace| ace.len()).sum();
+
+        let owner_offset = SECURITY_DESCRIPTOR_HEADER_SIZE;
+        let dacl_offset = owner_offset + SYSTEM_SID.len();
+
+        let mut data = vec![1, 0];
+        data.extend_from_slice(&(SE_DACL_PRESENT | 0x8000).to_le_bytes());
+        data.extend_from_slice(&(owner_offset as u32).to_le_bytes());
+        data.extend_from_slice(&0_u32.to_le_bytes());
+        data.extend_from_slice(&0_u32.to_le_bytes());
+        data.extend_from_slice(&(dacl_offset as u32).to_le_bytes());
+        data.extend_from_slice(&SYSTEM_SID);
+
+        data.extend_from_slice(&[2, 0]);
+        data.extend_from_slice(&((ACL_HEADER_SIZE + aces_size) as u16).to_le_bytes());
+        data.extend_from_slice(&(aces.len() as u16).to_le_bytes());
+        data.extend_from_slice(&[0, 0]);
+        for ace in aces.iter() {
+            data.extend_from_slice(ace);
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_sid_display() {
+        assert_eq!(Sid::parse(&ADMINISTRATORS_SID).unwrap().to_string(), "S-1-5-32-544");
+    }
+
+    #[test]
+    fn test_parse_security_descriptor() {
+        let descriptor = SecurityDescriptor::parse(&security_descriptor()).unwrap();
+
+        assert_eq!(descriptor.owner.unwrap().to_string(), "S-1-5-18");
+        assert!(descriptor.group.is_none());
+        assert!(descriptor.sacl.is_none());
+
+        let aces: Vec<Ace> = descriptor.dacl.unwrap().aces;
+
+        assert_eq!(aces.len(), 2);
+        assert_eq!(aces[0].ace_type, AceType::AccessAllowed);
+        assert_eq!(
+            aces[0].flags,
+            AceFlags::OBJECT_INHERIT | AceFlags::CONTAINER_INHERIT | AceFlags::INHERITED
+        );
+        assert_eq!(aces[0].access_mask, 0x001f_01ff);
+        assert_eq!(aces[1].ace_type, AceType::AccessDenied);
+        assert_eq!(
+            aces[1].sid.as_ref().unwrap().to_string(),
+            "S-1-5-32-544"
+        );
+    }
+
+    #[test]
+    fn test_truncated_security_descriptor() {
+        let data = security_descriptor();
+
+        assert!(SecurityDescriptor::parse(&data[..data.len() - 4]).is_err());
+    }
+}
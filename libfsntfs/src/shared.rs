@@ -0,0 +1,216 @@
+//! Volumes which can be cloned into worker threads.
+//!
+//! libfsntfs volumes are not thread-safe, so every clone of a `SharedVolume` opens a libfsntfs
+//! volume of its own over the same image. Only reading the image itself is shared:
+//!
+//! * Volumes opened by path give every clone its own file descriptor, nothing is serialized.
+//! * Volumes opened from a reader share it behind a mutex, reads of the image are serialized
+//!   (everything else, e.g. parsing MFT entries, runs concurrently).
+//!
+//! ```ignore
+//! let volume = SharedVolume::open("image.dd", &VolumeOpenOptions::new())?;
+//!
+//! let workers: Vec<_> = (0..4)
+//!     .map(|_| {
+//!         let volume = volume.try_clone()?;
+//!         Ok(thread::spawn(move || volume.get_root_directory().map(|root| root.get_size())))
+//!     })
+//!     .collect::<Result<_, Error>>()?;
+//! ```
+use crate::error::Error;
+use crate::images::PartitionReader;
+use crate::options::VolumeOpenOptions;
+use crate::volume::Volume;
+use libbfio_rs::handle::Handle;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+/// Readers which volumes can be opened from, see `SharedVolume::from_reader`.
+pub trait ReadSeekSend: Read + Seek + Send {}
+impl<T: Read + Seek + Send> ReadSeekSend for T {}
+
+enum Source {
+    Path(String),
+    Reader(Arc<Mutex<Box<dyn ReadSeekSend>>>),
+}
+
+/// A view of the shared reader with a position of its own, every read seeks first.
+pub(crate) struct SharedReader {
+    inner: Arc<Mutex<Box<dyn ReadSeekSend>>>,
+    position: u64,
+}
+
+impl SharedReader {
+    pub(crate) fn new(inner: Arc<Mutex<Box<dyn ReadSeekSend>>>) -> Self {
+        SharedReader { inner, position: 0 }
+    }
+
+    fn lock(&self) -> io::Result<std::sync::MutexGuard<Box<dyn ReadSeekSend>>> {
+        self.inner
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "shared reader mutex is poisoned"))
+    }
+}
+
+impl Read for SharedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = {
+            let mut inner = self.lock()?;
+            inner.seek(SeekFrom::Start(self.position))?;
+            inner.read(buf)?
+        };
+
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for SharedReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::Current(offset) => {
+                let position = self.position as i64 + offset;
+
+                if position < 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "seek before the start of the stream",
+                    ));
+                }
+
+                position as u64
+            }
+            SeekFrom::End(offset) => self.lock()?.seek(SeekFrom::End(offset))?,
+        };
+
+        Ok(self.position)
+    }
+}
+
+/// A volume which can be cloned (`try_clone`) and sent to other threads, see the module docs.
+/// Dereferences to `Volume`.
+pub struct SharedVolume {
+    // Dropped (closed) before the handle it reads from.
+    volume: Volume,
+    handle: Option<Handle>,
+    source: Arc<Source>,
+}
+
+// The volume and the handle are owned by this clone alone, the state shared with the other
+// clones is the `Source`, of which the reader is behind a mutex.
+unsafe impl Send for SharedVolume {}
+
+impl SharedVolume {
+    pub fn open(filename: impl AsRef<str>, options: &VolumeOpenOptions) -> Result<Self, Error> {
+        SharedVolume::open_source(
+            Arc::new(Source::Path(filename.as_ref().to_owned())),
+            options,
+        )
+    }
+
+    /// Opens the volume starting `offset` bytes into the image at `filename`, e.g. a partition
+    /// (see `partition::PartitionTable`).
+    pub fn open_with_offset(
+        filename: impl AsRef<str>,
+        offset: u64,
+        options: &VolumeOpenOptions,
+    ) -> Result<Self, Error> {
+        let file = File::open(filename.as_ref())?;
+        let size = file.metadata()?.len().saturating_sub(offset);
+
+        SharedVolume::from_reader(PartitionReader::new(file, offset, size), options)
+    }
+
+    /// Opens the volume in `reader`, e.g. a partition of a disk image.
+    pub fn from_reader(
+        reader: impl Read + Seek + Send + 'static,
+        options: &VolumeOpenOptions,
+    ) -> Result<Self, Error> {
+        let reader: Box<dyn ReadSeekSend> = Box::new(reader);
+
+        SharedVolume::open_source(
+            Arc::new(Source::Reader(Arc::new(Mutex::new(reader)))),
+            options,
+        )
+    }
+
+    fn open_source(source: Arc<Source>, options: &VolumeOpenOptions) -> Result<Self, Error> {
+        let (volume, handle) = match *source {
+            Source::Path(ref filename) => (Volume::open_with_options(filename, options)?, None),
+            Source::Reader(ref reader) => {
+                let reader: Box<dyn ReadSeekSend> = Box::new(SharedReader::new(reader.clone()));
+                let (reader, recovery) = Volume::with_recovery(reader, options)?;
+                let handle = Volume::io_handle(reader, options)?;
+
+                let mut volume = Volume::open_file_object_with_options(&handle, options)?;
+                volume.set_recovery(recovery);
+                (volume, Some(handle))
+            }
+        };
+
+        Ok(SharedVolume {
+            volume,
+            handle,
+            source,
+        })
+    }
+
+    /// Opens another libfsntfs volume over the same image, with the same options.
+    /// Caches (e.g. of MFT entries) are not shared between clones.
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        SharedVolume::open_source(self.source.clone(), self.volume.options())
+    }
+}
+
+impl Deref for SharedVolume {
+    type Target = Volume;
+
+    fn deref(&self) -> &Volume {
+        &self.volume
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use std::thread;
+
+    fn root_sub_entries(volume: &Volume) -> i32 {
+        volume
+            .get_root_directory()
+            .unwrap()
+            .get_number_of_sub_file_entries()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_clones_in_threads() {
+        let file = File::open(sample_volume_path()).unwrap();
+        let volume = SharedVolume::from_reader(file, &VolumeOpenOptions::new()).unwrap();
+        let expected = root_sub_entries(&volume);
+
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let volume = volume.try_clone().unwrap();
+                thread::spawn(move || root_sub_entries(&volume))
+            })
+            .collect();
+
+        for worker in workers {
+            assert_eq!(worker.join().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_open_with_offset() {
+        let volume =
+            SharedVolume::open_with_offset(sample_volume_path(), 0, &VolumeOpenOptions::new())
+                .unwrap();
+
+        assert!(root_sub_entries(&volume) > 0);
+    }
+}
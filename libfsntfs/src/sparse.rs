@@ -0,0 +1,397 @@
+//! Proportionate collection: images holding only the clusters needed to parse some artifacts
+//! again, found by auditing the reads of parsing them (see `audit`).
+//!
+//! ```ignore
+//! let sparse = SparseImage::for_file_references("image.dd", &[reference], &options)?;
+//!
+//! // A raw image of the size of the volume, with the other clusters left unwritten.
+//! sparse.write_raw(&mut File::open("image.dd")?, &mut File::create("sparse.dd")?)?;
+//!
+//! // Or only the ranges, which `RangeContainerReader` reads back as a volume.
+//! sparse.write_container(&mut File::open("image.dd")?, &mut File::create("sparse.ntr")?)?;
+//! let volume = SharedVolume::from_reader(
+//!     RangeContainerReader::open(File::open("sparse.ntr")?)?,
+//!     &VolumeOpenOptions::new(),
+//! )?;
+//! ```
+use crate::audit::ReadAudit;
+use crate::error::Error;
+use crate::mft_record::invalid;
+use crate::options::VolumeOpenOptions;
+use crate::paths::PathResolver;
+use crate::volume::Volume;
+use std::convert::TryInto;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+/// Signature at the start of a range container.
+pub const RANGE_CONTAINER_SIGNATURE: &[u8; 8] = b"NTFSRNG1";
+
+/// The byte ranges of a volume to collect, in whole clusters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseImage {
+    ranges: Vec<Range<u64>>,
+}
+
+impl SparseImage {
+    /// The ranges of `audit`, widened to whole clusters of `cluster_size` bytes, which must not
+    /// be 0.
+    pub fn from_audit(audit: &ReadAudit, cluster_size: u64) -> Result<Self, Error> {
+        if cluster_size == 0 {
+            return Err(Error::Other("the cluster size must not be 0".to_owned()));
+        }
+
+        let clusters = ReadAudit::new();
+
+        for range in audit.ranges() {
+            let start = range.start / cluster_size * cluster_size;
+            let end = (range.end + cluster_size - 1) / cluster_size * cluster_size;
+
+            clusters.record(start..end);
+        }
+
+        Ok(SparseImage {
+            ranges: clusters.ranges(),
+        })
+    }
+
+    /// What parsing the entries `file_references` point to reads from the volume in
+    /// `image_path`: their MFT records, their paths (the records and indexes of their parent
+    /// directories) and all of their data streams, besides the metadata libfsntfs reads when
+    /// opening the volume.
+    pub fn for_file_references(
+        image_path: impl AsRef<str>,
+        file_references: &[u64],
+        options: &VolumeOpenOptions,
+    ) -> Result<Self, Error> {
+        let audit = ReadAudit::new();
+        let options = options.clone().read_audit(audit.clone());
+        let volume = Volume::open_with_options(image_path, &options)?;
+
+        let mut resolver = PathResolver::new();
+
+        for &file_reference in file_references {
+            let (entry, state) = volume.get_file_entry_by_reference(file_reference)?;
+
+            if state.is_stale() {
+                return Err(Error::NotFound(format!(
+                    "ref:{:#x}, its MFT entry was reused",
+                    file_reference
+                )));
+            }
+
+            resolver.link_paths(&entry)?;
+
+            if let Some(mut stream) = entry.data_stream(None)? {
+                io::copy(&mut stream, &mut io::sink())?;
+            }
+
+            for stream in entry.iter_alternate_data_streams()? {
+                io::copy(&mut stream?, &mut io::sink())?;
+            }
+        }
+
+        let cluster_size = volume.get_cluster_block_size()? as u64;
+
+        SparseImage::from_audit(&audit, cluster_size)
+    }
+
+    pub fn ranges(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    /// How many bytes are collected.
+    pub fn size(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum()
+    }
+
+    /// Copies the ranges from `image` to the same offsets of `output`, which ends up as large
+    /// as `image`. The rest of `output` is not written, so files stay sparse where the file
+    /// system supports it.
+    pub fn write_raw<R: Read + Seek, W: Write + Seek>(
+        &self,
+        image: &mut R,
+        output: &mut W,
+    ) -> Result<(), Error> {
+        let image_size = image.seek(SeekFrom::End(0))?;
+        let mut end = 0;
+
+        for range in self.ranges_within(image_size) {
+            output.seek(SeekFrom::Start(range.start))?;
+            copy_range(image, &range, output)?;
+
+            end = range.end;
+        }
+
+        if end < image_size {
+            output.seek(SeekFrom::Start(image_size - 1))?;
+            output.write_all(&[0])?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the ranges of `image` to `output` as a range container: the
+    /// `RANGE_CONTAINER_SIGNATURE`, the size of the image and the number of ranges, then the
+    /// offset, length and data of each range (integers as little endian `u64`).
+    pub fn write_container<R: Read + Seek, W: Write>(
+        &self,
+        image: &mut R,
+        output: &mut W,
+    ) -> Result<(), Error> {
+        let image_size = image.seek(SeekFrom::End(0))?;
+        let ranges = self.ranges_within(image_size);
+
+        output.write_all(RANGE_CONTAINER_SIGNATURE)?;
+        output.write_all(&image_size.to_le_bytes())?;
+        output.write_all(&(ranges.len() as u64).to_le_bytes())?;
+
+        for range in ranges {
+            output.write_all(&range.start.to_le_bytes())?;
+            output.write_all(&(range.end - range.start).to_le_bytes())?;
+
+            copy_range(image, &range, output)?;
+        }
+
+        Ok(())
+    }
+
+    /// The ranges, cut at the end of the image (the last cluster might be partial).
+    fn ranges_within(&self, image_size: u64) -> Vec<Range<u64>> {
+        self.ranges
+            .iter()
+            .filter(|range| range.start < image_size)
+            .map(|range| range.start..range.end.min(image_size))
+            .collect()
+    }
+}
+
+/// Copies `range` of `image` to `output`, failing if the image ends before it.
+fn copy_range<R: Read + Seek, W: Write>(
+    image: &mut R,
+    range: &Range<u64>,
+    output: &mut W,
+) -> Result<(), Error> {
+    image.seek(SeekFrom::Start(range.start))?;
+    let copied = io::copy(&mut image.take(range.end - range.start), output)?;
+
+    if copied != range.end - range.start {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "copied {} of the {} bytes at offset {} of the image",
+                copied,
+                range.end - range.start,
+                range.start
+            ),
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Reads a range container as the image it was written from, with zeros outside its ranges.
+pub struct RangeContainerReader<R> {
+    inner: R,
+    image_size: u64,
+    /// The ranges of the image, with the offset of their data in the container.
+    ranges: Vec<(Range<u64>, u64)>,
+    position: u64,
+}
+
+impl<R: Read + Seek> RangeContainerReader<R> {
+    pub fn open(mut inner: R) -> Result<Self, Error> {
+        let mut header = [0; 24];
+        inner.seek(SeekFrom::Start(0))?;
+        inner.read_exact(&mut header)?;
+
+        if header[..8] != RANGE_CONTAINER_SIGNATURE[..] {
+            return Err(invalid("not a range container".to_owned()));
+        }
+
+        let image_size = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let number_of_ranges = u64::from_le_bytes(header[16..24].try_into().unwrap());
+
+        let mut ranges = vec![];
+        let mut offset = header.len() as u64;
+
+        for _ in 0..number_of_ranges {
+            let mut range_header = [0; 16];
+            inner.seek(SeekFrom::Start(offset))?;
+            inner.read_exact(&mut range_header)?;
+
+            let start = u64::from_le_bytes(range_header[..8].try_into().unwrap());
+            let length = u64::from_le_bytes(range_header[8..].try_into().unwrap());
+
+            let end = start
+                .checked_add(length)
+                .ok_or_else(|| invalid(format!("range at {} overflows", start)))?;
+            let data_offset = offset + 16;
+            offset = data_offset
+                .checked_add(length)
+                .ok_or_else(|| invalid(format!("range at {} overflows the container", start)))?;
+
+            ranges.push((start..end, data_offset));
+        }
+
+        Ok(RangeContainerReader {
+            inner,
+            image_size,
+            ranges,
+            position: 0,
+        })
+    }
+
+    pub fn image_size(&self) -> u64 {
+        self.image_size
+    }
+}
+
+impl<R: Read + Seek> Read for RangeContainerReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let position = self.position;
+        let available = self.image_size.saturating_sub(position);
+        let buf_len = (buf.len() as u64).min(available) as usize;
+
+        let read = match self
+            .ranges
+            .iter()
+            .find(|(range, _)| range.contains(&position))
+        {
+            Some((range, data_offset)) => {
+                let length = buf_len.min((range.end - position) as usize);
+
+                self.inner
+                    .seek(SeekFrom::Start(data_offset + position - range.start))?;
+                self.inner.read(&mut buf[..length])?
+            }
+            None => {
+                // Zeros up to the next range.
+                let length = self
+                    .ranges
+                    .iter()
+                    .map(|(range, _)| range.start)
+                    .filter(|start| *start > position)
+                    .map(|start| (start - position).min(buf_len as u64) as usize)
+                    .min()
+                    .unwrap_or(buf_len);
+
+                buf[..length].iter_mut().for_each(|byte| *byte = 0);
+                length
+            }
+        };
+
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for RangeContainerReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => offset_by(self.position, offset),
+            SeekFrom::End(offset) => offset_by(self.image_size, offset),
+        };
+
+        self.position = position
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start"))?;
+
+        Ok(self.position)
+    }
+}
+
+fn offset_by(position: u64, offset: i64) -> Option<u64> {
+    if offset < 0 {
+        position.checked_sub(offset.wrapping_neg() as u64)
+    } else {
+        position.checked_add(offset as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::shared::SharedVolume;
+    use crate::utils::mft_entry_index;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_from_audit() {
+        let audit = ReadAudit::new();
+        audit.record(10..20);
+        audit.record(4100..4200);
+
+        let sparse = SparseImage::from_audit(&audit, 4096).unwrap();
+
+        assert_eq!(sparse.ranges(), [0..8192]);
+        assert_eq!(sparse.size(), 8192);
+        assert!(SparseImage::from_audit(&audit, 0).is_err());
+    }
+
+    #[test]
+    fn test_container_with_overflowing_range() {
+        let mut container = RANGE_CONTAINER_SIGNATURE.to_vec();
+        container.extend_from_slice(&8u64.to_le_bytes());
+        container.extend_from_slice(&1u64.to_le_bytes());
+        container.extend_from_slice(&u64::max_value().to_le_bytes());
+        container.extend_from_slice(&1u64.to_le_bytes());
+
+        assert!(RangeContainerReader::open(Cursor::new(container)).is_err());
+    }
+
+    #[test]
+    fn test_write_raw() {
+        let audit = ReadAudit::new();
+        audit.record(2..4);
+
+        let mut output = Cursor::new(vec![]);
+        SparseImage::from_audit(&audit, 2)
+            .unwrap()
+            .write_raw(&mut Cursor::new(vec![1, 2, 3, 4, 5, 6]), &mut output)
+            .unwrap();
+
+        assert_eq!(output.into_inner(), [0, 0, 3, 4, 0, 0]);
+    }
+
+    #[test]
+    fn test_container_of_file() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+        let reference = entry.get_file_reference().unwrap();
+        let size = entry.get_size().unwrap();
+
+        let sparse = SparseImage::for_file_references(
+            sample_volume_path(),
+            &[reference],
+            &VolumeOpenOptions::new(),
+        )
+        .unwrap();
+
+        let mut image = File::open(sample_volume_path()).unwrap();
+        let image_size = image.seek(SeekFrom::End(0)).unwrap();
+        assert!(sparse.size() < image_size);
+
+        let mut container = Cursor::new(vec![]);
+        sparse.write_container(&mut image, &mut container).unwrap();
+
+        let reader = RangeContainerReader::open(container).unwrap();
+        assert_eq!(reader.image_size(), image_size);
+
+        let collected = SharedVolume::from_reader(reader, &VolumeOpenOptions::new()).unwrap();
+        let mut collected_entry = collected
+            .get_file_entry_by_mft_idx(mft_entry_index(reference))
+            .unwrap();
+
+        let mut data = vec![];
+        collected_entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data.len() as u64, size);
+    }
+}
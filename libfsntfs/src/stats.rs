@@ -0,0 +1,97 @@
+//! Volume wide statistics, for triage reports and for sizing extraction jobs.
+use crate::bitmap::ClusterBitmap;
+use crate::error::Error;
+use crate::metadata_files;
+use crate::volume::Volume;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeStats {
+    /// Number of MFT entries, allocated or not.
+    pub number_of_file_entries: u64,
+    pub number_of_allocated_file_entries: u64,
+    /// Allocated entries which have a directory index.
+    pub number_of_directories: u64,
+    /// Allocated entries which are not directories.
+    pub number_of_files: u64,
+    pub cluster_size: u64,
+    pub total_clusters: u64,
+    /// Clusters marked as in use in `$Bitmap`.
+    pub allocated_clusters: u64,
+    pub free_clusters: u64,
+    /// Size of the `$MFT` data, in bytes.
+    pub mft_size: u64,
+    /// Number of extents `$MFT` is split into, 1 means it is contiguous.
+    pub mft_fragments: u64,
+}
+
+impl VolumeStats {
+    pub(crate) fn collect(volume: &Volume) -> Result<Self, Error> {
+        let mut stats = VolumeStats::default();
+
+        for (_idx, entry) in volume.mft_entries()? {
+            stats.number_of_file_entries += 1;
+
+            // Entries which were never used (or are corrupted) cannot be read, they just count as unallocated.
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+
+            if !entry.is_allocated()? {
+                continue;
+            }
+
+            stats.number_of_allocated_file_entries += 1;
+
+            if entry.has_directory_entries_index()? {
+                stats.number_of_directories += 1;
+            } else {
+                stats.number_of_files += 1;
+            }
+        }
+
+        let mft = volume.get_file_entry_by_mft_idx(metadata_files::MFT)?;
+        stats.mft_size = mft.get_size()?;
+        stats.mft_fragments = mft.get_number_of_extents()? as u64;
+
+        let bitmap = ClusterBitmap::read(volume)?;
+        stats.cluster_size = bitmap.cluster_size();
+        stats.total_clusters = bitmap.total_clusters();
+        stats.allocated_clusters = bitmap.allocated_clusters();
+        stats.free_clusters = stats.total_clusters - stats.allocated_clusters;
+
+        Ok(stats)
+    }
+
+    /// Whether `$MFT` is split into more than one extent.
+    pub fn is_mft_fragmented(&self) -> bool {
+        self.mft_fragments > 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_stats() {
+        let volume = sample_volume().unwrap();
+        let stats = volume.stats().unwrap();
+
+        assert_eq!(
+            stats.number_of_file_entries,
+            volume.number_of_file_entries().unwrap()
+        );
+        assert_eq!(
+            stats.number_of_directories + stats.number_of_files,
+            stats.number_of_allocated_file_entries
+        );
+        assert_eq!(
+            stats.allocated_clusters + stats.free_clusters,
+            stats.total_clusters
+        );
+        assert!(stats.allocated_clusters > 0);
+        assert!(stats.mft_fragments >= 1);
+    }
+}
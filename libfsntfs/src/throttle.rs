@@ -0,0 +1,142 @@
+//! Bounds the rate at which a volume reads its image, for collection on live systems.
+//!
+//! ```ignore
+//! // Every volume opened with these options shares the same 20 MiB/s.
+//! let options = VolumeOpenOptions::new().throttle(Throttle::new(20 << 20));
+//! let volume = Volume::open_with_options("\\\\.\\C:", &options)?;
+//! ```
+use std::fmt::{self, Debug, Formatter};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Reading falls behind the rate by more than this (e.g. while idle) before the time lost is
+/// forgotten, which bounds the burst after an idle period.
+const MAX_CATCH_UP: Duration = Duration::from_secs(1);
+
+struct Bucket {
+    bytes_per_second: u64,
+    start: Instant,
+    consumed: u64,
+}
+
+/// A rate limit in bytes per second, clones share it.
+#[derive(Clone)]
+pub struct Throttle(Arc<Mutex<Bucket>>);
+
+impl Throttle {
+    pub fn new(bytes_per_second: u64) -> Self {
+        Throttle(Arc::new(Mutex::new(Bucket {
+            bytes_per_second: bytes_per_second.max(1),
+            start: Instant::now(),
+            consumed: 0,
+        })))
+    }
+
+    pub fn bytes_per_second(&self) -> u64 {
+        self.0
+            .lock()
+            .map(|bucket| bucket.bytes_per_second)
+            .unwrap_or(0)
+    }
+
+    /// Accounts for `bytes` which were read, sleeping until the rate allows them.
+    pub fn consume(&self, bytes: usize) {
+        let delay = {
+            let mut bucket = match self.0.lock() {
+                Ok(bucket) => bucket,
+                Err(_) => return,
+            };
+
+            let elapsed = bucket.start.elapsed();
+            let expected = Duration::from_secs_f64(
+                (bucket.consumed + bytes as u64) as f64 / bucket.bytes_per_second as f64,
+            );
+
+            if elapsed > expected + MAX_CATCH_UP {
+                bucket.start = Instant::now();
+                bucket.consumed = bytes as u64;
+                return;
+            }
+
+            bucket.consumed += bytes as u64;
+
+            if expected > elapsed {
+                expected - elapsed
+            } else {
+                return;
+            }
+        };
+
+        thread::sleep(delay);
+    }
+}
+
+impl Debug for Throttle {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("Throttle")
+            .field(&self.bytes_per_second())
+            .finish()
+    }
+}
+
+/// Reads from `inner` no faster than the throttle allows.
+pub struct ThrottledReader<R> {
+    inner: R,
+    throttle: Throttle,
+}
+
+impl<R: Read> ThrottledReader<R> {
+    pub fn new(inner: R, throttle: Throttle) -> Self {
+        ThrottledReader { inner, throttle }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for ThrottledReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.throttle.consume(read);
+
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for ThrottledReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::options::VolumeOpenOptions;
+    use crate::volume::Volume;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_throttled_reader() {
+        let mut reader = ThrottledReader::new(Cursor::new(vec![0; 1000]), Throttle::new(10_000));
+
+        let start = Instant::now();
+        let mut data = vec![];
+        reader.read_to_end(&mut data).unwrap();
+
+        assert_eq!(data.len(), 1000);
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[test]
+    fn test_throttled_volume() {
+        let options = VolumeOpenOptions::new().throttle(Throttle::new(1 << 30));
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+
+        assert!(volume.get_root_directory().is_ok());
+    }
+}
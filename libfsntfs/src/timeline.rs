@@ -0,0 +1,287 @@
+//! Timeline events of MFT entries, one per distinct timestamp of `$STANDARD_INFORMATION`
+//! and `$FILE_NAME`, written in the log2timeline/plaso CSV format (l2tcsv).
+//!
+//! Events are written in MFT order, sort them by date and time for a chronological timeline.
+use crate::attribute::AttributeType;
+use crate::csv_export::escape;
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::filter::EntryFilter;
+use crate::paths::PathResolver;
+use crate::timestamps::{MacbField, MacbTimes};
+use crate::utils::datetime_from_filetime;
+use crate::volume::{MftEntryIndex, Volume};
+use std::io::Write;
+
+pub const L2TCSV_HEADER: [&str; 17] = [
+    "date", "time", "timezone", "MACB", "source", "sourcetype", "type", "user", "host", "short",
+    "desc", "version", "filename", "inode", "notes", "format", "extra",
+];
+
+/// The MACB fields, in the order of the `MACB` column.
+const MACB_FIELDS: [(MacbField, char); 4] = [
+    (MacbField::Modification, 'M'),
+    (MacbField::Access, 'A'),
+    (MacbField::EntryModification, 'C'),
+    (MacbField::Creation, 'B'),
+];
+
+pub(crate) fn timestamp_description(field: MacbField) -> &'static str {
+    match field {
+        MacbField::Modification => "Content Modification Time",
+        MacbField::Access => "Last Access Time",
+        MacbField::EntryModification => "Metadata Modification Time",
+        MacbField::Creation => "Creation Time",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEvent {
+    /// The timestamp as a FILETIME.
+    pub filetime: u64,
+    /// Either `StandardInformation` or `FileName`.
+    pub attribute_type: AttributeType,
+    /// The fields of the attribute which share this timestamp.
+    pub fields: Vec<MacbField>,
+    pub mft_entry_index: MftEntryIndex,
+    pub path: String,
+}
+
+impl TimelineEvent {
+    /// The fields of the event as in `M.CB`.
+    pub fn macb(&self) -> String {
+        MACB_FIELDS
+            .iter()
+            .map(|(field, c)| if self.fields.contains(field) { *c } else { '.' })
+            .collect()
+    }
+
+    /// e.g. `Content Modification Time; Creation Time`.
+    pub fn timestamp_description(&self) -> String {
+        let descriptions: Vec<&str> = self
+            .fields
+            .iter()
+            .map(|field| timestamp_description(*field))
+            .collect();
+
+        descriptions.join("; ")
+    }
+
+    fn l2tcsv_row(&self) -> String {
+        let datetime = datetime_from_filetime(self.filetime);
+        let source_type = format!("NTFS {}", self.attribute_type.name());
+        let timestamp_description = self.timestamp_description();
+
+        let fields = [
+            datetime.format("%m/%d/%Y").to_string(),
+            datetime.format("%H:%M:%S").to_string(),
+            "UTC".to_owned(),
+            self.macb(),
+            "FILE".to_owned(),
+            source_type,
+            timestamp_description.clone(),
+            "-".to_owned(),
+            "-".to_owned(),
+            self.path.clone(),
+            format!("{} ({})", self.path, timestamp_description),
+            "2".to_owned(),
+            self.path.clone(),
+            self.mft_entry_index.to_string(),
+            "-".to_owned(),
+            "mft".to_owned(),
+            "-".to_owned(),
+        ];
+
+        let fields: Vec<String> = fields.iter().map(|field| escape(field)).collect();
+        fields.join(",")
+    }
+}
+
+/// Groups the fields of one attribute by timestamp, unset timestamps are left out.
+fn attribute_events(
+    times: MacbTimes,
+    attribute_type: AttributeType,
+    mft_entry_index: MftEntryIndex,
+    path: &str,
+) -> Vec<TimelineEvent> {
+    let mut events: Vec<TimelineEvent> = vec![];
+
+    for (field, _) in MACB_FIELDS.iter() {
+        let filetime = times.get(*field);
+
+        if filetime == 0 {
+            continue;
+        }
+
+        match events.iter_mut().find(|event| event.filetime == filetime) {
+            Some(event) => event.fields.push(*field),
+            None => events.push(TimelineEvent {
+                filetime,
+                attribute_type,
+                fields: vec![*field],
+                mft_entry_index,
+                path: path.to_owned(),
+            }),
+        }
+    }
+
+    events
+}
+
+impl<'a> FileEntry<'a> {
+    /// The timeline events of the entry, `path` is the full path of the entry.
+    pub fn timeline_events(
+        &self,
+        mft_entry_index: MftEntryIndex,
+        path: &str,
+    ) -> Result<Vec<TimelineEvent>, Error> {
+        let report = self.get_timestamp_report()?;
+        let mut events = vec![];
+
+        if let Some(times) = report.standard_information {
+            events.extend(attribute_events(
+                times,
+                AttributeType::StandardInformation,
+                mft_entry_index,
+                path,
+            ));
+        }
+
+        if let Some(times) = report.file_name {
+            events.extend(attribute_events(
+                times,
+                AttributeType::FileName,
+                mft_entry_index,
+                path,
+            ));
+        }
+
+        Ok(events)
+    }
+}
+
+pub struct L2tCsvWriter<W: Write> {
+    writer: W,
+    paths: PathResolver,
+}
+
+impl<W: Write> L2tCsvWriter<W> {
+    pub fn new(writer: W) -> Self {
+        L2tCsvWriter {
+            writer,
+            paths: PathResolver::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    pub fn write_header(&mut self) -> Result<(), Error> {
+        writeln!(self.writer, "{}", L2TCSV_HEADER.join(","))?;
+        Ok(())
+    }
+
+    pub fn write_event(&mut self, event: &TimelineEvent) -> Result<(), Error> {
+        writeln!(self.writer, "{}", event.l2tcsv_row())?;
+        Ok(())
+    }
+
+    /// Writes the events of `entry`, the entry at `idx` of the MFT.
+    /// Returns the number of rows written.
+    pub fn write_entry(&mut self, idx: MftEntryIndex, entry: &FileEntry) -> Result<u64, Error> {
+        let path = self.paths.full_path(idx, entry);
+        let events = entry.timeline_events(idx, &path)?;
+
+        for event in events.iter() {
+            self.write_event(event)?;
+        }
+
+        Ok(events.len() as u64)
+    }
+}
+
+impl Volume {
+    /// Writes the timeline of every MFT entry to `writer` as l2tcsv,
+    /// entries which cannot be read are skipped. Returns the number of rows written.
+    pub fn export_l2tcsv<W: Write>(&self, writer: W) -> Result<u64, Error> {
+        self.export_l2tcsv_filtered(writer, &EntryFilter::default())
+    }
+
+    /// Like `export_l2tcsv`, only writing the events of the entries matching `filter`.
+    pub fn export_l2tcsv_filtered<W: Write>(
+        &self,
+        writer: W,
+        filter: &EntryFilter,
+    ) -> Result<u64, Error> {
+        let mut l2tcsv_writer = L2tCsvWriter::new(writer);
+        l2tcsv_writer.write_header()?;
+
+        let mut rows = 0;
+
+        for (idx, entry) in self.mft_entries()? {
+            if let Ok(entry) = entry {
+                if !filter.matches(&entry).unwrap_or(false) {
+                    continue;
+                }
+
+                rows += l2tcsv_writer.write_entry(idx, &entry)?;
+            }
+        }
+
+        l2tcsv_writer.into_inner().flush()?;
+
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    // 2019-01-01 00:00:00.1234567
+    const TIME: u64 = 131_907_744_001_234_567;
+
+    #[test]
+    fn test_attribute_events_group_equal_timestamps() {
+        let times = MacbTimes {
+            modification: TIME,
+            access: TIME + 1,
+            entry_modification: TIME,
+            creation: 0,
+        };
+
+        let events = attribute_events(times, AttributeType::StandardInformation, 42, "\\a.txt");
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].macb(), "M.C.");
+        assert_eq!(
+            events[0].timestamp_description(),
+            "Content Modification Time; Metadata Modification Time"
+        );
+        assert_eq!(events[1].macb(), ".A..");
+
+        assert_eq!(
+            events[0].l2tcsv_row(),
+            "01/01/2019,00:00:00,UTC,M.C.,FILE,NTFS $STANDARD_INFORMATION,\
+             Content Modification Time; Metadata Modification Time,-,-,\\a.txt,\
+             \\a.txt (Content Modification Time; Metadata Modification Time),2,\\a.txt,42,-,mft,-"
+        );
+    }
+
+    #[test]
+    fn test_export_l2tcsv() {
+        let volume = sample_volume().unwrap();
+        let mut output = vec![];
+
+        let rows = volume.export_l2tcsv(&mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines();
+
+        assert_eq!(lines.next(), Some(L2TCSV_HEADER.join(",").as_str()));
+        assert_eq!(lines.count() as u64, rows);
+        assert!(rows > 0);
+    }
+}
@@ -0,0 +1,246 @@
+//! Compares the `$STANDARD_INFORMATION` and `$FILE_NAME` timestamps of an entry.
+//!
+//! Tools which change timestamps ("timestomping") usually only touch `$STANDARD_INFORMATION`,
+//! and often with a whole-second precision, which both leave traces this module reports.
+use crate::attribute::*;
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::utils::datetime_from_filetime;
+use chrono::{DateTime, Utc};
+use libyal_rs_common::ffi::AsTypeRef;
+use std::convert::TryFrom;
+use std::ptr;
+
+/// FILETIME ticks (100 nanoseconds) in a second.
+const FILETIME_TICKS_PER_SECOND: u64 = 10_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MacbField {
+    Modification,
+    Access,
+    /// Modification of the MFT entry itself (the `C` in MACB on NTFS).
+    EntryModification,
+    Creation,
+}
+
+const MACB_FIELDS: [MacbField; 4] = [
+    MacbField::Modification,
+    MacbField::Access,
+    MacbField::EntryModification,
+    MacbField::Creation,
+];
+
+/// The four MACB timestamps of a single attribute, as raw FILETIMEs (0 when not set).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MacbTimes {
+    pub modification: u64,
+    pub access: u64,
+    pub entry_modification: u64,
+    pub creation: u64,
+}
+
+impl MacbTimes {
+    pub fn get(&self, field: MacbField) -> u64 {
+        match field {
+            MacbField::Modification => self.modification,
+            MacbField::Access => self.access,
+            MacbField::EntryModification => self.entry_modification,
+            MacbField::Creation => self.creation,
+        }
+    }
+
+    pub fn get_datetime(&self, field: MacbField) -> Option<DateTime<Utc>> {
+        match self.get(field) {
+            0 => None,
+            filetime => Some(datetime_from_filetime(filetime)),
+        }
+    }
+
+    fn from_standard_information(attribute: &Attribute) -> Result<Self, Error> {
+        Ok(MacbTimes {
+            modification: get_u64_field!(
+                attribute,
+                libfsntfs_standard_information_attribute_get_modification_time
+            )?,
+            access: get_u64_field!(
+                attribute,
+                libfsntfs_standard_information_attribute_get_access_time
+            )?,
+            entry_modification: get_u64_field!(
+                attribute,
+                libfsntfs_standard_information_attribute_get_entry_modification_time
+            )?,
+            creation: get_u64_field!(
+                attribute,
+                libfsntfs_standard_information_attribute_get_creation_time
+            )?,
+        })
+    }
+
+    fn from_file_name(attribute: &Attribute) -> Result<Self, Error> {
+        Ok(MacbTimes {
+            modification: get_u64_field!(
+                attribute,
+                libfsntfs_file_name_attribute_get_modification_time
+            )?,
+            access: get_u64_field!(attribute, libfsntfs_file_name_attribute_get_access_time)?,
+            entry_modification: get_u64_field!(
+                attribute,
+                libfsntfs_file_name_attribute_get_entry_modification_time
+            )?,
+            creation: get_u64_field!(attribute, libfsntfs_file_name_attribute_get_creation_time)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimestampAnomaly {
+    /// The `$FILE_NAME` timestamp is later than the `$STANDARD_INFORMATION` one,
+    /// normally `$STANDARD_INFORMATION` is updated at least as often.
+    FileNameNewerThanStandardInformation(MacbField),
+    /// The timestamp has no sub-second part, which Windows itself practically never writes.
+    ZeroedSubSecondPrecision(AttributeType, MacbField),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampReport {
+    pub standard_information: Option<MacbTimes>,
+    /// From the first `$FILE_NAME` attribute of the entry.
+    pub file_name: Option<MacbTimes>,
+    pub anomalies: Vec<TimestampAnomaly>,
+}
+
+impl TimestampReport {
+    pub fn from_times(
+        standard_information: Option<MacbTimes>,
+        file_name: Option<MacbTimes>,
+    ) -> Self {
+        let mut anomalies = vec![];
+
+        if let (Some(si), Some(fn_)) = (standard_information, file_name) {
+            for field in MACB_FIELDS.iter() {
+                if si.get(*field) != 0 && fn_.get(*field) > si.get(*field) {
+                    anomalies.push(TimestampAnomaly::FileNameNewerThanStandardInformation(
+                        *field,
+                    ));
+                }
+            }
+        }
+
+        let attribute_times = [
+            (AttributeType::StandardInformation, standard_information),
+            (AttributeType::FileName, file_name),
+        ];
+
+        for (attribute_type, times) in attribute_times.iter() {
+            if let Some(times) = times {
+                for field in MACB_FIELDS.iter() {
+                    let filetime = times.get(*field);
+
+                    if filetime != 0 && filetime % FILETIME_TICKS_PER_SECOND == 0 {
+                        anomalies.push(TimestampAnomaly::ZeroedSubSecondPrecision(
+                            *attribute_type,
+                            *field,
+                        ));
+                    }
+                }
+            }
+        }
+
+        TimestampReport {
+            standard_information,
+            file_name,
+            anomalies,
+        }
+    }
+
+    pub fn is_suspicious(&self) -> bool {
+        !self.anomalies.is_empty()
+    }
+}
+
+impl<'a> FileEntry<'a> {
+    /// Collects all eight MACB timestamps of the entry and checks them for signs of manipulation.
+    pub fn get_timestamp_report(&self) -> Result<TimestampReport, Error> {
+        let mut standard_information = None;
+        let mut file_name = None;
+
+        for attribute in self.iter_attributes()? {
+            let attribute = attribute?;
+
+            match attribute.get_type()? {
+                AttributeType::StandardInformation if standard_information.is_none() => {
+                    standard_information = Some(MacbTimes::from_standard_information(&attribute)?);
+                }
+                AttributeType::FileName if file_name.is_none() => {
+                    file_name = Some(MacbTimes::from_file_name(&attribute)?);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(TimestampReport::from_times(standard_information, file_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    // 2019-01-01 00:00:00.1234567
+    const PRECISE_TIME: u64 = 131_907_744_001_234_567;
+
+    #[test]
+    fn test_consistent_timestamps() {
+        let times = MacbTimes {
+            modification: PRECISE_TIME,
+            access: PRECISE_TIME,
+            entry_modification: PRECISE_TIME,
+            creation: PRECISE_TIME,
+        };
+
+        let report = TimestampReport::from_times(Some(times), Some(times));
+
+        assert!(!report.is_suspicious());
+    }
+
+    #[test]
+    fn test_timestomped_creation_time() {
+        let file_name = MacbTimes {
+            modification: PRECISE_TIME,
+            access: PRECISE_TIME,
+            entry_modification: PRECISE_TIME,
+            creation: PRECISE_TIME,
+        };
+        let standard_information = MacbTimes {
+            // Backdated by a year, with whole seconds.
+            creation: PRECISE_TIME - 1_234_567 - 365 * 24 * 3600 * FILETIME_TICKS_PER_SECOND,
+            ..file_name
+        };
+
+        let report = TimestampReport::from_times(Some(standard_information), Some(file_name));
+
+        assert_eq!(
+            report.anomalies,
+            vec![
+                TimestampAnomaly::FileNameNewerThanStandardInformation(MacbField::Creation),
+                TimestampAnomaly::ZeroedSubSecondPrecision(
+                    AttributeType::StandardInformation,
+                    MacbField::Creation
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_timestamp_report() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+
+        let report = entry.get_timestamp_report().unwrap();
+
+        assert!(report.standard_information.is_some());
+        assert!(report.file_name.is_some());
+    }
+}
@@ -0,0 +1,108 @@
+//! Transactional NTFS (TxF) metadata, stored in the `$TXF_DATA` logged utility stream
+//! of files which were written inside a transaction.
+use crate::attribute::AttributeType;
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::mft_record::{invalid, read_u16, read_u64, AttributeForm, MftRecord};
+use crate::utils::mft_entry_index;
+
+pub const TXF_DATA_STREAM_NAME: &str = "$TXF_DATA";
+
+const TXF_DATA_SIZE: usize = 0x38;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxfData {
+    /// File reference of the resource manager root directory.
+    pub resource_manager_root_file_reference: u64,
+    /// Offset of the file's USN record.
+    pub usn_index: u64,
+    /// Identifies the file in the TxF logs (`$Extend\$RmMetadata\$TxfLog`).
+    pub file_identifier: u64,
+    /// Log sequence numbers of the last transacted change to the data, the metadata and the directory index.
+    pub data_lsn: u64,
+    pub metadata_lsn: u64,
+    pub directory_index_lsn: u64,
+    pub flags: u16,
+}
+
+impl TxfData {
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < TXF_DATA_SIZE {
+            return Err(invalid(format!(
+                "$TXF_DATA is {} bytes, expected {}",
+                data.len(),
+                TXF_DATA_SIZE
+            )));
+        }
+
+        Ok(TxfData {
+            resource_manager_root_file_reference: read_u64(data, 0x06)?,
+            usn_index: read_u64(data, 0x0e)?,
+            file_identifier: read_u64(data, 0x16)?,
+            data_lsn: read_u64(data, 0x1e)?,
+            metadata_lsn: read_u64(data, 0x26)?,
+            directory_index_lsn: read_u64(data, 0x2e)?,
+            flags: read_u16(data, 0x36)?,
+        })
+    }
+}
+
+impl<'a> FileEntry<'a> {
+    /// Retrieves the TxF metadata of the entry, `None` if it was never part of a transaction.
+    pub fn get_txf_data(&self) -> Result<Option<TxfData>, Error> {
+        let mft_entry_index = mft_entry_index(self.get_file_reference()?);
+        let record = MftRecord::read(self.volume(), mft_entry_index)?;
+
+        let attribute = match record
+            .find_attribute(AttributeType::LoggedUtilityStream, TXF_DATA_STREAM_NAME)?
+        {
+            Some(attribute) => attribute,
+            None => return Ok(None),
+        };
+
+        match attribute.form {
+            AttributeForm::Resident { data } => Ok(Some(TxfData::parse(data)?)),
+            AttributeForm::NonResident { .. } => {
+                Err(invalid("$TXF_DATA is not resident".to_owned()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_parse_txf_data() {
+        let mut data = vec![0; 6];
+        data.extend_from_slice(&0x0005_0000_0000_000b_u64.to_le_bytes());
+        data.extend_from_slice(&0x1000_u64.to_le_bytes());
+        data.extend_from_slice(&0x42_u64.to_le_bytes());
+        data.extend_from_slice(&0x2000_u64.to_le_bytes());
+        data.extend_from_slice(&0x3000_u64.to_le_bytes());
+        data.extend_from_slice(&0x4000_u64.to_le_bytes());
+        data.extend_from_slice(&0x0001_u16.to_le_bytes());
+
+        let txf_data = TxfData::parse(&data).unwrap();
+
+        assert_eq!(
+            txf_data.resource_manager_root_file_reference,
+            0x0005_0000_0000_000b
+        );
+        assert_eq!(txf_data.file_identifier, 0x42);
+        assert_eq!(txf_data.directory_index_lsn, 0x4000);
+        assert_eq!(txf_data.flags, 1);
+
+        assert!(TxfData::parse(&data[..0x20]).is_err());
+    }
+
+    #[test]
+    fn test_get_txf_data_without_transaction() {
+        let volume = sample_volume().unwrap();
+        let entry = file_entry(&volume).unwrap();
+
+        assert!(entry.get_txf_data().unwrap().is_none());
+    }
+}
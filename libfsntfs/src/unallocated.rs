@@ -0,0 +1,192 @@
+//! Reads the unallocated clusters of a volume as one contiguous stream, for carving.
+//!
+//! libfsntfs only reads through files, so the clusters themselves are read from the raw volume
+//! (e.g. the image the `Volume` was opened from), which must start at the start of the volume.
+use crate::bitmap::{ClusterBitmap, ClusterRun};
+use std::io::{self, Read, Seek, SeekFrom};
+
+pub struct UnallocatedReader<R> {
+    image: R,
+    runs: Vec<ClusterRun>,
+    cluster_size: u64,
+    /// Offset in the unallocated stream where each run starts.
+    run_starts: Vec<u64>,
+    /// Index of the run being read.
+    current_run: usize,
+    /// Offset into the unallocated stream.
+    position: u64,
+    /// Whether `image` is positioned at `position`.
+    image_in_sync: bool,
+}
+
+impl<R: Read + Seek> UnallocatedReader<R> {
+    pub fn new(image: R, bitmap: &ClusterBitmap) -> Self {
+        let cluster_size = bitmap.cluster_size();
+        let runs: Vec<ClusterRun> = bitmap.unallocated_runs().collect();
+
+        let mut run_starts = Vec::with_capacity(runs.len());
+        let mut run_start = 0;
+
+        for run in &runs {
+            run_starts.push(run_start);
+            run_start += run.size(cluster_size);
+        }
+
+        UnallocatedReader {
+            image,
+            runs,
+            cluster_size,
+            run_starts,
+            current_run: 0,
+            position: 0,
+            image_in_sync: false,
+        }
+    }
+
+    /// The unallocated cluster runs, in the order they are read.
+    pub fn runs(&self) -> &[ClusterRun] {
+        &self.runs
+    }
+
+    /// Total size of the unallocated stream, in bytes.
+    pub fn len(&self) -> u64 {
+        match (self.runs.last(), self.run_starts.last()) {
+            (Some(run), Some(start)) => start + run.size(self.cluster_size),
+            _ => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Maps an offset in the unallocated stream back to an offset in the volume,
+    /// so a carving hit can be located.
+    pub fn volume_offset(&self, offset: u64) -> Option<u64> {
+        let run_idx = self.run_containing(offset)?;
+        let run = &self.runs[run_idx];
+
+        Some(run.offset(self.cluster_size) + (offset - self.run_starts[run_idx]))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.image
+    }
+
+    fn run_containing(&self, offset: u64) -> Option<usize> {
+        if offset >= self.len() {
+            return None;
+        }
+
+        // `run_starts` is sorted, the run is the last one starting at or before `offset`.
+        match self.run_starts.binary_search(&offset) {
+            Ok(idx) => Some(idx),
+            Err(idx) => Some(idx - 1),
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for UnallocatedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let run_idx = match self.run_containing(self.position) {
+            Some(run_idx) => run_idx,
+            None => return Ok(0),
+        };
+
+        let run = self.runs[run_idx];
+        let offset_in_run = self.position - self.run_starts[run_idx];
+
+        if !self.image_in_sync || run_idx != self.current_run {
+            self.image.seek(SeekFrom::Start(
+                run.offset(self.cluster_size) + offset_in_run,
+            ))?;
+            self.current_run = run_idx;
+            self.image_in_sync = true;
+        }
+
+        // Never read past the end of the run, the next run is somewhere else in the volume.
+        let remaining_in_run = run.size(self.cluster_size) - offset_in_run;
+        let to_read = (buf.len() as u64).min(remaining_in_run) as usize;
+
+        let read = self.image.read(&mut buf[..to_read])?;
+        self.position += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for UnallocatedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => checked_add_signed(self.len(), offset),
+            SeekFrom::Current(offset) => checked_add_signed(self.position, offset),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+                self.image_in_sync = false;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+fn checked_add_signed(base: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.wrapping_neg() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const CLUSTER_SIZE: u64 = 4;
+
+    fn reader() -> UnallocatedReader<Cursor<Vec<u8>>> {
+        // 8 clusters of 4 bytes, each filled with its cluster number.
+        let image: Vec<u8> = (0..8_u8)
+            .flat_map(|cluster| vec![cluster; CLUSTER_SIZE as usize])
+            .collect();
+
+        // Clusters 1, 2 and 5 are free.
+        let bitmap = ClusterBitmap::from_bytes(vec![0b1101_1001], 8, CLUSTER_SIZE);
+
+        UnallocatedReader::new(Cursor::new(image), &bitmap)
+    }
+
+    #[test]
+    fn test_reads_only_unallocated_clusters() {
+        let mut reader = reader();
+        assert_eq!(reader.len(), 12);
+
+        let mut data = vec![];
+        reader.read_to_end(&mut data).unwrap();
+
+        assert_eq!(data, vec![1, 1, 1, 1, 2, 2, 2, 2, 5, 5, 5, 5]);
+    }
+
+    #[test]
+    fn test_seek_and_volume_offset() {
+        let mut reader = reader();
+
+        reader.seek(SeekFrom::Start(7)).unwrap();
+
+        let mut data = [0; 3];
+        reader.read_exact(&mut data).unwrap();
+
+        assert_eq!(data, [2, 5, 5]);
+        assert_eq!(reader.volume_offset(7), Some(2 * CLUSTER_SIZE + 3));
+        assert_eq!(reader.volume_offset(8), Some(5 * CLUSTER_SIZE));
+        assert_eq!(reader.volume_offset(12), None);
+    }
+}
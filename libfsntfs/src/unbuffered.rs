@@ -0,0 +1,204 @@
+//! Reads live volumes (`\\.\C:`) and images bypassing the Windows file cache, see
+//! `IoBackend::Unbuffered`.
+//!
+//! Unbuffered reads must start at, and cover, whole sectors into sector aligned memory, so
+//! reads are served from an aligned read-ahead buffer. This also keeps a collection from
+//! evicting the working set of the system from the cache.
+use std::alloc::{self, Layout};
+use std::cmp;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::mem;
+use std::os::windows::fs::OpenOptionsExt;
+use std::os::windows::io::AsRawHandle;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+use winapi::shared::minwindef::DWORD;
+use winapi::um::ioapiset::DeviceIoControl;
+use winapi::um::winbase::FILE_FLAG_NO_BUFFERING;
+use winapi::um::winioctl::{GET_LENGTH_INFORMATION, IOCTL_DISK_GET_LENGTH_INFO};
+
+/// A multiple of every sector size in use (512 and 4096 bytes).
+const ALIGNMENT: usize = 4096;
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+struct AlignedBuffer {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+impl AlignedBuffer {
+    fn new(size: usize) -> Self {
+        let layout = Layout::from_size_align(size, ALIGNMENT).expect("valid layout");
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+
+        AlignedBuffer { ptr, layout }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.layout.size()) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.layout.size()) }
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr, self.layout) };
+    }
+}
+
+// The buffer is owned by the reader alone.
+unsafe impl Send for AlignedBuffer {}
+
+pub struct UnbufferedReader {
+    file: File,
+    size: u64,
+    position: u64,
+    buffer: AlignedBuffer,
+    /// Offset of the buffer in the file, and how much of it was read.
+    buffer_offset: u64,
+    buffer_filled: usize,
+}
+
+impl UnbufferedReader {
+    /// Opens a device (`\\.\C:`, `\\.\PhysicalDrive0`) or a file, for reading.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(FILE_FLAG_NO_BUFFERING)
+            .open(path)?;
+
+        let size = match device_length(&file) {
+            Ok(size) => size,
+            // Not a device.
+            Err(_) => file.metadata()?.len(),
+        };
+
+        Ok(UnbufferedReader {
+            file,
+            size,
+            position: 0,
+            buffer: AlignedBuffer::new(BUFFER_SIZE),
+            buffer_offset: 0,
+            buffer_filled: 0,
+        })
+    }
+
+    fn is_buffered(&self, position: u64) -> bool {
+        position >= self.buffer_offset && position < self.buffer_offset + self.buffer_filled as u64
+    }
+
+    fn fill_buffer(&mut self, position: u64) -> io::Result<()> {
+        let aligned = position - position % ALIGNMENT as u64;
+
+        self.buffer_filled = 0;
+        self.file.seek(SeekFrom::Start(aligned))?;
+        self.buffer_offset = aligned;
+        self.buffer_filled = self.file.read(self.buffer.as_mut_slice())?;
+
+        Ok(())
+    }
+}
+
+/// The size of a disk or volume device.
+fn device_length(file: &File) -> io::Result<u64> {
+    let mut length: GET_LENGTH_INFORMATION = unsafe { mem::zeroed() };
+    let mut returned: DWORD = 0;
+
+    let ok = unsafe {
+        DeviceIoControl(
+            file.as_raw_handle() as _,
+            IOCTL_DISK_GET_LENGTH_INFO,
+            ptr::null_mut(),
+            0,
+            &mut length as *mut _ as *mut _,
+            mem::size_of::<GET_LENGTH_INFORMATION>() as DWORD,
+            &mut returned,
+            ptr::null_mut(),
+        )
+    };
+
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(unsafe { *length.Length.QuadPart() } as u64)
+}
+
+impl Read for UnbufferedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size {
+            return Ok(0);
+        }
+
+        if !self.is_buffered(self.position) {
+            self.fill_buffer(self.position)?;
+
+            if !self.is_buffered(self.position) {
+                return Ok(0);
+            }
+        }
+
+        let start = (self.position - self.buffer_offset) as usize;
+        let length = cmp::min(
+            cmp::min(buf.len(), self.buffer_filled - start),
+            (self.size - self.position) as usize,
+        );
+
+        buf[..length].copy_from_slice(&self.buffer.as_slice()[start..start + length]);
+        self.position += length as u64;
+
+        Ok(length)
+    }
+}
+
+impl Seek for UnbufferedReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+            SeekFrom::End(offset) => self.size as i64 + offset,
+        };
+
+        if position < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the stream",
+            ));
+        }
+
+        self.position = position as u64;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_unbuffered_reader() {
+        let mut expected = vec![];
+        File::open(sample_volume_path())
+            .unwrap()
+            .read_to_end(&mut expected)
+            .unwrap();
+
+        let mut reader = UnbufferedReader::open(sample_volume_path()).unwrap();
+        reader.seek(SeekFrom::Start(100)).unwrap();
+
+        let mut data = vec![];
+        reader.read_to_end(&mut data).unwrap();
+
+        assert_eq!(&data[..], &expected[100..]);
+    }
+}
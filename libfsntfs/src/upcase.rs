@@ -0,0 +1,102 @@
+//! The `$UpCase` table, which NTFS uses to compare (and sort) names case-insensitively.
+use crate::error::Error;
+use crate::metadata_files;
+use crate::volume::Volume;
+use std::cmp::Ordering;
+use std::io::Read;
+
+/// Maps every UTF-16 code unit to its uppercase form.
+#[derive(Debug, Clone, Default)]
+pub struct UpcaseTable(Vec<u16>);
+
+impl UpcaseTable {
+    /// Parses the data of `$UpCase`, little-endian code units.
+    pub fn from_bytes(data: &[u8]) -> Self {
+        UpcaseTable(
+            data.chunks_exact(2)
+                .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                .collect(),
+        )
+    }
+
+    pub fn read(volume: &Volume) -> Result<Self, Error> {
+        let mut upcase = volume.get_file_entry_by_mft_idx(metadata_files::UPCASE)?;
+
+        let mut data = vec![];
+        upcase.read_to_end(&mut data)?;
+
+        Ok(UpcaseTable::from_bytes(&data))
+    }
+
+    /// Code units past the end of the table are left as they are.
+    pub fn upcase(&self, unit: u16) -> u16 {
+        self.0.get(unit as usize).cloned().unwrap_or(unit)
+    }
+
+    /// The uppercase code units of every code unit of the table.
+    pub fn as_slice(&self) -> &[u16] {
+        &self.0
+    }
+
+    /// `name` as UTF-16, upcased a code unit at a time like NTFS does (surrogate pairs and
+    /// characters whose uppercase form is longer are left as they are).
+    pub fn fold(&self, name: &str) -> Vec<u16> {
+        name.encode_utf16().map(|unit| self.upcase(unit)).collect()
+    }
+
+    /// Compares names the way lookups on the volume match them, names which only differ in
+    /// case are equal.
+    pub fn compare_names(&self, a: &str, b: &str) -> Ordering {
+        a.encode_utf16()
+            .map(|unit| self.upcase(unit))
+            .cmp(b.encode_utf16().map(|unit| self.upcase(unit)))
+    }
+
+    pub fn names_equal(&self, a: &str, b: &str) -> bool {
+        self.compare_names(a, b) == Ordering::Equal
+    }
+
+    /// Compares like NTFS orders directory indexes, names which only differ in case are
+    /// then ordered by their code units so the order is total.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.compare_names(a, b)
+            .then_with(|| a.encode_utf16().cmp(b.encode_utf16()))
+    }
+}
+
+impl Volume {
+    /// Reads the `$UpCase` table of the volume.
+    pub fn get_upcase_table(&self) -> Result<UpcaseTable, Error> {
+        UpcaseTable::read(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_compare() {
+        let table = sample_volume().unwrap().get_upcase_table().unwrap();
+
+        assert_eq!(table.upcase(u16::from(b'a')), u16::from(b'A'));
+        assert_eq!(table.compare("abc", "ABD"), Ordering::Less);
+        assert_eq!(table.compare("ABC", "abc"), Ordering::Less);
+        assert_eq!(table.compare("abc", "abc"), Ordering::Equal);
+        // `_` (0x5f) sorts after the letters once they are upcased.
+        assert_eq!(table.compare("a_b", "aZb"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_compare_names() {
+        let table = sample_volume().unwrap().get_upcase_table().unwrap();
+
+        assert_eq!(table.as_slice().len(), 0x10000);
+        assert!(table.names_equal("$mft", "$MFT"));
+        assert_eq!(table.compare_names("abc", "ABD"), Ordering::Less);
+        // Unicode folds `ß` to `SS`, NTFS leaves it as it is.
+        assert!(!table.names_equal("straße", "STRASSE"));
+        assert_eq!(table.fold("é"), vec![0xc9]);
+    }
+}
@@ -0,0 +1,192 @@
+//! Reads images with io_uring, see `IoBackend::IoUring`. Experimental.
+//!
+//! Large reads (e.g. of data runs) are split into chunks which are submitted to the ring
+//! together, so the device sees them as one batch instead of one read after the other.
+use io_uring::{opcode, types, IoUring};
+use std::cmp;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::unix::io::AsRawFd;
+use std::thread;
+
+/// Chunks in flight at once, reads larger than `QUEUE_DEPTH * CHUNK_SIZE` are short.
+const QUEUE_DEPTH: usize = 32;
+const CHUNK_SIZE: usize = 64 * 1024;
+/// The ring is busy with completions which were not reaped yet, waiting again is fine.
+const EBUSY: i32 = 16;
+
+pub struct UringReader {
+    file: File,
+    ring: IoUring,
+    size: u64,
+    position: u64,
+    /// Set once entering the ring failed, reads are rejected from then on.
+    broken: bool,
+}
+
+impl UringReader {
+    pub fn new(file: File) -> io::Result<Self> {
+        let size = file.metadata()?.len();
+        let ring = IoUring::new(QUEUE_DEPTH as u32)?;
+
+        Ok(UringReader {
+            file,
+            ring,
+            size,
+            position: 0,
+            broken: false,
+        })
+    }
+
+    /// Reads the chunks of `buf` starting at `offset` as one batch, returns how much of `buf`
+    /// was filled from its start.
+    fn read_batch(&mut self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        if self.broken {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "io_uring failed on an earlier read",
+            ));
+        }
+
+        let fd = types::Fd(self.file.as_raw_fd());
+        let chunks: Vec<&mut [u8]> = buf.chunks_mut(CHUNK_SIZE).take(QUEUE_DEPTH).collect();
+        let lengths: Vec<usize> = chunks.iter().map(|chunk| chunk.len()).collect();
+
+        let mut error = None;
+        let mut queued = 0;
+
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let read = opcode::Read::new(fd, chunk.as_mut_ptr(), chunk.len() as u32)
+                .offset((offset + (i * CHUNK_SIZE) as u64) as i64)
+                .build()
+                .user_data(i as u64);
+
+            // The queue holds `QUEUE_DEPTH` entries, and every read is reaped below.
+            if unsafe { self.ring.submission().push(&read) }.is_err() {
+                error = Some(io::Error::new(
+                    io::ErrorKind::Other,
+                    "io_uring queue is full",
+                ));
+                break;
+            }
+
+            queued += 1;
+        }
+
+        // The kernel writes into `buf` until each read it took completes, so every one of them
+        // is reaped before returning, errors included. That also keeps their completions out
+        // of the next batch.
+        let mut read = vec![0; lengths.len()];
+        let mut reaped = 0;
+        // Reads the kernel took, all of them until entering the ring fails.
+        let mut taken = queued;
+
+        while reaped < taken {
+            if !self.broken {
+                match self.ring.submit_and_wait(taken - reaped) {
+                    Ok(_) => {}
+                    Err(ref e)
+                        if e.kind() == io::ErrorKind::Interrupted
+                            || e.kind() == io::ErrorKind::WouldBlock
+                            || e.raw_os_error() == Some(EBUSY) => {}
+                    Err(e) => {
+                        // The reads left in the submission queue point into `buf`, they must
+                        // never be submitted: the ring is not entered again.
+                        self.broken = true;
+                        taken = queued - self.ring.submission().len();
+                        error.get_or_insert(e);
+                    }
+                }
+            } else {
+                // The taken reads still complete, without waiting in the kernel.
+                thread::yield_now();
+            }
+
+            for completion in self.ring.completion() {
+                reaped += 1;
+
+                let result = completion.result();
+
+                if result < 0 {
+                    error.get_or_insert(io::Error::from_raw_os_error(-result));
+                } else if let Some(read) = read.get_mut(completion.user_data() as usize) {
+                    *read = result as usize;
+                }
+            }
+        }
+
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        // Only the chunks before the first short one are contiguous.
+        let mut filled = 0;
+        for (read, length) in read.into_iter().zip(lengths) {
+            filled += read;
+
+            if read < length {
+                break;
+            }
+        }
+
+        Ok(filled)
+    }
+}
+
+impl Read for UringReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size {
+            return Ok(0);
+        }
+
+        let length = cmp::min(buf.len() as u64, self.size - self.position) as usize;
+        let position = self.position;
+        let read = self.read_batch(&mut buf[..length], position)?;
+
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for UringReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+            SeekFrom::End(offset) => self.size as i64 + offset,
+        };
+
+        if position < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the stream",
+            ));
+        }
+
+        self.position = position as u64;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn test_uring_reader() {
+        let mut expected = vec![];
+        File::open(sample_volume_path())
+            .unwrap()
+            .read_to_end(&mut expected)
+            .unwrap();
+
+        let mut reader = UringReader::new(File::open(sample_volume_path()).unwrap()).unwrap();
+        reader.seek(SeekFrom::Start(100)).unwrap();
+
+        let mut data = vec![];
+        reader.read_to_end(&mut data).unwrap();
+
+        assert_eq!(&data[..], &expected[100..]);
+    }
+}
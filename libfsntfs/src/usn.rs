@@ -0,0 +1,467 @@
+//! The USN change journal (`$Extend\$UsnJrnl:$J`), and the changes recorded in it since a given USN.
+//!
+//! A USN is the offset of a record in `$J`, so a triage run can store `UsnChangeSet::next_usn`
+//! and only look at what changed since on the next run. A USN only means something in the
+//! journal it is of, so `UsnChangeSet::journal_id` is stored along with it.
+use crate::data_stream::DataStream;
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::flags::FileAttributeFlags;
+use crate::mft_record::{invalid, read_u16, read_u32, read_u64};
+use crate::options::Utf16Policy;
+use crate::paths::PathResolver;
+use crate::utils::{datetime_from_filetime, mft_entry_index};
+use crate::volume::Volume;
+use bitflags::bitflags;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+
+pub const USN_JOURNAL_PATH: &str = "\\$Extend\\$UsnJrnl";
+pub const USN_JOURNAL_STREAM_NAME: &str = "$J";
+/// The stream with the settings of the journal, and its ID.
+pub const USN_JOURNAL_MAX_STREAM_NAME: &str = "$Max";
+
+/// Records never cross a journal page, the rest of a page is zero filled.
+const USN_PAGE_SIZE: u64 = 4096;
+
+/// Size of a version 2 record without its name.
+const USN_RECORD_V2_SIZE: usize = 0x3c;
+/// Size of a version 3 record without its name.
+const USN_RECORD_V3_SIZE: usize = 0x4c;
+const MAX_USN_RECORD_SIZE: usize = 0x1_0000;
+
+bitflags! {
+    /// Why a record was written, the flags accumulate until the file is closed (`CLOSE`).
+    pub struct UsnReason: u32 {
+        const DATA_OVERWRITE = 0x0000_0001;
+        const DATA_EXTEND = 0x0000_0002;
+        const DATA_TRUNCATION = 0x0000_0004;
+        const NAMED_DATA_OVERWRITE = 0x0000_0010;
+        const NAMED_DATA_EXTEND = 0x0000_0020;
+        const NAMED_DATA_TRUNCATION = 0x0000_0040;
+        const FILE_CREATE = 0x0000_0100;
+        const FILE_DELETE = 0x0000_0200;
+        const EA_CHANGE = 0x0000_0400;
+        const SECURITY_CHANGE = 0x0000_0800;
+        const RENAME_OLD_NAME = 0x0000_1000;
+        const RENAME_NEW_NAME = 0x0000_2000;
+        const INDEXABLE_CHANGE = 0x0000_4000;
+        const BASIC_INFO_CHANGE = 0x0000_8000;
+        const HARD_LINK_CHANGE = 0x0001_0000;
+        const COMPRESSION_CHANGE = 0x0002_0000;
+        const ENCRYPTION_CHANGE = 0x0004_0000;
+        const OBJECT_ID_CHANGE = 0x0008_0000;
+        const REPARSE_POINT_CHANGE = 0x0010_0000;
+        const STREAM_CHANGE = 0x0020_0000;
+        const TRANSACTED_CHANGE = 0x0040_0000;
+        const INTEGRITY_CHANGE = 0x0080_0000;
+        const CLOSE = 0x8000_0000;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsnRecord {
+    pub usn: u64,
+    /// Version 3 records have 128-bit references, only the NTFS (lower 64 bits) part is kept.
+    pub file_reference: u64,
+    pub parent_file_reference: u64,
+    /// As a FILETIME.
+    pub timestamp: u64,
+    pub reason: UsnReason,
+    pub source_info: u32,
+    pub security_id: u32,
+    pub file_attribute_flags: FileAttributeFlags,
+    pub name: String,
+}
+
+impl UsnRecord {
+    /// Parses a version 2 or 3 record, replacing invalid UTF-16 in the name.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        UsnRecord::parse_with_utf16_policy(data, Utf16Policy::Lossy)
+    }
+
+    pub fn parse_with_utf16_policy(data: &[u8], utf16_policy: Utf16Policy) -> Result<Self, Error> {
+        let major_version = read_u16(data, 0x04)?;
+
+        // Offsets of the fields following the references differ by 16 bytes between versions.
+        let (parent_file_reference, base, record_size) = match major_version {
+            2 => (read_u64(data, 0x10)?, 0x18, USN_RECORD_V2_SIZE),
+            3 => (read_u64(data, 0x18)?, 0x28, USN_RECORD_V3_SIZE),
+            version => {
+                return Err(invalid(format!(
+                    "unsupported USN record version {}",
+                    version
+                )))
+            }
+        };
+
+        if data.len() < record_size {
+            return Err(invalid(format!(
+                "USN record is {} bytes, expected at least {}",
+                data.len(),
+                record_size
+            )));
+        }
+
+        let name_size = read_u16(data, base + 0x20)? as usize;
+        let name_offset = read_u16(data, base + 0x22)? as usize;
+
+        let name_bytes = data
+            .get(name_offset..name_offset + name_size)
+            .ok_or_else(|| invalid("USN record name is out of bounds".to_owned()))?;
+
+        let name_utf16: Vec<u16> = name_bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+
+        Ok(UsnRecord {
+            usn: read_u64(data, base)?,
+            file_reference: read_u64(data, 0x08)?,
+            parent_file_reference,
+            timestamp: read_u64(data, base + 0x08)?,
+            reason: UsnReason::from_bits_truncate(read_u32(data, base + 0x10)?),
+            source_info: read_u32(data, base + 0x14)?,
+            security_id: read_u32(data, base + 0x18)?,
+            file_attribute_flags: FileAttributeFlags::from(read_u32(data, base + 0x1c)?),
+            name: utf16_policy.decode(&name_utf16)?,
+        })
+    }
+
+    pub fn get_timestamp(&self) -> DateTime<Utc> {
+        datetime_from_filetime(self.timestamp)
+    }
+}
+
+/// The `$UsnJrnl` metadata file of a volume.
+pub struct UsnJournal<'a> {
+    file_entry: FileEntry<'a>,
+}
+
+impl<'a> UsnJournal<'a> {
+    /// Fails if the volume has no change journal.
+    pub fn open(volume: &'a Volume) -> Result<Self, Error> {
+        Ok(UsnJournal {
+            file_entry: volume.get_file_entry_by_path(USN_JOURNAL_PATH)?,
+        })
+    }
+
+    /// The ID of the journal, which changes when it is deleted and created again.
+    pub fn journal_id(&self) -> Result<u64, Error> {
+        let mut stream = self
+            .file_entry
+            .get_alternate_data_stream_by_name(USN_JOURNAL_MAX_STREAM_NAME)?;
+
+        // The maximum size, the allocation delta, the journal ID and the lowest valid USN.
+        let mut max = [0; 0x20];
+        stream.read_exact(&mut max)?;
+
+        read_u64(&max, 0x10)
+    }
+
+    /// Iterates over the records starting at `usn` (0 for the whole journal).
+    pub fn records_from(&self, usn: u64) -> Result<UsnRecords, Error> {
+        let mut stream = self
+            .file_entry
+            .get_alternate_data_stream_by_name(USN_JOURNAL_STREAM_NAME)?;
+
+        let size = stream.get_size()?;
+        let sparse = sparse_ranges(&stream)?;
+        stream.seek(SeekFrom::Start(usn))?;
+
+        Ok(UsnRecords {
+            stream,
+            offset: usn,
+            size,
+            sparse,
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "usn_records",
+                image = self
+                    .file_entry
+                    .volume()
+                    .get_image_path()
+                    .unwrap_or_default(),
+                from_usn = usn
+            ),
+        })
+    }
+}
+
+/// The ranges of `stream` (offsets in the stream) which are sparse.
+fn sparse_ranges(stream: &DataStream) -> Result<Vec<Range<u64>>, Error> {
+    let mut ranges: Vec<Range<u64>> = vec![];
+    let mut offset = 0;
+
+    for extent_index in 0..stream.get_number_of_extents()? {
+        let extent = stream.get_extent(extent_index)?;
+        let end = offset + extent.size;
+
+        if extent.is_sparse() {
+            match ranges.last_mut() {
+                Some(last) if last.end == offset => last.end = end,
+                _ => ranges.push(offset..end),
+            }
+        }
+
+        offset = end;
+    }
+
+    Ok(ranges)
+}
+
+/// Skips the zero filled page tails (and the sparse start of `$J`) between records.
+pub struct UsnRecords<'a> {
+    stream: DataStream<'a>,
+    offset: u64,
+    size: u64,
+    /// Sparse ranges of `$J`, which are skipped without reading them (the journal starts with
+    /// the space freed by truncating it, which can be gigabytes).
+    sparse: Vec<Range<u64>>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl<'a> UsnRecords<'a> {
+    /// The USN of the next record, which is where the next run should continue from
+    /// once the iterator is exhausted.
+    pub fn next_usn(&self) -> u64 {
+        self.offset
+    }
+
+    fn read_record(&mut self) -> Result<Option<UsnRecord>, Error> {
+        loop {
+            let offset = self.offset;
+            if let Some(range) = self
+                .sparse
+                .iter()
+                .find(|range| range.start <= offset && offset < range.end)
+            {
+                self.offset = range.end.min(self.size);
+                self.stream.seek(SeekFrom::Start(self.offset))?;
+            }
+
+            if self.offset + 4 > self.size {
+                return Ok(None);
+            }
+
+            let mut length = [0; 4];
+            self.stream.read_exact(&mut length)?;
+            let length = u32::from_le_bytes(length) as usize;
+
+            if length == 0 {
+                let next_page = (self.offset / USN_PAGE_SIZE + 1) * USN_PAGE_SIZE;
+                self.offset = next_page.min(self.size);
+                self.stream.seek(SeekFrom::Start(self.offset))?;
+                continue;
+            }
+
+            if length < USN_RECORD_V2_SIZE || length > MAX_USN_RECORD_SIZE || length % 8 != 0 {
+                return Err(invalid(format!(
+                    "USN record at {} has an invalid length of {}",
+                    self.offset, length
+                )));
+            }
+
+            let mut data = vec![0; length];
+            data[..4].copy_from_slice(&(length as u32).to_le_bytes());
+            self.stream.read_exact(&mut data[4..])?;
+
+            self.offset += length as u64;
+
+            let utf16_policy = self
+                .stream
+                .file_entry()
+                .volume()
+                .options()
+                .get_utf16_policy();
+
+            return UsnRecord::parse_with_utf16_policy(&data, utf16_policy).map(Some);
+        }
+    }
+}
+
+impl<'a> Iterator for UsnRecords<'a> {
+    type Item = Result<UsnRecord, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        #[cfg(feature = "tracing")]
+        let span = self.span.clone();
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        match self.read_record() {
+            Ok(record) => record.map(Ok),
+            Err(e) => {
+                // Stop on corruption rather than yielding the same error forever.
+                self.offset = self.size;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// All the changes of one file since the starting USN.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsnChange {
+    pub file_reference: u64,
+    /// The name of the most recent record.
+    pub name: String,
+    /// The reasons of all records, combined.
+    pub reasons: UsnReason,
+    pub last_usn: u64,
+    /// As a FILETIME.
+    pub last_timestamp: u64,
+    /// The current path of the file, `None` if it was deleted (or its MFT entry was reused).
+    pub current_path: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UsnChangeSet {
+    /// One change per file, in the order the files first changed.
+    pub changes: Vec<UsnChange>,
+    /// Pass this to the next call to only get the changes made after this one.
+    pub next_usn: u64,
+    /// The journal `next_usn` is of, pass it to the next call as well.
+    pub journal_id: u64,
+}
+
+impl UsnChangeSet {
+    /// Replays the change journal of `volume` starting at `usn`. `journal_id` is the journal
+    /// `usn` is of (`None` on a first run), it fails with `Error::UsnJournalRecreated` if the
+    /// journal is another one now.
+    pub fn since(volume: &Volume, usn: u64, journal_id: Option<u64>) -> Result<Self, Error> {
+        let journal = UsnJournal::open(volume)?;
+
+        let current_journal_id = journal.journal_id()?;
+        if let Some(expected) = journal_id {
+            if expected != current_journal_id {
+                return Err(Error::UsnJournalRecreated {
+                    expected,
+                    actual: current_journal_id,
+                });
+            }
+        }
+
+        let mut records = journal.records_from(usn)?;
+
+        let mut changes: Vec<UsnChange> = vec![];
+        // The index of the change of each file in `changes`.
+        let mut change_indices: HashMap<u64, usize> = HashMap::new();
+
+        for record in records.by_ref() {
+            let record = record?;
+
+            match change_indices.get(&record.file_reference) {
+                Some(&change_index) => {
+                    let change = &mut changes[change_index];
+                    change.name = record.name;
+                    change.reasons |= record.reason;
+                    change.last_usn = record.usn;
+                    change.last_timestamp = record.timestamp;
+                }
+                None => {
+                    change_indices.insert(record.file_reference, changes.len());
+                    changes.push(UsnChange {
+                        file_reference: record.file_reference,
+                        name: record.name,
+                        reasons: record.reason,
+                        last_usn: record.usn,
+                        last_timestamp: record.timestamp,
+                        current_path: None,
+                    });
+                }
+            }
+        }
+
+        let next_usn = records.next_usn();
+
+        let mut paths = PathResolver::new();
+
+        for change in changes.iter_mut() {
+            let idx = mft_entry_index(change.file_reference);
+
+            if let Ok(entry) = volume.get_file_entry_by_mft_idx(idx) {
+                let is_same_file = entry.get_file_reference().ok() == Some(change.file_reference);
+
+                if is_same_file && entry.is_allocated().unwrap_or(false) {
+                    change.current_path = Some(paths.full_path(idx, &entry));
+                }
+            }
+        }
+
+        Ok(UsnChangeSet {
+            changes,
+            next_usn,
+            journal_id: current_journal_id,
+        })
+    }
+}
+
+impl Volume {
+    /// Opens the USN change journal, see `UsnJournal`.
+    pub fn usn_journal(&self) -> Result<UsnJournal, Error> {
+        UsnJournal::open(self)
+    }
+
+    /// The files changed since `usn` of the journal `journal_id`, resolved to their current
+    /// paths, see `UsnChangeSet::since`.
+    pub fn usn_changes_since(
+        &self,
+        usn: u64,
+        journal_id: Option<u64>,
+    ) -> Result<UsnChangeSet, Error> {
+        UsnChangeSet::since(self, usn, journal_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_v2(usn: u64, file_reference: u64, reason: u32, name: &str) -> Vec<u8> {
+        let name: Vec<u8> = name
+            .encode_utf16()
+            .flat_map(|c| c.to_le_bytes().to_vec())
+            .collect();
+        let length = (USN_RECORD_V2_SIZE + name.len() + 7) / 8 * 8;
+
+        let mut data = vec![0; length];
+        data[0x00..0x04].copy_from_slice(&(length as u32).to_le_bytes());
+        data[0x04..0x06].copy_from_slice(&2_u16.to_le_bytes());
+        data[0x08..0x10].copy_from_slice(&file_reference.to_le_bytes());
+        data[0x10..0x18].copy_from_slice(&0x0005_0000_0000_0005_u64.to_le_bytes());
+        data[0x18..0x20].copy_from_slice(&usn.to_le_bytes());
+        data[0x20..0x28].copy_from_slice(&131_907_744_001_234_567_u64.to_le_bytes());
+        data[0x28..0x2c].copy_from_slice(&reason.to_le_bytes());
+        data[0x34..0x38].copy_from_slice(&0x20_u32.to_le_bytes());
+        data[0x38..0x3a].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        data[0x3a..0x3c].copy_from_slice(&(USN_RECORD_V2_SIZE as u16).to_le_bytes());
+        data[USN_RECORD_V2_SIZE..USN_RECORD_V2_SIZE + name.len()].copy_from_slice(&name);
+        data
+    }
+
+    #[test]
+    fn test_parse_usn_record_v2() {
+        let data = record_v2(0x1000, 0x0002_0000_0000_0040, 0x8000_0100, "new.txt");
+
+        let record = UsnRecord::parse(&data).unwrap();
+
+        assert_eq!(record.usn, 0x1000);
+        assert_eq!(record.file_reference, 0x0002_0000_0000_0040);
+        assert_eq!(record.parent_file_reference, 0x0005_0000_0000_0005);
+        assert_eq!(record.reason, UsnReason::FILE_CREATE | UsnReason::CLOSE);
+        assert_eq!(record.file_attribute_flags, FileAttributeFlags::ARCHIVE);
+        assert_eq!(record.name, "new.txt");
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_version() {
+        let mut data = record_v2(0, 0, 0, "a");
+        data[0x04..0x06].copy_from_slice(&4_u16.to_le_bytes());
+
+        assert!(UsnRecord::parse(&data).is_err());
+    }
+}
@@ -1,4 +1,6 @@
 use chrono::prelude::*;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 use time::Duration;
 
 pub fn datetime_from_filetime(nanos_since_windows_epoch: u64) -> DateTime<Utc> {
@@ -8,3 +10,76 @@ pub fn datetime_from_filetime(nanos_since_windows_epoch: u64) -> DateTime<Utc> {
         Utc,
     )
 }
+
+/// The MFT entry index part (lower 48 bits) of a file reference, without the sequence number.
+pub fn mft_entry_index(file_reference: u64) -> u64 {
+    file_reference & 0x0000_ffff_ffff_ffff
+}
+
+/// The sequence number part (upper 16 bits) of a file reference.
+pub fn sequence_number(file_reference: u64) -> u16 {
+    (file_reference >> 48) as u16
+}
+
+/// Where the parent of a differencing image is, given the path stored in `image`.
+/// The stored path is often absolute on the machine which created the image, so when it does
+/// not exist the parent is looked up by its file name next to `image`.
+pub fn parent_image_path(image: &Path, parent: &str) -> PathBuf {
+    let stored = Path::new(parent);
+
+    if stored.is_absolute() && stored.exists() {
+        return stored.to_path_buf();
+    }
+
+    let directory = image.parent().unwrap_or_else(|| Path::new(""));
+    let relative = directory.join(parent);
+
+    if relative.exists() {
+        return relative;
+    }
+
+    // Windows paths, e.g. `C:\VMs\base.vhdx` or `.\base.vhdx`, also on other platforms.
+    let file_name = parent
+        .rsplit(|c| c == '\\' || c == '/')
+        .next()
+        .unwrap_or(parent);
+
+    directory.join(file_name)
+}
+
+/// Reads until `buf` is full or the reader ends.
+pub fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parent_image_path() {
+        let image = Path::new("images").join("child.vhdx");
+
+        assert_eq!(
+            parent_image_path(&image, "C:\\VMs\\base.vhdx"),
+            Path::new("images").join("base.vhdx")
+        );
+        assert_eq!(
+            parent_image_path(&image, ".\\base.vhdx"),
+            Path::new("images").join("base.vhdx")
+        );
+        assert_eq!(
+            parent_image_path(&image, "base.qcow2"),
+            Path::new("images").join("base.qcow2")
+        );
+    }
+}
@@ -0,0 +1,354 @@
+//! Confirms a completed collection: re-reads what was extracted (see `extract`) and compares it
+//! against the volume, or, for files which were only hashed, compares the stored hashes.
+//!
+//! ```ignore
+//! let mut extracted = vec![];
+//! extract(&volume, &options, |file| extracted.push(file.clone()))?;
+//!
+//! let report = verify(&volume, &extracted);
+//! for file in report.files.iter().filter(|file| !file.is_verified()) {
+//!     eprintln!("{}: {:?}", file.path, file.discrepancies);
+//! }
+//! ```
+use crate::extract::Extracted;
+#[cfg(any(feature = "md-5", feature = "sha-1", feature = "sha2"))]
+use crate::hashing::{Hashes, HashingReader};
+use crate::utils::read_up_to;
+use crate::volume::{MftEntryIndex, Volume};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Bytes compared at once, and the granularity of unreadable ranges.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Discrepancy {
+    /// The entry could not be opened on the volume.
+    Missing(String),
+    /// The extracted file could not be opened.
+    OutputMissing(String),
+    SizeDiffers {
+        expected: u64,
+        actual: u64,
+    },
+    /// Bytes of the extracted file which differ from the volume.
+    ContentDiffers {
+        offset: u64,
+        length: u64,
+    },
+    /// Bytes which could not be read from the volume.
+    Unreadable {
+        offset: u64,
+        length: u64,
+        error: String,
+    },
+    #[cfg(any(feature = "md-5", feature = "sha-1", feature = "sha2"))]
+    HashDiffers {
+        expected: Hashes,
+        actual: Hashes,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct FileVerification {
+    pub path: String,
+    pub mft_entry_index: MftEntryIndex,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl FileVerification {
+    pub fn is_verified(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub files: Vec<FileVerification>,
+    /// Files without any discrepancy.
+    pub verified: u64,
+    /// Bytes read from the volume.
+    pub bytes: u64,
+}
+
+impl VerifyReport {
+    pub fn is_verified(&self) -> bool {
+        self.verified as usize == self.files.len()
+    }
+}
+
+/// Adds `[offset, offset + length)` to `ranges`, extending the last range if it ends at `offset`.
+fn add_range(ranges: &mut Vec<(u64, u64)>, offset: u64, length: u64) {
+    if let Some(last) = ranges.last_mut() {
+        if last.0 + last.1 == offset {
+            last.1 += length;
+            return;
+        }
+    }
+
+    ranges.push((offset, length));
+}
+
+/// Reads `inner` (of `size` bytes) a chunk at a time, chunks which fail to read are read as
+/// zeros and remembered, so the rest of the data is still compared.
+struct SkippingReader<R> {
+    inner: R,
+    offset: u64,
+    size: u64,
+    unreadable: Vec<(u64, u64)>,
+    errors: Vec<String>,
+}
+
+impl<R: Read + Seek> SkippingReader<R> {
+    fn new(inner: R, size: u64) -> Self {
+        SkippingReader {
+            inner,
+            offset: 0,
+            size,
+            unreadable: vec![],
+            errors: vec![],
+        }
+    }
+
+    fn discrepancies(&self) -> Vec<Discrepancy> {
+        self.unreadable
+            .iter()
+            .zip(&self.errors)
+            .map(|(&(offset, length), error)| Discrepancy::Unreadable {
+                offset,
+                length,
+                error: error.clone(),
+            })
+            .collect()
+    }
+}
+
+impl<R: Read + Seek> Read for SkippingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.size.saturating_sub(self.offset);
+        let length = (buf.len() as u64).min(CHUNK_SIZE as u64).min(remaining) as usize;
+
+        if length == 0 {
+            return Ok(0);
+        }
+
+        match self.inner.read(&mut buf[..length]) {
+            Ok(read) => {
+                self.offset += read as u64;
+                Ok(read)
+            }
+            Err(e) => {
+                for byte in &mut buf[..length] {
+                    *byte = 0;
+                }
+
+                let ranges = self.unreadable.len();
+                add_range(&mut self.unreadable, self.offset, length as u64);
+                if self.unreadable.len() > ranges {
+                    self.errors.push(e.to_string());
+                }
+
+                self.offset += length as u64;
+                self.inner.seek(SeekFrom::Start(self.offset))?;
+
+                Ok(length)
+            }
+        }
+    }
+}
+
+/// The ranges in which `extracted` differs from `original`, compared a chunk at a time.
+fn compare(original: &mut impl Read, extracted: &mut impl Read) -> io::Result<Vec<(u64, u64)>> {
+    let mut expected = vec![0; CHUNK_SIZE];
+    let mut actual = vec![0; CHUNK_SIZE];
+    let mut differences = vec![];
+    let mut offset = 0;
+
+    loop {
+        let expected_length = read_up_to(original, &mut expected)?;
+        let actual_length = read_up_to(extracted, &mut actual)?;
+        let length = expected_length.min(actual_length);
+
+        if expected[..length] != actual[..length] {
+            add_range(&mut differences, offset, length as u64);
+        }
+
+        offset += length as u64;
+
+        if length < CHUNK_SIZE {
+            return Ok(differences);
+        }
+    }
+}
+
+/// Compares `extracted` against the volume: the file it was written to, byte by byte, or when
+/// it was not written, its hashes (with any of the hashing features).
+pub fn verify_file(volume: &Volume, extracted: &Extracted) -> FileVerification {
+    let mut verification = FileVerification {
+        path: extracted.path.clone(),
+        mft_entry_index: extracted.mft_entry_index,
+        discrepancies: vec![],
+    };
+
+    if let Err(discrepancy) = check(volume, extracted, &mut verification.discrepancies) {
+        verification.discrepancies.push(discrepancy);
+    }
+
+    verification
+}
+
+fn check(
+    volume: &Volume,
+    extracted: &Extracted,
+    discrepancies: &mut Vec<Discrepancy>,
+) -> Result<(), Discrepancy> {
+    let missing = |e: &dyn ToString| Discrepancy::Missing(e.to_string());
+
+    let entry = volume
+        .get_file_entry_by_mft_idx(extracted.mft_entry_index)
+        .map_err(|e| missing(&e))?;
+    let size = entry.get_size().map_err(|e| missing(&e))?;
+
+    if size != extracted.size {
+        discrepancies.push(Discrepancy::SizeDiffers {
+            expected: extracted.size,
+            actual: size,
+        });
+    }
+
+    let mut original = SkippingReader::new(entry, size);
+
+    match extracted.output_path {
+        Some(ref output_path) => {
+            compare_output(&mut original, output_path, discrepancies)?;
+        }
+        None => {
+            #[cfg(any(feature = "md-5", feature = "sha-1", feature = "sha2"))]
+            {
+                let actual = HashingReader::new(&mut original)
+                    .hash_to_end()
+                    .map_err(|e| missing(&e))?;
+
+                if actual != extracted.hashes {
+                    discrepancies.push(Discrepancy::HashDiffers {
+                        expected: extracted.hashes.clone(),
+                        actual,
+                    });
+                }
+            }
+        }
+    }
+
+    discrepancies.extend(original.discrepancies());
+
+    Ok(())
+}
+
+fn compare_output<R: Read + Seek>(
+    original: &mut SkippingReader<R>,
+    output_path: &Path,
+    discrepancies: &mut Vec<Discrepancy>,
+) -> Result<(), Discrepancy> {
+    let output_missing = |e: io::Error| Discrepancy::OutputMissing(e.to_string());
+
+    let mut output = File::open(output_path).map_err(output_missing)?;
+    let output_size = output.metadata().map_err(output_missing)?.len();
+
+    if output_size != original.size {
+        discrepancies.push(Discrepancy::SizeDiffers {
+            expected: original.size,
+            actual: output_size,
+        });
+    }
+
+    let differences = compare(original, &mut output).map_err(output_missing)?;
+
+    discrepancies.extend(
+        differences
+            .into_iter()
+            .map(|(offset, length)| Discrepancy::ContentDiffers { offset, length }),
+    );
+
+    Ok(())
+}
+
+/// Verifies every file of a collection, see `verify_file`.
+pub fn verify(volume: &Volume, extracted: &[Extracted]) -> VerifyReport {
+    let mut report = VerifyReport::default();
+
+    for file in extracted {
+        let verification = verify_file(volume, file);
+
+        if verification.is_verified() {
+            report.verified += 1;
+        }
+
+        report.bytes += file.size;
+        report.files.push(verification);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::{extract, ExtractOptions};
+    use crate::fixtures::*;
+    use crate::options::VolumeOpenOptions;
+    use crate::shared::SharedVolume;
+    use crate::walk::{PathMatcher, WalkOptions};
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    #[test]
+    fn test_add_range() {
+        let mut ranges = vec![];
+        add_range(&mut ranges, 0, 10);
+        add_range(&mut ranges, 10, 5);
+        add_range(&mut ranges, 20, 5);
+
+        assert_eq!(ranges, [(0, 15), (20, 5)]);
+    }
+
+    #[test]
+    fn test_verify_detects_changes() {
+        let output_directory = std::env::temp_dir().join("libfsntfs-rs-verify");
+        let volume = SharedVolume::open(sample_volume_path(), &VolumeOpenOptions::new()).unwrap();
+        let options = ExtractOptions::new()
+            .walk(WalkOptions {
+                include: vec![PathMatcher::prefix("\\$LogFile")],
+                ..WalkOptions::default()
+            })
+            .output_directory(&output_directory);
+
+        let mut extracted = vec![];
+        extract(&volume, &options, |file| extracted.push(file.clone())).unwrap();
+        assert_eq!(extracted.len(), 1);
+
+        let report = verify(&volume, &extracted);
+        assert!(report.is_verified(), "{:?}", report.files);
+
+        let output_path = extracted[0].output_path.clone().unwrap();
+        let mut output = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&output_path)
+            .unwrap();
+        let mut byte = [0];
+        output.seek(SeekFrom::Start(100)).unwrap();
+        output.read_exact(&mut byte).unwrap();
+        output.seek(SeekFrom::Start(100)).unwrap();
+        output.write_all(&[!byte[0]]).unwrap();
+        drop(output);
+
+        let report = verify(&volume, &extracted);
+        fs::remove_dir_all(&output_directory).unwrap();
+
+        match report.files[0].discrepancies.as_slice() {
+            [Discrepancy::ContentDiffers { offset, .. }] => assert_eq!(*offset, 0),
+            discrepancies => panic!("unexpected discrepancies {:?}", discrepancies),
+        }
+    }
+}
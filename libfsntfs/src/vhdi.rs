@@ -0,0 +1,214 @@
+//! Reads VHD and VHDX virtual disk images with libvhdi.
+//!
+//! Differencing images are opened together with their parents, which are looked up by the
+//! path stored in the image (see `utils::parent_image_path`).
+//!
+//! ```ignore
+//! let disk = VhdiImage::open("snapshot.avhdx")?;
+//! let volume = SharedVolume::from_reader(disk, &VolumeOpenOptions::new())?;
+//! ```
+use crate::error::Error;
+use crate::ffi_error::to_io_error;
+use crate::utils::parent_image_path;
+use libcerror_rs::CError;
+use libfsntfs_sys::{SEEK_CUR, SEEK_END, SEEK_SET};
+use libvhdi_sys::{
+    libvhdi_file_close, libvhdi_file_free, libvhdi_file_get_disk_type, libvhdi_file_get_media_size,
+    libvhdi_file_get_utf8_parent_filename, libvhdi_file_get_utf8_parent_filename_size,
+    libvhdi_file_initialize, libvhdi_file_open, libvhdi_file_read_buffer, libvhdi_file_seek_offset,
+    libvhdi_file_set_parent_file, size_t, FilePtr, LIBVHDI_ACCESS_FLAGS_LIBVHDI_ACCESS_FLAG_READ,
+    LIBVHDI_DISK_TYPES_LIBVHDI_DISK_TYPE_DIFFERENTIAL,
+};
+use log::error;
+use std::ffi::CString;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::raw::{c_int, c_void};
+use std::path::Path;
+use std::ptr;
+
+/// Differencing chains longer than this are assumed to loop.
+const MAX_PARENT_DEPTH: usize = 64;
+
+/// A VHD or VHDX image, reads return the contents of the virtual disk.
+pub struct VhdiImage {
+    ptr: FilePtr,
+    /// Whether the file was opened, and has to be closed.
+    opened: bool,
+    // Read from by this image, so freed after it (`Drop::drop` runs before fields are dropped).
+    parent: Option<Box<VhdiImage>>,
+}
+
+// The image and its parents are owned by this value alone.
+unsafe impl Send for VhdiImage {}
+
+impl VhdiImage {
+    /// Opens the image at `path`, and the parents of differencing images.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        VhdiImage::open_chain(path.as_ref(), 0)
+    }
+
+    fn open_chain(path: &Path, depth: usize) -> Result<Self, Error> {
+        if depth > MAX_PARENT_DEPTH {
+            return Err(Error::Other(format!(
+                "more than {} parent images, the differencing chain of {} loops",
+                MAX_PARENT_DEPTH,
+                path.display()
+            )));
+        }
+
+        let mut handle = FilePtr::null();
+
+        let filename =
+            CString::new(path.to_string_lossy().into_owned()).map_err(Error::StringContainsNul)?;
+
+        try_ffi!(libvhdi_file_initialize(handle.as_out_ptr()));
+
+        let mut image = VhdiImage {
+            ptr: handle,
+            opened: false,
+            parent: None,
+        };
+
+        try_ffi!(libvhdi_file_open(
+            image.ptr.as_ptr(),
+            filename.as_ptr(),
+            LIBVHDI_ACCESS_FLAGS_LIBVHDI_ACCESS_FLAG_READ as c_int,
+        ));
+        image.opened = true;
+
+        if image.is_differential()? {
+            let parent_filename = image.get_parent_filename()?.ok_or_else(|| {
+                Error::NotFound(format!(
+                    "differencing image {} does not name its parent",
+                    path.display()
+                ))
+            })?;
+
+            let parent =
+                VhdiImage::open_chain(&parent_image_path(path, &parent_filename), depth + 1)?;
+
+            try_ffi!(libvhdi_file_set_parent_file(
+                image.ptr.as_ptr(),
+                parent.ptr.as_ptr(),
+            ));
+
+            image.parent = Some(Box::new(parent));
+        }
+
+        Ok(image)
+    }
+
+    /// Size of the virtual disk, in bytes.
+    pub fn get_media_size(&self) -> Result<u64, Error> {
+        let mut size = 0;
+
+        try_ffi!(libvhdi_file_get_media_size(self.ptr.as_ptr(), &mut size));
+
+        Ok(size)
+    }
+
+    /// Whether the image only holds the changes to a parent image.
+    pub fn is_differential(&self) -> Result<bool, Error> {
+        let mut disk_type = 0;
+
+        try_ffi!(libvhdi_file_get_disk_type(
+            self.ptr.as_ptr(),
+            &mut disk_type
+        ));
+
+        Ok(disk_type == LIBVHDI_DISK_TYPES_LIBVHDI_DISK_TYPE_DIFFERENTIAL as u32)
+    }
+
+    /// The path of the parent image, as stored in a differencing image.
+    pub fn get_parent_filename(&self) -> Result<Option<String>, Error> {
+        let mut size: size_t = 0;
+        let mut error = ptr::null_mut();
+
+        match unsafe {
+            libvhdi_file_get_utf8_parent_filename_size(self.ptr.as_ptr(), &mut size, &mut error)
+        } {
+            1 => {}
+            0 => return Ok(None),
+            _ => return Err(Error::from(unsafe { CError::from_raw(error) })),
+        }
+
+        if size == 0 {
+            return Ok(Some(String::new()));
+        }
+
+        let mut filename = vec![0; size as usize];
+
+        try_ffi!(libvhdi_file_get_utf8_parent_filename(
+            self.ptr.as_ptr(),
+            filename.as_mut_ptr(),
+            size,
+        ));
+
+        // Without the nul terminator.
+        filename.pop();
+
+        String::from_utf8(filename)
+            .map(Some)
+            .map_err(Error::StringContainsInvalidUTF8)
+    }
+
+    pub fn parent(&self) -> Option<&VhdiImage> {
+        self.parent.as_ref().map(|parent| &**parent)
+    }
+}
+
+impl Read for VhdiImage {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut error = ptr::null_mut();
+
+        let read_count = unsafe {
+            libvhdi_file_read_buffer(
+                self.ptr.as_ptr(),
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len() as size_t,
+                &mut error,
+            )
+        };
+
+        if read_count <= -1 {
+            Err(to_io_error(error))
+        } else {
+            Ok(read_count as usize)
+        }
+    }
+}
+
+impl Seek for VhdiImage {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset as i64, SEEK_SET as c_int),
+            SeekFrom::Current(offset) => (offset, SEEK_CUR as c_int),
+            SeekFrom::End(offset) => (offset, SEEK_END as c_int),
+        };
+
+        let mut error = ptr::null_mut();
+
+        let position =
+            unsafe { libvhdi_file_seek_offset(self.ptr.as_ptr(), offset, whence, &mut error) };
+
+        if position <= -1 {
+            Err(to_io_error(error))
+        } else {
+            Ok(position as u64)
+        }
+    }
+}
+
+impl Drop for VhdiImage {
+    fn drop(&mut self) {
+        let mut error = ptr::null_mut();
+
+        if self.opened && unsafe { libvhdi_file_close(self.ptr.as_ptr(), &mut error) } != 0 {
+            error!("`libvhdi_file_close` failed!");
+        }
+
+        if unsafe { libvhdi_file_free(self.ptr.as_out_ptr(), &mut error) } != 1 {
+            error!("`libvhdi_file_free` failed!");
+        }
+    }
+}
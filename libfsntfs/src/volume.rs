@@ -1,7 +1,32 @@
+use crate::attribute::AttributeType;
+use crate::bitmap::ClusterBitmap;
+use crate::boot::{BootSector, BootSectorFallback, BootSectorSource};
 use crate::error::Error;
 use crate::ffi_error::{LibfsntfsError, LibfsntfsErrorRef, LibfsntfsErrorRefMut};
 use crate::file_entry::{FileEntry, FileEntryRef, FileEntryRefMut};
-use libbfio_rs::handle::{Handle, HandleRef};
+use crate::flags::VolumeFlags;
+use crate::geometry::VolumeGeometry;
+use crate::instrument::Metric;
+use crate::intercept::InterceptedReader;
+use crate::metadata_files;
+#[cfg(feature = "mmap")]
+use crate::mmap::MmapReader;
+use crate::options::{IoBackend, VolumeOpenOptions};
+use crate::quota::{self, QuotaEntry};
+use crate::recovery::{MftMirrorFallback, Recovery};
+use crate::reparse_index::{self, ReparseIndexEntry};
+use crate::resolve::{self, ResolveOptions, Resolved};
+use crate::secure::SecurityDescriptors;
+use crate::shared::ReadSeekSend;
+use crate::stats::VolumeStats;
+use crate::throttle::ThrottledReader;
+use crate::unallocated::UnallocatedReader;
+#[cfg(all(windows, feature = "unbuffered"))]
+use crate::unbuffered::UnbufferedReader;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use crate::uring::UringReader;
+use libbfio_rs::handle::{Handle, HandleRef, LibbfioAccessFlags};
+use libbfio_rs::IoHandle;
 use libfsntfs_sys::{
     libfsntfs_file_entry_t, size32_t, LIBFSNTFS_ACCESS_FLAGS,
     LIBFSNTFS_ACCESS_FLAGS_LIBFSNTFS_ACCESS_FLAG_READ,
@@ -12,11 +37,13 @@ use log::error;
 use std::convert::TryFrom;
 use std::ffi::{c_void, CStr, CString};
 use std::fs::File;
+use std::io::{Read, Seek};
 use std::marker::PhantomData;
 use std::mem;
 use std::os::raw::c_int;
 use std::path::{Iter, Path, PathBuf};
 use std::ptr;
+use std::sync::Arc;
 
 #[repr(C)]
 pub struct __Volume(isize);
@@ -25,7 +52,17 @@ pub type VolumeRefMut = *mut __Volume;
 pub type VolumeRef = *const __Volume;
 
 #[repr(C)]
-pub struct Volume(VolumeRefMut);
+pub struct Volume {
+    ptr: VolumeRefMut,
+    options: VolumeOpenOptions,
+    /// When the volume opened the image through a handle of its own (e.g. to throttle it), it
+    /// is dropped after the volume is closed.
+    handle: Option<Handle>,
+    /// The path of the image, if it was opened by filename.
+    image_path: Option<String>,
+    /// The copies of damaged structures the volume was opened from.
+    recovery: Recovery,
+}
 
 impl AsTypeRef for Volume {
     type Ref = VolumeRef;
@@ -34,21 +71,51 @@ impl AsTypeRef for Volume {
     #[inline]
     fn as_type_ref(&self) -> Self::Ref {
         // https://users.rust-lang.org/t/is-it-ub-to-convert-t-to-mut-t/16238/4
-        self.0 as *const _
+        self.ptr as *const _
     }
 
     fn as_type_ref_mut(&mut self) -> Self::RefMut {
-        self.0
+        self.ptr
     }
 
     fn as_raw(&mut self) -> *mut Self::RefMut {
-        &mut self.0 as *mut _
+        &mut self.ptr as *mut _
     }
 }
 
 impl Volume {
     pub fn wrap_ptr(ptr: VolumeRefMut) -> Volume {
-        Volume(ptr)
+        Volume {
+            ptr,
+            options: VolumeOpenOptions::default(),
+            handle: None,
+            image_path: None,
+            recovery: Recovery::default(),
+        }
+    }
+
+    /// The options this volume was opened with.
+    pub fn options(&self) -> &VolumeOpenOptions {
+        &self.options
+    }
+
+    /// The path of the image, `None` for volumes opened from a `Handle`.
+    pub fn get_image_path(&self) -> Option<&str> {
+        self.image_path.as_ref().map(String::as_str)
+    }
+
+    /// Which boot sector the volume was opened from, see `VolumeOpenOptions::boot_sector_fallback`.
+    pub fn get_boot_sector_source(&self) -> BootSectorSource {
+        self.recovery.boot_sector
+    }
+
+    /// What the volume was opened from instead of damaged structures, see `recovery`.
+    pub fn get_recovery(&self) -> &Recovery {
+        &self.recovery
+    }
+
+    pub(crate) fn set_recovery(&mut self, recovery: Recovery) {
+        self.recovery = recovery;
     }
 }
 
@@ -81,6 +148,11 @@ extern "C" {
         volume: *mut VolumeRefMut,
         error: *mut LibfsntfsErrorRefMut,
     ) -> c_int;
+    pub fn libfsntfs_volume_set_ascii_codepage(
+        volume: VolumeRef,
+        ascii_codepage: c_int,
+        error: *mut LibfsntfsErrorRefMut,
+    ) -> c_int;
     pub fn libfsntfs_volume_signal_abort(
         volume: VolumeRef,
         error: *mut LibfsntfsErrorRefMut,
@@ -186,6 +258,7 @@ extern "C" {
     ) -> c_int;
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AccessMode {
     Read,
     Write,
@@ -226,69 +299,241 @@ impl<'a> Iterator for IterFileEntries<'a> {
     }
 }
 
+/// Iterates over the MFT by index, including entries which are not allocated.
+pub struct MftEntries<'a> {
+    volume: &'a Volume,
+    idx: MftEntryIndex,
+    end: MftEntryIndex,
+}
+
+impl<'a> Iterator for MftEntries<'a> {
+    type Item = (MftEntryIndex, Result<FileEntry<'a>, Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx < self.end {
+            let idx = self.idx;
+            self.idx += 1;
+
+            return Some((idx, self.volume.get_file_entry_by_mft_idx(idx)));
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.end - self.idx) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> DoubleEndedIterator for MftEntries<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.idx < self.end {
+            self.end -= 1;
+
+            return Some((self.end, self.volume.get_file_entry_by_mft_idx(self.end)));
+        }
+
+        None
+    }
+}
+
+impl<'a> ExactSizeIterator for MftEntries<'a> {}
+
 impl<'a> Volume {
     /// Opens a volume by filename.
     pub fn open(filename: impl AsRef<str>, mode: AccessMode) -> Result<Self, Error> {
+        Volume::open_with_options(filename, &VolumeOpenOptions::new().access_mode(mode))
+    }
+
+    /// Opens a volume by filename, see `VolumeOpenOptions`.
+    pub fn open_with_options(
+        filename: impl AsRef<str>,
+        options: &VolumeOpenOptions,
+    ) -> Result<Self, Error> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!("open_volume", image = filename.as_ref());
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        if options.get_throttle().is_some()
+            || options.read_interceptor.is_some()
+            || options.read_audit.is_some()
+            || options.io_backend != IoBackend::File
+            || options.boot_sector_fallback
+            || options.mft_mirror_fallback
+        {
+            let mut volume = Volume::open_through_handle(filename.as_ref(), options)?;
+            volume.image_path = Some(filename.as_ref().to_owned());
+
+            return Ok(volume);
+        }
+
         let mut handle = ptr::null_mut();
 
         let c_string = CString::new(filename.as_ref()).map_err(Error::StringContainsNul)?;
 
-        let mut init_error = ptr::null_mut();
+        try_ffi!(libfsntfs_volume_initialize(&mut handle));
+
+        let mut volume = Volume::wrap_ptr(handle);
+        volume.options = options.clone();
+
+        try_ffi!(libfsntfs_volume_set_ascii_codepage(
+            volume.as_type_ref(),
+            options.codepage.number() as c_int,
+        ));
+        volume.image_path = Some(filename.as_ref().to_owned());
+
+        try_ffi!(libfsntfs_volume_open(
+            volume.as_type_ref(),
+            c_string.as_ptr(),
+            options.access_mode.as_flag() as c_int,
+        ));
 
-        let retcode =
-            unsafe { libfsntfs_volume_initialize(&mut handle as _, &mut init_error as _) };
+        Ok(volume)
+    }
 
-        if retcode != 1 {
-            return Err(Error::try_from(init_error)?);
+    /// Opens the image through a handle of the volume's own, for options libfsntfs cannot
+    /// apply when it opens the file itself.
+    fn open_through_handle(filename: &str, options: &VolumeOpenOptions) -> Result<Self, Error> {
+        if options.access_mode != AccessMode::Read {
+            // The handle only reads, so the access mode would silently be dropped.
+            return Err(Error::Unsupported(format!(
+                "{:?} access together with throttling, read interceptors or audits, the {:?} \
+                 backend or recovery fallbacks, which read the image through a read-only handle",
+                options.access_mode, options.io_backend
+            )));
         }
 
-        let volume = Volume::wrap_ptr(handle);
+        let reader: Box<dyn ReadSeekSend> = match options.io_backend {
+            IoBackend::File => Box::new(File::open(filename)?),
+            #[cfg(feature = "mmap")]
+            IoBackend::Mmap => Box::new(MmapReader::new(&File::open(filename)?)?),
+            #[cfg(not(feature = "mmap"))]
+            IoBackend::Mmap => {
+                return Err(Error::Unsupported(
+                    "memory mapped images, enable the `mmap` feature".to_owned(),
+                ))
+            }
+            #[cfg(all(windows, feature = "unbuffered"))]
+            IoBackend::Unbuffered => Box::new(UnbufferedReader::open(filename)?),
+            #[cfg(not(all(windows, feature = "unbuffered")))]
+            IoBackend::Unbuffered => {
+                return Err(Error::Unsupported(
+                    "unbuffered reads, enable the `unbuffered` feature (Windows only)".to_owned(),
+                ))
+            }
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            IoBackend::IoUring => Box::new(UringReader::new(File::open(filename)?)?),
+            #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+            IoBackend::IoUring => {
+                return Err(Error::Unsupported(
+                    "io_uring, enable the `io-uring` feature (Linux only)".to_owned(),
+                ))
+            }
+        };
+
+        let (reader, recovery) = Volume::with_recovery(reader, options)?;
+        let file_handle = Volume::io_handle(reader, options)?;
+
+        let mut volume = Volume::open_file_object_with_options(&file_handle, options)?;
+        volume.handle = Some(file_handle);
+        volume.recovery = recovery;
+
+        Ok(volume)
+    }
 
-        let mut error = ptr::null_mut();
+    /// Replaces the damaged structures of `reader` by their copies, as far as the options ask
+    /// for it: the boot sector by the backup, then the first MFT records by `$MFTMirr`.
+    pub(crate) fn with_recovery(
+        mut reader: Box<dyn ReadSeekSend>,
+        options: &VolumeOpenOptions,
+    ) -> Result<(Box<dyn ReadSeekSend>, Recovery), Error> {
+        let mut recovery = Recovery::default();
+
+        if options.boot_sector_fallback {
+            let fallback = BootSectorFallback::new(reader)?;
+            recovery.boot_sector = fallback.source();
+            reader = Box::new(fallback);
+        }
 
-        if unsafe {
-            libfsntfs_volume_open(
-                volume.as_type_ref(),
-                c_string.as_ptr(),
-                mode.as_flag() as c_int,
-                &mut error as _,
-            )
-        } != 1
-        {
-            Err(Error::try_from(error)?)
-        } else {
-            Ok(volume)
+        if options.mft_mirror_fallback {
+            let fallback = MftMirrorFallback::new(reader)?;
+            recovery.mirrored_records = fallback.mirrored_records().to_vec();
+            reader = Box::new(fallback);
         }
+
+        Ok((reader, recovery))
+    }
+
+    /// The handle libfsntfs reads `reader` through, throttled, intercepted and audited as the
+    /// options ask for it.
+    pub(crate) fn io_handle(
+        reader: Box<dyn ReadSeekSend>,
+        options: &VolumeOpenOptions,
+    ) -> Result<Handle, Error> {
+        let reader: Box<dyn ReadSeekSend> = match options.get_throttle() {
+            Some(throttle) => Box::new(ThrottledReader::new(reader, throttle.clone())),
+            None => reader,
+        };
+
+        // Above the throttle, reads it answers do not count against the rate.
+        let reader: Box<dyn ReadSeekSend> = match options.get_read_interceptor() {
+            Some(interceptor) => Box::new(InterceptedReader::new(reader, interceptor.clone())?),
+            None => reader,
+        };
+
+        // Above the interceptor, so reads it answers (e.g. from a cache) are recorded too.
+        let io_handle = match options.get_read_audit() {
+            Some(audit) => {
+                IoHandle::read_seek(InterceptedReader::new(reader, Arc::new(audit.clone()))?)
+            }
+            None => IoHandle::read_seek(reader),
+        };
+
+        Handle::from_io_handle(io_handle, LibbfioAccessFlags::Read)
+            .map_err(|e| Error::Other(format!("libbfio: {}", e)))
     }
 
     pub fn open_file_object(file_handle: &Handle) -> Result<Self, Error> {
+        Volume::open_file_object_with_options(file_handle, &VolumeOpenOptions::default())
+    }
+
+    /// Opens the volume in `file_handle`, which must outlive the volume.
+    /// The access mode of `options` is ignored, the volume is opened for reading.
+    pub fn open_file_object_with_options(
+        file_handle: &Handle,
+        options: &VolumeOpenOptions,
+    ) -> Result<Self, Error> {
         let mut volume_handle = ptr::null_mut();
-        let mut init_error = ptr::null_mut();
 
-        let retcode =
-            unsafe { libfsntfs_volume_initialize(&mut volume_handle as _, &mut init_error as _) };
+        try_ffi!(libfsntfs_volume_initialize(&mut volume_handle));
 
-        if retcode != 1 {
-            return Err(Error::try_from(init_error)?);
-        }
+        let mut volume = Volume::wrap_ptr(volume_handle);
+        volume.options = options.clone();
 
-        let volume = Volume::wrap_ptr(volume_handle);
+        try_ffi!(libfsntfs_volume_set_ascii_codepage(
+            volume.as_type_ref(),
+            options.codepage.number() as c_int,
+        ));
 
-        let mut error = ptr::null_mut();
+        try_ffi!(libfsntfs_volume_open_file_io_handle(
+            volume.as_type_ref(),
+            file_handle.as_type_ref(),
+            1_u8,
+        ));
 
-        if unsafe {
-            libfsntfs_volume_open_file_io_handle(
-                volume.as_type_ref(),
-                file_handle.as_type_ref(),
-                1_u8,
-                &mut error as _,
-            )
-        } != 1
-        {
-            Err(Error::try_from(error)?)
-        } else {
-            Ok(volume)
-        }
+        Ok(volume)
+    }
+
+    /// Iterates over all MFT entries in order, yielding each entry with its index.
+    /// Unlike walking the directory tree, this also reaches entries which are not allocated.
+    pub fn mft_entries(&self) -> Result<MftEntries, Error> {
+        Ok(MftEntries {
+            volume: self,
+            idx: 0,
+            end: self.number_of_file_entries()?,
+        })
     }
 
     pub fn iter_entries(&self) -> Result<IterFileEntries, Error> {
@@ -302,71 +547,105 @@ impl<'a> Volume {
     /// Retrieves the volume serial number.
     pub fn get_serial_number(&self) -> Result<SerialNumber, Error> {
         let mut serial_number = 0_u64;
-        let mut error = ptr::null_mut();
 
-        if unsafe {
-            libfsntfs_volume_get_serial_number(self.as_type_ref(), &mut serial_number, &mut error)
-        } != 1
-        {
-            Err(Error::try_from(error)?)
-        } else {
-            Ok(serial_number)
-        }
+        try_ffi!(libfsntfs_volume_get_serial_number(
+            self.as_type_ref(),
+            &mut serial_number
+        ));
+
+        Ok(serial_number)
     }
 
     /// Retrieves a file entry specified by the path.
     pub fn get_file_entry_by_path(&self, path: impl AsRef<Path>) -> Result<FileEntry, Error> {
         let mut file_entry = ptr::null_mut();
-        let mut error = ptr::null_mut();
 
         let path_as_str = path
             .as_ref()
             .to_str()
             .ok_or_else(|| Error::Other("String is invalid UTF-8".to_owned()))?;
 
-        if unsafe {
-            libfsntfs_volume_get_file_entry_by_utf8_path(
+        try_ffi!(libfsntfs_volume_get_file_entry_by_utf8_path(
+            self.as_type_ref(),
+            path_as_str.as_ptr(),
+            path_as_str.len(),
+            &mut file_entry,
+        ));
+
+        let file_entry = FileEntry::wrap_ptr(self, file_entry);
+
+        if self.options.case_sensitive {
+            self.check_path_case(path_as_str)?;
+        }
+
+        Ok(file_entry)
+    }
+
+    /// libfsntfs matches names regardless of case, so verify every component of the path
+    /// against the name stored on the volume.
+    fn check_path_case(&self, path: &str) -> Result<(), Error> {
+        let mut prefix = String::with_capacity(path.len());
+
+        for component in path.split('\\').filter(|component| !component.is_empty()) {
+            prefix.push('\\');
+            prefix.push_str(component);
+
+            let mut file_entry = ptr::null_mut();
+
+            try_ffi!(libfsntfs_volume_get_file_entry_by_utf8_path(
                 self.as_type_ref(),
-                path_as_str.as_ptr(),
-                path_as_str.len(),
+                prefix.as_ptr(),
+                prefix.len(),
                 &mut file_entry,
-                &mut error,
-            )
-        } != 1
-        {
-            Err(Error::try_from(error)?)
-        } else {
-            Ok(FileEntry::wrap_ptr(self, file_entry))
+            ));
+
+            let name = FileEntry::wrap_ptr(self, file_entry).get_name()?;
+
+            if name != component {
+                return Err(Error::NotFound(format!(
+                    "`{}`, `{}` does not match the case of `{}`",
+                    path, component, name
+                )));
+            }
         }
+
+        Ok(())
+    }
+
+    /// Retrieves the entry at `path`, following symbolic links and mount points along the way.
+    /// Links to other volumes resolve to `Resolved::OutsideVolume`.
+    pub fn resolve(
+        &self,
+        path: impl AsRef<str>,
+        options: &ResolveOptions,
+    ) -> Result<Resolved, Error> {
+        resolve::resolve(self, path.as_ref(), options)
     }
 
     /// Retrieves a specific file entry.
     pub fn get_file_entry_by_mft_idx(&self, idx: MftEntryIndex) -> Result<FileEntry, Error> {
         let mut file_entry = ptr::null_mut();
-        let mut error = ptr::null_mut();
 
-        if unsafe {
-            libfsntfs_volume_get_file_entry_by_index(
-                self.as_type_ref(),
-                idx,
-                &mut file_entry,
-                &mut error,
-            )
-        } != 1
-        {
-            Err(Error::try_from(error)?)
-        } else {
-            Ok(FileEntry::wrap_ptr(self, file_entry))
-        }
+        self.options()
+            .record(Metric::FfiCall("libfsntfs_volume_get_file_entry_by_index"));
+        try_ffi!(libfsntfs_volume_get_file_entry_by_index(
+            self.as_type_ref(),
+            idx,
+            &mut file_entry,
+        ));
+
+        Ok(FileEntry::wrap_ptr(self, file_entry))
     }
 
-    /// Retrieves the name.
+    /// Retrieves the name, decoded by the `Utf16Policy` of the volume.
     pub fn get_name(&self) -> Result<String, Error> {
-        get_sized_utf8_string!(
+        let name = get_sized_utf16_units!(
             self,
-            libfsntfs_volume_get_utf8_name_size,
-            libfsntfs_volume_get_utf8_name
-        )
+            libfsntfs_volume_get_utf16_name_size,
+            libfsntfs_volume_get_utf16_name
+        )?;
+
+        self.options().get_utf16_policy().decode(&name)
     }
 
     /// Closes a volume.
@@ -377,40 +656,151 @@ impl<'a> Volume {
     /// Retrieves the root directory.
     pub fn get_root_directory(&self) -> Result<FileEntry, Error> {
         let mut file_entry = ptr::null_mut();
-        let mut error = ptr::null_mut();
 
-        if unsafe {
-            libfsntfs_volume_get_root_directory(self.as_type_ref(), &mut file_entry, &mut error)
-        } != 1
-        {
-            Err(Error::try_from(error)?)
-        } else {
-            Ok(FileEntry::wrap_ptr(self, file_entry))
-        }
+        try_ffi!(libfsntfs_volume_get_root_directory(
+            self.as_type_ref(),
+            &mut file_entry
+        ));
+
+        Ok(FileEntry::wrap_ptr(self, file_entry))
     }
 
     /// Retrieves the number of file entries.
     pub fn get_number_of_file_entries(&self) -> Result<usize, Error> {
+        Ok(self.number_of_file_entries()? as usize)
+    }
+
+    /// The number of entries in the MFT (allocated or not), which is one past the last valid `MftEntryIndex`.
+    pub fn number_of_file_entries(&self) -> Result<MftEntryIndex, Error> {
         let mut number_of_file_entries = 0;
-        let mut error = ptr::null_mut();
 
-        if unsafe {
-            libfsntfs_volume_get_number_of_file_entries(
-                self.as_type_ref(),
-                &mut number_of_file_entries,
-                &mut error,
-            )
-        } != 1
-        {
-            Err(Error::try_from(error)?)
-        } else {
-            Ok(number_of_file_entries as usize)
+        try_ffi!(libfsntfs_volume_get_number_of_file_entries(
+            self.as_type_ref(),
+            &mut number_of_file_entries,
+        ));
+
+        Ok(number_of_file_entries as MftEntryIndex)
+    }
+
+    /// Retrieves the size of a cluster, in bytes.
+    pub fn get_cluster_block_size(&self) -> Result<usize, Error> {
+        let mut cluster_block_size = 0;
+
+        try_ffi!(libfsntfs_volume_get_cluster_block_size(
+            self.as_type_ref(),
+            &mut cluster_block_size
+        ));
+
+        Ok(cluster_block_size)
+    }
+
+    /// Retrieves the volume flags from `$VOLUME_INFORMATION`,
+    /// `VolumeFlags::DIRTY` tells whether the volume was cleanly unmounted.
+    pub fn flags(&self) -> Result<VolumeFlags, Error> {
+        let volume_file = self.get_file_entry_by_mft_idx(metadata_files::VOLUME)?;
+
+        for attribute in volume_file.iter_attributes()? {
+            let attribute = attribute?;
+
+            if attribute.get_type()? == AttributeType::VolumeInformation {
+                return attribute.get_volume_flags();
+            }
         }
+
+        Err(Error::Other(
+            "$Volume has no $VOLUME_INFORMATION attribute".to_owned(),
+        ))
     }
 
-    /// Retrieves the USN change journal.
-    fn get_usn_change_journal(&self) {
-        unimplemented!();
+    /// Retrieves the NTFS version as (major, minor), e.g. (3, 1) since Windows XP.
+    pub fn format_version(&self) -> Result<(u8, u8), Error> {
+        let mut major_version = 0;
+        let mut minor_version = 0;
+
+        try_ffi!(libfsntfs_volume_get_version(
+            self.as_type_ref(),
+            &mut major_version,
+            &mut minor_version
+        ));
+
+        Ok((major_version, minor_version))
+    }
+
+    /// Reads the boot sector the volume was opened from, see `get_boot_sector_source`.
+    pub fn boot_sector(&self) -> Result<BootSector, Error> {
+        BootSector::read(self)
+    }
+
+    /// Reads the layout of the volume from the boot sector.
+    pub fn geometry(&self) -> Result<VolumeGeometry, Error> {
+        VolumeGeometry::read(self)
+    }
+
+    /// Retrieves the size of an MFT entry, in bytes.
+    pub fn get_mft_entry_size(&self) -> Result<u32, Error> {
+        let mut mft_entry_size = 0;
+
+        try_ffi!(libfsntfs_volume_get_mft_entry_size(
+            self.as_type_ref(),
+            &mut mft_entry_size
+        ));
+
+        Ok(mft_entry_size)
+    }
+
+    /// Retrieves the size of an index record (`INDX`), in bytes.
+    pub fn get_index_entry_size(&self) -> Result<u32, Error> {
+        let mut index_entry_size = 0;
+
+        try_ffi!(libfsntfs_volume_get_index_entry_size(
+            self.as_type_ref(),
+            &mut index_entry_size
+        ));
+
+        Ok(index_entry_size)
+    }
+
+    /// Reads the quota entries from `$Extend\$Quota`, `image` is the raw volume
+    /// (large quota indexes are stored outside of the MFT record).
+    pub fn get_quota_entries<R: Read + Seek>(
+        &self,
+        image: &mut R,
+    ) -> Result<Vec<QuotaEntry>, Error> {
+        quota::read_quota_entries(self, image)
+    }
+
+    /// Lists every file carrying a reparse point, from `$Extend\$Reparse`.
+    /// `image` is the raw volume.
+    pub fn get_reparse_points<R: Read + Seek>(
+        &self,
+        image: &mut R,
+    ) -> Result<Vec<ReparseIndexEntry>, Error> {
+        reparse_index::read_reparse_points(self, image)
+    }
+
+    /// Collects statistics about the volume, see `VolumeStats`.
+    /// This reads every MFT entry once, so it takes a while on large volumes.
+    pub fn stats(&self) -> Result<VolumeStats, Error> {
+        VolumeStats::collect(self)
+    }
+
+    /// Reads the cluster allocation bitmap (`$Bitmap`) of the volume.
+    pub fn get_cluster_bitmap(&self) -> Result<ClusterBitmap, Error> {
+        ClusterBitmap::read(self)
+    }
+
+    /// Reads the unallocated clusters of the volume from `image`,
+    /// which must be the raw volume this was opened from (starting at the volume's first byte).
+    pub fn unallocated_reader<R: Read + Seek>(
+        &self,
+        image: R,
+    ) -> Result<UnallocatedReader<R>, Error> {
+        Ok(UnallocatedReader::new(image, &self.get_cluster_bitmap()?))
+    }
+
+    /// Reads all security descriptors of the volume from `$Secure:$SDS`.
+    pub fn get_security_descriptors(&self) -> Result<SecurityDescriptors, Error> {
+        SecurityDescriptors::read(self)
     }
 
     /// Signals the volume to abort the current activity.
@@ -423,6 +813,7 @@ impl<'a> Volume {
 mod tests {
     use super::*;
     use crate::fixtures::*;
+    use crate::options::{Codepage, Utf16Policy};
     use log::{info, trace};
     use std::path::PathBuf;
 
@@ -442,6 +833,40 @@ mod tests {
         assert!(sample_volume().is_ok());
     }
 
+    #[test]
+    fn test_open_with_codepage() {
+        let options = VolumeOpenOptions::new().codepage(Codepage::Windows1251);
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+
+        assert_eq!(volume.options().get_codepage(), Codepage::Windows1251);
+        assert_eq!(volume.get_name().unwrap(), "KW-SRCH-1");
+    }
+
+    #[test]
+    fn test_open_with_utf16_policy() {
+        let options = VolumeOpenOptions::new().utf16_policy(Utf16Policy::Strict);
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+
+        assert_eq!(volume.get_name().unwrap(), "KW-SRCH-1");
+
+        let entry = volume.get_file_entry_by_path("\\$MFT").unwrap();
+        assert_eq!(
+            entry.get_utf16_name().unwrap(),
+            "$MFT".encode_utf16().collect::<Vec<u16>>()
+        );
+        assert_eq!(entry.get_name().unwrap(), "$MFT");
+    }
+
+    #[test]
+    fn test_get_image_path() {
+        let volume = sample_volume().unwrap();
+        assert_eq!(volume.get_image_path(), Some(sample_volume_path().as_str()));
+
+        let handle = sample_volume_io_handle().unwrap();
+        let volume = Volume::open_file_object(&handle).unwrap();
+        assert_eq!(volume.get_image_path(), None);
+    }
+
     #[test]
     fn test_get_volume_name_works() {
         let volume_name_result = sample_volume().unwrap().get_name();
@@ -462,6 +887,22 @@ mod tests {
         assert_eq!(volume_name_result.unwrap(), 13425491701870188067)
     }
 
+    #[test]
+    fn test_mft_entries() {
+        let volume = sample_volume().unwrap();
+        let number_of_file_entries = volume.number_of_file_entries().unwrap();
+
+        let mft_entries = volume.mft_entries().unwrap();
+        assert_eq!(mft_entries.len() as u64, number_of_file_entries);
+
+        let indices: Vec<MftEntryIndex> = mft_entries.map(|(idx, _entry)| idx).collect();
+        assert_eq!(indices.first(), Some(&0));
+        assert_eq!(indices.last(), Some(&(number_of_file_entries - 1)));
+
+        let (_, mft) = volume.mft_entries().unwrap().next().unwrap();
+        assert_eq!(mft.unwrap().get_name().unwrap(), "$MFT");
+    }
+
     #[test]
     fn test_iter_entries() {
         let volume = sample_volume().unwrap();
@@ -471,4 +912,59 @@ mod tests {
             println!("{:?}", entry);
         }
     }
+
+    #[test]
+    fn test_unallocated_reader() {
+        let volume = sample_volume().unwrap();
+        let image = File::open(sample_volume_path()).unwrap();
+
+        let reader = volume.unallocated_reader(image).unwrap();
+        let stats = volume.stats().unwrap();
+
+        assert_eq!(reader.len(), stats.free_clusters * stats.cluster_size);
+    }
+
+    #[test]
+    fn test_get_quota_entries() {
+        let volume = sample_volume().unwrap();
+        let mut image = File::open(sample_volume_path()).unwrap();
+
+        let entries = volume.get_quota_entries(&mut image).unwrap();
+
+        assert!(entries
+            .iter()
+            .any(|entry| entry.owner_id == quota::DEFAULT_QUOTA_OWNER_ID));
+    }
+
+    #[test]
+    fn test_open_with_options_case_sensitive() {
+        let options = VolumeOpenOptions::new().case_sensitive(true);
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+
+        assert!(volume.options().is_case_sensitive());
+        assert!(volume.get_file_entry_by_path("\\$Extend\\$Reparse").is_ok());
+        match volume.get_file_entry_by_path("\\$EXTEND\\$Reparse") {
+            Err(Error::NotFound(_)) => {}
+            other => panic!("expected a not found error, got {:?}", other.map(|_| ())),
+        }
+
+        assert!(sample_volume()
+            .unwrap()
+            .get_file_entry_by_path("\\$EXTEND\\$Reparse")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_flags() {
+        let flags = sample_volume().unwrap().flags().unwrap();
+
+        // The sample was unmounted cleanly, no flag is set.
+        assert_eq!(flags, VolumeFlags::empty());
+        assert!(!flags.contains(VolumeFlags::DIRTY));
+    }
+
+    #[test]
+    fn test_format_version() {
+        assert_eq!(sample_volume().unwrap().format_version().unwrap(), (3, 1));
+    }
 }
@@ -0,0 +1,694 @@
+//! Walks the directory tree of a volume depth-first, with include and exclude filters
+//! evaluated against the full path of each entry (e.g. `\Windows\System32\cmd.exe`).
+//!
+//! Symbolic links and junctions are handled by `WalkOptions::reparse_policy`: by default they
+//! are yielded without being descended into, so links such as `\ProgramData\Application Data`
+//! (which points to its own parent) neither duplicate nor loop.
+use crate::error::Error;
+use crate::file_entry::FileEntry;
+use crate::instrument::Metric;
+use crate::kind::Kind;
+use crate::options::OrphanPolicy;
+use crate::paths::PathResolver;
+use crate::resolve::{ResolveOptions, Resolved};
+use crate::upcase::UpcaseTable;
+use crate::utils::mft_entry_index;
+use crate::volume::{MftEntries, MftEntryIndex, Volume};
+#[cfg(feature = "globset")]
+use globset::{GlobBuilder, GlobMatcher};
+#[cfg(feature = "regex")]
+use regex::{Regex, RegexBuilder};
+use std::collections::HashSet;
+
+/// Matches full paths, ignoring case like NTFS does.
+#[derive(Debug, Clone)]
+pub enum PathMatcher {
+    /// The path itself or anything below it, e.g. `\Windows` matches `\Windows\notepad.exe`.
+    Prefix(String),
+    /// Globs see the path with `/` separators, e.g. `/Users/*/AppData/**/*.exe`.
+    #[cfg(feature = "globset")]
+    Glob(GlobMatcher),
+    #[cfg(feature = "regex")]
+    Regex(Regex),
+}
+
+impl PathMatcher {
+    pub fn prefix(prefix: impl AsRef<str>) -> Self {
+        PathMatcher::Prefix(prefix.as_ref().trim_end_matches('\\').to_lowercase())
+    }
+
+    #[cfg(feature = "globset")]
+    pub fn glob(pattern: &str) -> Result<Self, Error> {
+        let glob = GlobBuilder::new(pattern)
+            .case_insensitive(true)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| Error::Other(format!("invalid glob `{}`: {}", pattern, e)))?;
+
+        Ok(PathMatcher::Glob(glob.compile_matcher()))
+    }
+
+    #[cfg(feature = "regex")]
+    pub fn regex(pattern: &str) -> Result<Self, Error> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| Error::Other(format!("invalid regex `{}`: {}", pattern, e)))?;
+
+        Ok(PathMatcher::Regex(regex))
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        match self {
+            PathMatcher::Prefix(prefix) => {
+                let path = path.to_lowercase();
+                path == *prefix || path.starts_with(&format!("{}\\", prefix))
+            }
+            #[cfg(feature = "globset")]
+            PathMatcher::Glob(glob) => glob.is_match(path.replace('\\', "/")),
+            #[cfg(feature = "regex")]
+            PathMatcher::Regex(regex) => regex.is_match(path),
+        }
+    }
+}
+
+/// What the walk does with symbolic links and junctions.
+#[derive(Debug, Clone)]
+pub enum ReparsePolicy {
+    /// Links are yielded (with `WalkEntry::link`), but not descended into.
+    Report,
+    /// Links are left out.
+    Skip,
+    /// Links are yielded, and those resolving to a directory of this volume are descended
+    /// into, with the children of the target at paths below the link. Targets outside the
+    /// volume (see `ResolveOptions::drive_letter`) and links to one of their own ancestors are
+    /// never followed.
+    Follow(ResolveOptions),
+}
+
+impl Default for ReparsePolicy {
+    fn default() -> Self {
+        ReparsePolicy::Report
+    }
+}
+
+/// What happened to a link during the walk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkOutcome {
+    /// The policy is not `ReparsePolicy::Follow`.
+    NotFollowed,
+    /// The link resolved to an entry of the volume, `Link::target_mft_entry_index`.
+    Followed,
+    /// The target is on another volume or a network share.
+    OutsideVolume,
+    /// The target is an ancestor of the link, or resolving it loops.
+    Cycle,
+    /// The target does not exist.
+    Dangling,
+}
+
+/// A symbolic link or junction met by the walk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Either `Kind::SymbolicLink` or `Kind::Junction`.
+    pub kind: Kind,
+    /// The substitute name of the reparse point, e.g. `\??\C:\Users\Public`.
+    pub target: Option<String>,
+    pub outcome: LinkOutcome,
+    /// The entry the link resolved to, when followed.
+    pub target_mft_entry_index: Option<MftEntryIndex>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// When not empty, only entries matching one of these are yielded.
+    /// Directories are still descended into, as their children may match.
+    pub include: Vec<PathMatcher>,
+    /// Entries matching one of these are skipped, directories with everything below them.
+    pub exclude: Vec<PathMatcher>,
+    /// Files with several hard links are yielded once, with all their paths in `hard_links`.
+    pub deduplicate_hard_links: bool,
+    /// Directories deeper than this are yielded, but not descended into.
+    /// The children of the root directory are at depth 1.
+    pub max_depth: Option<usize>,
+    /// Yields the children of every directory ordered by name, compared through the `$UpCase`
+    /// table of the volume, instead of the order libfsntfs lists them in.
+    pub sorted: bool,
+    pub reparse_policy: ReparsePolicy,
+}
+
+impl WalkOptions {
+    fn is_excluded(&self, path: &str) -> bool {
+        self.exclude.iter().any(|matcher| matcher.matches(path))
+    }
+
+    fn is_included(&self, path: &str) -> bool {
+        self.include.is_empty() || self.include.iter().any(|matcher| matcher.matches(path))
+    }
+}
+
+#[derive(Debug)]
+pub struct WalkEntry<'a> {
+    /// The full path, starting with `\`.
+    pub path: String,
+    pub entry: FileEntry<'a>,
+    /// Every path of the file (`path` included) when it has several hard links and
+    /// `WalkOptions::deduplicate_hard_links` is set, empty otherwise.
+    pub hard_links: Vec<String>,
+    /// Set when the entry is a symbolic link or a junction.
+    pub link: Option<Link>,
+}
+
+struct Directory<'a> {
+    entry: FileEntry<'a>,
+    file_reference: u64,
+    path: String,
+    next_sub_entry: i32,
+    number_of_sub_entries: i32,
+    /// Indexes of the sub entries in the order they are yielded, when sorted.
+    order: Option<Vec<i32>>,
+    /// Reached through a followed link. Directories below it can have been walked already
+    /// at their own path, so only the ancestors guard against cycles.
+    followed: bool,
+}
+
+impl<'a> Directory<'a> {
+    /// Reads the names of all sub entries up front when sorted by `upcase`.
+    fn new(
+        entry: FileEntry<'a>,
+        path: String,
+        upcase: Option<&UpcaseTable>,
+    ) -> Result<Self, Error> {
+        let number_of_sub_entries = entry.get_number_of_sub_file_entries()?;
+
+        let order = match upcase {
+            Some(upcase) => {
+                let mut names = Vec::with_capacity(number_of_sub_entries as usize);
+
+                for sub_entry_index in 0..number_of_sub_entries {
+                    let name = entry.get_sub_file_entry(sub_entry_index)?.get_name()?;
+                    names.push((name, sub_entry_index));
+                }
+
+                names.sort_by(|(a, _), (b, _)| upcase.compare(a, b));
+                Some(names.into_iter().map(|(_, index)| index).collect())
+            }
+            None => None,
+        };
+
+        Ok(Directory {
+            file_reference: entry.get_file_reference()?,
+            number_of_sub_entries,
+            next_sub_entry: 0,
+            order,
+            entry,
+            path,
+            followed: false,
+        })
+    }
+
+    fn next_sub_entry_index(&mut self) -> i32 {
+        let next = self.next_sub_entry;
+        self.next_sub_entry += 1;
+
+        match self.order {
+            Some(ref order) => order[next as usize],
+            None => next,
+        }
+    }
+}
+
+/// The allocated entries which cannot be reached from the root directory.
+struct Orphans<'a> {
+    entries: MftEntries<'a>,
+    paths: PathResolver,
+    /// With a trailing `\`.
+    root: String,
+}
+
+impl<'a> Orphans<'a> {
+    fn next_orphan(&mut self) -> Option<WalkEntry<'a>> {
+        loop {
+            let (idx, entry) = self.entries.next()?;
+
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+
+            if !entry.is_allocated().unwrap_or(false) {
+                continue;
+            }
+
+            let path = self.paths.full_path(idx, &entry);
+
+            if path.starts_with(&self.root) {
+                return Some(WalkEntry {
+                    path,
+                    entry,
+                    hard_links: vec![],
+                    link: None,
+                });
+            }
+        }
+    }
+}
+
+/// Depth-first, yields the children of a directory right after the directory itself.
+/// With `OrphanPolicy::VirtualRoot` the orphans of the volume follow the directory tree.
+pub struct Walk<'a> {
+    volume: &'a Volume,
+    stack: Vec<Directory<'a>>,
+    orphans: Option<Orphans<'a>>,
+    /// Set once the walk can not go on (listing the orphans failed), so it ends instead of
+    /// failing the same way on every call.
+    done: bool,
+    /// Directories which were descended into, which are never descended into again.
+    /// Corrupt images can have directories listing themselves, an ancestor or each other.
+    visited_directories: HashSet<MftEntryIndex>,
+    /// Files with several hard links which were already yielded.
+    seen_hard_links: HashSet<MftEntryIndex>,
+    paths: PathResolver,
+    upcase: Option<UpcaseTable>,
+    options: WalkOptions,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl<'a> Walk<'a> {
+    pub fn new(volume: &'a Volume, options: WalkOptions) -> Result<Self, Error> {
+        let upcase = if options.sorted {
+            Some(volume.get_upcase_table()?)
+        } else {
+            None
+        };
+
+        let root = Directory::new(volume.get_root_directory()?, String::new(), upcase.as_ref())?;
+
+        let mut visited_directories = HashSet::new();
+        visited_directories.insert(mft_entry_index(root.file_reference));
+
+        Ok(Walk {
+            volume,
+            stack: vec![root],
+            orphans: None,
+            done: false,
+            visited_directories,
+            seen_hard_links: HashSet::new(),
+            paths: PathResolver::new(),
+            upcase,
+            options,
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!("walk", image = volume.get_image_path().unwrap_or_default()),
+        })
+    }
+
+    fn next_orphan(&mut self) -> Result<Option<WalkEntry<'a>>, Error> {
+        if self.orphans.is_none() {
+            let root = match self.volume.options().get_orphan_policy() {
+                OrphanPolicy::VirtualRoot(root) => format!("{}\\", root),
+                OrphanPolicy::Exclude => return Ok(None),
+            };
+
+            let entries = match self.volume.mft_entries() {
+                Ok(entries) => entries,
+                Err(e) => {
+                    self.done = true;
+                    return Err(e);
+                }
+            };
+
+            self.orphans = Some(Orphans {
+                entries,
+                paths: PathResolver::new(),
+                root,
+            });
+        }
+
+        let orphans = match self.orphans.as_mut() {
+            Some(orphans) => orphans,
+            None => return Ok(None),
+        };
+
+        while let Some(orphan) = orphans.next_orphan() {
+            if !self.options.is_excluded(&orphan.path) && self.options.is_included(&orphan.path) {
+                return Ok(Some(orphan));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Whether the directory at `idx` is one of the directories being walked.
+    fn is_ancestor(&self, idx: MftEntryIndex) -> bool {
+        self.stack
+            .iter()
+            .any(|directory| mft_entry_index(directory.file_reference) == idx)
+    }
+
+    /// The link `entry` at `path` is, if any, and the directory to descend into when it is
+    /// followed.
+    fn link(
+        &self,
+        path: &str,
+        entry: &FileEntry<'a>,
+    ) -> Result<Option<(Link, Option<FileEntry<'a>>)>, Error> {
+        let kind = entry.kind()?;
+
+        if !kind.is_link() {
+            return Ok(None);
+        }
+
+        let mut link = Link {
+            kind,
+            target: entry.get_reparse_point_substitute_name()?,
+            outcome: LinkOutcome::NotFollowed,
+            target_mft_entry_index: None,
+        };
+
+        let resolve_options = match self.options.reparse_policy {
+            ReparsePolicy::Follow(ref resolve_options) => resolve_options,
+            _ => return Ok(Some((link, None))),
+        };
+
+        let target = match self.volume.resolve(path, resolve_options) {
+            Ok(Resolved::Entry(target)) => target,
+            Ok(Resolved::OutsideVolume { .. }) => {
+                link.outcome = LinkOutcome::OutsideVolume;
+                return Ok(Some((link, None)));
+            }
+            Err(Error::ReparsePointLoop(_)) | Err(Error::TooManyReparsePoints(_)) => {
+                link.outcome = LinkOutcome::Cycle;
+                return Ok(Some((link, None)));
+            }
+            Err(_) => {
+                link.outcome = LinkOutcome::Dangling;
+                return Ok(Some((link, None)));
+            }
+        };
+
+        let idx = mft_entry_index(target.get_file_reference()?);
+        link.target_mft_entry_index = Some(idx);
+
+        if self.is_ancestor(idx) {
+            link.outcome = LinkOutcome::Cycle;
+            return Ok(Some((link, None)));
+        }
+
+        link.outcome = LinkOutcome::Followed;
+
+        if target.has_directory_entries_index()? {
+            Ok(Some((link, Some(target))))
+        } else {
+            Ok(Some((link, None)))
+        }
+    }
+
+    fn next_entry(&mut self) -> Result<Option<WalkEntry<'a>>, Error> {
+        loop {
+            let directory = match self.stack.last_mut() {
+                Some(directory) => directory,
+                None => return self.next_orphan(),
+            };
+
+            if directory.next_sub_entry >= directory.number_of_sub_entries {
+                self.stack.pop();
+                continue;
+            }
+
+            let sub_entry_index = directory.next_sub_entry_index();
+            let sub_entry = directory.entry.get_sub_file_entry(sub_entry_index)?;
+
+            let path = format!("{}\\{}", directory.path, sub_entry.get_name()?);
+
+            let followed = directory.followed;
+
+            if self.options.is_excluded(&path) {
+                continue;
+            }
+
+            let link = self.link(&path, &sub_entry)?;
+
+            if link.is_some() {
+                if let ReparsePolicy::Skip = self.options.reparse_policy {
+                    continue;
+                }
+            }
+
+            let is_directory = sub_entry.has_directory_entries_index()?;
+
+            // The stack holds the root directory and the ancestors of `sub_entry`.
+            let depth = self.stack.len();
+            let below_max_depth = self.options.max_depth.map_or(true, |max| depth < max);
+
+            let link = match link {
+                Some((link, target_directory)) => {
+                    if let (Some(target_directory), true) = (target_directory, below_max_depth) {
+                        let mut directory =
+                            Directory::new(target_directory, path.clone(), self.upcase.as_ref())?;
+                        directory.followed = true;
+
+                        self.stack.push(directory);
+                    }
+
+                    Some(link)
+                }
+                None => {
+                    if is_directory && below_max_depth {
+                        let idx = mft_entry_index(sub_entry.get_file_reference()?);
+
+                        let revisited = if followed {
+                            self.is_ancestor(idx)
+                        } else {
+                            !self.visited_directories.insert(idx)
+                        };
+
+                        if revisited {
+                            continue;
+                        }
+
+                        // The entry itself is yielded, the walk continues through a handle of
+                        // its own.
+                        let directory_entry = sub_entry.volume().get_file_entry_by_mft_idx(idx)?;
+
+                        let mut directory =
+                            Directory::new(directory_entry, path.clone(), self.upcase.as_ref())?;
+                        directory.followed = followed;
+
+                        self.stack.push(directory);
+                    }
+
+                    None
+                }
+            };
+
+            if !self.options.is_included(&path) {
+                continue;
+            }
+
+            let mut hard_links = vec![];
+
+            if self.options.deduplicate_hard_links && !is_directory {
+                hard_links = self.paths.link_paths(&sub_entry)?;
+
+                if hard_links.len() < 2 {
+                    hard_links.clear();
+                } else if !self
+                    .seen_hard_links
+                    .insert(mft_entry_index(sub_entry.get_file_reference()?))
+                {
+                    continue;
+                }
+            }
+
+            return Ok(Some(WalkEntry {
+                path,
+                entry: sub_entry,
+                hard_links,
+                link,
+            }));
+        }
+    }
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = Result<WalkEntry<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        #[cfg(feature = "tracing")]
+        let span = self.span.clone();
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        if self.done {
+            return None;
+        }
+
+        match self.next_entry() {
+            Ok(entry) => entry.map(|entry| {
+                entry.entry.volume().options().record(Metric::EntryWalked);
+                Ok(entry)
+            }),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl Volume {
+    /// Walks the directory tree, see `Walk` and `WalkOptions`.
+    pub fn walk(&self, options: WalkOptions) -> Result<Walk, Error> {
+        Walk::new(self, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::options::VolumeOpenOptions;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn test_prefix_matcher() {
+        let matcher = PathMatcher::prefix("\\Windows\\");
+
+        assert!(matcher.matches("\\Windows"));
+        assert!(matcher.matches("\\windows\\System32\\cmd.exe"));
+        assert!(!matcher.matches("\\WindowsApps"));
+    }
+
+    #[cfg(feature = "globset")]
+    #[test]
+    fn test_glob_matcher() {
+        let matcher = PathMatcher::glob("/Users/*/**/*.exe").unwrap();
+
+        assert!(matcher.matches("\\Users\\bob\\Downloads\\setup.EXE"));
+        assert!(!matcher.matches("\\Windows\\notepad.exe"));
+    }
+
+    #[test]
+    fn test_walk_prunes_excluded_directories() {
+        let volume = sample_volume().unwrap();
+
+        let all: Vec<String> = volume
+            .walk(WalkOptions::default())
+            .unwrap()
+            .map(|entry| entry.unwrap().path)
+            .collect();
+
+        assert!(all.iter().any(|path| path == "\\$Extend"));
+        assert!(all.iter().any(|path| path.starts_with("\\$Extend\\")));
+
+        let options = WalkOptions {
+            exclude: vec![PathMatcher::prefix("\\$Extend")],
+            ..WalkOptions::default()
+        };
+
+        let pruned: Vec<String> = volume
+            .walk(options)
+            .unwrap()
+            .map(|entry| entry.unwrap().path)
+            .collect();
+
+        assert!(!pruned.iter().any(|path| path.starts_with("\\$Extend")));
+        assert!(pruned.len() < all.len());
+    }
+
+    #[test]
+    fn test_walk_deduplicates_hard_links() {
+        let volume = sample_volume().unwrap();
+        let options = WalkOptions {
+            deduplicate_hard_links: true,
+            ..WalkOptions::default()
+        };
+
+        let mut seen = HashSet::new();
+
+        for entry in volume.walk(options).unwrap() {
+            let entry = entry.unwrap();
+
+            if !entry.hard_links.is_empty() {
+                assert!(seen.insert(entry.entry.get_file_reference().unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_walk_max_depth() {
+        let volume = sample_volume().unwrap();
+        let options = WalkOptions {
+            max_depth: Some(1),
+            ..WalkOptions::default()
+        };
+
+        let paths: Vec<String> = volume
+            .walk(options)
+            .unwrap()
+            .map(|entry| entry.unwrap().path)
+            .filter(|path| !path.starts_with("\\$Orphan\\"))
+            .collect();
+
+        assert!(paths.iter().any(|path| path == "\\$Extend"));
+        assert!(paths.iter().all(|path| path.matches('\\').count() == 1));
+    }
+
+    #[test]
+    fn test_walk_sorted() {
+        let volume = sample_volume().unwrap();
+        let upcase = volume.get_upcase_table().unwrap();
+        let options = WalkOptions {
+            sorted: true,
+            max_depth: Some(1),
+            ..WalkOptions::default()
+        };
+
+        let names: Vec<String> = volume
+            .walk(options)
+            .unwrap()
+            .map(|entry| entry.unwrap().path)
+            .filter(|path| !path.starts_with("\\$Orphan\\"))
+            .collect();
+
+        for pair in names.windows(2) {
+            assert_eq!(upcase.compare(&pair[0], &pair[1]), Ordering::Less);
+        }
+    }
+
+    #[test]
+    fn test_walk_following_links() {
+        let volume = sample_volume().unwrap();
+        let options = WalkOptions {
+            reparse_policy: ReparsePolicy::Follow(ResolveOptions::default()),
+            ..WalkOptions::default()
+        };
+
+        let walked: Vec<WalkEntry> = volume
+            .walk(options)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .collect();
+
+        let reported = volume.walk(WalkOptions::default()).unwrap().count();
+        assert!(walked.len() >= reported);
+
+        for entry in walked.iter() {
+            if let Some(ref link) = entry.link {
+                assert!(link.kind.is_link());
+                if link.outcome == LinkOutcome::Followed {
+                    assert!(link.target_mft_entry_index.is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_walk_without_orphans() {
+        let options = VolumeOpenOptions::new().orphan_policy(OrphanPolicy::Exclude);
+        let volume = Volume::open_with_options(sample_volume_path(), &options).unwrap();
+
+        assert!(volume
+            .walk(WalkOptions::default())
+            .unwrap()
+            .all(|entry| !entry.unwrap().path.starts_with("\\$Orphan\\")));
+    }
+}
@@ -0,0 +1,297 @@
+//! Converts between the paths of this crate (`\Users\bob`, relative to the root of the volume)
+//! and the full paths Windows stores in artifacts such as the registry or event logs.
+//!
+//! ```ignore
+//! let mut drives = DriveMap::new();
+//! drives.insert('C', "system.dd");
+//!
+//! let (image, path) = drives.resolve("\\\\?\\C:\\Windows\\System32\\cmd.exe").unwrap();
+//! assert_eq!((*image, path.as_str()), ("system.dd", "\\Windows\\System32\\cmd.exe"));
+//! ```
+use std::collections::BTreeMap;
+
+/// Prefixes of paths in the Win32 and NT namespaces, which are followed by a drive, a volume
+/// GUID or `UNC\`.
+const NAMESPACE_PREFIXES: &[&str] = &["\\\\?\\", "\\\\.\\", "\\??\\"];
+
+/// Where a Windows path starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowsRoot {
+    /// An uppercase drive letter.
+    Drive(char),
+    /// `Volume{GUID}`, as in `\\?\Volume{GUID}\`.
+    Volume(String),
+    /// A network share, `\\server\share`.
+    Unc { server: String, share: String },
+}
+
+/// A full Windows path, split into its root and the path inside the volume.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowsPath {
+    pub root: WindowsRoot,
+    /// Starts with `\`, `\` alone for the root directory.
+    pub path: String,
+}
+
+/// `\` followed by the components of `path`, with `/` read as `\` and empty or `.`
+/// components left out. `..` components are kept, they are names like any other on NTFS.
+fn volume_path(path: &str) -> String {
+    let components: Vec<&str> = path
+        .split(|c| c == '\\' || c == '/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect();
+
+    format!("\\{}", components.join("\\"))
+}
+
+fn drive_letter(path: &str) -> Option<char> {
+    let mut chars = path.chars();
+
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(letter), Some(':'), None)
+        | (Some(letter), Some(':'), Some('\\'))
+        | (Some(letter), Some(':'), Some('/'))
+            if letter.is_ascii_alphabetic() =>
+        {
+            Some(letter.to_ascii_uppercase())
+        }
+        _ => None,
+    }
+}
+
+fn starts_with_ignore_case(path: &str, prefix: &str) -> bool {
+    path.get(..prefix.len())
+        .map_or(false, |start| start.eq_ignore_ascii_case(prefix))
+}
+
+fn split_first(path: &str) -> (&str, &str) {
+    match path.find(|c| c == '\\' || c == '/') {
+        Some(separator) => (&path[..separator], &path[separator..]),
+        None => (path, ""),
+    }
+}
+
+fn unc(path: &str) -> Option<WindowsPath> {
+    let (server, rest) = split_first(path);
+    let (share, rest) = split_first(rest.get(1..).unwrap_or(""));
+
+    if server.is_empty() || share.is_empty() {
+        return None;
+    }
+
+    Some(WindowsPath {
+        root: WindowsRoot::Unc {
+            server: server.to_owned(),
+            share: share.to_owned(),
+        },
+        path: volume_path(rest),
+    })
+}
+
+impl WindowsPath {
+    /// Parses `C:\...`, `\\?\C:\...`, `\\.\C:\...`, `\??\C:\...` (NT paths, as stored in
+    /// reparse points), `\\?\Volume{GUID}\...`, `\\server\share\...` and `\\?\UNC\...`.
+    /// Relative paths (including `\...` without a drive) are `None`.
+    pub fn parse(path: &str) -> Option<Self> {
+        for prefix in NAMESPACE_PREFIXES {
+            if path.starts_with(prefix) {
+                let rest = &path[prefix.len()..];
+
+                if starts_with_ignore_case(rest, "UNC\\") {
+                    return unc(&rest[4..]);
+                }
+
+                if let Some(letter) = drive_letter(rest) {
+                    return Some(WindowsPath {
+                        root: WindowsRoot::Drive(letter),
+                        path: volume_path(&rest[2..]),
+                    });
+                }
+
+                let (volume, rest) = split_first(rest);
+                if volume.len() > 6 && starts_with_ignore_case(volume, "Volume") {
+                    return Some(WindowsPath {
+                        root: WindowsRoot::Volume(volume.to_owned()),
+                        path: volume_path(rest),
+                    });
+                }
+
+                return None;
+            }
+        }
+
+        if path.starts_with("\\\\") {
+            return unc(&path[2..]);
+        }
+
+        drive_letter(path).map(|letter| WindowsPath {
+            root: WindowsRoot::Drive(letter),
+            path: volume_path(&path[2..]),
+        })
+    }
+
+    /// The Win32 form, e.g. `C:\Users\bob` or `\\server\share\file`.
+    pub fn to_win32(&self) -> String {
+        match &self.root {
+            WindowsRoot::Drive(letter) => format!("{}:{}", letter, self.path),
+            WindowsRoot::Volume(volume) => format!("\\\\?\\{}{}", volume, self.path),
+            WindowsRoot::Unc { server, share } => {
+                format!("\\\\{}\\{}{}", server, share, self.path)
+            }
+        }
+    }
+
+    /// The extended length form, e.g. `\\?\C:\Users\bob` or `\\?\UNC\server\share\file`.
+    pub fn to_extended(&self) -> String {
+        match &self.root {
+            WindowsRoot::Drive(letter) => format!("\\\\?\\{}:{}", letter, self.path),
+            WindowsRoot::Volume(volume) => format!("\\\\?\\{}{}", volume, self.path),
+            WindowsRoot::Unc { server, share } => {
+                format!("\\\\?\\UNC\\{}\\{}{}", server, share, self.path)
+            }
+        }
+    }
+}
+
+/// The Windows path of `path` (a path of this crate) on the volume mounted as `drive_letter`.
+pub fn to_windows_path(drive_letter: char, path: &str) -> String {
+    WindowsPath {
+        root: WindowsRoot::Drive(drive_letter.to_ascii_uppercase()),
+        path: volume_path(path),
+    }
+    .to_win32()
+}
+
+/// Maps drive letters (and volume GUIDs) to the volumes they were on, e.g. images or
+/// `SharedVolume`s, as the caller knows them.
+#[derive(Debug, Clone)]
+pub struct DriveMap<T> {
+    drives: BTreeMap<char, T>,
+    volumes: BTreeMap<String, T>,
+}
+
+impl<T> Default for DriveMap<T> {
+    fn default() -> Self {
+        DriveMap {
+            drives: BTreeMap::new(),
+            volumes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> DriveMap<T> {
+    pub fn new() -> Self {
+        DriveMap::default()
+    }
+
+    pub fn insert(&mut self, drive_letter: char, volume: T) {
+        self.drives
+            .insert(drive_letter.to_ascii_uppercase(), volume);
+    }
+
+    /// Maps `Volume{GUID}` (the GUID is matched ignoring case).
+    pub fn insert_volume_guid(&mut self, volume_name: &str, volume: T) {
+        self.volumes
+            .insert(volume_name.to_ascii_lowercase(), volume);
+    }
+
+    pub fn get(&self, root: &WindowsRoot) -> Option<&T> {
+        match root {
+            WindowsRoot::Drive(letter) => self.drives.get(&letter.to_ascii_uppercase()),
+            WindowsRoot::Volume(volume) => self.volumes.get(&volume.to_ascii_lowercase()),
+            WindowsRoot::Unc { .. } => None,
+        }
+    }
+
+    /// The volume `windows_path` is on and the path inside it, `None` for relative paths,
+    /// network shares and unmapped drives.
+    pub fn resolve(&self, windows_path: &str) -> Option<(&T, String)> {
+        let parsed = WindowsPath::parse(windows_path)?;
+
+        self.get(&parsed.root).map(|volume| (volume, parsed.path))
+    }
+
+    /// The Windows path of `path` on `volume`, by the first drive letter it is mapped to.
+    pub fn to_windows_path(&self, volume: &T, path: &str) -> Option<String>
+    where
+        T: PartialEq,
+    {
+        self.drives
+            .iter()
+            .find(|(_, mapped)| *mapped == volume)
+            .map(|(letter, _)| to_windows_path(*letter, path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drive(letter: char, path: &str) -> Option<WindowsPath> {
+        Some(WindowsPath {
+            root: WindowsRoot::Drive(letter),
+            path: path.to_owned(),
+        })
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            WindowsPath::parse("c:\\Users\\bob\\NTUSER.DAT"),
+            drive('C', "\\Users\\bob\\NTUSER.DAT")
+        );
+        assert_eq!(
+            WindowsPath::parse("\\\\?\\C:\\Windows\\"),
+            drive('C', "\\Windows")
+        );
+        assert_eq!(WindowsPath::parse("\\??\\D:\\a/./b"), drive('D', "\\a\\b"));
+        assert_eq!(WindowsPath::parse("C:"), drive('C', "\\"));
+        assert_eq!(
+            WindowsPath::parse("\\\\?\\Volume{0b1f}\\x").map(|path| path.root),
+            Some(WindowsRoot::Volume("Volume{0b1f}".to_owned()))
+        );
+        assert_eq!(
+            WindowsPath::parse("\\\\?\\UNC\\server\\share\\x"),
+            WindowsPath::parse("\\\\server\\share\\x")
+        );
+        assert_eq!(WindowsPath::parse("\\Windows"), None);
+        assert_eq!(WindowsPath::parse("Windows\\System32"), None);
+    }
+
+    #[test]
+    fn test_to_windows_path() {
+        assert_eq!(to_windows_path('c', "\\Users\\bob"), "C:\\Users\\bob");
+        assert_eq!(to_windows_path('C', "\\"), "C:\\");
+        assert_eq!(
+            WindowsPath::parse("C:\\x").unwrap().to_extended(),
+            "\\\\?\\C:\\x"
+        );
+        assert_eq!(
+            WindowsPath::parse("\\\\server\\share\\x")
+                .unwrap()
+                .to_extended(),
+            "\\\\?\\UNC\\server\\share\\x"
+        );
+    }
+
+    #[test]
+    fn test_drive_map() {
+        let mut drives = DriveMap::new();
+        drives.insert('c', "system.dd");
+        drives.insert_volume_guid("Volume{ABCD}", "data.dd");
+
+        assert_eq!(
+            drives.resolve("C:\\Windows"),
+            Some((&"system.dd", "\\Windows".to_owned()))
+        );
+        assert_eq!(
+            drives.resolve("\\\\?\\volume{abcd}\\x"),
+            Some((&"data.dd", "\\x".to_owned()))
+        );
+        assert_eq!(drives.resolve("E:\\x"), None);
+        assert_eq!(
+            drives.to_windows_path(&"system.dd", "\\Windows"),
+            Some("C:\\Windows".to_owned())
+        );
+    }
+}
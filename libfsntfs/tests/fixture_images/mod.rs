@@ -0,0 +1,225 @@
+//! Generates small NTFS images for the integration tests.
+//!
+//! On Linux the image is formatted with `mkntfs` and populated through an `ntfs-3g` (FUSE)
+//! mount, see `LAYOUT`. Elsewhere (or without the tools) an image with the same layout can be
+//! pointed to with `LIBFSNTFS_FIXTURE_IMAGE`, e.g. one generated on Linux by CI and pinned.
+//!
+//! The tests need one or the other, so they are ignored by default and fail (rather than pass
+//! without checking anything) when run with `cargo test -- --ignored` and neither is there.
+#![allow(dead_code)]
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const IMAGE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// What the generated image holds, by path in the volume.
+pub const PLAIN_FILE: &str = "\\plain.txt";
+pub const PLAIN_DATA: &[u8] = b"the quick brown fox jumps over the lazy dog\n";
+pub const ADS_FILE: &str = "\\with_ads.txt";
+pub const ADS_NAME: &str = "Zone.Identifier";
+pub const ADS_DATA: &[u8] = b"[ZoneTransfer]\r\nZoneId=3\r\n";
+pub const COMPRESSED_DIRECTORY: &str = "\\compressed";
+pub const COMPRESSED_FILE: &str = "\\compressed\\repetitive.bin";
+pub const COMPRESSED_SIZE: usize = 256 * 1024;
+pub const SPARSE_FILE: &str = "\\sparse.bin";
+/// The sparse file is zero up to its tail.
+pub const SPARSE_SIZE: u64 = 4 * 1024 * 1024;
+pub const SPARSE_TAIL: &[u8] = b"tail";
+pub const HARD_LINK: &str = "\\linked.txt";
+pub const HARD_LINK_OTHER: &str = "\\directory\\also_linked.txt";
+pub const DELETED_NAME: &str = "deleted.txt";
+/// A directory junction, made by setting its reparse data through ntfs-3g.
+pub const JUNCTION: &str = "\\junction";
+pub const JUNCTION_SUBSTITUTE_NAME: &str = "\\??\\C:\\target";
+pub const JUNCTION_PRINT_NAME: &str = "C:\\target";
+pub const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xa000_0003;
+
+/// The reparse data of `JUNCTION`: the tag, the size of the rest, then the offsets and sizes
+/// of the names in the path buffer, each name nul terminated.
+pub fn junction_reparse_data() -> Vec<u8> {
+    let utf16 = |name: &str| -> Vec<u8> {
+        name.encode_utf16()
+            .chain(Some(0))
+            .flat_map(|c| c.to_le_bytes().to_vec())
+            .collect()
+    };
+    let substitute_name = utf16(JUNCTION_SUBSTITUTE_NAME);
+    let print_name = utf16(JUNCTION_PRINT_NAME);
+
+    let mut buffer = vec![];
+    buffer.extend(&0_u16.to_le_bytes());
+    buffer.extend(&(substitute_name.len() as u16 - 2).to_le_bytes());
+    buffer.extend(&(substitute_name.len() as u16).to_le_bytes());
+    buffer.extend(&(print_name.len() as u16 - 2).to_le_bytes());
+    buffer.extend(&substitute_name);
+    buffer.extend(&print_name);
+
+    let mut data = vec![];
+    data.extend(&IO_REPARSE_TAG_MOUNT_POINT.to_le_bytes());
+    data.extend(&(buffer.len() as u16).to_le_bytes());
+    data.extend(&0_u16.to_le_bytes());
+    data.extend(buffer);
+    data
+}
+
+/// The contents of the compressed file, which compresses well.
+pub fn compressed_data() -> Vec<u8> {
+    (0..COMPRESSED_SIZE).map(|i| (i % 7) as u8).collect()
+}
+
+/// A generated image, removed when dropped (images from the environment are kept).
+pub struct FixtureImage {
+    path: PathBuf,
+    generated: bool,
+}
+
+impl FixtureImage {
+    pub fn path(&self) -> &str {
+        self.path.to_str().expect("temporary paths are UTF-8")
+    }
+}
+
+impl Drop for FixtureImage {
+    fn drop(&mut self) {
+        if self.generated {
+            let _ = fs::remove_dir_all(self.path.parent().expect("the image has a directory"));
+        }
+    }
+}
+
+/// The fixture image, panics when it cannot be generated here.
+pub fn generate() -> FixtureImage {
+    if let Some(path) = env::var_os("LIBFSNTFS_FIXTURE_IMAGE") {
+        return FixtureImage {
+            path: PathBuf::from(path),
+            generated: false,
+        };
+    }
+
+    match generate_with_ntfs_3g() {
+        Ok(image) => image,
+        Err(reason) => panic!(
+            "cannot generate a fixture image ({}), set LIBFSNTFS_FIXTURE_IMAGE",
+            reason
+        ),
+    }
+}
+
+static GENERATED: AtomicUsize = AtomicUsize::new(0);
+
+fn generate_with_ntfs_3g() -> Result<FixtureImage, String> {
+    if !cfg!(target_os = "linux") {
+        return Err("generating images needs ntfs-3g on Linux".to_owned());
+    }
+
+    let directory = env::temp_dir().join(format!(
+        "libfsntfs-fixture-{}-{}",
+        std::process::id(),
+        GENERATED.fetch_add(1, Ordering::SeqCst)
+    ));
+    let mount_point = directory.join("mnt");
+    fs::create_dir_all(&mount_point).map_err(|e| e.to_string())?;
+
+    let image = FixtureImage {
+        path: directory.join("fixture.img"),
+        generated: true,
+    };
+
+    File::create(&image.path)
+        .and_then(|file| file.set_len(IMAGE_SIZE))
+        .map_err(|e| e.to_string())?;
+
+    run(Command::new("mkntfs")
+        .args(&["--fast", "--force", "--quiet", "--label", "FIXTURE"])
+        .arg(&image.path))?;
+
+    run(Command::new("ntfs-3g")
+        .args(&["-o", "streams_interface=windows"])
+        .arg(&image.path)
+        .arg(&mount_point))?;
+
+    let populated = populate(&mount_point).map_err(|e| e.to_string());
+
+    run(Command::new("fusermount").arg("-u").arg(&mount_point))?;
+    populated?;
+
+    Ok(image)
+}
+
+fn run(command: &mut Command) -> Result<(), String> {
+    let status = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("{:?}: {}", command, e))?;
+
+    if !status.success() {
+        return Err(format!("{:?} failed with {}", command, status));
+    }
+
+    Ok(())
+}
+
+fn volume_path(root: &Path, path: &str) -> PathBuf {
+    path.split('\\')
+        .filter(|component| !component.is_empty())
+        .fold(root.to_path_buf(), |path, component| path.join(component))
+}
+
+/// Lays out the files the tests expect.
+fn populate(root: &Path) -> std::io::Result<()> {
+    fs::write(volume_path(root, PLAIN_FILE), PLAIN_DATA)?;
+
+    fs::write(volume_path(root, ADS_FILE), PLAIN_DATA)?;
+    let ads_path = format!("{}:{}", volume_path(root, ADS_FILE).display(), ADS_NAME);
+    fs::write(ads_path, ADS_DATA)?;
+
+    // Files created in a directory with the compressed attribute are compressed.
+    let compressed_directory = volume_path(root, COMPRESSED_DIRECTORY);
+    fs::create_dir(&compressed_directory)?;
+    run(Command::new("setfattr")
+        .args(&["-n", "system.ntfs_attrib_be", "-v", "0x00000810"])
+        .arg(&compressed_directory))
+    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    fs::write(volume_path(root, COMPRESSED_FILE), compressed_data())?;
+
+    // Writing past the end leaves a hole, which ntfs-3g does not allocate.
+    let mut sparse = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(volume_path(root, SPARSE_FILE))?;
+    sparse.seek(SeekFrom::Start(SPARSE_SIZE - SPARSE_TAIL.len() as u64))?;
+    sparse.write_all(SPARSE_TAIL)?;
+    drop(sparse);
+
+    fs::create_dir(volume_path(root, "\\directory"))?;
+    fs::write(volume_path(root, HARD_LINK), PLAIN_DATA)?;
+    fs::hard_link(
+        volume_path(root, HARD_LINK),
+        volume_path(root, HARD_LINK_OTHER),
+    )?;
+
+    // ntfs-3g adds the entry to `$Extend\$Reparse` as well.
+    let junction = volume_path(root, JUNCTION);
+    fs::create_dir(&junction)?;
+    let reparse_data: String = junction_reparse_data()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    run(Command::new("setfattr")
+        .args(&["-n", "system.ntfs_reparse_data", "-v"])
+        .arg(format!("0x{}", reparse_data))
+        .arg(&junction))
+    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+
+    let deleted = root.join(DELETED_NAME);
+    fs::write(&deleted, PLAIN_DATA)?;
+    fs::remove_file(&deleted)?;
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+//! Exercises the safe API end to end, against images generated with the features of NTFS
+//! the sample image does not have, see `fixture_images`. Ignored by default, as they need
+//! ntfs-3g or `LIBFSNTFS_FIXTURE_IMAGE`: run them with `cargo test -- --ignored`.
+mod fixture_images;
+
+use fixture_images::*;
+use libfsntfs_rs::flags::FileAttributeFlags;
+use libfsntfs_rs::volume::{AccessMode, Volume};
+use libfsntfs_rs::walk::WalkOptions;
+use std::fs::File;
+use std::io::Read;
+
+fn open(image: &FixtureImage) -> Volume {
+    Volume::open(image.path(), AccessMode::Read).expect("the fixture image opens")
+}
+
+fn read_all(mut reader: impl Read) -> Vec<u8> {
+    let mut data = vec![];
+    reader.read_to_end(&mut data).unwrap();
+    data
+}
+
+#[test]
+#[ignore]
+fn test_plain_file() {
+    let image = generate();
+    let volume = open(&image);
+
+    assert_eq!(volume.get_name().unwrap(), "FIXTURE");
+
+    let entry = volume.get_file_entry_by_path(PLAIN_FILE).unwrap();
+    assert_eq!(entry.get_size().unwrap(), PLAIN_DATA.len() as u64);
+    assert_eq!(read_all(entry), PLAIN_DATA);
+}
+
+#[test]
+#[ignore]
+fn test_alternate_data_stream() {
+    let image = generate();
+    let volume = open(&image);
+
+    let entry = volume.get_file_entry_by_path(ADS_FILE).unwrap();
+    let stream = entry.get_alternate_data_stream_by_name(ADS_NAME).unwrap();
+
+    assert_eq!(read_all(stream), ADS_DATA);
+}
+
+#[test]
+#[ignore]
+fn test_compressed_file() {
+    let image = generate();
+    let volume = open(&image);
+
+    let entry = volume.get_file_entry_by_path(COMPRESSED_FILE).unwrap();
+    assert!(entry
+        .get_file_attribute_flags()
+        .unwrap()
+        .contains(FileAttributeFlags::COMPRESSED));
+    assert_eq!(read_all(entry), compressed_data());
+}
+
+#[test]
+#[ignore]
+fn test_sparse_file() {
+    let image = generate();
+    let volume = open(&image);
+
+    let entry = volume.get_file_entry_by_path(SPARSE_FILE).unwrap();
+    assert!(entry
+        .get_file_attribute_flags()
+        .unwrap()
+        .contains(FileAttributeFlags::SPARSE_FILE));
+
+    let data = read_all(entry);
+    let (hole, tail) = data.split_at(data.len() - SPARSE_TAIL.len());
+
+    assert_eq!(data.len() as u64, SPARSE_SIZE);
+    assert!(hole.iter().all(|&byte| byte == 0));
+    assert_eq!(tail, SPARSE_TAIL);
+}
+
+#[test]
+#[ignore]
+fn test_hard_links() {
+    let image = generate();
+    let volume = open(&image);
+
+    let entry = volume.get_file_entry_by_path(HARD_LINK).unwrap();
+    let mut names: Vec<String> = entry
+        .get_file_names()
+        .unwrap()
+        .into_iter()
+        .map(|file_name| file_name.name)
+        .collect();
+    names.sort();
+    names.dedup();
+
+    assert_eq!(names, ["also_linked.txt", "linked.txt"]);
+
+    let options = WalkOptions {
+        deduplicate_hard_links: true,
+        ..WalkOptions::default()
+    };
+    let linked: Vec<_> = volume
+        .walk(options)
+        .unwrap()
+        .map(Result::unwrap)
+        .filter(|entry| !entry.hard_links.is_empty())
+        .collect();
+
+    assert_eq!(linked.len(), 1);
+    assert!(linked[0].hard_links.contains(&HARD_LINK.to_owned()));
+    assert!(linked[0].hard_links.contains(&HARD_LINK_OTHER.to_owned()));
+}
+
+#[test]
+#[ignore]
+fn test_deleted_file() {
+    let image = generate();
+    let volume = open(&image);
+
+    assert!(volume
+        .get_file_entry_by_path(format!("\\{}", DELETED_NAME))
+        .is_err());
+
+    let deleted = volume
+        .mft_entries()
+        .unwrap()
+        .filter_map(|(_, entry)| entry.ok())
+        .find(|entry| entry.get_name().map_or(false, |name| name == DELETED_NAME))
+        .expect("the MFT entry of the deleted file is not reused");
+
+    assert!(!deleted.is_allocated().unwrap());
+}
+
+#[test]
+#[ignore]
+fn test_reparse_points() {
+    let image = generate();
+    let volume = open(&image);
+
+    let junction = volume.get_file_entry_by_path(JUNCTION).unwrap();
+    assert_eq!(
+        junction.get_reparse_point_tag().unwrap(),
+        Some(IO_REPARSE_TAG_MOUNT_POINT)
+    );
+    assert_eq!(
+        junction.get_reparse_point_substitute_name().unwrap(),
+        Some(JUNCTION_SUBSTITUTE_NAME.to_owned())
+    );
+    assert_eq!(
+        junction.get_reparse_point_print_name().unwrap(),
+        Some(JUNCTION_PRINT_NAME.to_owned())
+    );
+
+    let reparse_points = volume
+        .get_reparse_points(&mut File::open(image.path()).unwrap())
+        .unwrap();
+    assert_eq!(reparse_points.len(), 1);
+    assert_eq!(reparse_points[0].tag, IO_REPARSE_TAG_MOUNT_POINT);
+
+    let entry = volume
+        .get_file_entry_by_mft_idx(reparse_points[0].mft_entry_index())
+        .unwrap();
+    assert_eq!(
+        entry.get_file_reference().unwrap(),
+        junction.get_file_reference().unwrap()
+    );
+}
@@ -0,0 +1,73 @@
+use failure::{bail, Error};
+use libyal_rs_common_build::{
+    generate_bindings, get_lib_or_fetch, is_msvc_target, BuildError, LibBuilder,
+};
+use std::env;
+use std::path::PathBuf;
+
+fn build_and_link_static(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    if is_msvc_target() {
+        println!("cargo:rustc-link-lib=static=libqcow");
+
+        // Also static-link deps (otherwise we'll get missing symbols at link time).
+        let deps = [
+            "libbfio",
+            "libcaes",
+            "libcdata",
+            "libcerror",
+            "libcfile",
+            "libclocale",
+            "libcnotify",
+            "libcpath",
+            "libcsplit",
+            "libcthreads",
+            "libfcache",
+            "libfdata",
+            "libhmac",
+            "libuna",
+        ];
+
+        for dep in deps.iter() {
+            println!("cargo:rustc-link-lib=static={}", dep);
+        }
+    } else {
+        println!("cargo:rustc-link-lib=static=qcow");
+    }
+
+    LibBuilder::new(lib_path).shared(false).sync_and_build()
+}
+
+fn build_and_link_dynamic(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    if is_msvc_target() {
+        println!("cargo:rustc-link-lib=dylib=libqcow");
+    } else {
+        println!("cargo:rustc-link-lib=dylib=qcow");
+    }
+
+    LibBuilder::new(lib_path).shared(true).sync_and_build()
+}
+
+/// The release used when there is no local checkout of libqcow.
+const LIBQCOW_VERSION: &str = "20210419";
+/// The SHA-256 of the release package of `LIBQCOW_VERSION`. While it is not pinned, fetching
+/// the release is rejected unless the `LIBQCOW_SHA256` env var gives it.
+const LIBQCOW_SHA256: Option<&str> = None;
+
+fn build() -> Result<(), BuildError> {
+    let lib_path = get_lib_or_fetch("libqcow", LIBQCOW_VERSION, LIBQCOW_SHA256)?;
+
+    let include_folder_path = if cfg!(feature = "dynamic_link") {
+        build_and_link_dynamic(lib_path)?
+    } else {
+        build_and_link_static(lib_path)?
+    };
+
+    generate_bindings(&include_folder_path, "wrapper.h")
+}
+
+fn main() {
+    if let Err(e) = build() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1 @@
+#include "libqcow.h"
\ No newline at end of file
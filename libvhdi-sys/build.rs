@@ -0,0 +1,72 @@
+use failure::{bail, Error};
+use libyal_rs_common_build::{
+    generate_bindings, get_lib_or_fetch, is_msvc_target, BuildError, LibBuilder,
+};
+use std::env;
+use std::path::PathBuf;
+
+fn build_and_link_static(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    if is_msvc_target() {
+        println!("cargo:rustc-link-lib=static=libvhdi");
+
+        // Also static-link deps (otherwise we'll get missing symbols at link time).
+        let deps = [
+            "libbfio",
+            "libcdata",
+            "libcerror",
+            "libcfile",
+            "libclocale",
+            "libcnotify",
+            "libcpath",
+            "libcsplit",
+            "libcthreads",
+            "libfcache",
+            "libfdata",
+            "libfguid",
+            "libuna",
+        ];
+
+        for dep in deps.iter() {
+            println!("cargo:rustc-link-lib=static={}", dep);
+        }
+    } else {
+        println!("cargo:rustc-link-lib=static=vhdi");
+    }
+
+    LibBuilder::new(lib_path).shared(false).sync_and_build()
+}
+
+fn build_and_link_dynamic(lib_path: PathBuf) -> Result<PathBuf, BuildError> {
+    if is_msvc_target() {
+        println!("cargo:rustc-link-lib=dylib=libvhdi");
+    } else {
+        println!("cargo:rustc-link-lib=dylib=vhdi");
+    }
+
+    LibBuilder::new(lib_path).shared(true).sync_and_build()
+}
+
+/// The release used when there is no local checkout of libvhdi.
+const LIBVHDI_VERSION: &str = "20210425";
+/// The SHA-256 of the release package of `LIBVHDI_VERSION`. While it is not pinned, fetching
+/// the release is rejected unless the `LIBVHDI_SHA256` env var gives it.
+const LIBVHDI_SHA256: Option<&str> = None;
+
+fn build() -> Result<(), BuildError> {
+    let lib_path = get_lib_or_fetch("libvhdi", LIBVHDI_VERSION, LIBVHDI_SHA256)?;
+
+    let include_folder_path = if cfg!(feature = "dynamic_link") {
+        build_and_link_dynamic(lib_path)?
+    } else {
+        build_and_link_static(lib_path)?
+    };
+
+    generate_bindings(&include_folder_path, "wrapper.h")
+}
+
+fn main() {
+    if let Err(e) = build() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,61 @@
+//! Typed handles: wrappers of the raw handle pointers, so handles of different kinds cannot be
+//! mixed up. They are passed to the functions with `as_ptr` (or `as_out_ptr`, e.g. to
+//! `libvhdi_file_initialize`).
+use crate::{libvhdi_error_t, libvhdi_file_t};
+use core::ptr;
+
+macro_rules! handle_ptr {
+    ($(#[$meta:meta])* $name:ident, $handle:ty) => {
+        $(#[$meta])*
+        #[repr(transparent)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name(*mut $handle);
+
+        impl $name {
+            /// A handle to be filled in by an `_initialize` or `_get_` function.
+            pub fn null() -> Self {
+                $name(ptr::null_mut())
+            }
+
+            /// `None` for a null pointer.
+            pub fn new(ptr: *mut $handle) -> Option<Self> {
+                if ptr.is_null() {
+                    None
+                } else {
+                    Some($name(ptr))
+                }
+            }
+
+            /// Wraps `ptr` as is, it may be null.
+            pub unsafe fn from_raw(ptr: *mut $handle) -> Self {
+                $name(ptr)
+            }
+
+            pub fn is_null(self) -> bool {
+                self.0.is_null()
+            }
+
+            pub fn as_ptr(self) -> *mut $handle {
+                self.0
+            }
+
+            /// Where functions which create a handle write it.
+            pub fn as_out_ptr(&mut self) -> *mut *mut $handle {
+                &mut self.0
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name::null()
+            }
+        }
+    };
+}
+
+handle_ptr!(FilePtr, libvhdi_file_t);
+handle_ptr!(
+    /// Set by functions which fail, free it with `libvhdi_error_free`.
+    ErrorPtr,
+    libvhdi_error_t
+);
@@ -0,0 +1 @@
+#include "libvhdi.h"
\ No newline at end of file
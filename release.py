@@ -37,9 +37,11 @@ class SemVer:
         return f"{self.major}.{self.minor}.{self.patch}"
 
 
-LIBYAL_LIBRARIES_DIRECTORIES = ["common", "common-build", "libcerror-sys", "libbfio-sys", "libbfio", "libfsntfs-sys",
+LIBYAL_LIBRARIES_DIRECTORIES = ["common", "common-build", "libcerror-sys", "libcerror", "libbfio-sys", "libbfio",
+                                "libbde-sys", "libvhdi-sys", "libqcow-sys", "libewf-sys", "libfsntfs-sys",
                                 "libfsntfs"]
-LIBYAL_LIBRARIES_PACKAGES = ["libyal-rs-common", "libyal-rs-common-build", "libcerror-sys", "libbfio-sys",
+LIBYAL_LIBRARIES_PACKAGES = ["libyal-rs-common", "libyal-rs-common-build", "libcerror-sys", "libcerror-rs",
+                             "libbfio-sys", "libbde-sys", "libvhdi-sys", "libqcow-sys", "libewf-sys",
                              "libfsntfs-sys", "libbfio-rs", "libfsntfs-rs"]
 
 